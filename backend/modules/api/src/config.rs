@@ -6,6 +6,9 @@ pub struct AppConfig {
     pub auth_rate_limit_burst: u32,
     pub game_rate_limit_per_sec: u64,
     pub game_rate_limit_burst: u32,
+    pub archive_after_days: i64,
+    pub archive_interval_secs: u64,
+    pub archive_dump_dir: String,
 }
 
 impl AppConfig {
@@ -27,6 +30,15 @@ impl AppConfig {
                 .unwrap_or_else(|_| "20".to_string())
                 .parse()
                 .unwrap_or(20),
+            archive_after_days: env::var("ARCHIVE_AFTER_DAYS")
+                .unwrap_or_else(|_| "90".to_string())
+                .parse()
+                .unwrap_or(90),
+            archive_interval_secs: env::var("ARCHIVE_INTERVAL_SECS")
+                .unwrap_or_else(|_| "3600".to_string())
+                .parse()
+                .unwrap_or(3600),
+            archive_dump_dir: env::var("ARCHIVE_DUMP_DIR").unwrap_or_else(|_| "archive".to_string()),
         }
     }
 }
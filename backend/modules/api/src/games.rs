@@ -1,9 +1,11 @@
 use actix_web::{
     HttpResponse, delete, get, post, put,
-    web::{self, Json, Path, Query},
+    http::header::{ContentDisposition, DispositionParam, DispositionType},
+    web::{self, Bytes, Json, Path, Query},
 };
+use futures_util::stream;
 use dto::{
-    games::{CreateGameRequest, GameDisplayDTO, MakeMoveRequest, JoinGameRequest, GameStatus, ListGamesQuery, ImportGameRequest, ImportGameResponse},
+    games::{CreateGameRequest, GameDisplayDTO, MakeMoveRequest, PlayedMoveDTO, GameStatus, ListGamesQuery, ImportGameRequest, ImportGameResponse, ExportFormat, ExportGameQuery, PlayerGamesExportQuery, GameExportRecord, GameSearchQuery},
     responses::{InvalidCredentialsResponse, NotFoundResponse},
 };
 use error::error::ApiError;
@@ -13,16 +15,42 @@ use uuid::Uuid;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use sea_orm::DatabaseConnection;
-use service::games::GameService;
+use actix::Addr;
+use security::AuthenticatedPlayer;
+use service::games::{ArchiveFilter, GameSearchFilter, GameService, ImportStatus, MoveOutcome};
+use service::imports::ImportJobs;
+use service::move_log::MoveLog;
+
+use crate::ws::{announce_end, LobbyState};
+
+/// Upper bound on games accepted in a single bulk import upload
+pub const MAX_BULK_IMPORT_GAMES: usize = 10_000;
+
+/// Upper bound on the size of a bulk import upload
+pub const MAX_BULK_IMPORT_BYTES: usize = 16 * 1024 * 1024;
+
+/// Games fetched per database round trip while streaming an archive export
+const EXPORT_PAGE_SIZE: u64 = 100;
+
+const PGN_CONTENT_TYPE: &str = "application/x-chess-pgn";
+const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+fn attachment(filename: String) -> ContentDisposition {
+    ContentDisposition {
+        disposition: DispositionType::Attachment,
+        parameters: vec![DispositionParam::Filename(filename)],
+    }
+}
 
 #[utoipa::path(
     post,
     path = "/v1/games",
     request_body = CreateGameRequest,
     responses(
-        (status = 201, description = "Game created successfully", body = GameDisplayDTO),
+        (status = 201, description = "Game created successfully, with the authenticated player seated", body = GameDisplayDTO),
         (status = 400, description = "Invalid request parameters", body = InvalidCredentialsResponse),
-        (status = 401, description = "Unauthorized", body = InvalidCredentialsResponse)
+        (status = 401, description = "Unauthorized", body = InvalidCredentialsResponse),
+        (status = 404, description = "Player not found", body = NotFoundResponse)
     ),
     security(
         ("jwt_auth" = [])
@@ -30,22 +58,23 @@ use service::games::GameService;
     tag = "Games"
 )]
 #[post("")]
-pub async fn create_game(payload: Json<CreateGameRequest>) -> HttpResponse {
-    match payload.0.validate() {
-        Ok(_) => {
-            // The real implementation would create a game in the database
-            // For now, we'll just return a mock response
-            HttpResponse::Created().json(json!({
-                "message": "Game created successfully",
-                "data": {
-                    "game": {
-                        "id": Uuid::new_v4(),
-                        "status": "waiting"
-                    }
-                }
-            }))
-        }
-        Err(errors) => ApiError::ValidationError(errors).error_response(),
+pub async fn create_game(
+    player: AuthenticatedPlayer,
+    payload: Json<CreateGameRequest>,
+    db: web::Data<DatabaseConnection>,
+) -> HttpResponse {
+    if let Err(errors) = payload.0.validate() {
+        return ApiError::ValidationError(errors).error_response();
+    }
+
+    match GameService::create_game(db.get_ref(), player.0, payload.into_inner()).await {
+        Ok(game) => HttpResponse::Created().json(json!({
+            "message": "Game created successfully",
+            "data": {
+                "game": GameDisplayDTO::from(game)
+            }
+        })),
+        Err(err) => err.error_response(),
     }
 }
 
@@ -65,18 +94,16 @@ pub async fn create_game(payload: Json<CreateGameRequest>) -> HttpResponse {
     tag = "Games"
 )]
 #[get("/{id}")]
-pub async fn get_game(id: Path<Uuid>) -> HttpResponse {
-    // The real implementation would fetch the game from the database
-    // For now, we'll just return a mock response
-    HttpResponse::Ok().json(json!({
-        "message": "Game found",
-        "data": {
-            "game": {
-                "id": id.into_inner(),
-                "status": "in_progress"
+pub async fn get_game(id: Path<Uuid>, db: web::Data<DatabaseConnection>) -> HttpResponse {
+    match GameService::get_game(db.get_ref(), id.into_inner()).await {
+        Ok(game) => HttpResponse::Ok().json(json!({
+            "message": "Game found",
+            "data": {
+                "game": GameDisplayDTO::from(game)
             }
-        }
-    }))
+        })),
+        Err(err) => err.error_response(),
+    }
 }
 
 #[utoipa::path(
@@ -88,8 +115,11 @@ pub async fn get_game(id: Path<Uuid>) -> HttpResponse {
     request_body = MakeMoveRequest,
     responses(
         (status = 200, description = "Move made successfully", body = GameDisplayDTO),
-        (status = 400, description = "Invalid move", body = InvalidCredentialsResponse),
-        (status = 404, description = "Game not found", body = NotFoundResponse)
+        (status = 400, description = "Invalid or illegal move", body = InvalidCredentialsResponse),
+        (status = 401, description = "Unauthorized", body = InvalidCredentialsResponse),
+        (status = 403, description = "Player is not part of this game", body = InvalidCredentialsResponse),
+        (status = 404, description = "Game not found", body = NotFoundResponse),
+        (status = 409, description = "Not the player's turn, the game is not in progress, the player's clock ran out, or the ply is stale (the body then carries the current game)", body = InvalidCredentialsResponse)
     ),
     security(
         ("jwt_auth" = [])
@@ -97,33 +127,55 @@ pub async fn get_game(id: Path<Uuid>) -> HttpResponse {
     tag = "Games"
 )]
 #[put("/{id}/move")]
-pub async fn make_move(id: Path<Uuid>, payload: Json<MakeMoveRequest>) -> HttpResponse {
-    match payload.0.validate() {
-        Ok(_) => {
-            // The real implementation would validate and make the move
-            // For now, we'll just return a mock response
+pub async fn make_move(
+    id: Path<Uuid>,
+    player: AuthenticatedPlayer,
+    payload: Json<MakeMoveRequest>,
+    db: web::Data<DatabaseConnection>,
+    move_log: web::Data<MoveLog>,
+    lobby: web::Data<Addr<LobbyState>>,
+) -> HttpResponse {
+    if let Err(errors) = payload.0.validate() {
+        return ApiError::ValidationError(errors).error_response();
+    }
+
+    match GameService::make_move(
+        db.get_ref(),
+        move_log.get_ref(),
+        id.into_inner(),
+        player.0,
+        &payload.chess_move,
+        payload.lag_ms,
+        payload.ply,
+    )
+    .await
+    {
+        Ok(MoveOutcome::Played { game, played, ended, rating_changes }) => {
+            // A retried final move replays the stored one and announces nothing
+            if ended {
+                announce_end(&lobby, &game, rating_changes);
+            }
             HttpResponse::Ok().json(json!({
                 "message": "Move made successfully",
                 "data": {
-                    "game": {
-                        "id": id.into_inner(),
-                        "status": "in_progress",
-                        "last_move": payload.0.chess_move
-                    }
+                    "game": GameDisplayDTO::from(game),
+                    "move": PlayedMoveDTO { uci: played.uci, san: played.san }
                 }
             }))
         }
-        Err(errors) => ApiError::ValidationError(errors).error_response(),
+        Ok(MoveOutcome::Flagged { game, rating_changes }) => {
+            announce_end(&lobby, &game, rating_changes);
+            ApiError::Conflict("Time expired; the game was lost on time".to_string()).error_response()
+        }
+        Err(err) => err.error_response(),
     }
 }
 
-
-
 #[utoipa::path(
     get,
     path = "/v1/games",
     params(
-        ("status" = Option<String>, Query, description = "Filter games by status (waiting, in_progress, completed, aborted)"),
+        ("status" = Option<String>, Query, description = "Filter games by status (waiting, in_progress, completed, aborted, adjudicated)"),
         ("player_id" = Option<String>, Query, description = "Filter games by player ID", format = "uuid"),
         ("page" = Option<i32>, Query, description = "Page number for pagination"),
         ("limit" = Option<i32>, Query, description = "Number of items per page")
@@ -152,6 +204,7 @@ pub async fn list_games(
             "in_progress" => Some(GameStatus::InProgress),
             "completed" => Some(GameStatus::Completed),
             "aborted" => Some(GameStatus::Aborted),
+            "adjudicated" => Some(GameStatus::Adjudicated),
             _ => None, // Invalid status ignores filter or could error. Current mock ignored it.
         }
     } else {
@@ -169,29 +222,7 @@ pub async fn list_games(
         status_enum,
     ).await {
         Ok((games, next_cursor)) => {
-            // Map Entity Models to DTOs
-            // We need a mapper. For now I will do manual mapping or basic json.
-            // GameDisplayDTO matches fields mostly? 
-            // We need to construct GameDisplayDTO from game::Model.
-            // game::Model has `result: Option<ResultSide>` (Enum). DTO has `Result: GameResult` (Enum).
-            // This mapping might be verbose. For the optimization task, I will do a best-effort mapping inline.
-            
-            let game_dtos: Vec<serde_json::Value> = games.into_iter().map(|g| {
-                // Return generic JSON for now to avoid extensive DTO mapping boilerplate 
-                // if mapper isn't available, but we should try to match structure.
-                json!({
-                    "id": g.id,
-                    "white_player_id": g.white_player,
-                    "black_player_id": g.black_player,
-                    "status": if g.result.is_some() { "completed" } else { "in_progress" }, // simplified
-                    "result": g.result,
-                    "current_fen": g.fen,
-                    "time_control": 600, // placeholder as it's not in Game entity directly (duration_sec is there but it's different?)
-                    "increment": 0,
-                    "created_at": g.created_at,
-                    "started_at": g.started_at,
-                })
-            }).collect();
+            let game_dtos: Vec<GameDisplayDTO> = games.into_iter().map(GameDisplayDTO::from).collect();
 
             // Construct response with cursor
             HttpResponse::Ok().json(json!({
@@ -213,40 +244,105 @@ pub async fn list_games(
 }
 
 #[utoipa::path(
-    post,
-    path = "/v1/games/{id}/join",
+    get,
+    path = "/v1/games/search",
     params(
-        ("id" = String, Path, description = "Game ID in UUID format", format = "uuid")
+        ("player_id" = Option<String>, Query, description = "Games involving this player", format = "uuid"),
+        ("eco" = Option<String>, Query, description = "ECO code or prefix (e.g. B or B90); only imported games are classified"),
+        ("opening" = Option<String>, Query, description = "Opening name prefix, case-insensitive; only imported games are classified"),
+        ("min_rating" = Option<i32>, Query, description = "Minimum average rating of both players"),
+        ("max_rating" = Option<i32>, Query, description = "Maximum average rating of both players"),
+        ("since" = Option<String>, Query, description = "Only games started at or after this time", format = "date-time"),
+        ("until" = Option<String>, Query, description = "Only games started before this time", format = "date-time"),
+        ("result" = Option<String>, Query, description = "Filter by result (white_win, black_win, draw, abandoned, in_progress)"),
+        ("termination" = Option<String>, Query, description = "Filter by termination (checkmate, stalemate, ...)"),
+        ("variant" = Option<String>, Query, description = "Filter by variant (standard, chess960, ...)"),
+        ("speed" = Option<String>, Query, description = "Time control category (bullet, blitz, rapid, classical)"),
+        ("min_moves" = Option<i32>, Query, description = "Minimum number of full moves"),
+        ("max_moves" = Option<i32>, Query, description = "Maximum number of full moves"),
+        ("fen" = Option<String>, Query, description = "Games that reached this position"),
+        ("zobrist" = Option<i64>, Query, description = "Games that reached the position with this Zobrist key"),
+        ("cursor" = Option<String>, Query, description = "Cursor returned by the previous page"),
+        ("limit" = Option<i32>, Query, description = "Number of items per page")
     ),
-    request_body = JoinGameRequest,
     responses(
-        (status = 200, description = "Joined game successfully", body = GameDisplayDTO),
-        (status = 400, description = "Cannot join game", body = InvalidCredentialsResponse),
-        (status = 404, description = "Game not found", body = NotFoundResponse)
+        (status = 200, description = "Matching games", body = Vec<GameDisplayDTO>),
+        (status = 400, description = "Invalid filter", body = InvalidCredentialsResponse)
     ),
     security(
         ("jwt_auth" = [])
     ),
     tag = "Games"
 )]
-#[post("/{id}/join")]
-pub async fn join_game(id: Path<Uuid>, payload: Json<JoinGameRequest>) -> HttpResponse {
-    match payload.0.validate() {
-        Ok(_) => {
-            // The real implementation would add the player to the game
-            // For now, we'll just return a mock response
+#[get("/search")]
+pub async fn search_games(
+    query: Query<GameSearchQuery>,
+    db: web::Data<DatabaseConnection>,
+) -> HttpResponse {
+    if let Err(errors) = query.0.validate() {
+        return ApiError::ValidationError(errors).error_response();
+    }
+
+    let filter = match GameSearchFilter::try_from(&query.0) {
+        Ok(filter) => filter,
+        Err(err) => return err.error_response(),
+    };
+
+    let limit = query.limit.unwrap_or(10);
+
+    match GameService::search_games(db.get_ref(), &filter, query.cursor.clone(), limit).await {
+        Ok((games, next_cursor)) => {
+            let game_dtos: Vec<GameDisplayDTO> = games.into_iter().map(GameDisplayDTO::from).collect();
+
             HttpResponse::Ok().json(json!({
-                "message": "Joined game successfully",
+                "message": "Games found",
                 "data": {
-                    "game": {
-                        "id": id.into_inner(),
-                        "status": "in_progress",
-                        "player_id": payload.0.player_id
-                    }
+                    "games": game_dtos,
+                    "next_cursor": next_cursor,
+                    "limit": limit
                 }
             }))
+        },
+        Err(e) => {
+            eprintln!("Error searching games: {}", e);
+            HttpResponse::InternalServerError().json(json!({
+                "message": "Internal server error"
+            }))
         }
-        Err(errors) => ApiError::ValidationError(errors).error_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/games/{id}/join",
+    params(
+        ("id" = String, Path, description = "Game ID in UUID format", format = "uuid")
+    ),
+    responses(
+        (status = 200, description = "Joined game successfully", body = GameDisplayDTO),
+        (status = 401, description = "Unauthorized", body = InvalidCredentialsResponse),
+        (status = 404, description = "Game or player not found", body = NotFoundResponse),
+        (status = 409, description = "Game is full, finished, or the player is already seated", body = InvalidCredentialsResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    ),
+    tag = "Games"
+)]
+#[post("/{id}/join")]
+pub async fn join_game(
+    id: Path<Uuid>,
+    player: AuthenticatedPlayer,
+    db: web::Data<DatabaseConnection>,
+) -> HttpResponse {
+    match GameService::join_game(db.get_ref(), id.into_inner(), player.0).await {
+        Ok(game) => HttpResponse::Ok().json(json!({
+            "message": "Joined game successfully",
+            "data": {
+                "game": GameDisplayDTO::from(game)
+            }
+        })),
+        Err(err) => err.error_response(),
     }
 }
 
@@ -257,8 +353,11 @@ pub async fn join_game(id: Path<Uuid>, payload: Json<JoinGameRequest>) -> HttpRe
         ("id" = String, Path, description = "Game ID in UUID format", format = "uuid")
     ),
     responses(
-        (status = 200, description = "Game abandoned successfully"),
-        (status = 404, description = "Game not found", body = NotFoundResponse)
+        (status = 200, description = "Game aborted, or ended as a loss for the player who left once it is long enough to be rated", body = GameDisplayDTO),
+        (status = 401, description = "Unauthorized", body = InvalidCredentialsResponse),
+        (status = 403, description = "Player is not part of this game", body = InvalidCredentialsResponse),
+        (status = 404, description = "Game not found", body = NotFoundResponse),
+        (status = 409, description = "Game is already finished", body = InvalidCredentialsResponse)
     ),
     security(
         ("jwt_auth" = [])
@@ -266,13 +365,24 @@ pub async fn join_game(id: Path<Uuid>, payload: Json<JoinGameRequest>) -> HttpRe
     tag = "Games"
 )]
 #[delete("/{id}")]
-pub async fn abandon_game(id: Path<Uuid>) -> HttpResponse {
-    // The real implementation would mark the game as abandoned
-    // For now, we'll just return a mock response
-    HttpResponse::Ok().json(json!({
-        "message": "Game abandoned successfully",
-        "data": {}
-    }))
+pub async fn abandon_game(
+    id: Path<Uuid>,
+    player: AuthenticatedPlayer,
+    db: web::Data<DatabaseConnection>,
+    lobby: web::Data<Addr<LobbyState>>,
+) -> HttpResponse {
+    match GameService::abandon_game(db.get_ref(), id.into_inner(), player.0).await {
+        Ok((game, rating_changes)) => {
+            announce_end(&lobby, &game, rating_changes);
+            HttpResponse::Ok().json(json!({
+                "message": "Game abandoned successfully",
+                "data": {
+                    "game": GameDisplayDTO::from(game)
+                }
+            }))
+        }
+        Err(err) => err.error_response(),
+    }
 }
 
 #[utoipa::path(
@@ -282,6 +392,7 @@ pub async fn abandon_game(id: Path<Uuid>) -> HttpResponse {
     responses(
         (status = 201, description = "Game imported successfully", body = ImportGameResponse),
         (status = 400, description = "Invalid PGN format", body = InvalidCredentialsResponse),
+        (status = 409, description = "Game has already been imported", body = ImportGameResponse),
         (status = 422, description = "Illegal moves in PGN", body = InvalidCredentialsResponse)
     ),
     security(
@@ -337,9 +448,22 @@ pub async fn import_game(
     // Convert PGN result to string
     let result_str = validated.headers.result.to_pgn_string().to_string();
 
-    // TODO: Store in database with is_imported = true
-    // For now, we'll return a mock response with a generated UUID
-    let game_id = Uuid::new_v4();
+    let game_id = match GameService::store_imported_game(db.get_ref(), &validated, &payload.pgn).await {
+        Ok(ImportStatus::Imported(game)) => game.id,
+        Ok(ImportStatus::Duplicate(existing_id)) => {
+            return HttpResponse::Conflict().json(ImportGameResponse {
+                success: false,
+                game_id: Some(existing_id),
+                white_player: validated.headers.white,
+                black_player: validated.headers.black,
+                result: result_str,
+                move_count: validated.ply_count,
+                final_fen: Some(validated.final_fen),
+                error: Some("Game has already been imported".to_string()),
+            });
+        }
+        Err(err) => return err.error_response(),
+    };
 
     HttpResponse::Created().json(ImportGameResponse {
         success: true,
@@ -351,4 +475,207 @@ pub async fn import_game(
         final_fen: Some(validated.final_fen),
         error: None,
     })
-}
\ No newline at end of file
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/games/import/bulk",
+    request_body(
+        content = String,
+        content_type = "application/x-chess-pgn",
+        description = "One or more PGN games separated by blank lines"
+    ),
+    responses(
+        (status = 202, description = "Import job queued", body = BulkImportJob),
+        (status = 400, description = "Upload contains no games or too many games", body = InvalidCredentialsResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    ),
+    tag = "Games"
+)]
+#[post("/import/bulk")]
+pub async fn bulk_import_games(
+    body: String,
+    db: web::Data<DatabaseConnection>,
+    jobs: web::Data<ImportJobs>,
+) -> HttpResponse {
+    let games = chess::split_pgn_games(&body);
+    if games.is_empty() {
+        return ApiError::BadRequest("Upload does not contain any PGN games".to_string()).error_response();
+    }
+    if games.len() > MAX_BULK_IMPORT_GAMES {
+        return ApiError::BadRequest(format!(
+            "Upload contains {} games; at most {} are allowed per job",
+            games.len(),
+            MAX_BULK_IMPORT_GAMES
+        ))
+        .error_response();
+    }
+
+    let job = jobs.start(db.into_inner(), games);
+    HttpResponse::Accepted().json(json!({
+        "message": "Import job queued",
+        "data": {
+            "job": job
+        }
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/games/import/jobs/{job_id}",
+    params(
+        ("job_id" = String, Path, description = "Import job ID in UUID format", format = "uuid")
+    ),
+    responses(
+        (status = 200, description = "Import job progress", body = BulkImportJob),
+        (status = 404, description = "Import job not found", body = NotFoundResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    ),
+    tag = "Games"
+)]
+#[get("/import/jobs/{job_id}")]
+pub async fn get_import_job(job_id: Path<Uuid>, jobs: web::Data<ImportJobs>) -> HttpResponse {
+    let job_id = job_id.into_inner();
+    match jobs.get(job_id) {
+        Some(job) => HttpResponse::Ok().json(json!({
+            "message": "Import job found",
+            "data": {
+                "job": job
+            }
+        })),
+        None => ApiError::NotFound(format!("Import job {}", job_id)).error_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/games/{id}/export",
+    params(
+        ("id" = String, Path, description = "Game ID in UUID format", format = "uuid"),
+        ("clocks" = Option<bool>, Query, description = "Include clock annotations"),
+        ("evals" = Option<bool>, Query, description = "Include engine evaluations"),
+        ("comments" = Option<bool>, Query, description = "Include move comments")
+    ),
+    responses(
+        (status = 200, description = "Game as PGN", body = String, content_type = "application/x-chess-pgn"),
+        (status = 404, description = "Game not found", body = NotFoundResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    ),
+    tag = "Games"
+)]
+#[get("/{id}/export")]
+pub async fn export_game(
+    id: Path<Uuid>,
+    query: Query<ExportGameQuery>,
+    db: web::Data<DatabaseConnection>,
+) -> HttpResponse {
+    let game_id = id.into_inner();
+    match GameService::export_pgn(db.get_ref(), game_id, &query).await {
+        Ok(pgn) => HttpResponse::Ok()
+            .content_type(PGN_CONTENT_TYPE)
+            .insert_header(attachment(format!("{}.pgn", game_id)))
+            .body(pgn),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/players/{id}/games/export",
+    params(
+        ("id" = String, Path, description = "Player ID in UUID format", format = "uuid"),
+        ("format" = Option<String>, Query, description = "pgn (default) or ndjson"),
+        ("since" = Option<String>, Query, description = "Only games started at or after this time", format = "date-time"),
+        ("until" = Option<String>, Query, description = "Only games started before this time", format = "date-time"),
+        ("variant" = Option<String>, Query, description = "Filter by variant (standard, chess960, ...)"),
+        ("rated" = Option<bool>, Query, description = "Filter rated or unrated games"),
+        ("result" = Option<String>, Query, description = "Filter by result (white_win, black_win, draw, abandoned, in_progress)"),
+        ("clocks" = Option<bool>, Query, description = "Include clock annotations"),
+        ("evals" = Option<bool>, Query, description = "Include engine evaluations"),
+        ("comments" = Option<bool>, Query, description = "Include move comments")
+    ),
+    responses(
+        (status = 200, description = "Streamed game archive", body = String),
+        (status = 400, description = "Invalid filter", body = InvalidCredentialsResponse),
+        (status = 404, description = "Player not found", body = NotFoundResponse)
+    ),
+    security(
+        ("jwt_auth" = [])
+    ),
+    tag = "Games"
+)]
+#[get("/{id}/games/export")]
+pub async fn export_player_games(
+    id: Path<Uuid>,
+    query: Query<PlayerGamesExportQuery>,
+    db: web::Data<DatabaseConnection>,
+) -> HttpResponse {
+    let player_id = id.into_inner();
+    let filter = match ArchiveFilter::try_from(&query.0) {
+        Ok(filter) => filter,
+        Err(err) => return err.error_response(),
+    };
+    if let Err(err) = GameService::ensure_player_exists(db.get_ref(), player_id).await {
+        return err.error_response();
+    }
+
+    let format = query.format;
+    let options = query.annotations();
+    let db = db.into_inner();
+
+    // Each step fetches one keyset page, so memory use is bounded by the page size
+    let body = stream::try_unfold(Some(None::<String>), move |cursor| {
+        let db = db.clone();
+        let filter = filter.clone();
+        let options = options.clone();
+        async move {
+            let Some(cursor) = cursor else {
+                return Ok(None);
+            };
+
+            let (games, next_cursor) =
+                GameService::list_player_games(&db, player_id, &filter, cursor, EXPORT_PAGE_SIZE)
+                    .await
+                    .map_err(actix_web::error::ErrorInternalServerError)?;
+            let names = GameService::player_names(&db, &games)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+
+            let mut chunk = String::new();
+            for game in games {
+                let pgn = GameService::game_to_pgn(&game, &names, &options);
+                match format {
+                    ExportFormat::Pgn => {
+                        chunk.push_str(&pgn);
+                        chunk.push('\n');
+                    }
+                    ExportFormat::Ndjson => {
+                        let record = GameExportRecord { game: GameDisplayDTO::from(game), pgn };
+                        chunk.push_str(
+                            &serde_json::to_string(&record)
+                                .map_err(actix_web::error::ErrorInternalServerError)?,
+                        );
+                        chunk.push('\n');
+                    }
+                }
+            }
+
+            Ok::<_, actix_web::Error>(Some((Bytes::from(chunk), next_cursor.map(Some))))
+        }
+    });
+
+    let (content_type, extension) = match format {
+        ExportFormat::Pgn => (PGN_CONTENT_TYPE, "pgn"),
+        ExportFormat::Ndjson => (NDJSON_CONTENT_TYPE, "ndjson"),
+    };
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(attachment(format!("{}-games.{}", player_id, extension)))
+        .streaming(body)
+}
@@ -31,14 +31,22 @@ impl Modify for SecurityAddon {
         players::find_player_by_id,
         players::update_player,
         players::delete_player,
+        players::get_player_ratings,
+        players::get_rating_history,
         
         // Game endpoints
         games::create_game,
         games::get_game,
         games::make_move,
         games::list_games,
+        games::search_games,
         games::join_game,
         games::abandon_game,
+        games::import_game,
+        games::bulk_import_games,
+        games::get_import_job,
+        games::export_game,
+        games::export_player_games,
         
         // Authentication endpoints
         auth::login,
@@ -55,15 +63,29 @@ impl Modify for SecurityAddon {
             dto::players::UpdatePlayer,
             dto::players::DisplayPlayer,
             dto::players::UpdatedPlayer,
+            dto::players::PlayerRatingDTO,
+            dto::players::RatingHistoryQuery,
+            dto::players::RatingHistoryEntryDTO,
             
             // Game schemas
             dto::games::CreateGameRequest,
+            dto::games::StartPosition,
             dto::games::GameDisplayDTO,
             dto::games::MakeMoveRequest,
-            dto::games::JoinGameRequest,
+            dto::games::PlayedMoveDTO,
+            dto::games::ImportGameRequest,
+            dto::games::ImportGameResponse,
+            dto::games::BulkImportJob,
+            dto::games::BulkImportError,
+            dto::games::ImportJobState,
+            dto::games::ExportFormat,
+            dto::games::ExportGameQuery,
+            dto::games::PlayerGamesExportQuery,
+            dto::games::GameExportRecord,
             dto::games::GameStatus,
             dto::games::GameResult,
             dto::games::ListGamesQuery,
+            dto::games::GameSearchQuery,
             
             // Auth schemas
             dto::auth::LoginRequest,
@@ -130,6 +152,14 @@ JWT authentication is mandatory for all WebSocket connections. Provide your JWT
 
 If authentication fails or the token is missing, the connection will be immediately closed with an authentication_error message.
 
+### Encoding
+Request an encoding with the `Sec-WebSocket-Protocol` header; the server picks the first one it supports:
+
+- `xlmate.msgpack.v1` - MessagePack in binary frames (recommended)
+- `xlmate.json.v1` - JSON in text frames, with a `version` field (default when no subprotocol is requested)
+
+Both encodings carry the same message shapes shown below.
+
 ## Event Types
 
 ### Player Joins Game
@@ -183,6 +213,48 @@ If authentication fails or the token is missing, the connection will be immediat
 }
 ```
 
+### Clock Update
+Remaining seconds per side, stamped with the server's wall clock in milliseconds.
+```json
+{
+  "type": "Clock",
+  "payload": { "white": 290, "black": 300, "server_time_ms": 1718000000000 }
+}
+```
+
+### Time Synchronization
+Send a ping with your wall clock (milliseconds since the epoch):
+```json
+{ "type": "TimeSync", "payload": { "client_time_ms": 1718000000000 } }
+```
+The server answers immediately with its receive and send times:
+```json
+{
+  "type": "TimeSync",
+  "payload": { "client_time_ms": 1718000000000, "server_receive_ms": 1718000000041, "server_send_ms": 1718000000041 }
+}
+```
+With `t0 = client_time_ms`, `t1 = server_receive_ms`, `t2 = server_send_ms` and `t3` the local receive time,
+the round trip is `(t3 - t0) - (t2 - t1)` and the clock offset is `((t1 - t0) + (t2 - t3)) / 2`.
+Repeat a few times and keep the sample with the lowest round trip.
+
+### Game End
+Sent once the game is over. `rating_changes` is `null` for unrated games, aborted games and games
+ended before each side made two moves.
+```json
+{
+  "type": "End",
+  "payload": {
+    "result": "white_win | black_win | draw | abandoned",
+    "final_fen": "string",
+    "rating_changes": {
+      "white": { "player_id": "uuid", "category": "blitz", "rating": 1512, "rating_change": 12 },
+      "black": { "player_id": "uuid", "category": "blitz", "rating": 1488, "rating_change": -12 }
+    }
+  }
+}
+```
+
 ### Chat Message
 ```json
 {
@@ -196,6 +268,12 @@ If authentication fails or the token is missing, the connection will be immediat
 }
 ```
 
+## Close Codes
+
+| Code | Meaning |
+|------|---------|
+| 4001 | Resync required: the client fell too far behind and was evicted. Refetch the game state over REST and reconnect. |
+
 ## Error Messages
 ```json
 {
@@ -1,15 +1,19 @@
 use actix_web::{
     HttpResponse, delete, get, post, put,
-    web::{Json, Path},
+    web::{self, Json, Path, Query},
 };
 use dto::{
-    players::{DisplayPlayer, NewPlayer, UpdatePlayer, UpdatedPlayer},
+    players::{
+        DisplayPlayer, NewPlayer, PlayerRatingDTO, RatingHistoryEntryDTO, RatingHistoryQuery, UpdatePlayer,
+        UpdatedPlayer,
+    },
     responses::{
         InvalidCredentialsResponse, NotFoundResponse, PlayerAdded, PlayerDeleted, PlayerFound,
         PlayerUpdated,
     },
 };
 use error::error::ApiError;
+use sea_orm::DatabaseConnection;
 use serde_json::json;
 use validator::Validate;
 
@@ -17,6 +21,8 @@ use service::players::{
     add_player as add_new_player, delete_player as delete_player_by_id,
     find_player_by_id as get_single_player_by_id, update_player as update_player_by_id,
 };
+use service::games::GameService;
+use service::ratings::RatingService;
 use uuid::Uuid;
 
 #[utoipa::path(
@@ -123,3 +129,66 @@ pub async fn delete_player(id: Path<Uuid>) -> HttpResponse {
         Err(err) => err.error_response(),
     }
 }
+
+#[utoipa::path(
+    get,
+    path = "/v1/players/{id}/ratings",
+    params(
+        ("id" = String, Path, description = "Player ID in UUID format", format="uuid")
+    ),
+    responses(
+        (status = 200, description = "Ratings of every category the player has played", body=[PlayerRatingDTO]),
+        (status = 404, description = "Not found", body=NotFoundResponse)
+    )
+)]
+#[get("/{id}/ratings")]
+pub async fn get_player_ratings(id: Path<Uuid>, db: web::Data<DatabaseConnection>) -> HttpResponse {
+    let player_id = id.into_inner();
+    if let Err(err) = GameService::ensure_player_exists(db.get_ref(), player_id).await {
+        return err.error_response();
+    }
+
+    match RatingService::ratings(db.get_ref(), player_id).await {
+        Ok(ratings) => HttpResponse::Ok().json(json!({
+            "message":"Ratings found",
+            "data":{
+                "ratings": ratings.into_iter().map(PlayerRatingDTO::from).collect::<Vec<_>>()
+            }
+        })),
+        Err(err) => err.error_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/players/{id}/ratings/history",
+    params(
+        ("id" = String, Path, description = "Player ID in UUID format", format="uuid"),
+        ("category" = Option<String>, Query, description = "bullet, blitz, rapid, classical, correspondence, chess960 or three_check")
+    ),
+    responses(
+        (status = 200, description = "Rating changes, oldest first", body=[RatingHistoryEntryDTO]),
+        (status = 404, description = "Not found", body=NotFoundResponse)
+    )
+)]
+#[get("/{id}/ratings/history")]
+pub async fn get_rating_history(
+    id: Path<Uuid>,
+    query: Query<RatingHistoryQuery>,
+    db: web::Data<DatabaseConnection>,
+) -> HttpResponse {
+    let player_id = id.into_inner();
+    if let Err(err) = GameService::ensure_player_exists(db.get_ref(), player_id).await {
+        return err.error_response();
+    }
+
+    match RatingService::history(db.get_ref(), player_id, query.category).await {
+        Ok(history) => HttpResponse::Ok().json(json!({
+            "message":"Rating history found",
+            "data":{
+                "history": history.into_iter().map(RatingHistoryEntryDTO::from).collect::<Vec<_>>()
+            }
+        })),
+        Err(err) => err.error_response(),
+    }
+}
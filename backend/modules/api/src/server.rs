@@ -10,8 +10,13 @@ use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 use utoipa_redoc::{Redoc, Servable};
 use actix::Actor;
-use crate::players::{add_player, delete_player, find_player_by_id, update_player};
-use crate::games::{create_game, get_game, make_move, list_games, join_game, abandon_game, import_game};
+use crate::players::{add_player, delete_player, find_player_by_id, get_player_ratings, get_rating_history, update_player};
+use crate::games::{create_game, get_game, make_move, list_games, join_game, abandon_game, import_game, bulk_import_games, get_import_job, export_game, export_player_games, search_games, MAX_BULK_IMPORT_BYTES};
+use service::imports::ImportJobs;
+use service::archive::{ArchiveConfig, ArchiveService};
+use service::move_log::MoveLog;
+use service::ratings::MatchmakingRatings;
+use matchmaking::{MatchmakingService, MatchmakingWorker, RatingSource, WorkerConfig};
 use crate::auth::{login, register, refresh, logout};
 use crate::ai::{get_ai_suggestion, analyze_position};
 use crate::ws::{LobbyState, ws_route};
@@ -75,9 +80,53 @@ pub async fn main() -> std::io::Result<()> {
     // Create a shared LobbyState actor
     let lobby = LobbyState::new().start();
 
+    // Bulk import jobs are tracked in memory and shared by all workers
+    let import_jobs = web::Data::new(ImportJobs::new());
+
+    // Played moves are written to game_move in batches by a background task
+    let (move_log, move_log_writer) = MoveLog::start(db.clone());
+    let move_log = web::Data::new(move_log);
+
     // Load AppConfig
     let config = AppConfig::from_env();
 
+    // Move old finished games to cold storage in the background
+    ArchiveService::start(
+        db.clone(),
+        ArchiveConfig {
+            archive_after: chrono::Duration::days(config.archive_after_days),
+            interval: std::time::Duration::from_secs(config.archive_interval_secs),
+            dump_dir: config.archive_dump_dir.clone().into(),
+        },
+    );
+
+    // Matchmaking pools live in Redis when REDIS_URL is set, so replicas
+    // share them; otherwise in this process
+    let matchmaking_service = match env::var("REDIS_URL") {
+        Ok(redis_url) => {
+            let redis_pool = matchmaking::redis::create_redis_pool(&redis_url).map_err(|e| {
+                std::io::Error::other(format!("Invalid REDIS_URL: {}", e))
+            })?;
+            match matchmaking::redis::test_redis_connection(&redis_pool).await {
+                Ok(()) => eprintln!("Redis connection successful"),
+                Err(e) => eprintln!("Warning: Redis connection failed, matchmaking is unavailable: {}", e),
+            }
+            MatchmakingService::new(redis_pool)
+        }
+        Err(_) => {
+            eprintln!("REDIS_URL not set, keeping matchmaking pools in memory");
+            MatchmakingService::in_memory()
+        }
+    };
+    let matchmaking_ratings: std::sync::Arc<dyn RatingSource> =
+        std::sync::Arc::new(MatchmakingRatings::new(db.clone()));
+    let matchmaking_ratings = web::Data::from(matchmaking_ratings);
+
+    // Pair waiting players in the background; replicas coordinate through
+    // the store's worker lock
+    MatchmakingWorker::new(matchmaking_service.clone(), WorkerConfig::default()).start();
+    let matchmaking_service = web::Data::new(matchmaking_service);
+
     eprintln!("Starting HTTP server on {}", server_addr);
 
     // Define the app factory closure
@@ -132,6 +181,10 @@ pub async fn main() -> std::io::Result<()> {
             .app_data(web::Data::from(db.clone()))
             .app_data(web::Data::new(jwt_service.clone()))
             .app_data(web::Data::new(lobby.clone()))
+            .app_data(import_jobs.clone())
+            .app_data(move_log.clone())
+            .app_data(matchmaking_service.clone())
+            .app_data(matchmaking_ratings.clone())
             // WebSocket route mounting
             .route("/ws/{game_id}", web::get().to(ws_route))
             // Register your routes
@@ -143,13 +196,21 @@ pub async fn main() -> std::io::Result<()> {
                     .service(add_player)
                     .service(find_player_by_id)
                     .service(update_player)
-                    .service(delete_player),
+                    .service(delete_player)
+                    .service(get_player_ratings)
+                    .service(get_rating_history)
+                    .service(export_player_games),
             )
             // Game routes
             .service(
                 web::scope("/v1/games")
                     .wrap(Governor::new(&game_governor_conf))
+                    .app_data(web::PayloadConfig::new(MAX_BULK_IMPORT_BYTES))
+                    .service(bulk_import_games)
+                    .service(get_import_job)
                     .service(create_game)
+                    .service(search_games)
+                    .service(export_game)
                     .service(get_game)
                     .service(list_games)
                     .service(join_game)
@@ -166,6 +227,8 @@ pub async fn main() -> std::io::Result<()> {
                     .service(refresh)
                     .service(logout)
             )
+            // Matchmaking routes
+            .configure(matchmaking::routes::config)
             // AI routes
             .service(
                 web::scope("/v1/ai")
@@ -199,5 +262,9 @@ pub async fn main() -> std::io::Result<()> {
         }
     }
 
-    server.run().await
+    server.run().await?;
+
+    // Workers have stopped; write the moves still queued before exiting
+    move_log_writer.shutdown().await;
+    Ok(())
 }
@@ -149,4 +149,63 @@ mod tests {
         );
         assert_eq!(status, StatusCode::BAD_REQUEST);
     }
+
+    #[actix_web::test]
+    async fn test_rating_history_filters_by_category() {
+        use chrono::Utc;
+        use db_entity::{player, player_rating::RatingCategory, rating_history};
+        use sea_orm::{DbBackend, MockDatabase};
+        use uuid::Uuid;
+
+        use crate::players::get_rating_history;
+
+        let player_id = Uuid::new_v4();
+        let entry = |id: i64, rating: f64, rating_change: f64| rating_history::Model {
+            id,
+            player_id,
+            category: RatingCategory::Blitz,
+            game_id: Some(Uuid::new_v4()),
+            rating,
+            deviation: 250.0,
+            volatility: 0.06,
+            rating_change,
+            recorded_at: Utc::now().into(),
+        };
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![player::Model {
+                id: player_id,
+                username: "rated_player".to_string(),
+                email: "rated@example.com".to_string(),
+                password_hash: vec![],
+                biography: String::new(),
+                country: String::new(),
+                flair: String::new(),
+                real_name: String::new(),
+                location: None,
+                fide_rating: None,
+                social_links: None,
+                is_enabled: true,
+            }]])
+            .append_query_results(vec![vec![entry(1, 1662.4, 162.4), entry(2, 1640.0, -22.4)]])
+            .into_connection();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db))
+                .service(web::scope("/v1/players").service(get_rating_history)),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri(&format!("/v1/players/{}/ratings/history?category=blitz", player_id))
+            .to_request();
+        let res = app.call(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body: serde_json::Value = serde_json::from_slice(&test::read_body(res).await).unwrap();
+        let history = body["data"]["history"].as_array().unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0]["category"], "blitz");
+        assert_eq!(history[0]["rating"], 1662);
+        assert_eq!(history[1]["rating_change"], -22);
+    }
 }
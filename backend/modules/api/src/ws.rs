@@ -1,13 +1,17 @@
 use actix::prelude::*;
 use actix_web::{HttpRequest, HttpResponse, Error, web};
 use actix_web_actors::ws;
-use serde::{Serialize};
-use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
 use std::env;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use security::jwt::Claims;
 use jsonwebtoken::{decode, DecodingKey, Validation, Algorithm};
 use actix_web::error::ErrorUnauthorized;
+use actix_web::http::header::SEC_WEBSOCKET_PROTOCOL;
+use db_entity::game::{self, ResultSide};
 use serde_json::{Value, json};
+use service::ratings::GameRatingChanges;
 
 /// Core WebSocket message types
 #[derive(Message, Serialize, Clone, Debug, PartialEq)]
@@ -15,17 +19,170 @@ use serde_json::{Value, json};
 #[serde(tag = "type", content = "payload")]
 pub enum WsMessage {
     Move { from: String, to: String, san: String, fen: String },
-    Clock { white: u32, black: u32 },
-    End   { result: String, final_fen: String },
+    /// Remaining seconds per side, stamped with the server's wall clock so
+    /// clients can correct for latency using their time-sync offset
+    Clock { white: u32, black: u32, server_time_ms: u64 },
+    /// Reply to a client `TimeSync` ping, NTP style: the client combines its
+    /// own send/receive times with these to estimate offset and latency
+    TimeSync { client_time_ms: u64, server_receive_ms: u64, server_send_ms: u64 },
+    /// Sent once a game is over; `rating_changes` is set for rated games
+    End   { result: String, final_fen: String, rating_changes: Option<GameRatingChanges> },
     Error { code: u16, message: String },
 }
 
+impl WsMessage {
+    /// Builds a clock update stamped with the current server time.
+    pub fn clock(white: u32, black: u32) -> Self {
+        WsMessage::Clock { white, black, server_time_ms: now_ms() }
+    }
+
+    /// End-of-game event for a finished game
+    pub fn end(game: &game::Model, rating_changes: Option<GameRatingChanges>) -> Self {
+        let result = match game.result {
+            Some(ResultSide::WhiteWins) => "white_win",
+            Some(ResultSide::BlackWins) => "black_win",
+            Some(ResultSide::Draw) => "draw",
+            Some(ResultSide::Abandoned) => "abandoned",
+            None | Some(ResultSide::Ongoing) => "in_progress",
+        };
+        WsMessage::End { result: result.to_string(), final_fen: game.fen.clone(), rating_changes }
+    }
+
+    /// Clock updates are superseded by the next one, so a pending clock
+    /// update can be replaced instead of queued behind it.
+    fn is_coalescable(&self) -> bool {
+        matches!(self, WsMessage::Clock { .. })
+    }
+}
+
+/// Messages a client can send over the game socket
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", content = "payload")]
+pub enum WsClientMessage {
+    /// Time-sync ping carrying the client's wall clock at send time
+    TimeSync { client_time_ms: u64 },
+}
+
+/// Milliseconds since the Unix epoch on the server's wall clock
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Round-trip time measurements for one session, taken from heartbeat pongs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RttStats {
+    pub samples: u32,
+    pub last_ms: u64,
+    /// Exponentially weighted moving average (1/8 weight per sample, as in TCP's SRTT)
+    pub smoothed_ms: u64,
+    pub max_ms: u64,
+}
+
+impl RttStats {
+    pub fn record(&mut self, rtt_ms: u64) {
+        self.smoothed_ms = if self.samples == 0 {
+            rtt_ms
+        } else {
+            (self.smoothed_ms * 7 + rtt_ms) / 8
+        };
+        self.samples += 1;
+        self.last_ms = rtt_ms;
+        self.max_ms = self.max_ms.max(rtt_ms);
+    }
+}
+
+/// Wire encoding of a session, negotiated through `Sec-WebSocket-Protocol`.
+///
+/// MessagePack is the compact default for production clients; JSON stays
+/// available for debugging and for clients that don't request a subprotocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireFormat {
+    Json,
+    MessagePack,
+}
+
+impl WireFormat {
+    pub const JSON_PROTOCOL: &'static str = "xlmate.json.v1";
+    pub const MSGPACK_PROTOCOL: &'static str = "xlmate.msgpack.v1";
+    /// Subprotocols the server understands, in order of preference.
+    pub const PROTOCOLS: &'static [&'static str] = &[Self::MSGPACK_PROTOCOL, Self::JSON_PROTOCOL];
+
+    /// Picks the first subprotocol offered by the client that the server
+    /// supports, the same way the handshake does. Falls back to JSON.
+    pub fn negotiate(req: &HttpRequest) -> Self {
+        req.headers()
+            .get(SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|offered| offered.split(',').map(str::trim).find_map(Self::from_protocol))
+            .unwrap_or(WireFormat::Json)
+    }
+
+    fn from_protocol(protocol: &str) -> Option<Self> {
+        match protocol {
+            Self::JSON_PROTOCOL => Some(WireFormat::Json),
+            Self::MSGPACK_PROTOCOL => Some(WireFormat::MessagePack),
+            _ => None,
+        }
+    }
+
+    /// Serializes a message into a text (JSON) or binary (MessagePack) frame.
+    pub fn encode(&self, msg: &WsMessage) -> Result<EncodedFrame, String> {
+        match self {
+            WireFormat::Json => {
+                // Inject the version field; binary clients get it from the subprotocol name
+                let mut val = serde_json::to_value(msg).map_err(|e| e.to_string())?;
+                if let Value::Object(ref mut m) = val {
+                    m.insert("version".into(), json!("1.0"));
+                }
+                serde_json::to_string(&val).map(EncodedFrame::Text).map_err(|e| e.to_string())
+            }
+            WireFormat::MessagePack => rmp_serde::to_vec_named(msg)
+                .map(EncodedFrame::Binary)
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Parses a client frame. JSON text frames are accepted on every session.
+    pub fn decode(&self, msg: &ws::Message) -> Option<Result<WsClientMessage, String>> {
+        match msg {
+            ws::Message::Text(text) => Some(serde_json::from_str(text).map_err(|e| e.to_string())),
+            ws::Message::Binary(bytes) if *self == WireFormat::MessagePack => {
+                Some(rmp_serde::from_slice(bytes).map_err(|e| e.to_string()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A serialized message ready to be written to the socket
+#[derive(Debug, Clone, PartialEq)]
+pub enum EncodedFrame {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+/// Maximum number of messages buffered by the lobby for a single session
+/// whose mailbox is full. Exceeding it evicts the session.
+pub const OUTBOUND_QUEUE_CAPACITY: usize = 64;
+/// Mailbox capacity of each `WsSession`; once full, the lobby buffers instead.
+pub const SESSION_MAILBOX_CAPACITY: usize = 16;
+/// How often the lobby retries delivery of buffered messages.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(50);
+/// A session whose mailbox stays full for this long is evicted.
+const SLOW_CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Close code sent to evicted clients; they must refetch the game state.
+pub const RESYNC_REQUIRED_CLOSE_CODE: u16 = 4001;
+
 /// Actor messages
 #[derive(Message)]
 #[rtype(result = "()")]
 pub struct Connect {
     pub game_id: String,
     pub addr: Recipient<WsMessage>,
+    pub evict: Recipient<Evict>,
 }
 
 #[derive(Message)]
@@ -42,19 +199,137 @@ pub struct Broadcast {
     pub message: WsMessage,
 }
 
+/// Tells a session it was evicted for being too slow and must resync.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Evict {
+    pub reason: String,
+}
+
+/// Returns a snapshot of the lobby's backpressure counters.
+#[derive(Message)]
+#[rtype(result = "LobbyMetrics")]
+pub struct GetLobbyMetrics;
+
+/// Backpressure counters kept by the lobby
+#[derive(MessageResponse, Debug, Clone, Default, PartialEq, Eq)]
+pub struct LobbyMetrics {
+    /// Deliveries that found a session mailbox full and had to be buffered
+    pub queue_full_events: u64,
+    /// Buffered clock updates replaced by a newer one
+    pub coalesced_clock_updates: u64,
+    /// Sessions evicted for being chronically slow
+    pub evicted_sessions: u64,
+}
+
+/// Messages waiting for a session whose mailbox is full
+struct SessionQueue {
+    evict: Recipient<Evict>,
+    pending: VecDeque<WsMessage>,
+    full_since: Option<Instant>,
+}
+
+impl SessionQueue {
+    fn new(evict: Recipient<Evict>) -> Self {
+        SessionQueue { evict, pending: VecDeque::new(), full_since: None }
+    }
+
+    /// Buffers a message, replacing any pending clock update it supersedes.
+    /// Returns true if a pending message was coalesced away.
+    fn push(&mut self, msg: WsMessage) -> bool {
+        let mut coalesced = false;
+        if msg.is_coalescable() {
+            let before = self.pending.len();
+            self.pending.retain(|pending| !pending.is_coalescable());
+            coalesced = self.pending.len() != before;
+        }
+        self.pending.push_back(msg);
+        coalesced
+    }
+}
+
+/// Outcome of trying to drain a session's queue into its mailbox
+enum FlushOutcome {
+    Drained,
+    Full,
+    Closed,
+}
+
 /// Lobby state actor
 pub struct LobbyState {
-    sessions: HashMap<String, HashSet<Recipient<WsMessage>>>,
+    sessions: HashMap<String, HashMap<Recipient<WsMessage>, SessionQueue>>,
+    metrics: LobbyMetrics,
 }
 
 impl LobbyState {
     pub fn new() -> Self {
-        LobbyState { sessions: HashMap::new() }
+        LobbyState { sessions: HashMap::new(), metrics: LobbyMetrics::default() }
+    }
+
+    /// Delivers as many buffered messages as the session's mailbox accepts,
+    /// preserving their order.
+    fn flush(recipient: &Recipient<WsMessage>, queue: &mut SessionQueue) -> FlushOutcome {
+        while let Some(msg) = queue.pending.pop_front() {
+            match recipient.try_send(msg) {
+                Ok(()) => {}
+                Err(SendError::Full(msg)) => {
+                    queue.pending.push_front(msg);
+                    queue.full_since.get_or_insert_with(Instant::now);
+                    return FlushOutcome::Full;
+                }
+                Err(SendError::Closed(_)) => return FlushOutcome::Closed,
+            }
+        }
+        queue.full_since = None;
+        FlushOutcome::Drained
+    }
+
+    /// Retries delivery for every backed-up session and evicts the ones that
+    /// have been stalled for too long.
+    fn flush_all(&mut self) {
+        let metrics = &mut self.metrics;
+        for (game_id, sessions) in self.sessions.iter_mut() {
+            sessions.retain(|recipient, queue| {
+                if queue.pending.is_empty() {
+                    return true;
+                }
+                match Self::flush(recipient, queue) {
+                    FlushOutcome::Drained => true,
+                    FlushOutcome::Closed => false,
+                    FlushOutcome::Full => {
+                        let stalled = queue
+                            .full_since
+                            .is_some_and(|since| since.elapsed() >= SLOW_CLIENT_TIMEOUT);
+                        if stalled {
+                            Self::evict(metrics, game_id, queue, "client too slow");
+                        }
+                        !stalled
+                    }
+                }
+            });
+        }
+        self.sessions.retain(|_, sessions| !sessions.is_empty());
+    }
+
+    fn evict(metrics: &mut LobbyMetrics, game_id: &str, queue: &SessionQueue, reason: &str) {
+        metrics.evicted_sessions += 1;
+        log::warn!(
+            "Evicting slow WebSocket session for game {}: {} ({} messages pending)",
+            game_id,
+            reason,
+            queue.pending.len()
+        );
+        // do_send ignores the mailbox capacity, so the eviction always gets through
+        queue.evict.do_send(Evict { reason: reason.to_string() });
     }
 }
 
 impl Actor for LobbyState {
     type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(FLUSH_INTERVAL, |act, _| act.flush_all());
+    }
 }
 
 impl Handler<Connect> for LobbyState {
@@ -62,7 +337,7 @@ impl Handler<Connect> for LobbyState {
 
     fn handle(&mut self, msg: Connect, _: &mut Context<Self>) {
         let entry = self.sessions.entry(msg.game_id).or_default();
-        entry.insert(msg.addr);
+        entry.insert(msg.addr, SessionQueue::new(msg.evict));
     }
 }
 
@@ -83,20 +358,51 @@ impl Handler<Broadcast> for LobbyState {
     type Result = ();
 
     fn handle(&mut self, msg: Broadcast, _: &mut Context<Self>) {
-        if let Some(set) = self.sessions.get(&msg.game_id) {
-            for recipient in set.iter() {
-                // backpressure: drop if send fails
-                let _ = recipient.do_send(msg.message.clone());
+        let Some(sessions) = self.sessions.get_mut(&msg.game_id) else {
+            return;
+        };
+        let metrics = &mut self.metrics;
+        sessions.retain(|recipient, queue| {
+            // Anything already buffered must go out first to keep ordering
+            if queue.push(msg.message.clone()) {
+                metrics.coalesced_clock_updates += 1;
+            }
+            match Self::flush(recipient, queue) {
+                FlushOutcome::Drained => true,
+                FlushOutcome::Closed => false,
+                FlushOutcome::Full => {
+                    metrics.queue_full_events += 1;
+                    if queue.pending.len() > OUTBOUND_QUEUE_CAPACITY {
+                        // Move events can't be dropped, so the client has to resync
+                        Self::evict(metrics, &msg.game_id, queue, "outbound queue overflow");
+                        return false;
+                    }
+                    true
+                }
             }
+        });
+        if sessions.is_empty() {
+            self.sessions.remove(&msg.game_id);
         }
     }
 }
 
+impl Handler<GetLobbyMetrics> for LobbyState {
+    type Result = LobbyMetrics;
+
+    fn handle(&mut self, _: GetLobbyMetrics, _: &mut Context<Self>) -> LobbyMetrics {
+        self.metrics.clone()
+    }
+}
+
 /// WebSocket session actor
 pub struct WsSession {
     pub game_id: String,
+    pub player_id: String,
     pub lobby: Addr<LobbyState>,
     hb: std::time::Instant,
+    format: WireFormat,
+    rtt: RttStats,
 }
 
 impl WsSession {
@@ -117,9 +423,48 @@ impl WsSession {
                 ctx.stop();
                 return;
             }
-            ctx.ping(b"");
+            // The pong echoes our send time back, which gives us the round trip
+            ctx.ping(&now_ms().to_be_bytes());
         });
     }
+
+    fn write(&self, msg: &WsMessage, ctx: &mut ws::WebsocketContext<Self>) {
+        match self.format.encode(msg) {
+            Ok(EncodedFrame::Text(text)) => ctx.text(text),
+            Ok(EncodedFrame::Binary(bytes)) => ctx.binary(bytes),
+            Err(e) => log::error!("Failed to encode message for game {}: {}", self.game_id, e),
+        }
+    }
+
+    fn record_pong(&mut self, payload: &[u8]) {
+        let Ok(sent) = <[u8; 8]>::try_from(payload) else {
+            return;
+        };
+        let rtt_ms = now_ms().saturating_sub(u64::from_be_bytes(sent));
+        self.rtt.record(rtt_ms);
+        log::debug!(
+            "RTT for player {} in game {}: {}ms (smoothed {}ms)",
+            self.player_id,
+            self.game_id,
+            rtt_ms,
+            self.rtt.smoothed_ms
+        );
+    }
+
+    fn handle_client_message(&mut self, msg: WsClientMessage, ctx: &mut ws::WebsocketContext<Self>) {
+        match msg {
+            WsClientMessage::TimeSync { client_time_ms } => {
+                let server_receive_ms = now_ms();
+                let reply = WsMessage::TimeSync {
+                    client_time_ms,
+                    server_receive_ms,
+                    server_send_ms: now_ms(),
+                };
+                // Reply directly rather than through the lobby so queueing doesn't skew the sample
+                self.write(&reply, ctx);
+            }
+        }
+    }
 }
 
 impl Actor for WsSession {
@@ -127,12 +472,21 @@ impl Actor for WsSession {
 
     fn started(&mut self, ctx: &mut Self::Context) {
         self.hb(ctx);
+        ctx.set_mailbox_capacity(SESSION_MAILBOX_CAPACITY);
         let addr = ctx.address().recipient();
-        self.lobby.do_send(Connect { game_id: self.game_id.clone(), addr });
+        let evict = ctx.address().recipient();
+        self.lobby.do_send(Connect { game_id: self.game_id.clone(), addr, evict });
     }
 
     fn stopped(&mut self, ctx: &mut Self::Context) {
-        log::info!("WebSocket disconnected for game: {}", self.game_id);
+        log::info!(
+            "WebSocket disconnected for game: {} (player {}, rtt samples {}, smoothed {}ms, max {}ms)",
+            self.game_id,
+            self.player_id,
+            self.rtt.samples,
+            self.rtt.smoothed_ms,
+            self.rtt.max_ms
+        );
         let addr = ctx.address().recipient();
         self.lobby.do_send(Disconnect { game_id: self.game_id.clone(), addr });
     }
@@ -145,10 +499,19 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsSession {
                 self.hb = std::time::Instant::now();
                 ctx.pong(&msg);
             }
-            Ok(ws::Message::Pong(_)) => {
+            Ok(ws::Message::Pong(payload)) => {
                 self.hb = std::time::Instant::now();
+                self.record_pong(&payload);
+            }
+            Ok(frame @ (ws::Message::Text(_) | ws::Message::Binary(_))) => {
+                match self.format.decode(&frame) {
+                    Some(Ok(client_msg)) => self.handle_client_message(client_msg, ctx),
+                    Some(Err(e)) => {
+                        log::debug!("Ignoring malformed message for game {}: {}", self.game_id, e)
+                    }
+                    None => {}
+                }
             }
-            Ok(ws::Message::Text(_)) | Ok(ws::Message::Binary(_)) => {}
             Ok(ws::Message::Close(reason)) => {
                 ctx.close(reason);
                 ctx.stop();
@@ -162,16 +525,29 @@ impl Handler<WsMessage> for WsSession {
     type Result = ();
 
     fn handle(&mut self, msg: WsMessage, ctx: &mut ws::WebsocketContext<Self>) {
-        // Serialize message and inject version field
-        let mut val = serde_json::to_value(&msg).unwrap();
-        if let Value::Object(ref mut m) = val {
-            m.insert("version".into(), json!("1.0"));
-        }
-        let text = serde_json::to_string(&val).unwrap();
-        ctx.text(text);
+        self.write(&msg, ctx);
     }
 }
 
+impl Handler<Evict> for WsSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: Evict, ctx: &mut ws::WebsocketContext<Self>) {
+        ctx.close(Some(ws::CloseReason {
+            code: ws::CloseCode::Other(RESYNC_REQUIRED_CLOSE_CODE),
+            description: Some(format!("resync required: {}", msg.reason)),
+        }));
+        ctx.stop();
+    }
+}
+
+/// Broadcast an `End` event to a game's sockets. Called by the request
+/// that finished the game, with the rating changes it made.
+pub fn announce_end(lobby: &Addr<LobbyState>, game: &game::Model, rating_changes: Option<GameRatingChanges>) {
+    let message = WsMessage::end(game, rating_changes);
+    lobby.do_send(Broadcast { game_id: game.id.to_string(), message });
+}
+
 /// WebSocket route handler with auth
 pub async fn ws_route(
     req: HttpRequest,
@@ -180,7 +556,7 @@ pub async fn ws_route(
 ) -> Result<HttpResponse, Error> {
     // Validate JWT token from header
     let auth_header = req.headers().get("Authorization").and_then(|h| h.to_str().ok());
-    if let Some(header) = auth_header {
+    let player_id = if let Some(header) = auth_header {
         if !header.starts_with("Bearer ") {
             return Err(ErrorUnauthorized("Invalid authorization token format"));
         }
@@ -188,32 +564,57 @@ pub async fn ws_route(
         let secret = env::var("JWT_SECRET_KEY").unwrap_or_else(|_| "development_secret_key".to_string());
         let validation = Validation::new(Algorithm::HS256);
         decode::<Claims>(token, &DecodingKey::from_secret(secret.as_bytes()), &validation)
-            .map_err(|_| ErrorUnauthorized("Invalid or expired token"))?;
+            .map_err(|_| ErrorUnauthorized("Invalid or expired token"))?
+            .claims
+            .sub
     } else {
         return Err(ErrorUnauthorized("Missing authorization token"));
-    }
+    };
 
     let game_id = req.match_info().get("game_id").unwrap_or("").to_string();
-    ws::start(
-        WsSession { game_id, lobby: lobby.get_ref().clone(), hb: std::time::Instant::now() },
-        &req,
-        stream,
-    )
+    let format = WireFormat::negotiate(&req);
+    let session = WsSession {
+        game_id,
+        player_id,
+        lobby: lobby.get_ref().clone(),
+        hb: std::time::Instant::now(),
+        format,
+        rtt: RttStats::default(),
+    };
+    ws::WsResponseBuilder::new(session, &req, stream)
+        .protocols(WireFormat::PROTOCOLS)
+        .start()
 }
 
 // Unit tests for LobbyState and session
 #[cfg(test)]
 mod tests {
     use super::*;
-    use actix::prelude::*;
     use tokio::sync::mpsc::unbounded_channel;
+    use tokio::sync::oneshot;
 
     struct TestRecipient {
         tx: tokio::sync::mpsc::UnboundedSender<WsMessage>,
+        evicted: Option<tokio::sync::mpsc::UnboundedSender<String>>,
+        /// While set, the actor stops processing its mailbox until the sender fires
+        stall: Option<oneshot::Receiver<()>>,
+    }
+
+    impl TestRecipient {
+        fn new(tx: tokio::sync::mpsc::UnboundedSender<WsMessage>) -> Self {
+            TestRecipient { tx, evicted: None, stall: None }
+        }
     }
 
     impl Actor for TestRecipient {
         type Context = Context<Self>;
+
+        fn started(&mut self, ctx: &mut Context<Self>) {
+            if let Some(stall) = self.stall.take() {
+                ctx.set_mailbox_capacity(1);
+                ctx.wait(async move { let _ = stall.await; }.into_actor(self));
+            }
+        }
     }
 
     impl Handler<WsMessage> for TestRecipient {
@@ -224,21 +625,248 @@ mod tests {
         }
     }
 
+    impl Handler<Evict> for TestRecipient {
+        type Result = ();
+
+        fn handle(&mut self, msg: Evict, _: &mut Context<Self>) {
+            if let Some(evicted) = &self.evicted {
+                let _ = evicted.send(msg.reason);
+            }
+        }
+    }
+
+    fn connect(game_id: &str, addr: &Addr<TestRecipient>) -> Connect {
+        Connect {
+            game_id: game_id.to_string(),
+            addr: addr.clone().recipient(),
+            evict: addr.clone().recipient(),
+        }
+    }
+
+    fn move_msg(san: &str) -> WsMessage {
+        WsMessage::Move { from: String::new(), to: String::new(), san: san.to_string(), fen: String::new() }
+    }
+
     #[actix_web::test]
     async fn test_broadcast_to_two_clients() {
         let lobby = LobbyState::new().start();
         let (tx1, mut rx1) = unbounded_channel();
         let (tx2, mut rx2) = unbounded_channel();
-        let recipient1 = TestRecipient { tx: tx1 }.start().recipient();
-        let recipient2 = TestRecipient { tx: tx2 }.start().recipient();
+        let recipient1 = TestRecipient::new(tx1).start();
+        let recipient2 = TestRecipient::new(tx2).start();
         let game_id = "game123".to_string();
-        lobby.send(Connect { game_id: game_id.clone(), addr: recipient1.clone() }).await.unwrap();
-        lobby.send(Connect { game_id: game_id.clone(), addr: recipient2.clone() }).await.unwrap();
-        let msg = WsMessage::Clock { white: 60, black: 60 };
+        lobby.send(connect(&game_id, &recipient1)).await.unwrap();
+        lobby.send(connect(&game_id, &recipient2)).await.unwrap();
+        let msg = WsMessage::clock(60, 60);
         lobby.send(Broadcast { game_id: game_id.clone(), message: msg.clone() }).await.unwrap();
         let received1 = rx1.recv().await.unwrap();
         let received2 = rx2.recv().await.unwrap();
         assert_eq!(received1, msg);
         assert_eq!(received2, msg);
     }
+
+    #[test]
+    fn test_negotiate_prefers_first_supported_protocol() {
+        let req = actix_web::test::TestRequest::default()
+            .insert_header((SEC_WEBSOCKET_PROTOCOL, "graphql-ws, xlmate.msgpack.v1, xlmate.json.v1"))
+            .to_http_request();
+        assert_eq!(WireFormat::negotiate(&req), WireFormat::MessagePack);
+
+        let req = actix_web::test::TestRequest::default()
+            .insert_header((SEC_WEBSOCKET_PROTOCOL, "xlmate.json.v1"))
+            .to_http_request();
+        assert_eq!(WireFormat::negotiate(&req), WireFormat::Json);
+
+        let req = actix_web::test::TestRequest::default().to_http_request();
+        assert_eq!(WireFormat::negotiate(&req), WireFormat::Json);
+    }
+
+    #[test]
+    fn test_msgpack_encoding_is_smaller_and_decodes() {
+        let msg = WsMessage::Move {
+            from: "e2".into(),
+            to: "e4".into(),
+            san: "e4".into(),
+            fen: "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1".into(),
+        };
+        let EncodedFrame::Text(text) = WireFormat::Json.encode(&msg).unwrap() else {
+            panic!("JSON must be sent as a text frame");
+        };
+        let EncodedFrame::Binary(bytes) = WireFormat::MessagePack.encode(&msg).unwrap() else {
+            panic!("MessagePack must be sent as a binary frame");
+        };
+        assert!(bytes.len() < text.len());
+
+        let decoded: Value = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(decoded["type"], "Move");
+        assert_eq!(decoded["payload"]["san"], "e4");
+        assert!(decoded.get("version").is_none());
+        let json: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(json["version"], "1.0");
+    }
+
+    #[test]
+    fn test_decode_time_sync_ping() {
+        let frame = ws::Message::Text(r#"{"type":"TimeSync","payload":{"client_time_ms":1700000000000}}"#.into());
+        let decoded = WireFormat::Json.decode(&frame).unwrap().unwrap();
+        assert_eq!(decoded, WsClientMessage::TimeSync { client_time_ms: 1_700_000_000_000 });
+
+        // Binary frames are only understood on MessagePack sessions
+        let bytes = rmp_serde::to_vec_named(&serde_json::json!({
+            "type": "TimeSync",
+            "payload": { "client_time_ms": 42 }
+        }))
+        .unwrap();
+        let frame = ws::Message::Binary(bytes.into());
+        assert_eq!(
+            WireFormat::MessagePack.decode(&frame).unwrap().unwrap(),
+            WsClientMessage::TimeSync { client_time_ms: 42 }
+        );
+        assert!(WireFormat::Json.decode(&frame).is_none());
+    }
+
+    #[test]
+    fn test_clock_carries_server_time() {
+        let before = now_ms();
+        let WsMessage::Clock { server_time_ms, .. } = WsMessage::clock(30, 45) else {
+            panic!("expected a clock message");
+        };
+        assert!(server_time_ms >= before && server_time_ms <= now_ms());
+    }
+
+    #[test]
+    fn test_rtt_stats_smoothing() {
+        let mut rtt = RttStats::default();
+        rtt.record(80);
+        assert_eq!(rtt.smoothed_ms, 80);
+        rtt.record(160);
+        assert_eq!(rtt.smoothed_ms, 90);
+        assert_eq!(rtt.last_ms, 160);
+        assert_eq!(rtt.max_ms, 160);
+        assert_eq!(rtt.samples, 2);
+    }
+
+    #[test]
+    fn test_session_queue_coalesces_clock_updates() {
+        let (tx, _rx) = unbounded_channel();
+        let system = System::new();
+        system.block_on(async {
+            let addr = TestRecipient::new(tx).start();
+            let mut queue = SessionQueue::new(addr.recipient());
+            let latest = WsMessage::clock(58, 60);
+            assert!(!queue.push(WsMessage::clock(60, 60)));
+            assert!(!queue.push(move_msg("e4")));
+            assert!(queue.push(latest.clone()));
+            assert_eq!(queue.pending.iter().cloned().collect::<Vec<_>>(), vec![move_msg("e4"), latest]);
+        });
+    }
+
+    #[actix_web::test]
+    async fn test_slow_client_receives_every_move_in_order() {
+        let lobby = LobbyState::new().start();
+        let (tx, mut rx) = unbounded_channel();
+        let (release, stall) = oneshot::channel();
+        let recipient = TestRecipient { tx, evicted: None, stall: Some(stall) }.start();
+        lobby.send(connect("slow", &recipient)).await.unwrap();
+
+        let sans = ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"];
+        for (i, san) in sans.iter().enumerate() {
+            lobby.send(Broadcast { game_id: "slow".into(), message: move_msg(san) }).await.unwrap();
+            let clock = WsMessage::clock(60 - i as u32, 60);
+            lobby.send(Broadcast { game_id: "slow".into(), message: clock }).await.unwrap();
+        }
+        let metrics = lobby.send(GetLobbyMetrics).await.unwrap();
+        assert!(metrics.queue_full_events > 0);
+        assert!(metrics.coalesced_clock_updates > 0);
+
+        release.send(()).unwrap();
+        let mut received_moves = Vec::new();
+        loop {
+            match rx.recv().await.unwrap() {
+                WsMessage::Move { san, .. } => received_moves.push(san),
+                WsMessage::Clock { white: 55, .. } => break,
+                _ => {}
+            }
+        }
+        assert_eq!(received_moves, sans);
+    }
+
+    #[actix_web::test]
+    async fn test_overflowing_client_is_evicted() {
+        let lobby = LobbyState::new().start();
+        let (tx, _rx) = unbounded_channel();
+        let (evicted_tx, mut evicted_rx) = unbounded_channel();
+        let (release, stall) = oneshot::channel();
+        let recipient =
+            TestRecipient { tx, evicted: Some(evicted_tx), stall: Some(stall) }.start();
+        lobby.send(connect("overflow", &recipient)).await.unwrap();
+
+        for i in 0..OUTBOUND_QUEUE_CAPACITY + 4 {
+            let message = move_msg(&format!("move{}", i));
+            lobby.send(Broadcast { game_id: "overflow".into(), message }).await.unwrap();
+        }
+        let metrics = lobby.send(GetLobbyMetrics).await.unwrap();
+        assert_eq!(metrics.evicted_sessions, 1);
+
+        release.send(()).unwrap();
+        let reason = evicted_rx.recv().await.unwrap();
+        assert_eq!(reason, "outbound queue overflow");
+    }
+
+    #[actix_web::test]
+    async fn test_game_end_is_announced_with_rating_changes() {
+        use chrono::Utc;
+        use db_entity::{game::{GameStatus, GameVariant}, player_rating::RatingCategory};
+        use service::ratings::RatingChange;
+        use uuid::Uuid;
+
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let now = Utc::now().fixed_offset();
+        let game = game::Model {
+            id: Uuid::new_v4(),
+            white_player: Some(white),
+            black_player: Some(black),
+            fen: "final-fen".to_string(),
+            pgn: json!({ "moves": ["f3", "e5", "g4", "Qh4#"], "final_ply": 4 }),
+            result: Some(ResultSide::BlackWins),
+            variant: GameVariant::Standard,
+            started_at: now,
+            duration_sec: 600,
+            created_at: now,
+            updated_at: now,
+            is_imported: false,
+            original_pgn: None,
+            import_hash: None,
+            position_keys: vec![],
+            status: GameStatus::Completed,
+            initial_time_ms: 180_000,
+            increment_ms: 2_000,
+            rated: true,
+            termination: Some("checkmate".to_string()),
+            last_move_at: Some(now),
+            moves_encoded: None,
+            initial_fen: "start-fen".to_string(),
+            version: 5,
+        };
+        let change = |player_id, rating, rating_change| RatingChange {
+            player_id,
+            category: RatingCategory::Blitz,
+            rating,
+            rating_change,
+        };
+        let changes = GameRatingChanges { white: change(white, 1488, -12), black: change(black, 1612, 12) };
+
+        let lobby = LobbyState::new().start();
+        let (tx, mut rx) = unbounded_channel();
+        let recipient = TestRecipient::new(tx).start();
+        lobby.send(connect(&game.id.to_string(), &recipient)).await.unwrap();
+        announce_end(&lobby, &game, Some(changes));
+
+        let WsMessage::End { result, final_fen, rating_changes: Some(changes) } = rx.recv().await.unwrap() else {
+            panic!("expected End with rating changes");
+        };
+        assert_eq!((result.as_str(), final_fen.as_str()), ("black_win", "final-fen"));
+        assert_eq!((changes.white.player_id, changes.white.rating_change), (white, -12));
+        assert_eq!((changes.black.rating, changes.black.rating_change), (1612, 12));
+    }
 }
@@ -0,0 +1,106 @@
+//! Chess960 Starting Positions
+//!
+//! Positions are numbered 0-959 using the standard (Scharnagl) scheme, in
+//! which position 518 is the regular chess setup.
+
+use crate::moves::MoveError;
+
+/// Number of Chess960 starting positions
+pub const CHESS960_POSITIONS: u16 = 960;
+
+/// Knight placements over the five squares left after bishops and queen
+const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [
+    (0, 1), (0, 2), (0, 3), (0, 4), (1, 2),
+    (1, 3), (1, 4), (2, 3), (2, 4), (3, 4),
+];
+
+/// White's back rank for a Chess960 position, from the a-file to the h-file
+pub fn chess960_back_rank(number: u16) -> Result<[char; 8], MoveError> {
+    if number >= CHESS960_POSITIONS {
+        return Err(MoveError::InvalidPosition(format!(
+            "Chess960 position must be below {}, got {}",
+            CHESS960_POSITIONS, number
+        )));
+    }
+
+    let mut rank = [' '; 8];
+    let n = number as usize;
+
+    // Light-squared bishop on b/d/f/h, dark-squared bishop on a/c/e/g
+    rank[2 * (n % 4) + 1] = 'B';
+    let n = n / 4;
+    rank[2 * (n % 4)] = 'B';
+    let n = n / 4;
+
+    place_on_empty(&mut rank, n % 6, 'Q');
+
+    // Place the second knight first so the first one's index is unchanged
+    let (first, second) = KNIGHT_PLACEMENTS[n / 6];
+    place_on_empty(&mut rank, second, 'N');
+    place_on_empty(&mut rank, first, 'N');
+
+    // The king always stands between the rooks
+    for piece in ['R', 'K', 'R'] {
+        place_on_empty(&mut rank, 0, piece);
+    }
+
+    Ok(rank)
+}
+
+/// FEN of a Chess960 starting position
+///
+/// Castling rights are written as `KQkq`, which refers to the outermost
+/// rooks and therefore identifies the castling rooks unambiguously.
+pub fn chess960_fen(number: u16) -> Result<String, MoveError> {
+    let white: String = chess960_back_rank(number)?.iter().collect();
+    Ok(format!(
+        "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1",
+        white.to_lowercase(),
+        white
+    ))
+}
+
+/// Put `piece` on the `index`-th empty square of the rank
+fn place_on_empty(rank: &mut [char; 8], index: usize, piece: char) {
+    if let Some(square) = rank.iter_mut().filter(|c| **c == ' ').nth(index) {
+        *square = piece;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::{legal_moves, play_uci, STARTING_FEN};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_numbering_matches_reference_positions() {
+        assert_eq!(chess960_fen(518).unwrap(), STARTING_FEN);
+        assert_eq!(chess960_back_rank(0).unwrap().iter().collect::<String>(), "BBQNNRKR");
+        assert_eq!(chess960_back_rank(959).unwrap().iter().collect::<String>(), "RKRNNQBB");
+        assert!(chess960_back_rank(960).is_err());
+
+        let distinct: HashSet<[char; 8]> = (0..CHESS960_POSITIONS)
+            .map(|n| chess960_back_rank(n).unwrap())
+            .collect();
+        assert_eq!(distinct.len(), 960);
+    }
+
+    #[test]
+    fn test_chess960_positions_are_playable() {
+        let fen = chess960_fen(0).unwrap();
+        assert_eq!(legal_moves(&fen).unwrap().len(), 20);
+        assert!(play_uci(&fen, "d1c3").is_ok());
+    }
+
+    #[test]
+    fn test_chess960_castling_is_king_takes_rook() {
+        // King on b1 with its queenside rook on a1 castles onto c1/d1
+        let fen = "4k3/8/8/8/8/8/8/RK6 w Q - 0 1";
+        assert!(legal_moves(fen).unwrap().contains(&"b1a1".to_string()));
+
+        let played = play_uci(fen, "b1a1").unwrap();
+        assert_eq!(played.san, "O-O-O");
+        assert_eq!(played.fen, "4k3/8/8/8/8/8/8/2KR4 b - - 1 1");
+    }
+}
@@ -0,0 +1,231 @@
+//! Compact Move Encoding
+//!
+//! Stores a game's moves as indexes into each position's legal move list.
+//! Legal moves are ordered by origin square, target square and promotion
+//! piece, and every index is written with just enough bits to address that
+//! many moves: forced moves cost nothing and a typical middlegame move takes
+//! five or six bits, against roughly 60 bytes for a SAN string plus FEN.
+//!
+//! Layout: a format version byte, the ply count as a LEB128 varint, then the
+//! bit-packed indexes, most significant bit first.
+
+use shakmaty::{
+    san::{San, SanPlus},
+    Chess, Move, Position,
+};
+
+use crate::moves::{position_from_fen, MoveError};
+
+/// Format version written as the first byte of every encoding
+pub const ENCODING_VERSION: u8 = 1;
+
+/// Encode SAN moves played from `start_fen`.
+pub fn encode_moves<S: AsRef<str>>(start_fen: &str, moves: &[S]) -> Result<Vec<u8>, MoveError> {
+    let mut position = position_from_fen(start_fen)?;
+
+    let mut out = vec![ENCODING_VERSION];
+    write_varint(&mut out, moves.len() as u64);
+    let mut bits = BitWriter::new(out);
+
+    for san_text in moves {
+        let san_text = san_text.as_ref();
+        let san: San = san_text
+            .parse::<SanPlus>()
+            .map(|plus| plus.san)
+            .map_err(|_| MoveError::InvalidNotation(san_text.to_string()))?;
+        let chess_move = san
+            .to_move(&position)
+            .map_err(|_| MoveError::IllegalMove(san_text.to_string()))?;
+
+        let legal = ordered_legal_moves(&position);
+        let index = legal
+            .iter()
+            .position(|m| *m == chess_move)
+            .ok_or_else(|| MoveError::IllegalMove(san_text.to_string()))?;
+        bits.write(index as u32, index_width(legal.len()));
+
+        position.play_unchecked(&chess_move);
+    }
+
+    Ok(bits.finish())
+}
+
+/// Decode moves produced by `encode_moves` back into SAN, starting from
+/// the same position.
+pub fn decode_moves(start_fen: &str, encoded: &[u8]) -> Result<Vec<String>, MoveError> {
+    let mut position = position_from_fen(start_fen)?;
+
+    let (&version, rest) = encoded
+        .split_first()
+        .ok_or_else(|| MoveError::InvalidEncoding("empty input".to_string()))?;
+    if version != ENCODING_VERSION {
+        return Err(MoveError::InvalidEncoding(format!("unsupported version {}", version)));
+    }
+    let (ply_count, header_len) = read_varint(rest)?;
+    let mut bits = BitReader::new(&rest[header_len..]);
+
+    let mut moves = Vec::with_capacity(ply_count.min(1024) as usize);
+    for ply in 0..ply_count {
+        let legal = ordered_legal_moves(&position);
+        let index = bits
+            .read(index_width(legal.len()))
+            .ok_or_else(|| MoveError::InvalidEncoding(format!("truncated at ply {}", ply + 1)))?;
+        let chess_move = legal
+            .get(index as usize)
+            .ok_or_else(|| MoveError::InvalidEncoding(format!("bad move index at ply {}", ply + 1)))?
+            .clone();
+        moves.push(SanPlus::from_move_and_play_unchecked(&mut position, &chess_move).to_string());
+    }
+
+    Ok(moves)
+}
+
+/// Legal moves in a stable order that does not depend on move generation
+fn ordered_legal_moves(position: &Chess) -> Vec<Move> {
+    let mut legal: Vec<Move> = position.legal_moves().into_iter().collect();
+    legal.sort_by_key(|m| {
+        (
+            m.from().map_or(u8::MAX, u8::from),
+            u8::from(m.to()),
+            m.promotion().map_or(0, |role| role as u8),
+        )
+    });
+    legal
+}
+
+/// Bits needed to address one of `count` moves
+fn index_width(count: usize) -> u32 {
+    match count {
+        0 | 1 => 0,
+        n => usize::BITS - (n - 1).leading_zeros(),
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(input: &[u8]) -> Result<(u64, usize), MoveError> {
+    let mut value = 0u64;
+    for (idx, byte) in input.iter().enumerate().take(10) {
+        value |= u64::from(byte & 0x7f) << (7 * idx);
+        if byte & 0x80 == 0 {
+            return Ok((value, idx + 1));
+        }
+    }
+    Err(MoveError::InvalidEncoding("bad ply count".to_string()))
+}
+
+struct BitWriter {
+    out: Vec<u8>,
+    current: u8,
+    used: u32,
+}
+
+impl BitWriter {
+    fn new(out: Vec<u8>) -> Self {
+        Self { out, current: 0, used: 0 }
+    }
+
+    fn write(&mut self, value: u32, width: u32) {
+        for shift in (0..width).rev() {
+            self.current = (self.current << 1) | ((value >> shift) & 1) as u8;
+            self.used += 1;
+            if self.used == 8 {
+                self.out.push(self.current);
+                self.current = 0;
+                self.used = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.used > 0 {
+            self.out.push(self.current << (8 - self.used));
+        }
+        self.out
+    }
+}
+
+struct BitReader<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        Self { input, position: 0 }
+    }
+
+    fn read(&mut self, width: u32) -> Option<u32> {
+        let mut value = 0u32;
+        for _ in 0..width {
+            let byte = *self.input.get(self.position / 8)?;
+            let bit = (byte >> (7 - self.position % 8)) & 1;
+            value = (value << 1) | u32::from(bit);
+            self.position += 1;
+        }
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::STARTING_FEN;
+
+    fn sans(moves: &str) -> Vec<String> {
+        moves.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_round_trip_with_castling_promotion_and_mate() {
+        let game = sans(
+            "e4 e5 Nf3 Nc6 Bc4 Nf6 O-O Be7 d4 exd4 e5 Ne4 Re1 d5 exd6 Nxd6 Bxf7+ Nxf7 Qxd4 O-O",
+        );
+        let encoded = encode_moves(STARTING_FEN, &game).unwrap();
+        assert_eq!(encoded[0], ENCODING_VERSION);
+        assert!(encoded.len() < 20);
+        assert_eq!(decode_moves(STARTING_FEN, &encoded).unwrap(), game);
+
+        let promotion_fen = "8/P6k/8/8/8/8/8/K7 w - - 0 1";
+        let promotion = sans("a8=Q Kg6 Qb8");
+        let encoded = encode_moves(promotion_fen, &promotion).unwrap();
+        assert_eq!(decode_moves(promotion_fen, &encoded).unwrap(), promotion);
+
+        let mate = sans("f3 e5 g4 Qh4#");
+        let encoded = encode_moves(STARTING_FEN, &mate).unwrap();
+        assert_eq!(decode_moves(STARTING_FEN, &encoded).unwrap(), mate);
+    }
+
+    #[test]
+    fn test_empty_game_and_forced_moves() {
+        let encoded = encode_moves::<&str>(STARTING_FEN, &[]).unwrap();
+        assert_eq!(encoded, vec![ENCODING_VERSION, 0]);
+        assert!(decode_moves(STARTING_FEN, &encoded).unwrap().is_empty());
+
+        // Black's only legal reply takes no bits
+        assert_eq!(index_width(1), 0);
+        assert_eq!(index_width(2), 1);
+        assert_eq!(index_width(20), 5);
+        assert_eq!(index_width(218), 8);
+    }
+
+    #[test]
+    fn test_rejects_illegal_moves_and_corrupt_input() {
+        assert_eq!(
+            encode_moves(STARTING_FEN, &sans("e4 e4")),
+            Err(MoveError::IllegalMove("e4".to_string()))
+        );
+        assert!(matches!(decode_moves(STARTING_FEN, &[]), Err(MoveError::InvalidEncoding(_))));
+        assert!(matches!(decode_moves(STARTING_FEN, &[9, 0]), Err(MoveError::InvalidEncoding(_))));
+
+        let mut encoded = encode_moves(STARTING_FEN, &sans("e4 e5 Nf3")).unwrap();
+        encoded.truncate(encoded.len() - 1);
+        assert!(matches!(decode_moves(STARTING_FEN, &encoded), Err(MoveError::InvalidEncoding(_))));
+    }
+}
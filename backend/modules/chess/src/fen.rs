@@ -0,0 +1,253 @@
+//! FEN Validation Module
+//!
+//! Checks that a FEN string is well formed and describes a position that can
+//! arise in a real game, reporting exactly which rule is broken. Engines may
+//! hang or crash on impossible positions, so everything that accepts a FEN
+//! from a client should go through here.
+
+use shakmaty::{
+    fen::{Fen, ParseFenError},
+    Bitboard, Board, CastlingMode, Chess, Color, PositionError, PositionErrorKinds, Role,
+};
+use thiserror::Error;
+
+/// Reasons a FEN string is rejected
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum FenError {
+    #[error("FEN must have 6 space-separated fields, found {0}")]
+    FieldCount(usize),
+
+    #[error("Invalid piece placement: {0}")]
+    Board(String),
+
+    #[error("Side to move must be 'w' or 'b', found '{0}'")]
+    Turn(String),
+
+    #[error("Invalid castling field '{0}'")]
+    Castling(String),
+
+    #[error("Invalid en passant field '{0}'")]
+    EnPassant(String),
+
+    #[error("Halfmove clock must be a non-negative integer, found '{0}'")]
+    HalfmoveClock(String),
+
+    #[error("Fullmove number must be a positive integer, found '{0}'")]
+    FullmoveNumber(String),
+
+    #[error("Illegal position: {}", .0.join("; "))]
+    IllegalPosition(Vec<String>),
+}
+
+/// Validate a FEN string without keeping the parsed position
+pub fn validate_fen(fen: &str) -> Result<(), FenError> {
+    parse_position(fen).map(|_| ())
+}
+
+/// Parse a FEN string into a legal position
+///
+/// Chess960 castling rights are recognised from the king and rook
+/// placement, so the same call handles standard and Chess960 games.
+pub(crate) fn parse_position(fen: &str) -> Result<Chess, FenError> {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    if fields.len() != 6 {
+        return Err(FenError::FieldCount(fields.len()));
+    }
+    check_board(fields[0])?;
+
+    let parsed: Fen = fen.parse().map_err(|e| match e {
+        ParseFenError::InvalidTurn => FenError::Turn(fields[1].to_string()),
+        ParseFenError::InvalidCastling => FenError::Castling(fields[2].to_string()),
+        ParseFenError::InvalidEpSquare => FenError::EnPassant(fields[3].to_string()),
+        ParseFenError::InvalidHalfmoveClock => FenError::HalfmoveClock(fields[4].to_string()),
+        ParseFenError::InvalidFullmoves => FenError::FullmoveNumber(fields[5].to_string()),
+        other => FenError::Board(other.to_string()),
+    })?;
+
+    let mode = CastlingMode::detect(parsed.as_setup());
+    let board = parsed.as_setup().board.clone();
+    parsed
+        .into_position(mode)
+        .map_err(|e: PositionError<Chess>| FenError::IllegalPosition(position_problems(&board, e.kinds())))
+}
+
+/// Check the piece placement field rank by rank, so the message can point
+/// at the broken rank
+fn check_board(placement: &str) -> Result<(), FenError> {
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::Board(format!("expected 8 ranks, found {}", ranks.len())));
+    }
+
+    for (idx, rank) in ranks.iter().enumerate() {
+        let rank_number = 8 - idx;
+        let mut squares = 0;
+        for c in rank.chars() {
+            match c {
+                '1'..='8' => squares += c.to_digit(10).unwrap_or(0),
+                'p' | 'n' | 'b' | 'r' | 'q' | 'k' | 'P' | 'N' | 'B' | 'R' | 'Q' | 'K' => squares += 1,
+                other => {
+                    return Err(FenError::Board(format!(
+                        "unexpected character '{}' on rank {}",
+                        other, rank_number
+                    )))
+                }
+            }
+        }
+        if squares != 8 {
+            return Err(FenError::Board(format!(
+                "rank {} covers {} squares instead of 8",
+                rank_number, squares
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Describe every rule the position breaks
+fn position_problems(board: &Board, kinds: PositionErrorKinds) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if kinds.contains(PositionErrorKinds::EMPTY_BOARD) {
+        problems.push("the board is empty".to_string());
+    }
+    if kinds.intersects(PositionErrorKinds::MISSING_KING | PositionErrorKinds::TOO_MANY_KINGS) {
+        for color in Color::ALL {
+            let kings = (board.by_color(color) & board.kings()).count();
+            if kings != 1 {
+                problems.push(format!("{} has {} kings instead of 1", color_name(color), kings));
+            }
+        }
+    }
+    if kinds.contains(PositionErrorKinds::PAWNS_ON_BACKRANK) {
+        problems.push("pawns cannot stand on the first or eighth rank".to_string());
+    }
+    if kinds.contains(PositionErrorKinds::TOO_MUCH_MATERIAL) {
+        for color in Color::ALL {
+            if let Some(problem) = material_problem(board, color) {
+                problems.push(problem);
+            }
+        }
+    }
+    if kinds.contains(PositionErrorKinds::INVALID_CASTLING_RIGHTS) {
+        problems.push("castling rights do not match the king and rook positions".to_string());
+    }
+    if kinds.contains(PositionErrorKinds::INVALID_EP_SQUARE) {
+        problems.push("the en passant square does not follow a double pawn push".to_string());
+    }
+    if kinds.contains(PositionErrorKinds::OPPOSITE_CHECK) {
+        problems.push("the side not to move is in check".to_string());
+    }
+    if kinds.contains(PositionErrorKinds::IMPOSSIBLE_CHECK) {
+        problems.push("the check cannot have been given by a legal move".to_string());
+    }
+    if problems.is_empty() {
+        problems.push("the position cannot arise in a game".to_string());
+    }
+    problems
+}
+
+/// Explain why one side has more material than promotions allow
+fn material_problem(board: &Board, color: Color) -> Option<String> {
+    let side = board.by_color(color);
+    let count = |role: Role| (side & board.by_role(role)).count();
+    let bishops = side & board.bishops();
+    let light_bishops = (bishops & Bitboard::LIGHT_SQUARES).count();
+    let dark_bishops = (bishops & Bitboard::DARK_SQUARES).count();
+
+    let pawns = count(Role::Pawn);
+    let pieces = side.count();
+    // Pieces beyond the starting set must have been promoted from pawns
+    let promoted = count(Role::Queen).saturating_sub(1)
+        + count(Role::Rook).saturating_sub(2)
+        + count(Role::Knight).saturating_sub(2)
+        + light_bishops.saturating_sub(1)
+        + dark_bishops.saturating_sub(1);
+
+    let name = color_name(color);
+    if pawns > 8 {
+        Some(format!("{} has {} pawns, at most 8 are possible", name, pawns))
+    } else if pieces > 16 {
+        Some(format!("{} has {} pieces, at most 16 are possible", name, pieces))
+    } else if promoted + pawns > 8 {
+        Some(format!(
+            "{} has {} promoted pieces but is missing only {} pawns",
+            name,
+            promoted,
+            8 - pawns
+        ))
+    } else {
+        None
+    }
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "White",
+        Color::Black => "Black",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::STARTING_FEN;
+
+    fn problems(fen: &str) -> Vec<String> {
+        match validate_fen(fen) {
+            Err(FenError::IllegalPosition(problems)) => problems,
+            other => panic!("expected an illegal position, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_accepts_legal_positions() {
+        assert_eq!(validate_fen(STARTING_FEN), Ok(()));
+        assert_eq!(validate_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"), Ok(()));
+        // Chess960 start with outer-rook castling rights
+        assert_eq!(validate_fen("bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"), Ok(()));
+    }
+
+    #[test]
+    fn test_rejects_malformed_fields() {
+        assert_eq!(validate_fen("8/8/8/8/8/8/8/8 w - -"), Err(FenError::FieldCount(4)));
+        assert_eq!(
+            validate_fen("rnbqkbnr/pppppppp/54/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            Err(FenError::Board("rank 6 covers 9 squares instead of 8".to_string()))
+        );
+        assert_eq!(
+            validate_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1"),
+            Err(FenError::Board("unexpected character 'X' on rank 1".to_string()))
+        );
+        assert_eq!(
+            validate_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1"),
+            Err(FenError::Turn("x".to_string()))
+        );
+        assert_eq!(
+            validate_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - -1 1"),
+            Err(FenError::HalfmoveClock("-1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_rejects_impossible_positions_with_reasons() {
+        assert_eq!(
+            problems("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/QQQQQQQK w - - 0 1"),
+            vec!["White has 6 promoted pieces but is missing only 0 pawns"]
+        );
+        assert_eq!(
+            problems("4k3/8/8/8/8/8/8/P3K3 w - - 0 1"),
+            vec!["pawns cannot stand on the first or eighth rank"]
+        );
+        assert_eq!(problems("4k3/8/8/8/8/8/8/8 w - - 0 1"), vec!["White has 0 kings instead of 1"]);
+        assert_eq!(problems("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1"), vec!["the side not to move is in check"]);
+        assert_eq!(
+            problems("4k3/8/8/8/8/8/8/4K3 w K - 0 1"),
+            vec!["castling rights do not match the king and rook positions"]
+        );
+        assert_eq!(
+            problems("4k3/8/8/8/8/8/8/4K3 w - e6 0 1"),
+            vec!["the en passant square does not follow a double pawn push"]
+        );
+    }
+}
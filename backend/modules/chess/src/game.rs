@@ -1,7 +1,31 @@
 use crate::time_control::{TimeControl, PlayerClock};
-use crate::db::schema::time_controls; // Assuming a database interaction library is used
-use diesel::prelude::*;
+use std::future::Future;
 use std::time::Duration;
+use uuid::Uuid;
+
+/// Clock state of a game as kept between sessions, in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSnapshot {
+    pub white_remaining_ms: u64,
+    pub black_remaining_ms: u64,
+    pub initial_time_ms: u64,
+    pub increment_ms: u64,
+    pub delay_ms: u64,
+}
+
+/// Storage for clock state.
+///
+/// Implemented outside this crate (see the service crate's SeaORM
+/// repository) so `Game` stays free of database code.
+pub trait ClockRepository {
+    type Error;
+
+    /// Store the clocks of a game, replacing any saved earlier
+    fn save_clocks(&self, game_id: Uuid, clocks: &ClockSnapshot) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Saved clocks of a game, if any
+    fn load_clocks(&self, game_id: Uuid) -> impl Future<Output = Result<Option<ClockSnapshot>, Self::Error>> + Send;
+}
 
 pub struct Game {
     // other fields
@@ -20,42 +44,40 @@ impl Game {
         }
     }
 
-    pub fn save_clocks_to_db(&self, conn: &PgConnection, game_id: uuid::Uuid) -> QueryResult<()> {
-        use diesel::dsl::insert_into;
-        let white_ms = self.white_clock.get_real_time_remaining().as_millis() as i64;
-        let black_ms = self.black_clock.get_real_time_remaining().as_millis() as i64;
-
-        insert_into(time_controls::table)
-            .values((
-                time_controls::game_id.eq(game_id),
-                time_controls::white_remaining_time.eq(white_ms),
-                time_controls::black_remaining_time.eq(black_ms),
-                time_controls::initial_time.eq(self.time_control.initial_time.as_millis() as i64),
-                time_controls::increment.eq(self.time_control.increment.as_millis() as i64),
-                time_controls::delay.eq(self.time_control.delay.as_millis() as i64),
-            ))
-            .on_conflict(time_controls::game_id)
-            .do_update()
-            .set((
-                time_controls::white_remaining_time.eq(white_ms),
-                time_controls::black_remaining_time.eq(black_ms),
-            ))
-            .execute(conn)?;
-        Ok(())
+    /// Current clock state, counting time on a running clock up to now
+    pub fn clock_snapshot(&self) -> ClockSnapshot {
+        ClockSnapshot {
+            white_remaining_ms: self.white_clock.get_real_time_remaining().as_millis() as u64,
+            black_remaining_ms: self.black_clock.get_real_time_remaining().as_millis() as u64,
+            initial_time_ms: self.time_control.initial_time.as_millis() as u64,
+            increment_ms: self.time_control.increment.as_millis() as u64,
+            delay_ms: self.time_control.delay.as_millis() as u64,
+        }
     }
 
-    pub fn load_clocks_from_db(&mut self, conn: &PgConnection, game_id: uuid::Uuid) -> QueryResult<()> {
-        use time_controls::{white_remaining_time, black_remaining_time};
-        let (white_ms, black_ms): (i64, i64) = time_controls::table
-            .select((white_remaining_time, black_remaining_time))
-            .find(game_id)
-            .first(conn)?;
-
+    /// Set both clocks from a snapshot. They are left stopped; the caller
+    /// starts the clock of the side to move when play resumes.
+    pub fn restore_clocks(&mut self, snapshot: &ClockSnapshot) {
         self.white_clock
-            .set_remaining_time(Duration::from_millis(white_ms as u64));
+            .set_remaining_time(Duration::from_millis(snapshot.white_remaining_ms));
         self.black_clock
-            .set_remaining_time(Duration::from_millis(black_ms as u64));
-        Ok(())
+            .set_remaining_time(Duration::from_millis(snapshot.black_remaining_ms));
+    }
+
+    pub async fn save_clocks<R: ClockRepository>(&self, repo: &R, game_id: Uuid) -> Result<(), R::Error> {
+        repo.save_clocks(game_id, &self.clock_snapshot()).await
+    }
+
+    /// Restore the clocks saved for `game_id`. Returns false, leaving the
+    /// clocks untouched, if none were saved.
+    pub async fn load_clocks<R: ClockRepository>(&mut self, repo: &R, game_id: Uuid) -> Result<bool, R::Error> {
+        match repo.load_clocks(game_id).await? {
+            Some(snapshot) => {
+                self.restore_clocks(&snapshot);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
     }
 
     pub fn handle_move(&mut self, is_white: bool) {
@@ -86,4 +108,38 @@ impl Game {
             None
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_snapshot_round_trip() {
+        let time_control = TimeControl {
+            initial_time: Duration::from_secs(300),
+            increment: Duration::from_secs(2),
+            delay: Duration::ZERO,
+        };
+        let mut game = Game::new(time_control.clone());
+        game.white_clock.set_remaining_time(Duration::from_millis(250_500));
+
+        let snapshot = game.clock_snapshot();
+        assert_eq!(
+            snapshot,
+            ClockSnapshot {
+                white_remaining_ms: 250_500,
+                black_remaining_ms: 300_000,
+                initial_time_ms: 300_000,
+                increment_ms: 2_000,
+                delay_ms: 0,
+            }
+        );
+
+        let mut restored = Game::new(time_control);
+        restored.black_clock.start();
+        restored.restore_clocks(&snapshot);
+        assert_eq!(restored.clock_snapshot(), snapshot);
+        assert!(!restored.black_clock.is_running);
+    }
+}
@@ -1,6 +1,16 @@
 pub mod bitboard;
 pub mod time_control;
+pub mod game;
 pub mod pgn;
+pub mod moves;
+pub mod encoding;
+pub mod chess960;
+pub mod fen;
 
-pub use time_control::{TimeControl, PlayerClock};
-pub use pgn::{parse_pgn, validate_game, ParsedGame, ValidatedGame, PgnError, PgnHeaders, GameResult as PgnGameResult};
+pub use time_control::{TimeControl, TimeControlCategory, PlayerClock};
+pub use game::{ClockRepository, ClockSnapshot, Game};
+pub use pgn::{parse_pgn, split_pgn_games, validate_game, write_pgn, MoveAnnotation, ParsedGame, ValidatedGame, PgnError, PgnHeaders, GameResult as PgnGameResult};
+pub use moves::{play_uci, play_move, legal_moves, validate_start_position, is_white_to_move, position_key, PlayedMove, MoveError, Termination, STARTING_FEN};
+pub use encoding::{encode_moves, decode_moves, ENCODING_VERSION};
+pub use chess960::{chess960_back_rank, chess960_fen, CHESS960_POSITIONS};
+pub use fen::{validate_fen, FenError};
@@ -0,0 +1,285 @@
+//! Move Validation Module
+//!
+//! Validates moves against a FEN position and produces the resulting
+//! position, so callers can persist games without replaying them.
+
+use shakmaty::{
+    fen::Fen,
+    san::SanPlus,
+    uci::UciMove,
+    zobrist::{Zobrist64, ZobristHash},
+    CastlingMode, Chess, Color, EnPassantMode, Move, Outcome, Position,
+};
+use thiserror::Error;
+
+use crate::fen::parse_position;
+use crate::pgn::GameResult;
+
+/// FEN of the standard starting position
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Errors that can occur while validating a move
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum MoveError {
+    #[error("Invalid position: {0}")]
+    InvalidPosition(String),
+
+    #[error("Invalid move notation: '{0}'")]
+    InvalidNotation(String),
+
+    #[error("Illegal move: '{0}'")]
+    IllegalMove(String),
+
+    #[error("Invalid move encoding: {0}")]
+    InvalidEncoding(String),
+}
+
+/// How a game ended on the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    InsufficientMaterial,
+}
+
+impl Termination {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Termination::Checkmate => "checkmate",
+            Termination::Stalemate => "stalemate",
+            Termination::InsufficientMaterial => "insufficient_material",
+        }
+    }
+}
+
+/// A validated move and the position it leads to
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayedMove {
+    /// The move in UCI notation (e.g. "e2e4", "e7e8q")
+    pub uci: String,
+    /// The move in SAN notation, including check/mate suffixes
+    pub san: String,
+    /// FEN of the position after the move
+    pub fen: String,
+    /// Set when the move ends the game (checkmate, stalemate, insufficient material)
+    pub outcome: Option<GameResult>,
+    /// Why the game ended, set together with `outcome`
+    pub termination: Option<Termination>,
+}
+
+/// Parse a FEN string into a position
+pub(crate) fn position_from_fen(fen: &str) -> Result<Chess, MoveError> {
+    parse_position(fen).map_err(|e| MoveError::InvalidPosition(e.to_string()))
+}
+
+/// UCI notation of a move, writing Chess960 castling as king-takes-rook
+fn uci_of(position: &Chess, chess_move: &Move) -> String {
+    UciMove::from_move(chess_move, position.castles().mode()).to_string()
+}
+
+/// Serialize a position to FEN
+pub(crate) fn position_to_fen(position: &Chess) -> String {
+    Fen::from_position(position.clone(), EnPassantMode::Legal).to_string()
+}
+
+/// Convert a game outcome into a PGN result
+pub(crate) fn outcome_to_result(outcome: Outcome) -> GameResult {
+    match outcome {
+        Outcome::Decisive { winner: Color::White } => GameResult::WhiteWins,
+        Outcome::Decisive { winner: Color::Black } => GameResult::BlackWins,
+        Outcome::Draw => GameResult::Draw,
+    }
+}
+
+/// Zobrist key of a position, for exact position lookups
+///
+/// Uses the Polyglot-compatible 64-bit hash, reinterpreted as `i64` so it
+/// fits a Postgres `bigint`. Move counters do not affect the key.
+pub fn position_key(fen: &str) -> Result<i64, MoveError> {
+    let position = position_from_fen(fen)?;
+    Ok(position.zobrist_hash::<Zobrist64>(EnPassantMode::Legal).0 as i64)
+}
+
+fn termination_of(position: &Chess) -> Option<Termination> {
+    if position.is_checkmate() {
+        Some(Termination::Checkmate)
+    } else if position.is_stalemate() {
+        Some(Termination::Stalemate)
+    } else if position.is_insufficient_material() {
+        Some(Termination::InsufficientMaterial)
+    } else {
+        None
+    }
+}
+
+/// Returns true if it is White's turn in the given position
+pub fn is_white_to_move(fen: &str) -> Result<bool, MoveError> {
+    Ok(position_from_fen(fen)?.turn() == Color::White)
+}
+
+/// All legal moves in a position, in UCI notation
+pub fn legal_moves(fen: &str) -> Result<Vec<String>, MoveError> {
+    let position = position_from_fen(fen)?;
+    Ok(position
+        .legal_moves()
+        .iter()
+        .map(|m| uci_of(&position, m))
+        .collect())
+}
+
+/// Validate a position for use as the start of a game
+///
+/// Returns the normalized FEN. Positions that are already over (no legal
+/// moves) are rejected.
+pub fn validate_start_position(fen: &str) -> Result<String, MoveError> {
+    let position = position_from_fen(fen)?;
+    if position.legal_moves().is_empty() {
+        return Err(MoveError::InvalidPosition("No legal moves in this position".to_string()));
+    }
+    Ok(position_to_fen(&position))
+}
+
+/// Validate a UCI move against a FEN position and play it
+pub fn play_uci(fen: &str, uci: &str) -> Result<PlayedMove, MoveError> {
+    let position = position_from_fen(fen)?;
+
+    let uci_move: UciMove = uci
+        .parse()
+        .map_err(|_| MoveError::InvalidNotation(uci.to_string()))?;
+    let chess_move = uci_move
+        .to_move(&position)
+        .map_err(|_| MoveError::IllegalMove(uci.to_string()))?;
+
+    Ok(play(position, chess_move))
+}
+
+/// Validate a move in any common notation against a FEN position and play it
+///
+/// Accepts UCI (`e2e4`, `e7e8q`), castling written either as the king's
+/// destination (`e1g1`) or as king-takes-rook (`e1h1`), and SAN (`Nf3`,
+/// `exd5`, `O-O`, `0-0-0`, with or without check suffixes). The returned
+/// move carries the canonical UCI and SAN forms.
+pub fn play_move(fen: &str, notation: &str) -> Result<PlayedMove, MoveError> {
+    let position = position_from_fen(fen)?;
+    let notation = notation.trim();
+
+    if let Ok(uci_move) = notation.parse::<UciMove>() {
+        let legal = position.legal_moves();
+        let chess_move = legal.iter().find(|m| {
+            [CastlingMode::Standard, CastlingMode::Chess960]
+                .iter()
+                .any(|mode| UciMove::from_move(m, *mode) == uci_move)
+        });
+        return match chess_move {
+            Some(chess_move) => Ok(play(position.clone(), chess_move.clone())),
+            None => Err(MoveError::IllegalMove(notation.to_string())),
+        };
+    }
+
+    // Zeros are a common way of writing castling
+    let san: SanPlus = notation
+        .replace('0', "O")
+        .parse()
+        .map_err(|_| MoveError::InvalidNotation(notation.to_string()))?;
+    let chess_move = san
+        .san
+        .to_move(&position)
+        .map_err(|_| MoveError::IllegalMove(notation.to_string()))?;
+
+    Ok(play(position, chess_move))
+}
+
+fn play(position: Chess, chess_move: Move) -> PlayedMove {
+    let mut after = position.clone();
+    let san = SanPlus::from_move_and_play_unchecked(&mut after, &chess_move);
+
+    PlayedMove {
+        uci: uci_of(&position, &chess_move),
+        san: san.to_string(),
+        fen: position_to_fen(&after),
+        outcome: after.outcome().map(outcome_to_result),
+        termination: termination_of(&after),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_play_opening_move() {
+        let played = play_uci(STARTING_FEN, "e2e4").unwrap();
+        assert_eq!(played.san, "e4");
+        assert_eq!(played.uci, "e2e4");
+        assert_eq!(
+            played.fen,
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+        );
+        assert_eq!(played.outcome, None);
+        assert!(!is_white_to_move(&played.fen).unwrap());
+    }
+
+    #[test]
+    fn test_reject_illegal_and_malformed_moves() {
+        assert_eq!(
+            play_uci(STARTING_FEN, "e2e5"),
+            Err(MoveError::IllegalMove("e2e5".to_string()))
+        );
+        assert_eq!(
+            play_uci(STARTING_FEN, "hello"),
+            Err(MoveError::InvalidNotation("hello".to_string()))
+        );
+        assert!(matches!(
+            play_uci("not a fen", "e2e4"),
+            Err(MoveError::InvalidPosition(_))
+        ));
+    }
+
+    #[test]
+    fn test_play_move_accepts_san_and_uci() {
+        for notation in ["g1f3", "Nf3", " Nf3 "] {
+            let played = play_move(STARTING_FEN, notation).unwrap();
+            assert_eq!((played.uci.as_str(), played.san.as_str()), ("g1f3", "Nf3"));
+        }
+
+        // Both king-to-square and king-takes-rook castling, plus SAN forms
+        let fen = "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1";
+        for notation in ["e1g1", "e1h1", "O-O", "0-0"] {
+            let played = play_move(fen, notation).unwrap();
+            assert_eq!((played.uci.as_str(), played.san.as_str()), ("e1g1", "O-O"));
+        }
+        assert_eq!(play_move(fen, "0-0-0").unwrap().uci, "e1c1");
+
+        assert_eq!(play_move(STARTING_FEN, "Nf6"), Err(MoveError::IllegalMove("Nf6".to_string())));
+        assert_eq!(play_move(STARTING_FEN, "e2e5"), Err(MoveError::IllegalMove("e2e5".to_string())));
+        assert_eq!(play_move(STARTING_FEN, "hello"), Err(MoveError::InvalidNotation("hello".to_string())));
+    }
+
+    #[test]
+    fn test_checkmate_sets_outcome() {
+        // Fool's mate: 1. f3 e5 2. g4 Qh4#
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2";
+        let played = play_uci(fen, "d8h4").unwrap();
+        assert_eq!(played.san, "Qh4#");
+        assert_eq!(played.outcome, Some(GameResult::BlackWins));
+        assert_eq!(played.termination, Some(Termination::Checkmate));
+    }
+
+    #[test]
+    fn test_legal_moves_from_start() {
+        let moves = legal_moves(STARTING_FEN).unwrap();
+        assert_eq!(moves.len(), 20);
+        assert!(moves.contains(&"g1f3".to_string()));
+    }
+
+    #[test]
+    fn test_position_key_ignores_move_counters() {
+        let start = position_key(STARTING_FEN).unwrap();
+        assert_eq!(start as u64, 0x463b96181691fc9c);
+        assert_eq!(
+            position_key("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 4 9").unwrap(),
+            start
+        );
+        assert_ne!(position_key(&play_uci(STARTING_FEN, "e2e4").unwrap().fen).unwrap(), start);
+    }
+}
@@ -103,6 +103,8 @@ pub struct ParsedGame {
 pub struct ValidatedGame {
     pub headers: PgnHeaders,
     pub moves: Vec<String>,
+    /// FEN after each move, parallel to `moves`
+    pub positions: Vec<String>,
     pub final_fen: String,
     pub ply_count: usize,
     pub is_valid: bool,
@@ -205,10 +207,139 @@ pub fn parse_pgn(pgn_string: &str) -> Result<ParsedGame, PgnError> {
     })
 }
 
+/// Optional annotations written after a move when exporting PGN
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MoveAnnotation {
+    /// Remaining clock time of the mover after the move, in milliseconds
+    pub clock_ms: Option<u64>,
+    /// Engine evaluation in pawns from White's point of view
+    pub eval: Option<f64>,
+    /// Free-text comment
+    pub comment: Option<String>,
+}
+
+impl MoveAnnotation {
+    fn is_empty(&self) -> bool {
+        self.clock_ms.is_none() && self.eval.is_none() && self.comment.is_none()
+    }
+
+    fn to_pgn_comment(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(ms) = self.clock_ms {
+            let secs = ms / 1000;
+            parts.push(format!("[%clk {}:{:02}:{:02}]", secs / 3600, (secs / 60) % 60, secs % 60));
+        }
+        if let Some(eval) = self.eval {
+            parts.push(format!("[%eval {:.2}]", eval));
+        }
+        if let Some(comment) = &self.comment {
+            // Braces cannot be escaped inside PGN comments
+            parts.push(comment.replace(['{', '}'], ""));
+        }
+        format!("{{{}}}", parts.join(" "))
+    }
+}
+
+/// Write a game as PGN text
+///
+/// Headers are written in the given order (the Seven Tag Roster should come
+/// first). `annotations` is matched to `moves` by index and may be shorter.
+pub fn write_pgn(
+    headers: &[(String, String)],
+    moves: &[String],
+    annotations: &[MoveAnnotation],
+    result: &GameResult,
+) -> String {
+    let mut out = String::new();
+    for (key, value) in headers {
+        let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+        out.push_str(&format!("[{} \"{}\"]\n", key, value));
+    }
+    out.push('\n');
+
+    // Games set up from a position continue its move number and side to move
+    let (first_move, black_first) = headers
+        .iter()
+        .find(|(key, _)| key == "FEN")
+        .map(|(_, fen)| movetext_start(fen))
+        .unwrap_or((1, false));
+
+    let mut tokens = Vec::with_capacity(moves.len() * 2 + 1);
+    for (idx, san) in moves.iter().enumerate() {
+        let annotation = annotations.get(idx).filter(|a| !a.is_empty());
+        let half_move = idx + black_first as usize;
+        let number = first_move + half_move / 2;
+        if half_move.is_multiple_of(2) {
+            tokens.push(format!("{}.", number));
+        } else if idx == 0 || annotations.get(idx - 1).is_some_and(|a| !a.is_empty()) {
+            // Black's move needs its number repeated after an annotation
+            tokens.push(format!("{}...", number));
+        }
+        tokens.push(san.clone());
+        if let Some(annotation) = annotation {
+            tokens.push(annotation.to_pgn_comment());
+        }
+    }
+    tokens.push(result.to_pgn_string().to_string());
+
+    // Wrap movetext at 80 columns as recommended by the PGN standard
+    let mut line_len = 0;
+    for token in tokens {
+        if line_len > 0 && line_len + 1 + token.len() > 80 {
+            out.push('\n');
+            line_len = 0;
+        } else if line_len > 0 {
+            out.push(' ');
+            line_len += 1;
+        }
+        line_len += token.len();
+        out.push_str(&token);
+    }
+    out.push('\n');
+    out
+}
+
+/// Full move number and whether Black moves first, from a FEN
+fn movetext_start(fen: &str) -> (usize, bool) {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    let black_first = fields.get(1) == Some(&"b");
+    let number = fields.get(5).and_then(|n| n.parse().ok()).filter(|n| *n > 0).unwrap_or(1);
+    (number, black_first)
+}
+
+/// Split a multi-game PGN file into one string per game
+///
+/// A new game starts at the first header line that follows move text.
+pub fn split_pgn_games(pgn: &str) -> Vec<String> {
+    let mut games = Vec::new();
+    let mut current = String::new();
+    let mut seen_moves = false;
+
+    for line in pgn.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && seen_moves {
+            games.push(std::mem::take(&mut current));
+            seen_moves = false;
+        } else if !trimmed.is_empty() && !trimmed.starts_with('[') {
+            seen_moves = true;
+        }
+        current.push_str(line);
+        current.push('\n');
+    }
+    games.push(current);
+
+    games
+        .into_iter()
+        .map(|game| game.trim().to_string())
+        .filter(|game| !game.is_empty())
+        .collect()
+}
+
 /// Validate a parsed game by replaying all moves
 pub fn validate_game(parsed: &ParsedGame) -> Result<ValidatedGame, PgnError> {
     let mut position: Chess = Chess::default();
     let mut validated_moves = Vec::new();
+    let mut positions = Vec::new();
     
     for (idx, move_san) in parsed.moves.iter().enumerate() {
         let move_number = (idx / 2) + 1;
@@ -234,6 +365,10 @@ pub fn validate_game(parsed: &ParsedGame) -> Result<ValidatedGame, PgnError> {
         })?;
         
         validated_moves.push(move_san.clone());
+        positions.push(
+            shakmaty::fen::Fen::from_position(position.clone(), shakmaty::EnPassantMode::Legal)
+                .to_string(),
+        );
     }
     
     // Get final FEN
@@ -243,6 +378,7 @@ pub fn validate_game(parsed: &ParsedGame) -> Result<ValidatedGame, PgnError> {
     Ok(ValidatedGame {
         headers: parsed.headers.clone(),
         moves: validated_moves,
+        positions,
         final_fen,
         ply_count: parsed.moves.len(),
         is_valid: true,
@@ -338,4 +474,74 @@ mod tests {
         assert_eq!(GameResult::from_pgn_string("1/2-1/2").unwrap(), GameResult::Draw);
         assert_eq!(GameResult::from_pgn_string("*").unwrap(), GameResult::Ongoing);
     }
+
+    #[test]
+    fn test_validate_records_positions() {
+        let pgn = r#"[White "Player1"]
+[Black "Player2"]
+
+1. e4 e5 *"#;
+
+        let validated = validate_game(&parse_pgn(pgn).unwrap()).unwrap();
+        assert_eq!(validated.positions.len(), 2);
+        assert_eq!(validated.positions.last(), Some(&validated.final_fen));
+    }
+
+    #[test]
+    fn test_split_multi_game_pgn() {
+        let pgn = r#"[White "A"]
+[Black "B"]
+[Result "1-0"]
+
+1. e4 e5
+2. Qh5 Nc6 1-0
+
+[White "C"]
+[Black "D"]
+[Result "*"]
+
+1. d4 *
+"#;
+
+        let games = split_pgn_games(pgn);
+        assert_eq!(games.len(), 2);
+        assert_eq!(parse_pgn(&games[0]).unwrap().moves.len(), 4);
+        assert_eq!(parse_pgn(&games[1]).unwrap().headers.white, "C");
+    }
+
+    #[test]
+    fn test_write_pgn_round_trips() {
+        let headers = vec![
+            ("White".to_string(), "Player1".to_string()),
+            ("Black".to_string(), "Player2".to_string()),
+            ("Event".to_string(), "The \"Open\"".to_string()),
+            ("Result".to_string(), "1-0".to_string()),
+        ];
+        let moves: Vec<String> = ["e4", "e5", "Nf3"].iter().map(|m| m.to_string()).collect();
+        let annotations = vec![MoveAnnotation {
+            clock_ms: Some(179_500),
+            eval: Some(0.3),
+            comment: Some("Best by test".to_string()),
+        }];
+
+        let pgn = write_pgn(&headers, &moves, &annotations, &GameResult::WhiteWins);
+        assert!(pgn.contains("[Event \"The \\\"Open\\\"\"]"));
+        assert!(pgn.contains("1. e4 {[%clk 0:02:59] [%eval 0.30] Best by test} 1... e5 2. Nf3 1-0"));
+
+        let parsed = parse_pgn(&pgn).unwrap();
+        assert_eq!(parsed.headers.white, "Player1");
+        assert_eq!(parsed.moves, moves);
+    }
+
+    #[test]
+    fn test_write_pgn_numbers_moves_from_setup_position() {
+        let headers = vec![
+            ("SetUp".to_string(), "1".to_string()),
+            ("FEN".to_string(), "4k3/8/8/8/8/8/4P3/4K3 b - - 0 12".to_string()),
+        ];
+        let moves: Vec<String> = ["Kd7", "e4"].iter().map(|m| m.to_string()).collect();
+
+        let pgn = write_pgn(&headers, &moves, &[], &GameResult::Ongoing);
+        assert!(pgn.ends_with("\n12... Kd7 13. e4 *\n"));
+    }
 }
@@ -7,6 +7,66 @@ pub struct TimeControl {
     pub delay: Duration,
 }
 
+/// Speed category of a time control, by estimated game duration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeControlCategory {
+    Bullet,
+    Blitz,
+    Rapid,
+    Classical,
+}
+
+impl TimeControlCategory {
+    /// Moves assumed per game when estimating duration from the increment
+    pub const ESTIMATED_MOVES: u32 = 40;
+
+    /// Estimated game duration in seconds: initial time plus 40 increments
+    pub fn estimated_duration_secs(initial_secs: u32, increment_secs: u32) -> u32 {
+        initial_secs + Self::ESTIMATED_MOVES * increment_secs
+    }
+
+    pub fn from_time_control(initial_secs: u32, increment_secs: u32) -> Self {
+        let estimate = Self::estimated_duration_secs(initial_secs, increment_secs);
+        [Self::Bullet, Self::Blitz, Self::Rapid]
+            .into_iter()
+            .find(|category| category.duration_bounds().1.is_some_and(|upper| estimate < upper))
+            .unwrap_or(Self::Classical)
+    }
+
+    /// Estimated duration range `[lower, upper)` in seconds covered by this category
+    pub fn duration_bounds(&self) -> (u32, Option<u32>) {
+        match self {
+            Self::Bullet => (0, Some(180)),
+            Self::Blitz => (180, Some(480)),
+            Self::Rapid => (480, Some(1500)),
+            Self::Classical => (1500, None),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Bullet => "bullet",
+            Self::Blitz => "blitz",
+            Self::Rapid => "rapid",
+            Self::Classical => "classical",
+        }
+    }
+}
+
+impl std::str::FromStr for TimeControlCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bullet" => Ok(Self::Bullet),
+            "blitz" => Ok(Self::Blitz),
+            "rapid" => Ok(Self::Rapid),
+            "classical" => Ok(Self::Classical),
+            other => Err(format!("Unknown time control category '{}'", other)),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PlayerClock {
     pub remaining_time: Duration,
@@ -71,3 +131,16 @@ impl PlayerClock {
         self.remaining_time.is_zero()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_category_from_time_control() {
+        assert_eq!(TimeControlCategory::from_time_control(60, 0), TimeControlCategory::Bullet);
+        assert_eq!(TimeControlCategory::from_time_control(180, 2), TimeControlCategory::Blitz);
+        assert_eq!(TimeControlCategory::from_time_control(600, 0), TimeControlCategory::Rapid);
+        assert_eq!(TimeControlCategory::from_time_control(1800, 20), TimeControlCategory::Classical);
+    }
+}
@@ -15,6 +15,7 @@ use dotenv::dotenv;
 const NUM_PLAYERS_TO_CREATE: usize = 100;
 const NUM_GAMES_TO_INSERT: usize = 1_000_000;
 const BATCH_SIZE: usize = 100; // Insert games in batches
+const NUM_ENCODING_SAMPLE_GAMES: usize = 2_000; // Random legal games for the move encoding comparison
 
 // Helper to connect to the database
 async fn setup_db() -> Result<DatabaseConnection, DbErr> {
@@ -60,12 +61,100 @@ fn generate_random_fen(rng: &mut ThreadRng) -> String {
        .collect::<String>() + " w KQkq - 0 1"
 }
 
+// Helper to play a random legal game, returning SAN moves and the FEN after each move
+fn generate_random_legal_game(rng: &mut ThreadRng) -> (Vec<String>, Vec<String>) {
+    let num_plies: usize = rng.gen_range(20..160);
+    let mut fen = chess::STARTING_FEN.to_string();
+    let (mut moves, mut fens) = (Vec::new(), Vec::new());
+    for _ in 0..num_plies {
+        let legal = chess::legal_moves(&fen).expect("valid position");
+        let Some(uci) = legal.choose(rng) else {
+            break; // Checkmate or stalemate
+        };
+        let played = chess::play_uci(&fen, uci).expect("legal move");
+        moves.push(played.san);
+        fens.push(played.fen.clone());
+        fen = played.fen;
+    }
+    (moves, fens)
+}
+
+// Compare storage size and decode speed of the JSONB move list (plus the
+// per-move SAN and FEN rows in game_move) against the compact encoding
+fn benchmark_move_encoding(rng: &mut ThreadRng) {
+    println!("\nBenchmarking move encoding on {} random legal games...", NUM_ENCODING_SAMPLE_GAMES);
+    let games: Vec<(Vec<String>, Vec<String>)> =
+        (0..NUM_ENCODING_SAMPLE_GAMES).map(|_| generate_random_legal_game(rng)).collect();
+    let total_plies: usize = games.iter().map(|(moves, _)| moves.len()).sum();
+
+    let json_docs: Vec<Vec<u8>> = games
+        .iter()
+        .map(|(moves, _)| serde_json::to_vec(&json!({ "moves": moves })).unwrap())
+        .collect();
+    let encode_start = Instant::now();
+    let encoded: Vec<Vec<u8>> = games
+        .iter()
+        .map(|(moves, _)| chess::encode_moves(chess::STARTING_FEN, moves).unwrap())
+        .collect();
+    let encode_duration = encode_start.elapsed();
+
+    let json_bytes: usize = json_docs.iter().map(Vec::len).sum();
+    let move_row_bytes: usize = games
+        .iter()
+        .flat_map(|(moves, fens)| moves.iter().zip(fens))
+        .map(|(san, fen)| san.len() + fen.len())
+        .sum();
+    let encoded_bytes: usize = encoded.iter().map(Vec::len).sum();
+    println!("- Plies: {} ({:.1} per game)", total_plies, total_plies as f64 / games.len() as f64);
+    println!("- JSONB move list: {} bytes ({:.2} bytes/ply)", json_bytes, json_bytes as f64 / total_plies as f64);
+    println!("- game_move SAN + FEN: {} bytes ({:.2} bytes/ply)", move_row_bytes, move_row_bytes as f64 / total_plies as f64);
+    println!(
+        "- Compact encoding: {} bytes ({:.2} bits/ply, {:.1}x smaller than JSONB)",
+        encoded_bytes,
+        encoded_bytes as f64 * 8.0 / total_plies as f64,
+        json_bytes as f64 / encoded_bytes as f64
+    );
+    println!("- Encode: {:.2?} total", encode_duration);
+
+    let decode_start = Instant::now();
+    let mut decoded_plies = 0;
+    for doc in &json_docs {
+        let value: JsonValue = serde_json::from_slice(doc).unwrap();
+        decoded_plies += value["moves"].as_array().map_or(0, Vec::len);
+    }
+    let json_duration = decode_start.elapsed();
+
+    let decode_start = Instant::now();
+    for bytes in &encoded {
+        decoded_plies += chess::decode_moves(chess::STARTING_FEN, bytes).unwrap().len();
+    }
+    let encoded_duration = decode_start.elapsed();
+    assert_eq!(decoded_plies, total_plies * 2);
+
+    println!(
+        "- Decode JSONB: {:.2?} ({:.0} ns/ply)",
+        json_duration,
+        json_duration.as_nanos() as f64 / total_plies as f64
+    );
+    println!(
+        "- Decode compact (replays moves, yields SAN): {:.2?} ({:.0} ns/ply)",
+        encoded_duration,
+        encoded_duration.as_nanos() as f64 / total_plies as f64
+    );
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut rng = thread_rng();
+
+    // `--encoding-only` runs the move encoding comparison without a database
+    if env::args().any(|arg| arg == "--encoding-only") {
+        benchmark_move_encoding(&mut rng);
+        return Ok(());
+    }
+
     println!("Starting game benchmark...");
     let db = setup_db().await?;
-    let mut rng = thread_rng();
 
     // === Setup: Create Players ===
     println!("Creating {} players...", NUM_PLAYERS_TO_CREATE);
@@ -117,8 +206,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         game_models.push(game::ActiveModel {
             id: Set(game_id), // Explicitly set the game ID
-            white_player: Set(white_player_id),
-            black_player: Set(black_player_id),
+            white_player: Set(Some(white_player_id)),
+            black_player: Set(Some(black_player_id)),
             fen: Set(generate_random_fen(&mut rng)),
             pgn: Set(generate_random_pgn(&mut rng)),
 
@@ -199,6 +288,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         duration
     );
 
+    // === Benchmark: Move encoding ===
+    benchmark_move_encoding(&mut rng);
+
     // === Cleanup (Optional but recommended) ===
     println!("\nStarting cleanup (deleting benchmark games and players)... This might take a while.");
     let cleanup_start = Instant::now();
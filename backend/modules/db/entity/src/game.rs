@@ -34,14 +34,30 @@ pub enum GameVariant {
     #[sea_orm(string_value = "classical")]
     Classical,
 }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "game_status")]
+pub enum GameStatus {
+    #[sea_orm(string_value = "waiting")]
+    Waiting,
+    #[sea_orm(string_value = "in_progress")]
+    InProgress,
+    #[sea_orm(string_value = "completed")]
+    Completed,
+    #[sea_orm(string_value = "aborted")]
+    Aborted,
+    #[sea_orm(string_value = "adjudicated")]
+    Adjudicated,
+}
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, DeriveEntityModel)]
 
 #[sea_orm(table_name = "game", schema_name = "smdb")]
 pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: Uuid,
-    pub white_player: Uuid,
-    pub black_player: Uuid,
+    /// Empty until a player takes the white seat
+    pub white_player: Option<Uuid>,
+    /// Empty until a player takes the black seat
+    pub black_player: Option<Uuid>,
     #[sea_orm(column_type = "Text")]
     pub fen: String,
     #[sea_orm(column_type = "JsonBinary")]
@@ -58,6 +74,32 @@ pub struct Model {
     /// Original PGN string if game was imported
     #[sea_orm(column_type = "Text", nullable)]
     pub original_pgn: Option<String>,
+    /// SHA-256 fingerprint used to detect repeated imports
+    #[sea_orm(nullable)]
+    pub import_hash: Option<String>,
+    /// Zobrist keys of every position reached, starting position included
+    pub position_keys: Vec<i64>,
+    pub status: GameStatus,
+    /// Starting clock time for each side
+    pub initial_time_ms: i64,
+    /// Time added to a player's clock after each of their moves
+    pub increment_ms: i64,
+    #[sea_orm(default_value = true)]
+    pub rated: bool,
+    /// How the game ended (e.g. checkmate, stalemate, abandoned)
+    #[sea_orm(nullable)]
+    pub termination: Option<String>,
+    #[sea_orm(nullable)]
+    pub last_move_at: Option<DateTimeWithTimeZone>,
+    /// Moves in the compact binary encoding of `chess::encoding`
+    #[sea_orm(column_type = "VarBinary(StringLen::None)", nullable)]
+    pub moves_encoded: Option<Vec<u8>>,
+    /// Position the game started from (standard, Chess960 or custom)
+    #[sea_orm(column_type = "Text")]
+    pub initial_fen: String,
+    /// Incremented on every write, for optimistic concurrency checks
+    #[sea_orm(default_value = 0)]
+    pub version: i64,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -78,6 +120,8 @@ pub enum Relation {
         on_delete = "Restrict"
     )]
     BlackPlayer,
+    #[sea_orm(has_many = "super::game_move::Entity")]
+    GameMove,
 }
 
 impl Related<super::player::Entity> for Entity {
@@ -86,4 +130,10 @@ impl Related<super::player::Entity> for Entity {
     }
 }
 
+impl Related<super::game_move::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::GameMove.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
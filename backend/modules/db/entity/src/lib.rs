@@ -1,7 +1,16 @@
 pub mod prelude;
 pub mod game;
+pub mod game_archive;
+pub mod game_event;
+pub mod game_move;
+pub mod game_player_state;
+pub mod game_transition;
 pub mod player;
+pub mod player_rating;
+pub mod rating_history;
 pub mod refresh_token;
+pub mod socket_room;
+pub mod time_control;
 
 #[path = "../user.rs"]
 pub mod user;
\ No newline at end of file
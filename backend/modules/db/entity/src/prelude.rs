@@ -1,5 +1,14 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.10
 
 pub use super::game::Entity as Game;
+pub use super::game_archive::Entity as GameArchive;
+pub use super::game_event::Entity as GameEvent;
+pub use super::game_move::Entity as GameMove;
+pub use super::game_player_state::Entity as GamePlayerState;
+pub use super::game_transition::Entity as GameTransition;
 pub use super::player::Entity as Player;
+pub use super::player_rating::Entity as PlayerRating;
+pub use super::rating_history::Entity as RatingHistory;
 pub use super::refresh_token::Entity as RefreshToken;
+pub use super::socket_room::Entity as SocketRoom;
+pub use super::time_control::Entity as TimeControl;
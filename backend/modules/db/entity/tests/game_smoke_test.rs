@@ -53,8 +53,8 @@ async fn test_insert_and_verify_game() -> Result<(), Box<dyn std::error::Error>>
 
     // 3. Create the ActiveModel for the new game
     let game_model = game::ActiveModel {
-        white_player: Set(player_id),
-        black_player: Set(player_id), // Using same player for white/black for simplicity
+        white_player: Set(Some(player_id)),
+        black_player: Set(Some(player_id)), // Using same player for white/black for simplicity
         fen: Set(game_fen.to_string()),
         pgn: Set(game_pgn.clone()), // Clone pgn json for comparison later
         result: Set(Some(game_result.clone())),
@@ -80,8 +80,8 @@ async fn test_insert_and_verify_game() -> Result<(), Box<dyn std::error::Error>>
 
     // Assert that fetched data matches the inserted data
     assert_eq!(fetched_game.id, game_id);
-    assert_eq!(fetched_game.white_player, player_id);
-    assert_eq!(fetched_game.black_player, player_id);
+    assert_eq!(fetched_game.white_player, Some(player_id));
+    assert_eq!(fetched_game.black_player, Some(player_id));
     assert_eq!(fetched_game.fen, game_fen);
     assert_eq!(fetched_game.pgn, game_pgn, "Fetched PGN JSON does not match");
     assert_eq!(fetched_game.result, Some(game_result));
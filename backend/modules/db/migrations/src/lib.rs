@@ -8,6 +8,22 @@ mod m20250604_160341_create_games_and_moves;
 mod m20250605_090000_add_game_search_indexes;
 mod m20260127_create_refresh_tokens_table;
 mod m20260127_180000_add_game_imported_flag;
+mod m20261018_000001_make_game_seats_nullable;
+mod m20261018_000002_add_game_import_hash;
+mod m20261018_000003_add_advanced_game_search_indexes;
+mod m20261018_000004_add_game_lifecycle_columns;
+mod m20261018_000005_add_move_timing_columns;
+mod m20261018_000006_add_game_moves_encoded;
+mod m20261018_000007_create_game_archive;
+mod m20261018_000008_add_game_initial_fen;
+mod m20261018_000009_create_game_transition;
+mod m20261018_000010_add_game_version;
+mod m20261018_000011_create_game_event_log;
+mod m20261018_000012_create_socket_room;
+mod m20261018_000013_create_time_controls;
+mod m20261018_000014_create_player_rating;
+mod m20261018_000015_add_rating_categories;
+mod m20261018_000016_add_game_archive_created_at;
 
 
 pub struct Migrator;
@@ -24,6 +40,22 @@ impl MigratorTrait for Migrator {
             Box::new(m20250605_090000_add_game_search_indexes::Migration),
             Box::new(m20260127_create_refresh_tokens_table::Migration),
             Box::new(m20260127_180000_add_game_imported_flag::Migration),
+            Box::new(m20261018_000001_make_game_seats_nullable::Migration),
+            Box::new(m20261018_000002_add_game_import_hash::Migration),
+            Box::new(m20261018_000003_add_advanced_game_search_indexes::Migration),
+            Box::new(m20261018_000004_add_game_lifecycle_columns::Migration),
+            Box::new(m20261018_000005_add_move_timing_columns::Migration),
+            Box::new(m20261018_000006_add_game_moves_encoded::Migration),
+            Box::new(m20261018_000007_create_game_archive::Migration),
+            Box::new(m20261018_000008_add_game_initial_fen::Migration),
+            Box::new(m20261018_000009_create_game_transition::Migration),
+            Box::new(m20261018_000010_add_game_version::Migration),
+            Box::new(m20261018_000011_create_game_event_log::Migration),
+            Box::new(m20261018_000012_create_socket_room::Migration),
+            Box::new(m20261018_000013_create_time_controls::Migration),
+            Box::new(m20261018_000014_create_player_rating::Migration),
+            Box::new(m20261018_000015_add_rating_categories::Migration),
+            Box::new(m20261018_000016_add_game_archive_created_at::Migration),
         ]
     }
 }
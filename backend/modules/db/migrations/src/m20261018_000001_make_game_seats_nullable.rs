@@ -0,0 +1,56 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // A newly created game only has one seated player until an opponent joins
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .modify_column(ColumnDef::new(Game::WhitePlayer).uuid().null())
+                    .modify_column(ColumnDef::new(Game::BlackPlayer).uuid().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Made white_player and black_player nullable on game table.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Waiting games cannot satisfy NOT NULL, so they are removed first
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "DELETE FROM smdb.game WHERE white_player IS NULL OR black_player IS NULL",
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .modify_column(ColumnDef::new(Game::WhitePlayer).uuid().not_null())
+                    .modify_column(ColumnDef::new(Game::BlackPlayer).uuid().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Made white_player and black_player required on game table.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    WhitePlayer,
+    BlackPlayer,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Fingerprint of an imported game's players, date, result and moves
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .add_column(ColumnDef::new(Game::ImportHash).string_len(64).null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Unique so that concurrent imports of the same game cannot both succeed
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_games_import_hash")
+                    .table((Smdb, Game::Table))
+                    .col(Game::ImportHash)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Added import_hash column to game table.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_games_import_hash")
+                    .table((Smdb, Game::Table))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .drop_column(Game::ImportHash)
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Removed import_hash column from game table.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    ImportHash,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
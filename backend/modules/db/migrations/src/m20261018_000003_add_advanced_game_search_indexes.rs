@@ -0,0 +1,105 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Expression indexes backing game search. Opening metadata, ratings and
+/// termination live in the `pgn` JSONB document, so each index must use the
+/// exact expression the search query filters on.
+const SEARCH_INDEXES: &[(&str, &str)] = &[
+    (
+        "idx_games_eco",
+        r#"CREATE INDEX IF NOT EXISTS "idx_games_eco" ON "smdb"."game" (("pgn" ->> 'eco') text_pattern_ops)"#,
+    ),
+    (
+        "idx_games_opening",
+        r#"CREATE INDEX IF NOT EXISTS "idx_games_opening" ON "smdb"."game" ((lower("pgn" ->> 'opening')) text_pattern_ops)"#,
+    ),
+    (
+        "idx_games_average_rating",
+        r#"CREATE INDEX IF NOT EXISTS "idx_games_average_rating" ON "smdb"."game" (((("pgn" ->> 'white_rating')::int + ("pgn" ->> 'black_rating')::int) / 2))"#,
+    ),
+    (
+        "idx_games_termination",
+        r#"CREATE INDEX IF NOT EXISTS "idx_games_termination" ON "smdb"."game" (("pgn" ->> 'termination'))"#,
+    ),
+    (
+        "idx_games_final_ply",
+        r#"CREATE INDEX IF NOT EXISTS "idx_games_final_ply" ON "smdb"."game" ((("pgn" ->> 'final_ply')::int))"#,
+    ),
+    (
+        "idx_games_estimated_duration",
+        r#"CREATE INDEX IF NOT EXISTS "idx_games_estimated_duration" ON "smdb"."game" (("duration_sec" + 40 * COALESCE(("pgn" ->> 'increment')::int, 0)))"#,
+    ),
+    (
+        "idx_games_variant_started_at",
+        r#"CREATE INDEX IF NOT EXISTS "idx_games_variant_started_at" ON "smdb"."game" ("variant", "started_at" DESC)"#,
+    ),
+    (
+        "idx_games_result_started_at",
+        r#"CREATE INDEX IF NOT EXISTS "idx_games_result_started_at" ON "smdb"."game" ("result", "started_at" DESC)"#,
+    ),
+    (
+        "idx_games_position_keys",
+        r#"CREATE INDEX IF NOT EXISTS "idx_games_position_keys" ON "smdb"."game" USING GIN ("position_keys")"#,
+    ),
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Zobrist keys of every position reached in the game, for exact position search.
+        // Games stored before this migration have no keys until the
+        // `backfill_position_keys` tool has run.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .add_column(
+                        ColumnDef::new(Game::PositionKeys)
+                            .array(ColumnType::BigInteger)
+                            .not_null()
+                            .default(Expr::cust("'{}'::bigint[]")),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        for (_, sql) in SEARCH_INDEXES {
+            manager.get_connection().execute_unprepared(sql).await?;
+        }
+
+        println!("Added position_keys column and game search indexes.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (name, _) in SEARCH_INDEXES.iter().rev() {
+            manager
+                .get_connection()
+                .execute_unprepared(&format!(r#"DROP INDEX IF EXISTS "smdb"."{}""#, name))
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .drop_column(Game::PositionKeys)
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Dropped game search indexes and position_keys column.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    PositionKeys,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
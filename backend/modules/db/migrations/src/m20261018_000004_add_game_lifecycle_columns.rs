@@ -0,0 +1,141 @@
+use sea_orm_migration::{prelude::*, prelude::extension::postgres::Type};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Fill the new columns from the data previously kept in `result`, the seats
+/// and the `pgn` document. `result` is compared as text because databases
+/// created by the original games migration use different enum labels.
+const BACKFILL: &str = r#"
+UPDATE "smdb"."game" AS g SET
+    "status" = (CASE
+        WHEN g."result"::text = 'abandoned' THEN 'aborted'
+        WHEN g."result"::text IN ('white_wins', 'black_wins', 'white', 'black', 'draw') THEN 'completed'
+        WHEN g."is_imported" OR (g."white_player" IS NOT NULL AND g."black_player" IS NOT NULL) THEN 'in_progress'
+        ELSE 'waiting'
+    END)::game_status,
+    "initial_time_ms" = g."duration_sec"::bigint * 1000,
+    "increment_ms" = COALESCE((g."pgn" ->> 'increment')::bigint, 0) * 1000,
+    "rated" = NOT g."is_imported",
+    "termination" = g."pgn" ->> 'termination',
+    "last_move_at" = (SELECT max(m."timestamp") FROM "smdb"."game_move" AS m WHERE m."game_id" = g."id")
+"#;
+
+/// Indexes on the new columns. Termination and speed search move off the
+/// `pgn` expression indexes created by the advanced search migration.
+const UP_INDEXES: &[&str] = &[
+    r#"DROP INDEX IF EXISTS "smdb"."idx_games_termination""#,
+    r#"DROP INDEX IF EXISTS "smdb"."idx_games_estimated_duration""#,
+    r#"CREATE INDEX IF NOT EXISTS "idx_games_termination" ON "smdb"."game" ("termination")"#,
+    r#"CREATE INDEX IF NOT EXISTS "idx_games_estimated_duration" ON "smdb"."game" (("initial_time_ms" + 40 * "increment_ms"))"#,
+    r#"CREATE INDEX IF NOT EXISTS "idx_games_status_created_at_id" ON "smdb"."game" ("status", "created_at" DESC, "id" DESC)"#,
+];
+
+const DOWN_INDEXES: &[&str] = &[
+    r#"DROP INDEX IF EXISTS "smdb"."idx_games_status_created_at_id""#,
+    r#"DROP INDEX IF EXISTS "smdb"."idx_games_estimated_duration""#,
+    r#"DROP INDEX IF EXISTS "smdb"."idx_games_termination""#,
+    r#"CREATE INDEX IF NOT EXISTS "idx_games_termination" ON "smdb"."game" (("pgn" ->> 'termination'))"#,
+    r#"CREATE INDEX IF NOT EXISTS "idx_games_estimated_duration" ON "smdb"."game" (("duration_sec" + 40 * COALESCE(("pgn" ->> 'increment')::int, 0)))"#,
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(GameStatus::Type)
+                    .values([
+                        GameStatus::Waiting,
+                        GameStatus::InProgress,
+                        GameStatus::Completed,
+                        GameStatus::Aborted,
+                    ])
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .add_column(
+                        ColumnDef::new(Game::Status)
+                            .custom(GameStatus::Type)
+                            .not_null()
+                            .default(Expr::cust("'waiting'::game_status")),
+                    )
+                    .add_column(ColumnDef::new(Game::InitialTimeMs).big_integer().not_null().default(0))
+                    .add_column(ColumnDef::new(Game::IncrementMs).big_integer().not_null().default(0))
+                    .add_column(ColumnDef::new(Game::Rated).boolean().not_null().default(true))
+                    // Text rather than a short varchar: imported games keep
+                    // the PGN `Termination` header as written
+                    .add_column(ColumnDef::new(Game::Termination).text().null())
+                    .add_column(ColumnDef::new(Game::LastMoveAt).timestamp_with_time_zone().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        db.execute_unprepared(BACKFILL).await?;
+        for sql in UP_INDEXES {
+            db.execute_unprepared(sql).await?;
+        }
+
+        println!("Added status, time control, rated, termination and last_move_at columns to game table.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        for sql in DOWN_INDEXES {
+            db.execute_unprepared(sql).await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .drop_column(Game::Status)
+                    .drop_column(Game::InitialTimeMs)
+                    .drop_column(Game::IncrementMs)
+                    .drop_column(Game::Rated)
+                    .drop_column(Game::Termination)
+                    .drop_column(Game::LastMoveAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(GameStatus::Type).to_owned())
+            .await?;
+
+        println!("Removed lifecycle columns from game table.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    Status,
+    InitialTimeMs,
+    IncrementMs,
+    Rated,
+    Termination,
+    LastMoveAt,
+}
+
+#[derive(DeriveIden)]
+enum GameStatus {
+    #[sea_orm(iden = "game_status")]
+    Type,
+    Waiting,
+    InProgress,
+    Completed,
+    Aborted,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
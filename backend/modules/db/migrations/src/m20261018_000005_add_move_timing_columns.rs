@@ -0,0 +1,51 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Timing of each move; empty for imported games without clock annotations
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, GameMove::Table))
+                    .add_column(ColumnDef::new(GameMove::ClockMs).big_integer().null())
+                    .add_column(ColumnDef::new(GameMove::TimeSpentMs).big_integer().null())
+                    .add_column(ColumnDef::new(GameMove::LagCompensationMs).big_integer().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Added clock and timing columns to game_move table.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, GameMove::Table))
+                    .drop_column(GameMove::ClockMs)
+                    .drop_column(GameMove::TimeSpentMs)
+                    .drop_column(GameMove::LagCompensationMs)
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Removed clock and timing columns from game_move table.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum GameMove {
+    Table,
+    ClockMs,
+    TimeSpentMs,
+    LagCompensationMs,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
@@ -0,0 +1,46 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Compact move list (see chess::encoding). Existing rows stay empty
+        // until the `backfill_moves_encoded` tool has run.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .add_column(ColumnDef::new(Game::MovesEncoded).binary().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Added moves_encoded column to game table.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .drop_column(Game::MovesEncoded)
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Removed moves_encoded column from game table.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    MovesEncoded,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
@@ -0,0 +1,92 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Cold storage for finished games. Only the columns needed to find a
+        // game are kept uncompressed; the game and its moves live in `payload`.
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, GameArchive::Table))
+                    .if_not_exists()
+                    .col(ColumnDef::new(GameArchive::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(GameArchive::WhitePlayer).uuid().null())
+                    .col(ColumnDef::new(GameArchive::BlackPlayer).uuid().null())
+                    .col(ColumnDef::new(GameArchive::StartedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(GameArchive::ArchiveMonth).date().not_null())
+                    .col(
+                        ColumnDef::new(GameArchive::ArchivedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(ColumnDef::new(GameArchive::Payload).binary().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_game_archive_white_player_started_at")
+                    .table((Smdb, GameArchive::Table))
+                    .col(GameArchive::WhitePlayer)
+                    .col(GameArchive::StartedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_game_archive_black_player_started_at")
+                    .table((Smdb, GameArchive::Table))
+                    .col(GameArchive::BlackPlayer)
+                    .col(GameArchive::StartedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_game_archive_month_id")
+                    .table((Smdb, GameArchive::Table))
+                    .col(GameArchive::ArchiveMonth)
+                    .col(GameArchive::Id)
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Created game_archive table.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table((Smdb, GameArchive::Table)).to_owned())
+            .await?;
+
+        println!("Dropped game_archive table.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum GameArchive {
+    Table,
+    Id,
+    WhitePlayer,
+    BlackPlayer,
+    StartedAt,
+    ArchiveMonth,
+    ArchivedAt,
+    Payload,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
@@ -0,0 +1,53 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Position the game started from. Every existing game used the
+        // standard setup, which the default fills in.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .add_column(
+                        ColumnDef::new(Game::InitialFen)
+                            .text()
+                            .not_null()
+                            .default(STARTING_FEN),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Added initial_fen column to game table.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, Game::Table))
+                    .drop_column(Game::InitialFen)
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Removed initial_fen column from game table.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Game {
+    Table,
+    InitialFen,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
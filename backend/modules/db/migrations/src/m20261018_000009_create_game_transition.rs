@@ -0,0 +1,101 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Games settled by an arbiter rather than on the board
+        manager
+            .get_connection()
+            .execute_unprepared(r#"ALTER TYPE "game_status" ADD VALUE IF NOT EXISTS 'adjudicated'"#)
+            .await?;
+
+        // Audit trail of lifecycle transitions. There is no foreign key to
+        // `game` so the history outlives games moved to the archive.
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, GameTransition::Table))
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(GameTransition::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(GameTransition::GameId).uuid().not_null())
+                    .col(ColumnDef::new(GameTransition::FromState).string_len(16).not_null())
+                    .col(ColumnDef::new(GameTransition::ToState).string_len(16).not_null())
+                    .col(ColumnDef::new(GameTransition::Result).custom(ResultSide::Type).null())
+                    .col(ColumnDef::new(GameTransition::Termination).string_len(32).null())
+                    .col(ColumnDef::new(GameTransition::Source).string_len(16).not_null())
+                    .col(ColumnDef::new(GameTransition::Actor).uuid().null())
+                    .col(
+                        ColumnDef::new(GameTransition::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_game_transition_game_id_id")
+                    .table((Smdb, GameTransition::Table))
+                    .col(GameTransition::GameId)
+                    .col(GameTransition::Id)
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Created game_transition table and added adjudicated game status.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table((Smdb, GameTransition::Table)).to_owned())
+            .await?;
+
+        // Postgres cannot drop an enum label, so adjudicated games are folded
+        // back into completed and the label is left unused.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"UPDATE "smdb"."game" SET "status" = 'completed' WHERE "status" = 'adjudicated'"#,
+            )
+            .await?;
+
+        println!("Dropped game_transition table.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum GameTransition {
+    Table,
+    Id,
+    GameId,
+    FromState,
+    ToState,
+    Result,
+    Termination,
+    Source,
+    Actor,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ResultSide {
+    #[sea_orm(iden = "result_side")]
+    Type,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
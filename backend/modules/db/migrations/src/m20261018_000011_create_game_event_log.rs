@@ -0,0 +1,128 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Append-only log every game's state can be rebuilt from. Like
+        // game_transition it has no foreign key to `game` so the history
+        // outlives games moved to the archive.
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, GameEvent::Table))
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(GameEvent::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(GameEvent::GameId).uuid().not_null())
+                    .col(ColumnDef::new(GameEvent::EventIndex).integer().not_null())
+                    .col(ColumnDef::new(GameEvent::Kind).string_len(32).not_null())
+                    .col(ColumnDef::new(GameEvent::Payload).json_binary().not_null())
+                    .col(ColumnDef::new(GameEvent::Actor).uuid().null())
+                    .col(
+                        ColumnDef::new(GameEvent::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_game_event_game_id_event_index")
+                    .table((Smdb, GameEvent::Table))
+                    .col(GameEvent::GameId)
+                    .col(GameEvent::EventIndex)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Replayed state as of an event, so rebuilding a game only has to
+        // apply the events after its latest snapshot
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, GamePlayerStates::Table))
+                    .if_not_exists()
+                    .col(ColumnDef::new(GamePlayerStates::Id).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(GamePlayerStates::GameId).uuid().not_null())
+                    .col(ColumnDef::new(GamePlayerStates::EventIndex).integer().not_null())
+                    .col(ColumnDef::new(GamePlayerStates::MoveNumber).integer().not_null())
+                    .col(ColumnDef::new(GamePlayerStates::PlayerId).uuid().null())
+                    .col(ColumnDef::new(GamePlayerStates::StateJson).json_binary().not_null())
+                    .col(
+                        ColumnDef::new(GamePlayerStates::Timestamp)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_game_player_states_game_id_event_index")
+                    .table((Smdb, GamePlayerStates::Table))
+                    .col(GamePlayerStates::GameId)
+                    .col(GamePlayerStates::EventIndex)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Created game_event and game_player_states tables.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table((Smdb, GamePlayerStates::Table)).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table((Smdb, GameEvent::Table)).to_owned())
+            .await?;
+
+        println!("Dropped game_event and game_player_states tables.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum GameEvent {
+    Table,
+    Id,
+    GameId,
+    EventIndex,
+    Kind,
+    Payload,
+    Actor,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum GamePlayerStates {
+    Table,
+    Id,
+    GameId,
+    EventIndex,
+    MoveNumber,
+    PlayerId,
+    StateJson,
+    Timestamp,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
@@ -0,0 +1,50 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Latest state of each live WebSocket room, reloaded after a restart
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, SocketRoom::Table))
+                    .if_not_exists()
+                    .col(ColumnDef::new(SocketRoom::Id).string_len(64).not_null().primary_key())
+                    .col(ColumnDef::new(SocketRoom::State).json_binary().not_null())
+                    .col(
+                        ColumnDef::new(SocketRoom::SavedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Created socket_room table.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table((Smdb, SocketRoom::Table)).to_owned())
+            .await?;
+
+        println!("Dropped socket_room table.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum SocketRoom {
+    Table,
+    Id,
+    State,
+    SavedAt,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
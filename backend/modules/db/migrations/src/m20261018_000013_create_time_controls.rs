@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Saved clocks of a game, one row per game; times in milliseconds
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, TimeControls::Table))
+                    .if_not_exists()
+                    .col(ColumnDef::new(TimeControls::GameId).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(TimeControls::WhiteRemainingTime).big_integer().not_null())
+                    .col(ColumnDef::new(TimeControls::BlackRemainingTime).big_integer().not_null())
+                    .col(ColumnDef::new(TimeControls::InitialTime).big_integer().not_null())
+                    .col(ColumnDef::new(TimeControls::Increment).big_integer().not_null())
+                    .col(ColumnDef::new(TimeControls::Delay).big_integer().not_null())
+                    .col(
+                        ColumnDef::new(TimeControls::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Created time_controls table.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table((Smdb, TimeControls::Table)).to_owned())
+            .await?;
+
+        println!("Dropped time_controls table.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum TimeControls {
+    Table,
+    GameId,
+    WhiteRemainingTime,
+    BlackRemainingTime,
+    InitialTime,
+    Increment,
+    Delay,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
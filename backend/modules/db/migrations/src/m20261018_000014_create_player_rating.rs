@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20250428_121011_create_players_table::Player;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Glicko-2 rating of a player; players without a row are unrated
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, PlayerRating::Table))
+                    .if_not_exists()
+                    .col(ColumnDef::new(PlayerRating::PlayerId).uuid().not_null().primary_key())
+                    .col(ColumnDef::new(PlayerRating::Rating).double().not_null().default(1500.0))
+                    .col(ColumnDef::new(PlayerRating::Deviation).double().not_null().default(350.0))
+                    .col(ColumnDef::new(PlayerRating::Volatility).double().not_null().default(0.06))
+                    .col(ColumnDef::new(PlayerRating::LastRatedAt).timestamp_with_time_zone().null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_player_rating_player")
+                            .from((Smdb, PlayerRating::Table), PlayerRating::PlayerId)
+                            .to(Player::Table, Player::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Created player_rating table.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table((Smdb, PlayerRating::Table)).to_owned())
+            .await?;
+
+        println!("Dropped player_rating table.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum PlayerRating {
+    Table,
+    PlayerId,
+    Rating,
+    Deviation,
+    Volatility,
+    LastRatedAt,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
@@ -0,0 +1,138 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20250428_121011_create_players_table::Player;
+
+/// Ratings written before categories existed are treated as blitz
+const UP_KEY: &str = r#"ALTER TABLE "smdb"."player_rating"
+    ALTER COLUMN "category" DROP DEFAULT,
+    DROP CONSTRAINT "player_rating_pkey",
+    ADD PRIMARY KEY ("player_id", "category")"#;
+
+/// Keeps one row per player, their most recently rated category
+const DOWN_KEY: &str = r#"DELETE FROM "smdb"."player_rating"
+    WHERE ("player_id", "category") NOT IN (
+        SELECT DISTINCT ON ("player_id") "player_id", "category"
+        FROM "smdb"."player_rating"
+        ORDER BY "player_id", "last_rated_at" DESC NULLS LAST, "category"
+    );
+ALTER TABLE "smdb"."player_rating"
+    DROP CONSTRAINT "player_rating_pkey",
+    ADD PRIMARY KEY ("player_id")"#;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One rating per speed (bullet ... correspondence) and per variant
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, PlayerRating::Table))
+                    .add_column(ColumnDef::new(PlayerRating::Category).string_len(32).not_null().default("blitz"))
+                    .add_column(ColumnDef::new(PlayerRating::GamesPlayed).integer().not_null().default(0))
+                    .to_owned(),
+            )
+            .await?;
+        manager.get_connection().execute_unprepared(UP_KEY).await?;
+
+        // Every rating change, for rating graphs
+        manager
+            .create_table(
+                Table::create()
+                    .table((Smdb, RatingHistory::Table))
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RatingHistory::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(RatingHistory::PlayerId).uuid().not_null())
+                    .col(ColumnDef::new(RatingHistory::Category).string_len(32).not_null())
+                    // No foreign key, so the history outlives games moved to the archive
+                    .col(ColumnDef::new(RatingHistory::GameId).uuid().null())
+                    .col(ColumnDef::new(RatingHistory::Rating).double().not_null())
+                    .col(ColumnDef::new(RatingHistory::Deviation).double().not_null())
+                    .col(ColumnDef::new(RatingHistory::Volatility).double().not_null())
+                    .col(ColumnDef::new(RatingHistory::RatingChange).double().not_null())
+                    .col(
+                        ColumnDef::new(RatingHistory::RecordedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_rating_history_player")
+                            .from((Smdb, RatingHistory::Table), RatingHistory::PlayerId)
+                            .to(Player::Table, Player::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_rating_history_player_category_recorded_at")
+                    .table((Smdb, RatingHistory::Table))
+                    .col(RatingHistory::PlayerId)
+                    .col(RatingHistory::Category)
+                    .col(RatingHistory::RecordedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Added rating categories and created rating_history table.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table((Smdb, RatingHistory::Table)).to_owned())
+            .await?;
+
+        manager.get_connection().execute_unprepared(DOWN_KEY).await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, PlayerRating::Table))
+                    .drop_column(PlayerRating::Category)
+                    .drop_column(PlayerRating::GamesPlayed)
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Dropped rating categories and rating_history table.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum PlayerRating {
+    Table,
+    Category,
+    GamesPlayed,
+}
+
+#[derive(DeriveIden)]
+enum RatingHistory {
+    Table,
+    Id,
+    PlayerId,
+    Category,
+    GameId,
+    Rating,
+    Deviation,
+    Volatility,
+    RatingChange,
+    RecordedAt,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
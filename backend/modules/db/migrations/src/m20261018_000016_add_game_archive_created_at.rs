@@ -0,0 +1,77 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Archived games are listed together with live ones, paginated on the
+/// game's (created_at, id), so the archive needs the same key and indexes.
+const UP_INDEXES: &[&str] = &[
+    r#"CREATE INDEX IF NOT EXISTS "idx_game_archive_created_at_id" ON "smdb"."game_archive" ("created_at" DESC, "id" DESC)"#,
+    r#"CREATE INDEX IF NOT EXISTS "idx_game_archive_white_player_created_at_id" ON "smdb"."game_archive" ("white_player", "created_at" DESC, "id" DESC)"#,
+    r#"CREATE INDEX IF NOT EXISTS "idx_game_archive_black_player_created_at_id" ON "smdb"."game_archive" ("black_player", "created_at" DESC, "id" DESC)"#,
+];
+
+const DOWN_INDEXES: &[&str] = &[
+    r#"DROP INDEX IF EXISTS "smdb"."idx_game_archive_black_player_created_at_id""#,
+    r#"DROP INDEX IF EXISTS "smdb"."idx_game_archive_white_player_created_at_id""#,
+    r#"DROP INDEX IF EXISTS "smdb"."idx_game_archive_created_at_id""#,
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, GameArchive::Table))
+                    .add_column(
+                        ColumnDef::new(GameArchive::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The exact creation time is inside the compressed payload; games are
+        // created moments before they start, which is close enough to order by
+        let db = manager.get_connection();
+        db.execute_unprepared(r#"UPDATE "smdb"."game_archive" SET "created_at" = "started_at""#)
+            .await?;
+        for sql in UP_INDEXES {
+            db.execute_unprepared(sql).await?;
+        }
+
+        println!("Added created_at column to game_archive table.");
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        for sql in DOWN_INDEXES {
+            db.execute_unprepared(sql).await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table((Smdb, GameArchive::Table))
+                    .drop_column(GameArchive::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        println!("Removed created_at column from game_archive table.");
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum GameArchive {
+    Table,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+struct Smdb;
@@ -0,0 +1,77 @@
+use db_entity::game;
+use db_entity::prelude::*;
+use sea_orm::{*, sea_query::Expr};
+use std::env;
+use dotenv::dotenv;
+use uuid::Uuid;
+
+// Games loaded and updated per transaction
+const BATCH_SIZE: u64 = 500;
+
+/// Fill `game.moves_encoded` for rows stored before the compact encoding
+/// existed, replaying the SAN list in `pgn.moves` from `initial_fen`. Safe
+/// to re-run: only rows without an encoding are touched.
+#[tokio::main]
+async fn main() -> Result<(), DbErr> {
+    dotenv().ok();
+    let db_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let db = Database::connect(&db_url).await?;
+
+    let mut last_id: Option<Uuid> = None;
+    let (mut encoded, mut skipped) = (0usize, 0usize);
+    let (mut json_bytes, mut encoded_bytes) = (0usize, 0usize);
+
+    loop {
+        let mut query = Game::find()
+            .filter(game::Column::MovesEncoded.is_null())
+            .order_by_asc(game::Column::Id)
+            .limit(BATCH_SIZE);
+        if let Some(id) = last_id {
+            query = query.filter(game::Column::Id.gt(id));
+        }
+        let games = query.all(&db).await?;
+        let Some(last) = games.last() else {
+            break;
+        };
+        last_id = Some(last.id);
+
+        let txn = db.begin().await?;
+        for game in &games {
+            let moves: Vec<&str> = game.pgn["moves"]
+                .as_array()
+                .map(|m| m.iter().filter_map(|v| v.as_str()).collect())
+                .unwrap_or_default();
+
+            match chess::encode_moves(&game.initial_fen, &moves) {
+                Ok(bytes) => {
+                    json_bytes += game.pgn["moves"].to_string().len();
+                    encoded_bytes += bytes.len();
+                    Game::update_many()
+                        .col_expr(game::Column::MovesEncoded, Expr::value(bytes))
+                        .filter(game::Column::Id.eq(game.id))
+                        .exec(&txn)
+                        .await?;
+                    encoded += 1;
+                }
+                Err(e) => {
+                    eprintln!("Skipping game {}: {}", game.id, e);
+                    skipped += 1;
+                }
+            }
+        }
+        txn.commit().await?;
+        println!("  Processed {} games ({} skipped)", encoded + skipped, skipped);
+    }
+
+    println!("Encoded {} games, skipped {}.", encoded, skipped);
+    if encoded > 0 {
+        println!(
+            "Move lists: {} bytes as JSON, {} bytes encoded ({:.1}x smaller).",
+            json_bytes,
+            encoded_bytes,
+            json_bytes as f64 / encoded_bytes.max(1) as f64
+        );
+    }
+
+    Ok(())
+}
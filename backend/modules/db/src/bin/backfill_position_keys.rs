@@ -0,0 +1,72 @@
+use db_entity::game;
+use db_entity::prelude::*;
+use sea_orm::{*, sea_query::Expr};
+use std::env;
+use dotenv::dotenv;
+use uuid::Uuid;
+
+// Games loaded and updated per transaction
+const BATCH_SIZE: u64 = 500;
+
+/// Fill `game.position_keys` for rows stored before position search existed,
+/// replaying the SAN list in `pgn.moves` from `initial_fen`. Safe to re-run:
+/// only rows without any keys are touched.
+#[tokio::main]
+async fn main() -> Result<(), DbErr> {
+    dotenv().ok();
+    let db_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let db = Database::connect(&db_url).await?;
+
+    let mut last_id: Option<Uuid> = None;
+    let (mut filled, mut skipped) = (0usize, 0usize);
+
+    loop {
+        let mut query = Game::find()
+            .filter(Expr::cust(r#"cardinality("position_keys") = 0"#))
+            .order_by_asc(game::Column::Id)
+            .limit(BATCH_SIZE);
+        if let Some(id) = last_id {
+            query = query.filter(game::Column::Id.gt(id));
+        }
+        let games = query.all(&db).await?;
+        let Some(last) = games.last() else {
+            break;
+        };
+        last_id = Some(last.id);
+
+        let txn = db.begin().await?;
+        for game in &games {
+            match position_keys(game) {
+                Ok(keys) => {
+                    Game::update_many()
+                        .col_expr(game::Column::PositionKeys, Expr::value(keys))
+                        .filter(game::Column::Id.eq(game.id))
+                        .exec(&txn)
+                        .await?;
+                    filled += 1;
+                }
+                Err(e) => {
+                    eprintln!("Skipping game {}: {}", game.id, e);
+                    skipped += 1;
+                }
+            }
+        }
+        txn.commit().await?;
+        println!("  Processed {} games ({} skipped)", filled + skipped, skipped);
+    }
+
+    println!("Filled position keys for {} games, skipped {}.", filled, skipped);
+    Ok(())
+}
+
+/// Keys of the starting position and of every position after each move
+fn position_keys(game: &game::Model) -> Result<Vec<i64>, chess::MoveError> {
+    let mut fen = game.initial_fen.clone();
+    let mut keys = vec![chess::position_key(&fen)?];
+    let moves = game.pgn["moves"].as_array().cloned().unwrap_or_default();
+    for san in moves.iter().filter_map(|m| m.as_str()) {
+        fen = chess::play_move(&fen, san)?.fen;
+        keys.push(chess::position_key(&fen)?);
+    }
+    Ok(keys)
+}
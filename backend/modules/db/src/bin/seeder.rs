@@ -1,6 +1,6 @@
 use db_entity::prelude::*;
 use db_entity::{player, game};
-use db_entity::game::{ResultSide, GameVariant}; // Added imports
+use db_entity::game::{ResultSide, GameVariant, GameStatus}; // Added imports
 use sea_orm::{*, prelude::*};
 use std::env;
 use dotenv::dotenv;
@@ -92,8 +92,8 @@ async fn main() -> Result<(), DbErr> {
 
         let game = game::ActiveModel {
             id: Set(Uuid::new_v4()),
-            white_player: Set(white_player_id),
-            black_player: Set(black_player_id),
+            white_player: Set(Some(white_player_id)),
+            black_player: Set(Some(black_player_id)),
             fen: Set(STARTING_FEN.to_string()), // Simple FEN for now
             pgn: Set(json!({ "moves": "e4 c5 ...", "final_ply": rng.gen_range(10..150) })), // Added final_ply for benchmark
             result: Set(Some(results.choose(&mut rng).unwrap().clone())),
@@ -104,6 +104,17 @@ async fn main() -> Result<(), DbErr> {
             updated_at: Set(Utc::now().into()),
             is_imported: Set(false),
             original_pgn: Set(None),
+            import_hash: Set(None),
+            position_keys: Set(vec![]),
+            status: Set(GameStatus::Completed),
+            initial_time_ms: Set(duration_sec as i64 * 1000),
+            increment_ms: Set(0),
+            rated: Set(true),
+            termination: Set(None),
+            last_move_at: Set(None),
+            moves_encoded: Set(None),
+            initial_fen: Set(STARTING_FEN.to_string()),
+            version: Set(0),
         };
 
         Game::insert(game).exec(&db).await?;
@@ -1,22 +1,12 @@
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use validator::Validate;
-use once_cell::sync::Lazy;
-use regex::Regex;
 
-// Define a regex for validating FEN chess position notation
-static FEN_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(
-      r"^(?=\S*K)(?=\S*k)([rnbqkpRNBQKP1-8]+/){7}[rnbqkpRNBQKP1-8]+\s[bw]\s(-|[KQkq]+)\s(-|[a-h][36])\s\d+\s\d+$"
-    ).unwrap()
-});
+use crate::fen::validate_fen;
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct AiSuggestionRequest {
-    #[validate(regex(
-        path = "FEN_REGEX", 
-        message = "Must be a valid FEN string in format: [piece placement] [active color] [castling] [en passant] [halfmove clock] [fullmove number]"
-    ))]
+    #[validate(custom = "validate_fen")]
     #[schema(example = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")]
     pub fen: String,
     
@@ -48,10 +38,7 @@ pub struct AiSuggestionResponse {
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct PositionAnalysisRequest {
-    #[validate(regex(
-        path = "FEN_REGEX",
-        message = "Must be a valid FEN string in format: [piece placement] [active color] [castling] [en passant] [halfmove clock] [fullmove number]"
-    ))]
+    #[validate(custom = "validate_fen")]
     #[schema(example = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")]
     pub fen: String,
     
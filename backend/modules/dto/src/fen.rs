@@ -0,0 +1,13 @@
+//! Validation for FEN fields in requests
+
+use validator::ValidationError;
+
+/// Reject FEN strings that are malformed or describe an impossible
+/// position, reporting the chess crate's reason as the message
+pub fn validate_fen(fen: &str) -> Result<(), ValidationError> {
+    chess::validate_fen(fen).map_err(|e| {
+        let mut error = ValidationError::new("fen");
+        error.message = Some(e.to_string().into());
+        error
+    })
+}
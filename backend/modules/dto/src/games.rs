@@ -5,10 +5,17 @@ use validator::{Validate, ValidationError};
 use chrono::{DateTime, Utc};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use db_entity::game::{self, GameVariant, ResultSide};
 
-// Define a regex for validating chess moves in algebraic notation
+use crate::fen::validate_fen;
+
+// Shape of a move in UCI (e2e4, e7e8q, e1h1) or SAN (Nf3, exd5, e8=Q+, O-O, 0-0-0);
+// legality is checked against the position by the chess crate
 static CHESS_MOVE_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"^[a-h][1-8][a-h][1-8][qrbnQRBN]?$").unwrap()
+    Regex::new(
+        r"^([a-h][1-8][a-h][1-8][qrbnQRBN]?|[KQRBN]?[a-h]?[1-8]?x?[a-h][1-8](=?[QRBN])?[+#]?|([O0]-){1,2}[O0][+#]?)$",
+    )
+    .unwrap()
 });
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -22,7 +29,7 @@ pub enum PlayerColor {
 }
 
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub enum GameStatus {
     #[serde(rename = "waiting")]
     Waiting,
@@ -32,6 +39,32 @@ pub enum GameStatus {
     Completed,
     #[serde(rename = "aborted")]
     Aborted,
+    #[serde(rename = "adjudicated")]
+    Adjudicated,
+}
+
+impl From<game::GameStatus> for GameStatus {
+    fn from(value: game::GameStatus) -> Self {
+        match value {
+            game::GameStatus::Waiting => GameStatus::Waiting,
+            game::GameStatus::InProgress => GameStatus::InProgress,
+            game::GameStatus::Completed => GameStatus::Completed,
+            game::GameStatus::Aborted => GameStatus::Aborted,
+            game::GameStatus::Adjudicated => GameStatus::Adjudicated,
+        }
+    }
+}
+
+impl From<GameStatus> for game::GameStatus {
+    fn from(value: GameStatus) -> Self {
+        match value {
+            GameStatus::Waiting => game::GameStatus::Waiting,
+            GameStatus::InProgress => game::GameStatus::InProgress,
+            GameStatus::Completed => game::GameStatus::Completed,
+            GameStatus::Aborted => game::GameStatus::Aborted,
+            GameStatus::Adjudicated => game::GameStatus::Adjudicated,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -44,8 +77,12 @@ pub enum GameResult {
     Draw,
     #[serde(rename = "in_progress")]
     InProgress,
+    #[serde(rename = "abandoned")]
+    Abandoned,
 }
 
+/// The authenticated player creates the game and is seated according to
+/// `player_color`
 #[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct CreateGameRequest {
     #[validate(range(min = 60, max = 7200, message = "Time control must be between 1 minute and 2 hours"))]
@@ -55,7 +92,39 @@ pub struct CreateGameRequest {
     pub increment: i32,
     
     pub player_color: Option<PlayerColor>,
+
+    /// Seats this opponent immediately instead of waiting for someone to join
+    #[schema(value_type = Option<String>, format = "uuid")]
     pub opponent_id: Option<Uuid>,
+
+    /// Whether the game affects ratings; defaults to true
+    pub rated: Option<bool>,
+
+    /// "standard" (default) or "chess960"
+    #[schema(example = "chess960")]
+    pub variant: Option<String>,
+
+    /// Position the game starts from. Defaults to the standard setup, or to
+    /// a random Chess960 position for Chess960 games.
+    #[validate(custom = "validate_start_position")]
+    pub start_position: Option<StartPosition>,
+}
+
+/// Starting position requested when creating a game
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StartPosition {
+    /// The regular chess setup
+    Standard,
+    /// A Chess960 position by its standard number (0-959, 518 is the regular setup)
+    Chess960 { number: u16 },
+    /// A Chess960 position picked at random
+    RandomChess960,
+    /// Any legal position given as FEN
+    Fen {
+        #[schema(example = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1")]
+        fen: String,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -63,8 +132,8 @@ pub struct GameDisplayDTO {
     #[schema(value_type = String, format = "uuid", example = "123e4567-e89b-12d3-a456-426614174000")]
     pub id: Uuid,
     
-    #[schema(value_type = String, format = "uuid", example = "123e4567-e89b-12d3-a456-426614174001")]
-    pub white_player_id: Uuid,
+    #[schema(value_type = Option<String>, format = "uuid", example = "123e4567-e89b-12d3-a456-426614174001")]
+    pub white_player_id: Option<Uuid>,
     
     #[schema(value_type = Option<String>, format = "uuid", example = "123e4567-e89b-12d3-a456-426614174002")]
     pub black_player_id: Option<Uuid>,
@@ -74,18 +143,43 @@ pub struct GameDisplayDTO {
     
     #[schema(example = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")]
     pub current_fen: String,
+
+    /// Game variant, e.g. "standard" or "chess960"
+    #[schema(example = "standard")]
+    pub variant: String,
+
+    /// Position the game started from
+    #[schema(example = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")]
+    pub initial_fen: String,
     
     pub move_history: Vec<String>,
+
+    /// Number of half-moves played
+    #[schema(example = 0)]
+    pub ply: i32,
+
+    /// Incremented on every change to the game
+    #[schema(example = 0)]
+    pub version: i64,
+
     pub time_control: i32,
     pub increment: i32,
     pub white_time_remaining: i32,
     pub black_time_remaining: i32,
+    pub rated: bool,
+
+    /// How the game ended (e.g. checkmate, stalemate, abandoned)
+    #[schema(example = "checkmate")]
+    pub termination: Option<String>,
     
     #[schema(value_type = String, format = "date-time")]
     pub created_at: DateTime<Utc>,
     
     #[schema(value_type = Option<String>, format = "date-time")]
     pub started_at: Option<DateTime<Utc>>,
+
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub last_move_at: Option<DateTime<Utc>>,
     
     #[schema(value_type = String, format = "date-time")]
     pub updated_at: DateTime<Utc>,
@@ -95,17 +189,102 @@ pub struct GameDisplayDTO {
 pub struct MakeMoveRequest {
     #[validate(regex(
         path = "CHESS_MOVE_REGEX",
-        message = "Move must be in valid algebraic notation (e.g., 'e2e4', 'g7g8q')"
+        message = "Move must be in UCI or SAN notation (e.g., 'e2e4', 'g7g8q', 'Nf3', 'O-O')"
     ))]
-    #[schema(example = "e2e4")]
+    #[schema(example = "Nf3")]
     pub chess_move: String,
+
+    /// Network lag measured by the client; credited back up to a server limit
+    #[validate(range(max = 10000, message = "Lag must not exceed 10 seconds"))]
+    #[schema(example = 120)]
+    pub lag_ms: Option<u32>,
+
+    /// Ply this move will have (1 for White's first move). A move for a ply
+    /// already played is accepted again only if it is the same move, so
+    /// retries are safe; anything else is rejected as stale.
+    #[validate(range(min = 1, message = "Ply starts at 1"))]
+    #[schema(example = 1)]
+    pub ply: Option<u32>,
 }
 
-#[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
-pub struct JoinGameRequest {
-    #[validate(custom = "validate_uuid")]
-    #[schema(value_type = String, format = "uuid", example = "123e4567-e89b-12d3-a456-426614174000")]
-    pub player_id: Uuid,
+/// A played move in both notations
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PlayedMoveDTO {
+    /// Canonical UCI; Chess960 castling is written king-takes-rook
+    #[schema(example = "g1f3")]
+    pub uci: String,
+
+    #[schema(example = "Nf3")]
+    pub san: String,
+}
+
+impl From<game::Model> for GameDisplayDTO {
+    fn from(value: game::Model) -> Self {
+        let status = GameStatus::from(value.status);
+        let result = match value.result {
+            None | Some(ResultSide::Ongoing) => GameResult::InProgress,
+            Some(ResultSide::WhiteWins) => GameResult::WhiteWin,
+            Some(ResultSide::BlackWins) => GameResult::BlackWin,
+            Some(ResultSide::Draw) => GameResult::Draw,
+            Some(ResultSide::Abandoned) => GameResult::Abandoned,
+        };
+
+        let move_history: Vec<String> = value.pgn["moves"]
+            .as_array()
+            .map(|moves| {
+                moves
+                    .iter()
+                    .filter_map(|m| m.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let time_control = (value.initial_time_ms / 1000) as i32;
+
+        Self {
+            id: value.id,
+            white_player_id: value.white_player,
+            black_player_id: value.black_player,
+            status,
+            result,
+            current_fen: value.fen,
+            variant: variant_name(&value.variant).to_string(),
+            initial_fen: value.initial_fen,
+            ply: move_history.len() as i32,
+            move_history,
+            version: value.version,
+            time_control,
+            increment: (value.increment_ms / 1000) as i32,
+            // Clocks are only tracked by the live socket session
+            white_time_remaining: time_control,
+            black_time_remaining: time_control,
+            rated: value.rated,
+            termination: value.termination,
+            created_at: value.created_at.into(),
+            started_at: (status != GameStatus::Waiting).then(|| value.started_at.into()),
+            last_move_at: value.last_move_at.map(Into::into),
+            updated_at: value.updated_at.into(),
+        }
+    }
+}
+
+/// API name of a variant, as accepted by the `variant` filters
+pub fn variant_name(variant: &GameVariant) -> &'static str {
+    match variant {
+        GameVariant::Standard => "standard",
+        GameVariant::Chess960 => "chess960",
+        GameVariant::ThreeCheck => "three_check",
+        GameVariant::Blitz => "blitz",
+        GameVariant::Rapid => "rapid",
+        GameVariant::Classical => "classical",
+    }
+}
+
+/// Custom starting positions must be legal
+fn validate_start_position(start: &StartPosition) -> Result<(), ValidationError> {
+    match start {
+        StartPosition::Fen { fen } => validate_fen(fen),
+        _ => Ok(()),
+    }
 }
 
 // UUID validation function
@@ -135,6 +314,75 @@ pub struct ListGamesQuery {
     pub cursor: Option<String>,
 }
 
+/// Filters for searching stored games. All filters are optional and combined with AND.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, ToSchema, Validate)]
+pub struct GameSearchQuery {
+    #[schema(value_type = Option<String>, format = "uuid")]
+    pub player_id: Option<Uuid>,
+
+    /// ECO code or prefix (e.g. "B" or "B90"); only imported games carry one
+    #[schema(example = "B90")]
+    pub eco: Option<String>,
+
+    /// Opening name prefix, case-insensitive; only imported games carry one
+    #[schema(example = "Sicilian Defense")]
+    pub opening: Option<String>,
+
+    /// Minimum average rating of the two players
+    #[schema(example = 1800)]
+    pub min_rating: Option<i32>,
+
+    /// Maximum average rating of the two players
+    #[schema(example = 2200)]
+    pub max_rating: Option<i32>,
+
+    /// Only games started at or after this time
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub since: Option<DateTime<Utc>>,
+
+    /// Only games started before this time
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub until: Option<DateTime<Utc>>,
+
+    /// One of white_win, black_win, draw, abandoned or in_progress
+    #[schema(example = "white_win")]
+    pub result: Option<String>,
+
+    /// Termination reason (e.g. checkmate, stalemate, abandoned, time_forfeit)
+    #[schema(example = "checkmate")]
+    pub termination: Option<String>,
+
+    #[schema(example = "standard")]
+    pub variant: Option<String>,
+
+    /// Time control category: bullet, blitz, rapid or classical
+    #[schema(example = "blitz")]
+    pub speed: Option<String>,
+
+    /// Minimum number of full moves
+    #[validate(range(min = 0, max = 10000, message = "Move count must be between 0 and 10000"))]
+    #[schema(example = 20)]
+    pub min_moves: Option<i32>,
+
+    /// Maximum number of full moves
+    #[validate(range(min = 0, max = 10000, message = "Move count must be between 0 and 10000"))]
+    #[schema(example = 60)]
+    pub max_moves: Option<i32>,
+
+    /// Games that reached this exact position (move counters are ignored)
+    #[validate(custom = "validate_fen")]
+    #[schema(example = "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2")]
+    pub fen: Option<String>,
+
+    /// Games that reached the position with this Zobrist key
+    pub zobrist: Option<i64>,
+
+    #[schema(default = 10, example = 10)]
+    pub limit: Option<u64>,
+
+    pub cursor: Option<String>,
+}
+
 /// Request body for importing a game from PGN format
 #[derive(Debug, Serialize, Deserialize, ToSchema, Validate)]
 pub struct ImportGameRequest {
@@ -168,3 +416,145 @@ pub struct ImportGameResponse {
     
     pub error: Option<String>,
 }
+
+/// Lifecycle of a bulk import job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub enum ImportJobState {
+    #[serde(rename = "queued")]
+    Queued,
+    #[serde(rename = "running")]
+    Running,
+    #[serde(rename = "completed")]
+    Completed,
+}
+
+/// A game from a bulk upload that could not be imported
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BulkImportError {
+    /// Zero-based position of the game in the upload
+    #[schema(example = 3)]
+    pub index: usize,
+
+    #[schema(example = "Magnus Carlsen")]
+    pub white_player: Option<String>,
+
+    #[schema(example = "Hikaru Nakamura")]
+    pub black_player: Option<String>,
+
+    #[schema(example = "Illegal move at move 12: 'Ke3' - Move is not legal in this position")]
+    pub error: String,
+}
+
+/// Progress of a bulk PGN import job
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BulkImportJob {
+    #[schema(value_type = String, format = "uuid")]
+    pub id: Uuid,
+
+    pub state: ImportJobState,
+
+    /// Number of games found in the upload
+    #[schema(example = 120)]
+    pub total: usize,
+
+    #[schema(example = 64)]
+    pub processed: usize,
+
+    #[schema(example = 60)]
+    pub imported: usize,
+
+    /// Games skipped because they were already imported
+    #[schema(example = 2)]
+    pub duplicates: usize,
+
+    #[schema(example = 2)]
+    pub failed: usize,
+
+    pub errors: Vec<BulkImportError>,
+
+    #[schema(value_type = String, format = "date-time")]
+    pub created_at: DateTime<Utc>,
+
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Output format for game exports
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub enum ExportFormat {
+    #[default]
+    #[serde(rename = "pgn")]
+    Pgn,
+    #[serde(rename = "ndjson")]
+    Ndjson,
+}
+
+/// Annotations to include when exporting a single game as PGN
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ExportGameQuery {
+    /// Include `[%clk]` remaining-time annotations
+    #[serde(default)]
+    pub clocks: bool,
+
+    /// Include `[%eval]` engine evaluations
+    #[serde(default)]
+    pub evals: bool,
+
+    /// Include move comments
+    #[serde(default)]
+    pub comments: bool,
+}
+
+/// Filters and options for exporting a player's game archive
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct PlayerGamesExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+
+    /// Only games started at or after this time
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub since: Option<DateTime<Utc>>,
+
+    /// Only games started before this time
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub until: Option<DateTime<Utc>>,
+
+    #[schema(example = "standard")]
+    pub variant: Option<String>,
+
+    pub rated: Option<bool>,
+
+    /// One of white_win, black_win, draw, abandoned or in_progress
+    #[schema(example = "white_win")]
+    pub result: Option<String>,
+
+    #[serde(default)]
+    pub clocks: bool,
+
+    #[serde(default)]
+    pub evals: bool,
+
+    #[serde(default)]
+    pub comments: bool,
+}
+
+impl PlayerGamesExportQuery {
+    /// The annotation options of this query
+    pub fn annotations(&self) -> ExportGameQuery {
+        ExportGameQuery {
+            clocks: self.clocks,
+            evals: self.evals,
+            comments: self.comments,
+        }
+    }
+}
+
+/// One line of an NDJSON game export
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct GameExportRecord {
+    #[serde(flatten)]
+    pub game: GameDisplayDTO,
+
+    /// The game rendered as PGN
+    pub pgn: String,
+}
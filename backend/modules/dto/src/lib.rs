@@ -2,4 +2,5 @@ pub mod players;
 pub mod responses;
 pub mod games;
 pub mod auth;
-pub mod ai;
\ No newline at end of file
+pub mod ai;
+pub mod fen;
@@ -1,4 +1,7 @@
+use chrono::{DateTime, Utc};
 use db_entity::player::Model;
+use db_entity::player_rating::{self, RatingCategory};
+use db_entity::rating_history;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
@@ -136,3 +139,64 @@ impl From<Model> for DisplayPlayer {
         }
     }
 }
+
+/// Ratings stay provisional until this many rated games in their category
+pub const PROVISIONAL_GAMES: i32 = 10;
+
+/// A player's rating in one category
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PlayerRatingDTO {
+    #[schema(value_type = String, example = "blitz")]
+    pub category: RatingCategory,
+    pub rating: i32,
+    pub deviation: i32,
+    pub games_played: i32,
+    /// Fewer than `PROVISIONAL_GAMES` rated games played
+    pub provisional: bool,
+}
+
+impl From<player_rating::Model> for PlayerRatingDTO {
+    fn from(value: player_rating::Model) -> Self {
+        Self {
+            category: value.category,
+            rating: value.rating.round() as i32,
+            deviation: value.deviation.round() as i32,
+            games_played: value.games_played,
+            provisional: value.games_played < PROVISIONAL_GAMES,
+        }
+    }
+}
+
+/// Filter for a player's rating history
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct RatingHistoryQuery {
+    /// Only changes in this category (bullet, blitz, rapid, classical,
+    /// correspondence, chess960 or three_check)
+    #[schema(value_type = Option<String>, example = "blitz")]
+    pub category: Option<RatingCategory>,
+}
+
+/// One point of a rating graph
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RatingHistoryEntryDTO {
+    #[schema(value_type = String, example = "blitz")]
+    pub category: RatingCategory,
+    pub game_id: Option<Uuid>,
+    pub rating: i32,
+    pub deviation: i32,
+    pub rating_change: i32,
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl From<rating_history::Model> for RatingHistoryEntryDTO {
+    fn from(value: rating_history::Model) -> Self {
+        Self {
+            category: value.category,
+            game_id: value.game_id,
+            rating: value.rating.round() as i32,
+            deviation: value.deviation.round() as i32,
+            rating_change: value.rating_change.round() as i32,
+            recorded_at: value.recorded_at.with_timezone(&Utc),
+        }
+    }
+}
@@ -20,6 +20,18 @@ pub enum ApiError {
         move_text: String,
         reason: String,
     },
+    /// Request is well-formed but cannot be applied (e.g. an illegal move)
+    BadRequest(String),
+    /// Caller is not allowed to act on the resource
+    Forbidden(String),
+    /// Request conflicts with the current state of the resource
+    Conflict(String),
+    /// Request was based on an outdated view of the resource; carries the
+    /// current state so the client can resynchronise
+    Stale {
+        message: String,
+        current: serde_json::Value,
+    },
 }
 
 impl From<DbErr> for ApiError {
@@ -89,6 +101,10 @@ impl fmt::Display for ApiError {
             ApiError::IllegalMoveError { move_number, move_text, reason } => {
                 write!(f, "Illegal move at move {}: '{}' - {}", move_number, move_text, reason)
             }
+            ApiError::BadRequest(msg) => write!(f, "{}", msg),
+            ApiError::Forbidden(msg) => write!(f, "{}", msg),
+            ApiError::Conflict(msg) => write!(f, "{}", msg),
+            ApiError::Stale { message, .. } => write!(f, "{}", message),
         }
     }
 }
@@ -124,6 +140,23 @@ impl ApiError {
                 "error": self.to_string(),
                 "code": 422
             })),
+            ApiError::BadRequest(_) => HttpResponse::BadRequest().json(json!({
+                "error": self.to_string(),
+                "code": 400
+            })),
+            ApiError::Forbidden(_) => HttpResponse::Forbidden().json(json!({
+                "error": self.to_string(),
+                "code": 403
+            })),
+            ApiError::Conflict(_) => HttpResponse::Conflict().json(json!({
+                "error": self.to_string(),
+                "code": 409
+            })),
+            ApiError::Stale { current, .. } => HttpResponse::Conflict().json(json!({
+                "error": self.to_string(),
+                "code": 409,
+                "current": current
+            })),
         }
     }
 }
@@ -1,7 +1,8 @@
 /// Pure Elo rating calculation.
 ///
 /// This module is intentionally stateless: it returns new ratings without
-/// performing any persistence or side-effects.
+/// performing any persistence or side-effects. Matchmaking and rated games
+/// use `glicko2`; this is kept for FIDE-style rating displays.
 ///
 /// Formula:
 /// - Expected score: E = 1 / (1 + 10^((opp - rating)/400))
@@ -0,0 +1,314 @@
+/// Glicko-2 rating calculation.
+///
+/// Like `elo`, this module is stateless: callers load a player's rating,
+/// pass it in with the results of a rating period and persist what comes
+/// back. Follows Glickman's "Example of the Glicko-2 system" (2013).
+///
+/// Each rating carries a deviation (how sure we are of the rating) and a
+/// volatility (how erratic the player's results are). Deviation shrinks as
+/// games are played and grows again while a player is inactive.
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// Factor between the Glicko and Glicko-2 scales
+const SCALE: f64 = 173.7178;
+
+/// Convergence tolerance of the volatility iteration
+const EPSILON: f64 = 0.000001;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Glicko2Rating {
+    pub rating: f64,
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+impl Glicko2Rating {
+    /// Rating given to players who have never played a rated game
+    pub const INITIAL: Glicko2Rating = Glicko2Rating {
+        rating: 1500.0,
+        deviation: 350.0,
+        volatility: 0.06,
+    };
+
+    fn mu(&self) -> f64 {
+        (self.rating - 1500.0) / SCALE
+    }
+
+    fn phi(&self) -> f64 {
+        self.deviation / SCALE
+    }
+
+    /// Lower and upper bound of the ~95% confidence interval
+    pub fn interval(&self) -> (f64, f64) {
+        (self.rating - 2.0 * self.deviation, self.rating + 2.0 * self.deviation)
+    }
+}
+
+impl Default for Glicko2Rating {
+    fn default() -> Self {
+        Self::INITIAL
+    }
+}
+
+/// Result of a game from one player's point of view
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameOutcome {
+    Win,
+    Draw,
+    Loss,
+}
+
+impl GameOutcome {
+    pub fn score(&self) -> f64 {
+        match self {
+            GameOutcome::Win => 1.0,
+            GameOutcome::Draw => 0.5,
+            GameOutcome::Loss => 0.0,
+        }
+    }
+
+    /// The same game from the opponent's point of view
+    pub fn reverse(&self) -> Self {
+        match self {
+            GameOutcome::Win => GameOutcome::Loss,
+            GameOutcome::Draw => GameOutcome::Draw,
+            GameOutcome::Loss => GameOutcome::Win,
+        }
+    }
+}
+
+/// System constants
+#[derive(Debug, Clone, Copy)]
+pub struct Glicko2 {
+    /// Constrains how fast volatility changes; Glickman suggests 0.3 to 1.2
+    pub tau: f64,
+    /// Deviation never grows past this, the deviation of an unrated player
+    pub max_deviation: f64,
+    /// Length of a rating period, used to inflate deviation for inactivity
+    pub rating_period: Duration,
+}
+
+impl Default for Glicko2 {
+    fn default() -> Self {
+        Self {
+            tau: 0.5,
+            max_deviation: Glicko2Rating::INITIAL.deviation,
+            rating_period: Duration::days(1),
+        }
+    }
+}
+
+impl Glicko2 {
+    /// Rate a player over one rating period with the opponents' ratings as
+    /// they were at the start of the period. A period without games only
+    /// inflates the deviation.
+    pub fn rate(&self, player: Glicko2Rating, results: &[(Glicko2Rating, GameOutcome)]) -> Glicko2Rating {
+        if results.is_empty() {
+            return self.inflate(player, 1);
+        }
+
+        let mu = player.mu();
+        let phi = player.phi();
+
+        // Estimated variance of the rating from game outcomes alone, and the
+        // improvement those outcomes suggest
+        let mut inverse_variance = 0.0;
+        let mut score_sum = 0.0;
+        for (opponent, outcome) in results {
+            let g = g(opponent.phi());
+            let e = expected_score(mu, opponent.mu(), g);
+            inverse_variance += g * g * e * (1.0 - e);
+            score_sum += g * (outcome.score() - e);
+        }
+        let variance = 1.0 / inverse_variance;
+        let delta = variance * score_sum;
+
+        let volatility = self.new_volatility(phi, player.volatility, variance, delta);
+
+        let phi_star = (phi * phi + volatility * volatility).sqrt();
+        let new_phi = 1.0 / (1.0 / (phi_star * phi_star) + 1.0 / variance).sqrt();
+        let new_mu = mu + new_phi * new_phi * score_sum;
+
+        Glicko2Rating {
+            rating: new_mu * SCALE + 1500.0,
+            deviation: (new_phi * SCALE).min(self.max_deviation),
+            volatility,
+        }
+    }
+
+    /// Rate a single game as its own rating period for both players.
+    /// Returns the new ratings of `white` and `black`.
+    pub fn rate_game(
+        &self,
+        white: Glicko2Rating,
+        black: Glicko2Rating,
+        white_outcome: GameOutcome,
+    ) -> (Glicko2Rating, Glicko2Rating) {
+        (
+            self.rate(white, &[(black, white_outcome)]),
+            self.rate(black, &[(white, white_outcome.reverse())]),
+        )
+    }
+
+    /// Grow the deviation as if `periods` rating periods passed without games.
+    pub fn inflate(&self, player: Glicko2Rating, periods: u32) -> Glicko2Rating {
+        let phi = player.phi();
+        let phi = (phi * phi + periods as f64 * player.volatility * player.volatility).sqrt();
+        Glicko2Rating {
+            deviation: (phi * SCALE).min(self.max_deviation),
+            ..player
+        }
+    }
+
+    /// Whole rating periods between a player's last rated game and `now`
+    pub fn periods_since(&self, last_rated_at: DateTime<Utc>, now: DateTime<Utc>) -> u32 {
+        let period_ms = self.rating_period.num_milliseconds();
+        if period_ms <= 0 {
+            return 0;
+        }
+        let elapsed_ms = now.signed_duration_since(last_rated_at).num_milliseconds().max(0);
+        (elapsed_ms / period_ms).min(u32::MAX as i64) as u32
+    }
+
+    /// Inflate the deviation for the time a player has been away, then rate
+    /// them on the results that brought them back.
+    pub fn rate_after_inactivity(
+        &self,
+        player: Glicko2Rating,
+        last_rated_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+        results: &[(Glicko2Rating, GameOutcome)],
+    ) -> Glicko2Rating {
+        let player = match last_rated_at {
+            Some(last_rated_at) => self.inflate(player, self.periods_since(last_rated_at, now)),
+            None => player,
+        };
+        self.rate(player, results)
+    }
+
+    /// Step 5 of the paper: solve for the new volatility with the Illinois
+    /// variant of regula falsi.
+    fn new_volatility(&self, phi: f64, volatility: f64, variance: f64, delta: f64) -> f64 {
+        let a = (volatility * volatility).ln();
+        let tau_sq = self.tau * self.tau;
+        let f = |x: f64| {
+            let ex = x.exp();
+            let denom = phi * phi + variance + ex;
+            ex * (delta * delta - phi * phi - variance - ex) / (2.0 * denom * denom) - (x - a) / tau_sq
+        };
+
+        let mut lower = a;
+        let mut upper = if delta * delta > phi * phi + variance {
+            (delta * delta - phi * phi - variance).ln()
+        } else {
+            let mut k = 1.0;
+            while f(a - k * self.tau) < 0.0 {
+                k += 1.0;
+            }
+            a - k * self.tau
+        };
+
+        let mut f_lower = f(lower);
+        let mut f_upper = f(upper);
+        while (upper - lower).abs() > EPSILON {
+            let c = lower + (lower - upper) * f_lower / (f_upper - f_lower);
+            let f_c = f(c);
+            if f_c * f_upper <= 0.0 {
+                lower = upper;
+                f_lower = f_upper;
+            } else {
+                f_lower /= 2.0;
+            }
+            upper = c;
+            f_upper = f_c;
+        }
+
+        (lower / 2.0).exp()
+    }
+}
+
+fn g(phi: f64) -> f64 {
+    1.0 / (1.0 + 3.0 * phi * phi / (PI * PI)).sqrt()
+}
+
+fn expected_score(mu: f64, opponent_mu: f64, g: f64) -> f64 {
+    1.0 / (1.0 + (-g * (mu - opponent_mu)).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rating(rating: f64, deviation: f64) -> Glicko2Rating {
+        Glicko2Rating { rating, deviation, volatility: 0.06 }
+    }
+
+    #[test]
+    fn glickman_paper_example() {
+        let player = rating(1500.0, 200.0);
+        let results = [
+            (rating(1400.0, 30.0), GameOutcome::Win),
+            (rating(1550.0, 100.0), GameOutcome::Loss),
+            (rating(1700.0, 300.0), GameOutcome::Loss),
+        ];
+
+        let rated = Glicko2::default().rate(player, &results);
+        assert!((rated.rating - 1464.06).abs() < 0.01, "rating {}", rated.rating);
+        assert!((rated.deviation - 151.52).abs() < 0.01, "deviation {}", rated.deviation);
+        assert!((rated.volatility - 0.05999).abs() < 0.00001, "volatility {}", rated.volatility);
+    }
+
+    #[test]
+    fn draw_between_equals_keeps_ratings_and_shrinks_deviation() {
+        let glicko = Glicko2::default();
+        let (white, black) = glicko.rate_game(rating(1500.0, 100.0), rating(1500.0, 100.0), GameOutcome::Draw);
+
+        assert!((white.rating - 1500.0).abs() < 1e-9);
+        assert!((black.rating - 1500.0).abs() < 1e-9);
+        assert!(white.deviation < 100.0);
+        assert_eq!(white, black);
+    }
+
+    #[test]
+    fn draw_against_stronger_player_gains_rating() {
+        let (weaker, stronger) =
+            Glicko2::default().rate_game(rating(1400.0, 80.0), rating(1800.0, 80.0), GameOutcome::Draw);
+        assert!(weaker.rating > 1400.0);
+        assert!(stronger.rating < 1800.0);
+    }
+
+    #[test]
+    fn uncertain_ratings_move_further() {
+        let glicko = Glicko2::default();
+        let opponent = rating(1500.0, 50.0);
+        let newcomer = glicko.rate(Glicko2Rating::INITIAL, &[(opponent, GameOutcome::Win)]);
+        let regular = glicko.rate(rating(1500.0, 50.0), &[(opponent, GameOutcome::Win)]);
+        assert!(newcomer.rating - 1500.0 > regular.rating - 1500.0);
+    }
+
+    #[test]
+    fn inactivity_inflates_deviation_up_to_the_cap() {
+        let glicko = Glicko2::default();
+        let player = rating(1700.0, 50.0);
+
+        let idle = glicko.rate(player, &[]);
+        assert_eq!(idle.rating, 1700.0);
+        assert!(idle.deviation > 50.0);
+
+        let now = Utc::now();
+        let month = glicko.inflate(player, glicko.periods_since(now - Duration::days(30), now));
+        assert!(month.deviation > idle.deviation);
+        assert_eq!(glicko.inflate(player, 1_000_000).deviation, 350.0);
+    }
+
+    #[test]
+    fn periods_since_counts_whole_periods() {
+        let glicko = Glicko2::default();
+        let now = Utc::now();
+        assert_eq!(glicko.periods_since(now - Duration::hours(23), now), 0);
+        assert_eq!(glicko.periods_since(now - Duration::hours(49), now), 2);
+        assert_eq!(glicko.periods_since(now + Duration::hours(1), now), 0);
+    }
+}
@@ -0,0 +1,390 @@
+/// `MatchmakingStore` in process memory, for tests and single instance
+/// setups.
+///
+/// All state sits behind one mutex that every operation takes exactly
+/// once and never holds across an await, which gives the same atomicity
+/// as the Redis scripts. Matches do not expire.
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use super::models::*;
+use super::store::*;
+
+#[derive(Default)]
+struct MemoryState {
+    /// Entries of each pool with their join timestamp, oldest first
+    queues: HashMap<MatchPool, Vec<(i64, QueueEntry)>>,
+    pools: HashSet<MatchPool>,
+    /// Pool of every queued request, by request id
+    request_pools: HashMap<Uuid, MatchPool>,
+    /// Invites by invited wallet
+    invites: HashMap<String, MatchRequest>,
+    matches: HashMap<Uuid, Match>,
+    request_matches: HashMap<Uuid, Uuid>,
+    /// Recent waits of each pool, newest first
+    waits: HashMap<MatchPool, VecDeque<u64>>,
+    lock: Option<(String, Instant)>,
+}
+
+#[derive(Default)]
+pub struct MemoryStore {
+    state: Mutex<MemoryState>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MemoryState> {
+        self.state.lock().unwrap()
+    }
+}
+
+impl MemoryState {
+    fn queue(&mut self, pool: &MatchPool) -> &mut Vec<(i64, QueueEntry)> {
+        self.queues.entry(pool.clone()).or_default()
+    }
+
+    fn is_queued(&self, pool: &MatchPool, member: &str) -> bool {
+        self.queues
+            .get(pool)
+            .is_some_and(|queue| queue.iter().any(|(_, entry)| entry.member == member))
+    }
+}
+
+#[async_trait]
+impl MatchmakingStore for MemoryStore {
+    async fn enqueue(&self, request: &MatchRequest, now: DateTime<Utc>) -> Result<(), String> {
+        let pool = MatchPool::of(request);
+        let member = request
+            .to_redis_value()
+            .map_err(|e| format!("Serialization error: {}", e))?;
+        let score = now.timestamp();
+        let cutoff = score - QUEUE_ENTRY_TTL_SECS;
+
+        let mut state = self.state();
+        let queue = state.queue(&pool);
+        let (expired, kept): (Vec<_>, Vec<_>) = queue
+            .drain(..)
+            .partition(|(joined, entry)| *joined <= cutoff || entry.request.id == request.id);
+        *queue = kept;
+        let position = queue.partition_point(|(joined, _)| *joined <= score);
+        queue.insert(
+            position,
+            (score, QueueEntry { member, request: request.clone() }),
+        );
+        for (_, entry) in expired {
+            state.request_pools.remove(&entry.request.id);
+        }
+        state.request_pools.insert(request.id, pool.clone());
+        state.pools.insert(pool);
+
+        Ok(())
+    }
+
+    async fn pools(&self) -> Result<Vec<MatchPool>, String> {
+        Ok(self.state().pools.iter().cloned().collect())
+    }
+
+    async fn forget_pool_if_empty(&self, pool: &MatchPool) -> Result<(), String> {
+        let mut state = self.state();
+        if state.queues.get(pool).is_none_or(Vec::is_empty) {
+            state.queues.remove(pool);
+            state.pools.remove(pool);
+        }
+        Ok(())
+    }
+
+    async fn queued(&self, pool: &MatchPool) -> Result<Vec<QueueEntry>, String> {
+        Ok(self
+            .state()
+            .queues
+            .get(pool)
+            .map(|queue| queue.iter().map(|(_, entry)| entry.clone()).collect())
+            .unwrap_or_default())
+    }
+
+    async fn pool_size(&self, pool: &MatchPool) -> Result<usize, String> {
+        Ok(self.state().queues.get(pool).map_or(0, Vec::len))
+    }
+
+    async fn take_opponent(&self, request: &MatchRequest) -> Result<Option<MatchRequest>, String> {
+        let mut state = self.state();
+        let queue = state.queue(&MatchPool::of(request));
+
+        let found = queue
+            .iter()
+            .position(|(_, entry)| takes_opponent(request, &entry.request));
+        let opponent = found.map(|index| queue.remove(index).1.request);
+        if let Some(opponent) = &opponent {
+            state.request_pools.remove(&opponent.id);
+        }
+        Ok(opponent)
+    }
+
+    async fn find_request(&self, request_id: Uuid) -> Result<Option<(MatchPool, usize)>, String> {
+        let state = self.state();
+        let Some(pool) = state.request_pools.get(&request_id) else {
+            return Ok(None);
+        };
+
+        Ok(state
+            .queues
+            .get(pool)
+            .and_then(|queue| queue.iter().position(|(_, entry)| entry.request.id == request_id))
+            .map(|index| (pool.clone(), index)))
+    }
+
+    async fn remove_request(&self, request_id: Uuid) -> Result<bool, String> {
+        let mut state = self.state();
+        let Some(pool) = state.request_pools.remove(&request_id) else {
+            return Ok(false);
+        };
+        let queue = state.queue(&pool);
+
+        match queue.iter().position(|(_, entry)| entry.request.id == request_id) {
+            Some(index) => {
+                queue.remove(index);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn replace_entry(
+        &self,
+        pool: &MatchPool,
+        entry: &QueueEntry,
+        updated: &MatchRequest,
+    ) -> Result<(), String> {
+        let member = updated
+            .to_redis_value()
+            .map_err(|e| format!("Serialization error: {}", e))?;
+
+        let mut state = self.state();
+        if let Some((_, queued)) = state
+            .queue(pool)
+            .iter_mut()
+            .find(|(_, queued)| queued.member == entry.member)
+        {
+            *queued = QueueEntry { member, request: updated.clone() };
+        }
+        Ok(())
+    }
+
+    async fn claim_pair(
+        &self,
+        pool: &MatchPool,
+        first: &QueueEntry,
+        second: &QueueEntry,
+        new_match: &Match,
+    ) -> Result<bool, String> {
+        let mut state = self.state();
+        if !state.is_queued(pool, &first.member) || !state.is_queued(pool, &second.member) {
+            return Ok(false);
+        }
+
+        state
+            .queue(pool)
+            .retain(|(_, entry)| entry.member != first.member && entry.member != second.member);
+        state.request_pools.remove(&first.request.id);
+        state.request_pools.remove(&second.request.id);
+        state.matches.insert(new_match.id, new_match.clone());
+        state.request_matches.insert(first.request.id, new_match.id);
+        state.request_matches.insert(second.request.id, new_match.id);
+
+        Ok(true)
+    }
+
+    async fn add_invite(&self, invite_address: &str, request: &MatchRequest) -> Result<(), String> {
+        self.state()
+            .invites
+            .insert(invite_address.to_string(), request.clone());
+        Ok(())
+    }
+
+    async fn invite_for(&self, wallet_address: &str) -> Result<Option<MatchRequest>, String> {
+        Ok(self.state().invites.get(wallet_address).cloned())
+    }
+
+    async fn find_invite(&self, request_id: Uuid) -> Result<Option<MatchRequest>, String> {
+        Ok(self
+            .state()
+            .invites
+            .values()
+            .find(|invite| invite.id == request_id)
+            .cloned())
+    }
+
+    async fn take_invite(&self, request_id: Uuid) -> Result<Option<MatchRequest>, String> {
+        let mut state = self.state();
+        let invite_address = state
+            .invites
+            .iter()
+            .find(|(_, invite)| invite.id == request_id)
+            .map(|(address, _)| address.clone());
+
+        Ok(invite_address.and_then(|address| state.invites.remove(&address)))
+    }
+
+    async fn save_match(&self, new_match: &Match, request_ids: &[Uuid]) -> Result<(), String> {
+        let mut state = self.state();
+        state.matches.insert(new_match.id, new_match.clone());
+        for request_id in request_ids {
+            state.request_matches.insert(*request_id, new_match.id);
+        }
+        Ok(())
+    }
+
+    async fn get_match(&self, match_id: Uuid) -> Result<Option<Match>, String> {
+        Ok(self.state().matches.get(&match_id).cloned())
+    }
+
+    async fn match_for_request(&self, request_id: Uuid) -> Result<Option<Uuid>, String> {
+        Ok(self.state().request_matches.get(&request_id).copied())
+    }
+
+    async fn record_waits(&self, pool: &MatchPool, waits: &[u64]) -> Result<(), String> {
+        let mut state = self.state();
+        let recent = state.waits.entry(pool.clone()).or_default();
+        for wait in waits {
+            recent.push_front(*wait);
+        }
+        recent.truncate(WAIT_SAMPLES);
+        Ok(())
+    }
+
+    async fn recent_waits(&self, pool: &MatchPool) -> Result<Vec<u64>, String> {
+        Ok(self
+            .state()
+            .waits
+            .get(pool)
+            .map(|recent| recent.iter().copied().collect())
+            .unwrap_or_default())
+    }
+
+    async fn acquire_lock(&self, token: &str, ttl: Duration) -> Result<bool, String> {
+        let mut state = self.state();
+        let now = Instant::now();
+        if state.lock.as_ref().is_some_and(|(_, expires)| *expires > now) {
+            return Ok(false);
+        }
+        state.lock = Some((token.to_string(), now + ttl));
+        Ok(true)
+    }
+
+    async fn release_lock(&self, token: &str) -> Result<(), String> {
+        let mut state = self.state();
+        if state.lock.as_ref().is_some_and(|(held, _)| held == token) {
+            state.lock = None;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn claim_fails_once_either_player_has_left() {
+        let store = MemoryStore::new();
+        let [a, b, c] = ["a", "b", "c"].map(|wallet| MatchRequest::test(wallet, 1500));
+        let pool = MatchPool::of(&a);
+        for queued in [&a, &b, &c] {
+            store.enqueue(queued, Utc::now()).await.unwrap();
+        }
+        let queue = store.queued(&pool).await.unwrap();
+
+        assert!(store.remove_request(b.id).await.unwrap());
+        let with_b = Match::between(&queue[0].request, &queue[1].request);
+        assert!(!store.claim_pair(&pool, &queue[0], &queue[1], &with_b).await.unwrap());
+
+        let with_c = Match::between(&queue[0].request, &queue[2].request);
+        assert!(store.claim_pair(&pool, &queue[0], &queue[2], &with_c).await.unwrap());
+        assert_eq!(store.pool_size(&pool).await.unwrap(), 0);
+        assert_eq!(store.match_for_request(c.id).await.unwrap(), Some(with_c.id));
+    }
+
+    #[tokio::test]
+    async fn replaced_entry_is_not_put_back_after_leaving() {
+        let store = MemoryStore::new();
+        let a = MatchRequest::test("a", 1500);
+        let pool = MatchPool::of(&a);
+        store.enqueue(&a, Utc::now()).await.unwrap();
+        let entry = store.queued(&pool).await.unwrap().remove(0);
+
+        store.remove_request(a.id).await.unwrap();
+        let mut widened = a.clone();
+        widened.range_expansion = 100;
+        store.replace_entry(&pool, &entry, &widened).await.unwrap();
+
+        assert_eq!(store.pool_size(&pool).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn requests_are_found_until_they_leave() {
+        let store = MemoryStore::new();
+        let (a, b) = (MatchRequest::test("a", 1500), MatchRequest::test("b", 1500));
+        let mut bullet = MatchRequest::test("c", 1500);
+        bullet.time_control = TimeControl { initial_secs: 60, increment_secs: 0 };
+        let long_ago = Utc::now() - chrono::Duration::seconds(QUEUE_ENTRY_TTL_SECS + 1);
+        store.enqueue(&a, long_ago).await.unwrap();
+        store.enqueue(&bullet, Utc::now()).await.unwrap();
+        assert_eq!(store.find_request(a.id).await.unwrap().unwrap().1, 0);
+
+        // Joining drops the expired entry of `a` from its pool
+        store.enqueue(&b, Utc::now()).await.unwrap();
+        assert!(store.find_request(a.id).await.unwrap().is_none());
+        assert!(!store.remove_request(a.id).await.unwrap());
+
+        let (pool, position) = store.find_request(bullet.id).await.unwrap().unwrap();
+        assert_eq!((pool, position), (MatchPool::of(&bullet), 0));
+        assert_eq!(store.take_opponent(&MatchRequest::test("d", 1500)).await.unwrap().unwrap().id, b.id);
+        assert!(store.find_request(b.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn wallet_is_not_taken_as_its_own_opponent() {
+        let store = MemoryStore::new();
+        let queued = MatchRequest::test("a", 1500);
+        store.enqueue(&queued, Utc::now()).await.unwrap();
+
+        assert!(store.take_opponent(&MatchRequest::test("a", 1500)).await.unwrap().is_none());
+        assert_eq!(store.take_opponent(&MatchRequest::test("b", 1500)).await.unwrap().unwrap().id, queued.id);
+    }
+
+    #[tokio::test]
+    async fn lock_is_exclusive_until_released_or_expired() {
+        let store = MemoryStore::new();
+        let ttl = Duration::from_millis(50);
+        assert!(store.acquire_lock("one", ttl).await.unwrap());
+        assert!(!store.acquire_lock("two", ttl).await.unwrap());
+
+        store.release_lock("two").await.unwrap();
+        assert!(!store.acquire_lock("two", ttl).await.unwrap());
+
+        store.release_lock("one").await.unwrap();
+        assert!(store.acquire_lock("two", ttl).await.unwrap());
+
+        tokio::time::sleep(ttl * 2).await;
+        assert!(store.acquire_lock("three", ttl).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn recent_waits_are_capped() {
+        let store = MemoryStore::new();
+        let pool = MatchPool::of(&MatchRequest::test("a", 1500));
+        let waits: Vec<u64> = (0..WAIT_SAMPLES as u64 + 10).collect();
+        store.record_waits(&pool, &waits).await.unwrap();
+
+        let recent = store.recent_waits(&pool).await.unwrap();
+        assert_eq!(recent.len(), WAIT_SAMPLES);
+        assert_eq!(recent[0], WAIT_SAMPLES as u64 + 9);
+    }
+}
@@ -3,8 +3,20 @@ pub mod routes;
 pub mod service;
 pub mod redis;
 pub mod elo;
+pub mod glicko2;
+pub mod worker;
+pub mod store;
+pub mod redis_store;
+pub mod memory_store;
+pub mod rating_source;
 
 pub use models::*;
 pub use routes::*;
 pub use service::*;
-pub use elo::*;
\ No newline at end of file
+pub use elo::*;
+pub use glicko2::*;
+pub use worker::*;
+pub use store::*;
+pub use redis_store::*;
+pub use memory_store::*;
+pub use rating_source::*;
\ No newline at end of file
@@ -5,7 +5,7 @@ use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum MatchType {
     Rated,
     Casual,
@@ -13,33 +13,192 @@ pub enum MatchType {
 }
 
 impl MatchType {
-    pub fn redis_key(&self) -> String {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchType::Rated => "rated",
+            MatchType::Casual => "casual",
+            MatchType::Private => "private",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Variant {
+    #[default]
+    Standard,
+    Chess960,
+    ThreeCheck,
+}
+
+impl Variant {
+    pub fn as_str(&self) -> &'static str {
         match self {
-            MatchType::Rated => "matchmaking:queue:rated".to_string(),
-            MatchType::Casual => "matchmaking:queue:casual".to_string(),
-            MatchType::Private => "matchmaking:invites".to_string(),
+            Variant::Standard => "standard",
+            Variant::Chess960 => "chess960",
+            Variant::ThreeCheck => "three_check",
+        }
+    }
+}
+
+/// Clock settings in seconds
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct TimeControl {
+    pub initial_secs: u32,
+    pub increment_secs: u32,
+}
+
+impl TimeControl {
+    /// Longest starting clock a request may ask for
+    pub const MAX_INITIAL_SECS: u32 = 3 * 3600;
+    /// Largest increment a request may ask for
+    pub const MAX_INCREMENT_SECS: u32 = 180;
+
+    /// Reject clocks no game could be played with, or so long they would
+    /// only open pools nobody else joins
+    pub fn validate(&self) -> Result<(), String> {
+        if self.initial_secs == 0 {
+            return Err("Starting clock must not be empty".to_string());
         }
+        if self.initial_secs > Self::MAX_INITIAL_SECS {
+            return Err(format!(
+                "Starting clock may be at most {} seconds",
+                Self::MAX_INITIAL_SECS
+            ));
+        }
+        if self.increment_secs > Self::MAX_INCREMENT_SECS {
+            return Err(format!(
+                "Increment may be at most {} seconds",
+                Self::MAX_INCREMENT_SECS
+            ));
+        }
+        Ok(())
     }
 }
 
+impl Default for TimeControl {
+    /// 5+0 blitz, for requests that do not say
+    fn default() -> Self {
+        Self {
+            initial_secs: 300,
+            increment_secs: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Color {
+    White,
+    Black,
+}
+
+impl Color {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Color::White => "white",
+            Color::Black => "black",
+        }
+    }
+
+    pub fn opposite(&self) -> Self {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+/// Players are only paired with others in the same pool
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct MatchPool {
+    pub match_type: MatchType,
+    pub variant: Variant,
+    pub time_control: TimeControl,
+}
+
+impl MatchPool {
+    pub fn of(request: &MatchRequest) -> Self {
+        Self {
+            match_type: request.match_type.clone(),
+            variant: request.variant,
+            time_control: request.time_control,
+        }
+    }
+
+    /// Sorted set of the requests waiting in this pool
+    pub fn redis_key(&self) -> String {
+        format!("matchmaking:queue:{}", self.name())
+    }
+
+    /// Hash from the id of each request waiting in this pool to its entry
+    /// in `redis_key`
+    pub fn members_key(&self) -> String {
+        format!("matchmaking:queue:{}:members", self.name())
+    }
+
+    /// Recent waits of players matched in this pool, in seconds
+    pub fn waits_key(&self) -> String {
+        format!("matchmaking:waits:{}", self.name())
+    }
+
+    /// e.g. `rated:standard:300+0`
+    fn name(&self) -> String {
+        format!(
+            "{}:{}:{}+{}",
+            self.match_type.as_str(),
+            self.variant.as_str(),
+            self.time_control.initial_secs,
+            self.time_control.increment_secs,
+        )
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Player {
     pub wallet_address: String,
-    pub elo: u32,
+    /// Glicko-2 rating
+    #[serde(alias = "elo")]
+    pub rating: u32,
+    /// Glicko-2 rating deviation; unrated players have the maximum
+    #[serde(default = "default_deviation")]
+    pub deviation: u32,
     pub join_time: DateTime<Utc>, 
 }
 
+fn default_deviation() -> u32 {
+    super::glicko2::Glicko2Rating::INITIAL.deviation as u32
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MatchRequest {
     pub id: Uuid,
     pub player: Player,
     pub match_type: MatchType,
     pub invite_address: Option<String>, // For private matches__
-    pub max_elo_diff: Option<u32>,      // For rated matches__
+    #[serde(alias = "max_elo_diff")]
+    pub max_rating_diff: Option<u32>,   // For rated matches__
+    /// Extra rating range earned by waiting, kept current by the worker
+    #[serde(default)]
+    pub range_expansion: u32,
+    #[serde(default)]
+    pub time_control: TimeControl,
+    #[serde(default)]
+    pub variant: Variant,
+    /// Color the player wants to play, if they mind
+    #[serde(default)]
+    pub color: Option<Color>,
 }
 
 impl MatchRequest {
+    /// Whether the color preferences of two requests can both be met
+    pub fn colors_compatible(&self, other: &MatchRequest) -> bool {
+        match (self.color, other.color) {
+            (Some(mine), Some(theirs)) => mine != theirs,
+            _ => true,
+        }
+    }
+
     pub fn to_redis_value(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string(self)
     }
@@ -56,9 +215,45 @@ pub struct Match {
     pub player1: Player,
     pub player2: Player,
     pub match_type: MatchType,
+    #[serde(default)]
+    pub time_control: TimeControl,
+    #[serde(default)]
+    pub variant: Variant,
+    /// Color of `player1`; `player2` has the other one
+    #[serde(default = "default_player1_color")]
+    pub player1_color: Color,
     pub created_at: DateTime<Utc>, 
 }
 
+fn default_player1_color() -> Color {
+    Color::White
+}
+
+impl Match {
+    /// Create a match between two requests of the same pool. Color
+    /// preferences are honored; otherwise colors are drawn at random.
+    pub fn between(first: &MatchRequest, second: &MatchRequest) -> Self {
+        let id = Uuid::new_v4();
+        // The id is random, so its low bit is a fair coin
+        let coin = if id.as_bytes()[15] & 1 == 0 { Color::White } else { Color::Black };
+        let player1_color = first
+            .color
+            .or(second.color.map(|color| color.opposite()))
+            .unwrap_or(coin);
+
+        Self {
+            id,
+            player1: first.player.clone(),
+            player2: second.player.clone(),
+            match_type: first.match_type.clone(),
+            time_control: first.time_control,
+            variant: first.variant,
+            player1_color,
+            created_at: Utc::now(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueueStatus {
     pub request_id: Uuid,
@@ -67,9 +262,74 @@ pub struct QueueStatus {
     pub match_type: MatchType,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolStats {
+    #[serde(flatten)]
+    pub pool: MatchPool,
+    pub players_waiting: usize,
+    /// Mean wait of recently matched players, `None` without recent matches
+    pub estimated_wait_time: Option<Duration>,
+    /// Number of recent matches the estimate is based on
+    pub recent_samples: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MatchmakingResponse {
     pub status: String,
     pub match_id: Option<Uuid>,
     pub request_id: Uuid,
-}
\ No newline at end of file
+}
+/// Test fixtures: a rated standard request joining now, adjusted through
+/// the `with_*` methods
+#[cfg(test)]
+impl MatchRequest {
+    pub(crate) fn test(wallet: &str, rating: u32) -> Self {
+        MatchRequest {
+            id: Uuid::new_v4(),
+            player: Player {
+                wallet_address: wallet.to_string(),
+                rating,
+                deviation: 60,
+                join_time: Utc::now(),
+            },
+            match_type: MatchType::Rated,
+            invite_address: None,
+            max_rating_diff: None,
+            range_expansion: 0,
+            time_control: TimeControl::default(),
+            variant: Variant::Standard,
+            color: None,
+        }
+    }
+
+    pub(crate) fn with_type(mut self, match_type: MatchType) -> Self {
+        self.match_type = match_type;
+        self
+    }
+
+    /// Joined `secs` seconds ago
+    pub(crate) fn with_wait(mut self, secs: i64) -> Self {
+        self.player.join_time = Utc::now() - chrono::Duration::seconds(secs);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_controls_are_bounded() {
+        let clock = |initial_secs, increment_secs| TimeControl { initial_secs, increment_secs };
+
+        assert!(TimeControl::default().validate().is_ok());
+        assert!(clock(60, 0).validate().is_ok());
+        assert!(clock(TimeControl::MAX_INITIAL_SECS, TimeControl::MAX_INCREMENT_SECS)
+            .validate()
+            .is_ok());
+
+        assert!(clock(0, 2).validate().is_err());
+        assert!(clock(TimeControl::MAX_INITIAL_SECS + 1, 0).validate().is_err());
+        assert!(clock(300, TimeControl::MAX_INCREMENT_SECS + 1).validate().is_err());
+    }
+}
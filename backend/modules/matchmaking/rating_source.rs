@@ -0,0 +1,18 @@
+/// Where matchmaking reads the ratings players are paired by.
+///
+/// Clients only say who they are. Their rating and deviation come from
+/// the ratings stored for the category a pool's games count towards, so a
+/// player cannot claim a rating to be paired against weaker opponents.
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use super::glicko2::Glicko2Rating;
+use super::models::MatchPool;
+
+#[async_trait]
+pub trait RatingSource: Send + Sync {
+    /// The stored rating of a player in the category of `pool`, or
+    /// `Glicko2Rating::INITIAL` if they have not played it yet. `None` if
+    /// there is no such player.
+    async fn rating(&self, player_id: Uuid, pool: &MatchPool) -> Result<Option<Glicko2Rating>, String>;
+}
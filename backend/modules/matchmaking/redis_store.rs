@@ -0,0 +1,590 @@
+/// `MatchmakingStore` on Redis, shared by every replica.
+///
+/// Each pool is a sorted set of request JSON scored by join time, next to a
+/// hash from request id to that JSON; every request points at its pool so
+/// it is found without walking the others. Invites
+/// live in one hash keyed by the invited wallet, and matches are plain keys
+/// that expire after an hour. Steps that read and then write run as Lua
+/// scripts so no other client can slip in between.
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use deadpool_redis::Pool;
+use redis::AsyncCommands;
+use std::collections::HashMap;
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::models::*;
+use super::service::{DEFAULT_MAX_RATING_DIFF, SETTLED_DEVIATION};
+use super::store::*;
+
+const INVITES_KEY: &str = "matchmaking:invites";
+// Set of every pool that has had players waiting, as MatchPool JSON
+const POOLS_KEY: &str = "matchmaking:pools";
+const WORKER_LOCK_KEY: &str = "matchmaking:worker:lock";
+// How long created matches stay readable, from any replica
+const MATCH_TTL_SECS: u64 = 3600;
+// How long recorded waits count as recent
+const WAIT_SAMPLES_TTL_SECS: i64 = 3600;
+
+fn match_key(match_id: Uuid) -> String {
+    format!("matchmaking:match:{}", match_id)
+}
+
+// Pool a request was queued in, as MatchPool JSON. It may outlive the
+// entry; the pool's member hash says whether the request still waits.
+fn request_pool_key(request_id: Uuid) -> String {
+    format!("matchmaking:request:{}:pool", request_id)
+}
+
+fn request_match_key(request_id: Uuid) -> String {
+    format!("matchmaking:request:{}:match", request_id)
+}
+
+#[derive(Clone)]
+pub struct RedisStore {
+    redis_pool: Pool,
+}
+
+impl RedisStore {
+    pub fn new(redis_pool: Pool) -> Self {
+        Self { redis_pool }
+    }
+
+    async fn get_redis_connection(
+        &self,
+    ) -> Result<deadpool_redis::Connection, String> {
+        self.redis_pool
+            .get()
+            .await
+            .map_err(|e| format!("Redis connection failed: {}", e))
+    }
+
+    /// The pool a request was queued in, if it was queued recently
+    async fn request_pool(
+        &self,
+        conn: &mut deadpool_redis::Connection,
+        request_id: Uuid,
+    ) -> Result<Option<MatchPool>, String> {
+        let value: Option<String> = conn
+            .get(request_pool_key(request_id))
+            .await
+            .map_err(|e| format!("Redis GET failed: {}", e))?;
+
+        Ok(value.and_then(|json| serde_json::from_str(&json).ok()))
+    }
+}
+
+#[async_trait]
+impl MatchmakingStore for RedisStore {
+    async fn enqueue(&self, request: &MatchRequest, now: DateTime<Utc>) -> Result<(), String> {
+        let mut conn = self.get_redis_connection().await?;
+        let pool = MatchPool::of(request);
+        let score = now.timestamp() as f64;
+        let value = request
+            .to_redis_value()
+            .map_err(|e| format!("Serialization error: {}", e))?;
+        let pool_value = serde_json::to_string(&pool)
+            .map_err(|e| format!("Serialization error: {}", e))?;
+
+        let cutoff = (now - chrono::Duration::seconds(QUEUE_ENTRY_TTL_SECS)).timestamp() as f64;
+
+        // Expired entries leave the member hash along with the queue, and
+        // the pool is only tracked once it has the player, so
+        // forget_pool_if_empty cannot drop it in between
+        let lua_script = r#"
+            local queue = KEYS[1]
+            local members = KEYS[2]
+
+            for i, member in ipairs(redis.call('ZRANGEBYSCORE', queue, '-inf', ARGV[1])) do
+                redis.call('HDEL', members, cjson.decode(member).id)
+            end
+            redis.call('ZREMRANGEBYSCORE', queue, '-inf', ARGV[1])
+
+            local previous = redis.call('HGET', members, ARGV[4])
+            if previous then
+                redis.call('ZREM', queue, previous)
+            end
+            redis.call('ZADD', queue, ARGV[2], ARGV[3])
+            redis.call('HSET', members, ARGV[4], ARGV[3])
+
+            redis.call('EXPIRE', queue, ARGV[6])
+            redis.call('EXPIRE', members, ARGV[6])
+            redis.call('SET', KEYS[3], ARGV[5], 'EX', ARGV[6])
+            redis.call('SADD', KEYS[4], ARGV[5])
+            return 1
+        "#;
+
+        redis::Script::new(lua_script)
+            .key(pool.redis_key())
+            .key(pool.members_key())
+            .key(request_pool_key(request.id))
+            .key(POOLS_KEY)
+            .arg(cutoff)
+            .arg(score)
+            .arg(&value)
+            .arg(request.id.to_string())
+            .arg(pool_value)
+            .arg(QUEUE_ENTRY_TTL_SECS)
+            .invoke_async::<_, i64>(&mut conn)
+            .await
+            .map_err(|e| format!("Redis Lua script failed: {}", e))?;
+
+        Ok(())
+    }
+
+    async fn pools(&self) -> Result<Vec<MatchPool>, String> {
+        let mut conn = self.get_redis_connection().await?;
+
+        let members: Vec<String> = conn
+            .smembers(POOLS_KEY)
+            .await
+            .map_err(|e| format!("Redis SMEMBERS failed: {}", e))?;
+
+        Ok(members
+            .iter()
+            .filter_map(|member| serde_json::from_str(member).ok())
+            .collect())
+    }
+
+    async fn forget_pool_if_empty(&self, pool: &MatchPool) -> Result<(), String> {
+        let mut conn = self.get_redis_connection().await?;
+        let pool_value = serde_json::to_string(pool)
+            .map_err(|e| format!("Serialization error: {}", e))?;
+
+        let lua_script = r#"
+            if redis.call('ZCARD', KEYS[1]) == 0 then
+                redis.call('SREM', KEYS[2], ARGV[1])
+                redis.call('DEL', KEYS[3])
+            end
+            return 1
+        "#;
+
+        redis::Script::new(lua_script)
+            .key(pool.redis_key())
+            .key(POOLS_KEY)
+            .key(pool.members_key())
+            .arg(pool_value)
+            .invoke_async::<_, i64>(&mut conn)
+            .await
+            .map_err(|e| format!("Redis Lua script failed: {}", e))?;
+
+        Ok(())
+    }
+
+    async fn queued(&self, pool: &MatchPool) -> Result<Vec<QueueEntry>, String> {
+        let mut conn = self.get_redis_connection().await?;
+
+        let members: Vec<String> = conn
+            .zrange(pool.redis_key(), 0, -1)
+            .await
+            .map_err(|e| format!("Redis ZRANGE failed: {}", e))?;
+
+        Ok(members
+            .into_iter()
+            .filter_map(|member| {
+                MatchRequest::from_redis_value(&member)
+                    .ok()
+                    .map(|request| QueueEntry { member, request })
+            })
+            .collect())
+    }
+
+    async fn pool_size(&self, pool: &MatchPool) -> Result<usize, String> {
+        let mut conn = self.get_redis_connection().await?;
+
+        conn.zcard(pool.redis_key())
+            .await
+            .map_err(|e| format!("Redis ZCARD failed: {}", e))
+    }
+
+    async fn take_opponent(&self, request: &MatchRequest) -> Result<Option<MatchRequest>, String> {
+        let mut conn = self.get_redis_connection().await?;
+        let pool = MatchPool::of(request);
+        let rated = request.match_type == MatchType::Rated;
+        let max_rating_diff = request.max_rating_diff.unwrap_or(DEFAULT_MAX_RATING_DIFF);
+
+        // Lua script for atomic find-and-remove operation, following
+        // `takes_opponent`. This prevents race conditions where two players
+        // try to match with the same opponent
+        let lua_script = r#"
+            local key = KEYS[1]
+            local members_key = KEYS[2]
+            local rated = ARGV[1] == '1'
+            local player_rating = tonumber(ARGV[2])
+            local player_deviation = tonumber(ARGV[3])
+            local max_rating_diff = tonumber(ARGV[4])
+            local settled_deviation = tonumber(ARGV[5])
+            local player_color = ARGV[6]
+            local wallet_address = ARGV[7]
+
+            local members = redis.call('ZRANGE', key, 0, -1)
+
+            for i, member in ipairs(members) do
+                local opponent = cjson.decode(member)
+                local same_player = opponent.player.wallet_address == wallet_address
+                local color_clash = player_color ~= '' and opponent.color == player_color
+                local in_range = true
+
+                if rated then
+                    -- Entries queued before the switch to Glicko-2 carry elo only
+                    local opponent_rating = opponent.player.rating or opponent.player.elo
+                    local opponent_deviation = opponent.player.deviation or settled_deviation
+                    local rating_diff = math.abs(opponent_rating - player_rating)
+                    local uncertainty = math.max(player_deviation, opponent_deviation) - settled_deviation
+                    in_range = rating_diff <= max_rating_diff + math.max(uncertainty, 0)
+                end
+
+                if not same_player and not color_clash and in_range then
+                    redis.call('ZREM', key, member)
+                    redis.call('HDEL', members_key, opponent.id)
+                    return member
+                end
+            end
+
+            return nil
+        "#;
+
+        let result: Option<String> = redis::Script::new(lua_script)
+            .key(pool.redis_key())
+            .key(pool.members_key())
+            .arg(if rated { 1 } else { 0 })
+            .arg(request.player.rating)
+            .arg(request.player.deviation)
+            .arg(max_rating_diff)
+            .arg(SETTLED_DEVIATION)
+            .arg(request.color.map(|color| color.as_str()).unwrap_or(""))
+            .arg(&request.player.wallet_address)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| format!("Redis Lua script failed: {}", e))?;
+
+        Ok(result.and_then(|json| MatchRequest::from_redis_value(&json).ok()))
+    }
+
+    async fn find_request(&self, request_id: Uuid) -> Result<Option<(MatchPool, usize)>, String> {
+        let mut conn = self.get_redis_connection().await?;
+        let Some(pool) = self.request_pool(&mut conn, request_id).await? else {
+            return Ok(None);
+        };
+
+        // Read the entry and its rank together, so a widened entry is not
+        // missed between the two
+        let lua_script = r#"
+            local member = redis.call('HGET', KEYS[2], ARGV[1])
+            if not member then
+                return nil
+            end
+            return redis.call('ZRANK', KEYS[1], member)
+        "#;
+
+        let rank: Option<usize> = redis::Script::new(lua_script)
+            .key(pool.redis_key())
+            .key(pool.members_key())
+            .arg(request_id.to_string())
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| format!("Redis Lua script failed: {}", e))?;
+
+        Ok(rank.map(|rank| (pool, rank)))
+    }
+
+    async fn remove_request(&self, request_id: Uuid) -> Result<bool, String> {
+        let mut conn = self.get_redis_connection().await?;
+        let Some(pool) = self.request_pool(&mut conn, request_id).await? else {
+            return Ok(false);
+        };
+
+        // Find and remove in one script, so the entry cannot be widened or
+        // claimed between reading it and removing it
+        let lua_script = r#"
+            local member = redis.call('HGET', KEYS[2], ARGV[1])
+            if not member then
+                return 0
+            end
+            redis.call('HDEL', KEYS[2], ARGV[1])
+            return redis.call('ZREM', KEYS[1], member)
+        "#;
+
+        let removed: i64 = redis::Script::new(lua_script)
+            .key(pool.redis_key())
+            .key(pool.members_key())
+            .arg(request_id.to_string())
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| format!("Redis Lua script failed: {}", e))?;
+
+        Ok(removed == 1)
+    }
+
+    async fn replace_entry(
+        &self,
+        pool: &MatchPool,
+        entry: &QueueEntry,
+        updated: &MatchRequest,
+    ) -> Result<(), String> {
+        let mut conn = self.get_redis_connection().await?;
+        let updated_value = updated
+            .to_redis_value()
+            .map_err(|e| format!("Serialization error: {}", e))?;
+
+        // Swap the entry only if it is still queued, so a player matched
+        // in the meantime is not put back
+        let lua_script = r#"
+            local score = redis.call('ZSCORE', KEYS[1], ARGV[1])
+            if score then
+                redis.call('ZREM', KEYS[1], ARGV[1])
+                redis.call('ZADD', KEYS[1], score, ARGV[2])
+                redis.call('HSET', KEYS[2], ARGV[3], ARGV[2])
+            end
+            return 1
+        "#;
+
+        redis::Script::new(lua_script)
+            .key(pool.redis_key())
+            .key(pool.members_key())
+            .arg(&entry.member)
+            .arg(&updated_value)
+            .arg(updated.id.to_string())
+            .invoke_async::<_, i64>(&mut conn)
+            .await
+            .map_err(|e| format!("Redis Lua script failed: {}", e))?;
+
+        Ok(())
+    }
+
+    async fn claim_pair(
+        &self,
+        pool: &MatchPool,
+        first: &QueueEntry,
+        second: &QueueEntry,
+        new_match: &Match,
+    ) -> Result<bool, String> {
+        let mut conn = self.get_redis_connection().await?;
+        let value = serde_json::to_string(new_match)
+            .map_err(|e| format!("Serialization error: {}", e))?;
+
+        let lua_script = r#"
+            local queue = KEYS[1]
+            if not redis.call('ZSCORE', queue, ARGV[1]) or not redis.call('ZSCORE', queue, ARGV[2]) then
+                return 0
+            end
+
+            redis.call('ZREM', queue, ARGV[1], ARGV[2])
+            redis.call('HDEL', KEYS[5], ARGV[6], ARGV[7])
+            redis.call('SET', KEYS[2], ARGV[3], 'EX', ARGV[5])
+            redis.call('SET', KEYS[3], ARGV[4], 'EX', ARGV[5])
+            redis.call('SET', KEYS[4], ARGV[4], 'EX', ARGV[5])
+            return 1
+        "#;
+
+        let claimed: i64 = redis::Script::new(lua_script)
+            .key(pool.redis_key())
+            .key(match_key(new_match.id))
+            .key(request_match_key(first.request.id))
+            .key(request_match_key(second.request.id))
+            .key(pool.members_key())
+            .arg(&first.member)
+            .arg(&second.member)
+            .arg(&value)
+            .arg(new_match.id.to_string())
+            .arg(MATCH_TTL_SECS)
+            .arg(first.request.id.to_string())
+            .arg(second.request.id.to_string())
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| format!("Redis Lua script failed: {}", e))?;
+
+        Ok(claimed == 1)
+    }
+
+    async fn add_invite(&self, invite_address: &str, request: &MatchRequest) -> Result<(), String> {
+        let mut conn = self.get_redis_connection().await?;
+        let value = request
+            .to_redis_value()
+            .map_err(|e| format!("Serialization error: {}", e))?;
+
+        conn.hset::<_, _, _, ()>(INVITES_KEY, invite_address, &value)
+            .await
+            .map_err(|e| format!("Redis HSET failed: {}", e))?;
+
+        Ok(())
+    }
+
+    async fn invite_for(&self, wallet_address: &str) -> Result<Option<MatchRequest>, String> {
+        let mut conn = self.get_redis_connection().await?;
+
+        let value: Option<String> = conn
+            .hget(INVITES_KEY, wallet_address)
+            .await
+            .map_err(|e| format!("Redis HGET failed: {}", e))?;
+
+        match value {
+            Some(json) => MatchRequest::from_redis_value(&json)
+                .map(Some)
+                .map_err(|e| format!("Deserialization error: {}", e)),
+            None => Ok(None),
+        }
+    }
+
+    async fn find_invite(&self, request_id: Uuid) -> Result<Option<MatchRequest>, String> {
+        let mut conn = self.get_redis_connection().await?;
+
+        let invites: HashMap<String, String> = conn
+            .hgetall(INVITES_KEY)
+            .await
+            .map_err(|e| format!("Redis HGETALL failed: {}", e))?;
+
+        Ok(invites
+            .values()
+            .filter_map(|json| MatchRequest::from_redis_value(json).ok())
+            .find(|request| request.id == request_id))
+    }
+
+    async fn take_invite(&self, request_id: Uuid) -> Result<Option<MatchRequest>, String> {
+        let mut conn = self.get_redis_connection().await?;
+
+        // Lua script for atomic find-and-remove operation
+        // This prevents race conditions where multiple players try to accept the same invite
+        let lua_script = r#"
+            local key = KEYS[1]
+            local target_request_id = ARGV[1]
+
+            local invites = redis.call('HGETALL', key)
+
+            for i = 1, #invites, 2 do
+                local invite_address = invites[i]
+                local invite_json = invites[i + 1]
+                local invite = cjson.decode(invite_json)
+
+                if invite.id == target_request_id then
+                    redis.call('HDEL', key, invite_address)
+                    return invite_json
+                end
+            end
+
+            return nil
+        "#;
+
+        let result: Option<String> = redis::Script::new(lua_script)
+            .key(INVITES_KEY)
+            .arg(request_id.to_string())
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| format!("Redis Lua script failed: {}", e))?;
+
+        Ok(result.and_then(|json| MatchRequest::from_redis_value(&json).ok()))
+    }
+
+    async fn save_match(&self, new_match: &Match, request_ids: &[Uuid]) -> Result<(), String> {
+        let mut conn = self.get_redis_connection().await?;
+        let value = serde_json::to_string(new_match)
+            .map_err(|e| format!("Serialization error: {}", e))?;
+
+        conn.set_ex::<_, _, ()>(match_key(new_match.id), &value, MATCH_TTL_SECS)
+            .await
+            .map_err(|e| format!("Redis SETEX failed: {}", e))?;
+
+        for request_id in request_ids {
+            conn.set_ex::<_, _, ()>(
+                request_match_key(*request_id),
+                new_match.id.to_string(),
+                MATCH_TTL_SECS,
+            )
+            .await
+            .map_err(|e| format!("Redis SETEX failed: {}", e))?;
+        }
+
+        Ok(())
+    }
+
+    async fn get_match(&self, match_id: Uuid) -> Result<Option<Match>, String> {
+        let mut conn = self.get_redis_connection().await?;
+
+        let value: Option<String> = conn
+            .get(match_key(match_id))
+            .await
+            .map_err(|e| format!("Redis GET failed: {}", e))?;
+
+        match value {
+            Some(json) => serde_json::from_str(&json)
+                .map(Some)
+                .map_err(|e| format!("Deserialization error: {}", e)),
+            None => Ok(None),
+        }
+    }
+
+    async fn match_for_request(&self, request_id: Uuid) -> Result<Option<Uuid>, String> {
+        let mut conn = self.get_redis_connection().await?;
+
+        let match_id: Option<String> = conn
+            .get(request_match_key(request_id))
+            .await
+            .map_err(|e| format!("Redis GET failed: {}", e))?;
+
+        Ok(match_id.and_then(|id| Uuid::parse_str(&id).ok()))
+    }
+
+    async fn record_waits(&self, pool: &MatchPool, waits: &[u64]) -> Result<(), String> {
+        let mut conn = self.get_redis_connection().await?;
+        let key = pool.waits_key();
+
+        conn.lpush::<_, _, ()>(&key, waits)
+            .await
+            .map_err(|e| format!("Redis LPUSH failed: {}", e))?;
+
+        conn.ltrim::<_, ()>(&key, 0, WAIT_SAMPLES as isize - 1)
+            .await
+            .map_err(|e| format!("Redis LTRIM failed: {}", e))?;
+
+        conn.expire::<_, ()>(&key, WAIT_SAMPLES_TTL_SECS)
+            .await
+            .map_err(|e| format!("Redis EXPIRE failed: {}", e))?;
+
+        Ok(())
+    }
+
+    async fn recent_waits(&self, pool: &MatchPool) -> Result<Vec<u64>, String> {
+        let mut conn = self.get_redis_connection().await?;
+
+        conn.lrange(pool.waits_key(), 0, -1)
+            .await
+            .map_err(|e| format!("Redis LRANGE failed: {}", e))
+    }
+
+    async fn acquire_lock(&self, token: &str, ttl: Duration) -> Result<bool, String> {
+        let mut conn = self.get_redis_connection().await?;
+
+        let acquired: Option<String> = redis::cmd("SET")
+            .arg(WORKER_LOCK_KEY)
+            .arg(token)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl.as_millis() as u64)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| format!("Redis SET NX failed: {}", e))?;
+
+        Ok(acquired.is_some())
+    }
+
+    async fn release_lock(&self, token: &str) -> Result<(), String> {
+        let mut conn = self.get_redis_connection().await?;
+
+        let lua_script = r#"
+            if redis.call('GET', KEYS[1]) == ARGV[1] then
+                return redis.call('DEL', KEYS[1])
+            end
+            return 0
+        "#;
+
+        redis::Script::new(lua_script)
+            .key(WORKER_LOCK_KEY)
+            .arg(token)
+            .invoke_async::<_, i64>(&mut conn)
+            .await
+            .map_err(|e| format!("Redis Lua script failed: {}", e))?;
+
+        Ok(())
+    }
+}
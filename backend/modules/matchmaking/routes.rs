@@ -4,21 +4,30 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use super::models::*;
+use super::rating_source::RatingSource;
 use super::service::MatchmakingService;
 
+/// Players are paired by their stored rating, so requests only name the
+/// player
 #[derive(Debug, Deserialize)]
 pub struct JoinQueueRequest {
+    pub player_id: Uuid,
     pub wallet_address: String,
-    pub elo: u32,
     pub match_type: MatchType,
     pub invite_address: Option<String>,
-    pub max_elo_diff: Option<u32>,
+    #[serde(alias = "max_elo_diff")]
+    pub max_rating_diff: Option<u32>,
+    #[serde(default)]
+    pub time_control: TimeControl,
+    #[serde(default)]
+    pub variant: Variant,
+    pub color: Option<Color>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct AcceptInviteRequest {
+    pub player_id: Uuid,
     pub wallet_address: String,
-    pub elo: u32,
     pub inviter_request_id: Uuid,
 }
 
@@ -31,6 +40,7 @@ pub struct CancelRequest {
 pub struct StatusResponse {
     pub status: String,
     pub queue_status: Option<QueueStatus>,
+    pub match_id: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize)]
@@ -46,20 +56,61 @@ pub fn config(cfg: &mut web::ServiceConfig) {
             .route("/status/{request_id}", web::get().to(get_status))
             .route("/cancel", web::post().to(cancel_request))
             .route("/accept-invite", web::post().to(accept_invite))
-            .route("/match/{match_id}", web::get().to(get_match)),
+            .route("/match/{match_id}", web::get().to(get_match))
+            .route("/pools", web::get().to(get_pools)),
     );
 }
 
+/// The player a request names, with their stored rating for `pool`
+async fn player_in(
+    ratings: &dyn RatingSource,
+    player_id: Uuid,
+    wallet_address: &str,
+    pool: &MatchPool,
+) -> Result<Option<Player>, String> {
+    Ok(ratings.rating(player_id, pool).await?.map(|rating| Player {
+        wallet_address: wallet_address.to_string(),
+        rating: rating.rating.round() as u32,
+        deviation: rating.deviation.round() as u32,
+        join_time: Utc::now(),
+    }))
+}
+
+fn player_not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(serde_json::json!({
+        "status": "Player not found"
+    }))
+}
+
 async fn join_queue(
     service: web::Data<MatchmakingService>,
+    ratings: web::Data<dyn RatingSource>,
     req: web::Json<JoinQueueRequest>,
 ) -> impl Responder {
+    if let Err(e) = req.time_control.validate() {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            status: "error".to_string(),
+            error: e,
+        });
+    }
+
     let request_id = Uuid::new_v4();
+    let pool = MatchPool {
+        match_type: req.match_type.clone(),
+        variant: req.variant,
+        time_control: req.time_control,
+    };
 
-    let player = Player {
-        wallet_address: req.wallet_address.clone(),
-        elo: req.elo,
-        join_time: Utc::now(),
+    let player = match player_in(ratings.get_ref(), req.player_id, &req.wallet_address, &pool).await {
+        Ok(Some(player)) => player,
+        Ok(None) => return player_not_found(),
+        Err(e) => {
+            log::error!("Failed to look up rating: {}", e);
+            return HttpResponse::ServiceUnavailable().json(ErrorResponse {
+                status: "error".to_string(),
+                error: "internal_error".to_string(),
+            });
+        }
     };
 
     let match_request = MatchRequest {
@@ -67,7 +118,11 @@ async fn join_queue(
         player,
         match_type: req.match_type.clone(),
         invite_address: req.invite_address.clone(),
-        max_elo_diff: req.max_elo_diff,
+        max_rating_diff: req.max_rating_diff,
+        range_expansion: 0,
+        time_control: req.time_control,
+        variant: req.variant,
+        color: req.color,
     };
 
     match service.join_queue(match_request).await {
@@ -88,14 +143,30 @@ async fn get_status(
 ) -> impl Responder {
     let request_id = path.into_inner();
 
-    match service.get_queue_status(request_id).await {
-        Ok(Some(status)) => HttpResponse::Ok().json(StatusResponse {
+    let status = match service.get_queue_status(request_id).await {
+        Ok(Some(status)) => Ok((Some(status), None)),
+        Ok(None) => service
+            .matched_request(request_id)
+            .await
+            .map(|match_id| (None, match_id)),
+        Err(e) => Err(e),
+    };
+
+    match status {
+        Ok((Some(status), _)) => HttpResponse::Ok().json(StatusResponse {
             status: "In queue".to_string(),
             queue_status: Some(status),
+            match_id: None,
+        }),
+        Ok((None, Some(match_id))) => HttpResponse::Ok().json(StatusResponse {
+            status: "Match found".to_string(),
+            queue_status: None,
+            match_id: Some(match_id),
         }),
-        Ok(None) => HttpResponse::NotFound().json(StatusResponse {
+        Ok((None, None)) => HttpResponse::NotFound().json(StatusResponse {
             status: "Request not found".to_string(),
             queue_status: None,
+            match_id: None,
         }),
         Err(e) => {
             log::error!("Failed to get queue status: {}", e);
@@ -130,12 +201,37 @@ async fn cancel_request(
 
 async fn accept_invite(
     service: web::Data<MatchmakingService>,
+    ratings: web::Data<dyn RatingSource>,
     req: web::Json<AcceptInviteRequest>,
 ) -> impl Responder {
-    let player = Player {
-        wallet_address: req.wallet_address.clone(),
-        elo: req.elo,
-        join_time: Utc::now(),
+    let invite = match service.find_invite(req.inviter_request_id).await {
+        Ok(Some(invite)) => invite,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "status": "Invite not found"
+            }))
+        }
+        Err(e) => {
+            log::error!("Failed to accept invite: {}", e);
+            return HttpResponse::ServiceUnavailable().json(ErrorResponse {
+                status: "error".to_string(),
+                error: "internal_error".to_string(),
+            });
+        }
+    };
+
+    // The invited player is rated in the pool of the game they are offered
+    let pool = MatchPool::of(&invite);
+    let player = match player_in(ratings.get_ref(), req.player_id, &req.wallet_address, &pool).await {
+        Ok(Some(player)) => player,
+        Ok(None) => return player_not_found(),
+        Err(e) => {
+            log::error!("Failed to look up rating: {}", e);
+            return HttpResponse::ServiceUnavailable().json(ErrorResponse {
+                status: "error".to_string(),
+                error: "internal_error".to_string(),
+            });
+        }
     };
 
     match service.accept_private_invite(req.inviter_request_id, player).await {
@@ -159,11 +255,30 @@ async fn get_match(
 ) -> impl Responder {
     let match_id = path.into_inner();
 
-    if let Some(match_data) = service.get_match(match_id) {
-        HttpResponse::Ok().json(match_data)
-    } else {
-        HttpResponse::NotFound().json(serde_json::json!({
+    match service.get_match(match_id).await {
+        Ok(Some(match_data)) => HttpResponse::Ok().json(match_data),
+        Ok(None) => HttpResponse::NotFound().json(serde_json::json!({
             "status": "Match not found"
-        }))
+        })),
+        Err(e) => {
+            log::error!("Failed to get match: {}", e);
+            HttpResponse::ServiceUnavailable().json(ErrorResponse {
+                status: "error".to_string(),
+                error: "internal_error".to_string(),
+            })
+        }
+    }
+}
+
+async fn get_pools(service: web::Data<MatchmakingService>) -> impl Responder {
+    match service.pool_stats().await {
+        Ok(pools) => HttpResponse::Ok().json(pools),
+        Err(e) => {
+            log::error!("Failed to get pool stats: {}", e);
+            HttpResponse::ServiceUnavailable().json(ErrorResponse {
+                status: "error".to_string(),
+                error: "internal_error".to_string(),
+            })
+        }
     }
 }
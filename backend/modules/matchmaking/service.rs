@@ -1,40 +1,61 @@
 use actix_web::web;
 use chrono::Utc;
 use deadpool_redis::Pool;
-use redis::AsyncCommands;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::time::Duration;
 use uuid::Uuid;
 
+use super::memory_store::MemoryStore;
 use super::models::*;
-
-const ELO_RANGE_INCREMENT_PER_MINUTE: u32 = 50;
-const DEFAULT_MAX_ELO_DIFF: u32 = 200;
+use super::redis_store::RedisStore;
+use super::store::*;
+
+const RATING_RANGE_INCREMENT_PER_MINUTE: u32 = 50;
+pub(crate) const DEFAULT_MAX_RATING_DIFF: u32 = 200;
+// Deviations above this widen the rating range by the excess, as the
+// ratings of new or returning players are still a rough guess
+pub(crate) const SETTLED_DEVIATION: u32 = 100;
 const DEFAULT_ESTIMATED_WAIT_TIME: Duration = Duration::from_secs(60);
 
 #[derive(Clone)]
 pub struct MatchmakingService {
-    redis_pool: Pool,
-    active_matches: Arc<Mutex<HashMap<Uuid, Match>>>,
+    store: Arc<dyn MatchmakingStore>,
 }
 
 impl MatchmakingService {
     pub fn new(redis_pool: Pool) -> Self {
-        Self {
-            redis_pool,
-            active_matches: Arc::new(Mutex::new(HashMap::new())),
-        }
+        Self::with_store(Arc::new(RedisStore::new(redis_pool)))
     }
 
-    async fn get_redis_connection(
-        &self,
-    ) -> Result<deadpool_redis::Connection, String> {
-        self.redis_pool
-            .get()
-            .await
-            .map_err(|e| format!("Redis connection failed: {}", e))
+    pub fn with_store(store: Arc<dyn MatchmakingStore>) -> Self {
+        Self { store }
+    }
+
+    /// A service keeping everything in this process
+    pub fn in_memory() -> Self {
+        Self::with_store(Arc::new(MemoryStore::new()))
+    }
+
+    /// Remember how long the players of a new match waited in their pool
+    async fn record_waits(&self, pool: &MatchPool, requests: &[&MatchRequest]) -> Result<(), String> {
+        let now = Utc::now();
+        let waits: Vec<u64> = requests
+            .iter()
+            .map(|request| now.signed_duration_since(request.player.join_time).num_seconds().max(0) as u64)
+            .collect();
+
+        self.store.record_waits(pool, &waits).await
+    }
+
+    /// Mean of the recent waits in a pool and the number of samples
+    async fn recent_wait(&self, pool: &MatchPool) -> Result<(Option<Duration>, usize), String> {
+        let waits = self.store.recent_waits(pool).await?;
+
+        if waits.is_empty() {
+            return Ok((None, 0));
+        }
+        let mean = waits.iter().sum::<u64>() / waits.len() as u64;
+        Ok((Some(Duration::from_secs(mean)), waits.len()))
     }
 
     pub async fn join_queue(
@@ -44,21 +65,15 @@ impl MatchmakingService {
         let request_id = request.id;
 
         match request.match_type {
-            MatchType::Rated => {
-                if let Some(match_result) = self.find_rated_match(&request).await? {
+            MatchType::Rated | MatchType::Casual => {
+                if let Some(match_result) = self.find_match(&request).await? {
                     return Ok(match_result);
                 }
-                self.add_to_redis_queue(&request).await?;
-            }
-            MatchType::Casual => {
-                if let Some(match_result) = self.find_casual_match(&request).await? {
-                    return Ok(match_result);
-                }
-                self.add_to_redis_queue(&request).await?;
+                self.store.enqueue(&request, Utc::now()).await?;
             }
             MatchType::Private => {
                 if let Some(invite_address) = &request.invite_address {
-                    self.add_private_invite(invite_address, &request).await?;
+                    self.store.add_invite(invite_address, &request).await?;
                     return Ok(MatchmakingResponse {
                         status: "Waiting for invited player".to_string(),
                         match_id: None,
@@ -82,68 +97,48 @@ impl MatchmakingService {
         })
     }
 
-    async fn add_to_redis_queue(&self, request: &MatchRequest) -> Result<(), String> {
-        let mut conn = self.get_redis_connection().await?;
-        let key = request.match_type.redis_key();
-        let now = Utc::now();
-        let score = now.timestamp() as f64;
-        let value = request
-            .to_redis_value()
-            .map_err(|e| format!("Serialization error: {}", e))?;
-
-        let cutoff = (now - chrono::Duration::hours(1)).timestamp() as f64;
-        conn.zrembyscore::<_, _, _, ()>(&key, f64::NEG_INFINITY, cutoff)
-            .await
-            .map_err(|e| format!("Redis ZREMRANGEBYSCORE failed: {}", e))?;
-
-        conn.zadd::<_, _, _, ()>(&key, &value, score)
-            .await
-            .map_err(|e| format!("Redis ZADD failed: {}", e))?;
-
-        conn.expire::<_, ()>(&key, 3600)
-            .await
-            .map_err(|e| format!("Redis EXPIRE failed: {}", e))?;
+    /// Every pool that may have players waiting
+    pub async fn pools(&self) -> Result<Vec<MatchPool>, String> {
+        self.store.pools().await
+    }
 
-        Ok(())
+    /// Stop tracking a pool once nobody waits in it
+    pub async fn forget_pool_if_empty(&self, pool: &MatchPool) -> Result<(), String> {
+        self.store.forget_pool_if_empty(pool).await
     }
 
+    /// Size and recent waits of every pool with players waiting
+    pub async fn pool_stats(&self) -> Result<Vec<PoolStats>, String> {
+        let mut stats = Vec::new();
 
-    async fn add_private_invite(
-        &self,
-        invite_address: &str,
-        request: &MatchRequest,
-    ) -> Result<(), String> {
-        let mut conn = self.get_redis_connection().await?;
-        let key = "matchmaking:invites";
-        let value = request
-            .to_redis_value()
-            .map_err(|e| format!("Serialization error: {}", e))?;
+        for pool in self.store.pools().await? {
+            let players_waiting = self.store.pool_size(&pool).await?;
+            if players_waiting == 0 {
+                continue;
+            }
 
-        conn.hset::<_, _, _, ()>(key, invite_address, &value)
-            .await
-            .map_err(|e| format!("Redis HSET failed: {}", e))?;
+            let (estimated_wait_time, recent_samples) = self.recent_wait(&pool).await?;
+            stats.push(PoolStats {
+                pool,
+                players_waiting,
+                estimated_wait_time,
+                recent_samples,
+            });
+        }
 
-        Ok(())
+        Ok(stats)
     }
 
     pub async fn check_private_invite(
         &self,
         wallet_address: &str,
     ) -> Result<Option<MatchRequest>, String> {
-        let mut conn = self.get_redis_connection().await?;
-        let key = "matchmaking:invites";
-
-        let value: Option<String> = conn
-            .hget(key, wallet_address)
-            .await
-            .map_err(|e| format!("Redis HGET failed: {}", e))?;
-
-        match value {
-            Some(json) => MatchRequest::from_redis_value(&json)
-                .map(Some)
-                .map_err(|e| format!("Deserialization error: {}", e)),
-            None => Ok(None),
-        }
+        self.store.invite_for(wallet_address).await
+    }
+
+    /// A private invite that has not been accepted yet
+    pub async fn find_invite(&self, request_id: Uuid) -> Result<Option<MatchRequest>, String> {
+        self.store.find_invite(request_id).await
     }
 
     pub async fn accept_private_invite(
@@ -151,124 +146,38 @@ impl MatchmakingService {
         inviter_request_id: Uuid,
         accepting_player: Player,
     ) -> Result<Option<MatchmakingResponse>, String> {
-        let mut conn = self.get_redis_connection().await?;
-        let key = "matchmaking:invites";
-
-        // Lua script for atomic find-and-remove operation
-        // This prevents race conditions where multiple players try to accept the same invite
-        let lua_script = r#"
-            local key = KEYS[1]
-            local target_request_id = ARGV[1]
-            
-            local invites = redis.call('HGETALL', key)
-            
-            for i = 1, #invites, 2 do
-                local invite_address = invites[i]
-                local invite_json = invites[i + 1]
-                local invite = cjson.decode(invite_json)
-                
-                if invite.id == target_request_id then
-                    redis.call('HDEL', key, invite_address)
-                    return invite_json
-                end
-            end
-            
-            return nil
-        "#;
-
-        let result: Option<String> = redis::Script::new(lua_script)
-            .key(key)
-            .arg(inviter_request_id.to_string())
-            .invoke_async(&mut conn)
-            .await
-            .map_err(|e| format!("Redis Lua script failed: {}", e))?;
-
-        if let Some(invite_json) = result {
-            if let Ok(invite_request) = MatchRequest::from_redis_value(&invite_json) {
-                // Create match
-                let match_id = Uuid::new_v4();
-                let new_match = Match {
-                    id: match_id,
-                    player1: invite_request.player,
-                    player2: accepting_player,
-                    match_type: MatchType::Private,
-                    created_at: Utc::now(),
-                };
-
-                let mut active_matches = self.active_matches.lock().unwrap();
-                active_matches.insert(match_id, new_match);
-
-                return Ok(Some(MatchmakingResponse {
-                    status: "Match created".to_string(),
-                    match_id: Some(match_id),
-                    request_id: inviter_request_id,
-                }));
-            }
-        }
-
-        Ok(None)
-
+        let Some(invite_request) = self.store.take_invite(inviter_request_id).await? else {
+            return Ok(None);
+        };
+
+        // The invited player takes the game as offered
+        let accepting_request = MatchRequest {
+            id: Uuid::new_v4(),
+            player: accepting_player,
+            invite_address: None,
+            color: None,
+            ..invite_request.clone()
+        };
+        let new_match = Match::between(&invite_request, &accepting_request);
+
+        self.store.save_match(&new_match, &[inviter_request_id]).await?;
+
+        Ok(Some(MatchmakingResponse {
+            status: "Match created".to_string(),
+            match_id: Some(new_match.id),
+            request_id: inviter_request_id,
+        }))
     }
 
     pub async fn cancel_request(&self, request_id: Uuid) -> Result<bool, String> {
-        let mut conn = self.get_redis_connection().await?;
-
-        // Try to remove from rated queue
-        if self
-            .remove_from_queue(&mut conn, "matchmaking:queue:rated", request_id)
-            .await?
-        {
-            return Ok(true);
-        }
-
-        // Try to remove from casual queue
-        if self
-            .remove_from_queue(&mut conn, "matchmaking:queue:casual", request_id)
-            .await?
-        {
+        // Try to remove from the public pools
+        if self.store.remove_request(request_id).await? {
             return Ok(true);
         }
 
         // Try to remove from private invites
-        let invites: HashMap<String, String> = conn
-            .hgetall("matchmaking:invites")
-            .await
-            .map_err(|e| format!("Redis HGETALL failed: {}", e))?;
-
-        for (invite_address, json) in invites {
-            if let Ok(request) = MatchRequest::from_redis_value(&json) {
-                if request.id == request_id {
-                    conn.hdel::<_, _, ()>("matchmaking:invites", &invite_address)
-                        .await
-                        .map_err(|e| format!("Redis HDEL failed: {}", e))?;
-                    return Ok(true);
-                }
-            }
-        }
-
-        Ok(false)
-    }
-
-    async fn remove_from_queue(
-        &self,
-        conn: &mut deadpool_redis::Connection,
-        key: &str,
-        request_id: Uuid,
-    ) -> Result<bool, String> {
-        let members: Vec<String> = conn
-            .zrange(key, 0, -1)
-            .await
-            .map_err(|e| format!("Redis ZRANGE failed: {}", e))?;
-
-        for member in members {
-            if let Ok(request) = MatchRequest::from_redis_value(&member) {
-                if request.id == request_id {
-                    conn.zrem::<_, _, ()>(key, &member)
-                        .await
-                        .map_err(|e| format!("Redis ZREM failed: {}", e))?;
-                    return Ok(true);
-                }
-            }
+        if self.store.take_invite(request_id).await?.is_some() {
+            return Ok(true);
         }
 
         Ok(false)
@@ -278,187 +187,55 @@ impl MatchmakingService {
         &self,
         request_id: Uuid,
     ) -> Result<Option<QueueStatus>, String> {
-        let mut conn = self.get_redis_connection().await?;
-
-        // Check rated queue
-        if let Some(status) = self
-            .get_status_from_queue(
-                &mut conn,
-                "matchmaking:queue:rated",
+        // Check the public pools
+        if let Some((pool, index)) = self.store.find_request(request_id).await? {
+            let estimated_wait_time = match self.recent_wait(&pool).await? {
+                (Some(recent), _) => recent,
+                (None, _) => self.estimate_wait_time(index, &pool.match_type),
+            };
+            return Ok(Some(QueueStatus {
                 request_id,
-                MatchType::Rated,
-            )
-            .await?
-        {
-            return Ok(Some(status));
-        }
-
-        // Check casual queue
-        if let Some(status) = self
-            .get_status_from_queue(
-                &mut conn,
-                "matchmaking:queue:casual",
-                request_id,
-                MatchType::Casual,
-            )
-            .await?
-        {
-            return Ok(Some(status));
+                position: index + 1,
+                estimated_wait_time,
+                match_type: pool.match_type,
+            }));
         }
 
         // Check private invites
-        let invites: HashMap<String, String> = conn
-            .hgetall("matchmaking:invites")
-            .await
-            .map_err(|e| format!("Redis HGETALL failed: {}", e))?;
-
-        for (_, json) in invites {
-            if let Ok(request) = MatchRequest::from_redis_value(&json) {
-                if request.id == request_id {
-                    return Ok(Some(QueueStatus {
-                        request_id,
-                        position: 1,
-                        estimated_wait_time: DEFAULT_ESTIMATED_WAIT_TIME,
-                        match_type: MatchType::Private,
-                    }));
-                }
-            }
-        }
-
-        Ok(None)
-    }
-
-    async fn get_status_from_queue(
-        &self,
-        conn: &mut deadpool_redis::Connection,
-        key: &str,
-        request_id: Uuid,
-        match_type: MatchType,
-    ) -> Result<Option<QueueStatus>, String> {
-        let members: Vec<String> = conn
-            .zrange(key, 0, -1)
-            .await
-            .map_err(|e| format!("Redis ZRANGE failed: {}", e))?;
-
-        for (index, member) in members.iter().enumerate() {
-            if let Ok(request) = MatchRequest::from_redis_value(member) {
-                if request.id == request_id {
-                    return Ok(Some(QueueStatus {
-                        request_id,
-                        position: index + 1,
-                        estimated_wait_time: self.estimate_wait_time(index, &match_type),
-                        match_type,
-                    }));
-                }
-            }
-        }
-
-        Ok(None)
-    }
-
-    async fn find_rated_match(
-        &self,
-        request: &MatchRequest,
-    ) -> Result<Option<MatchmakingResponse>, String> {
-        let mut conn = self.get_redis_connection().await?;
-        let key = "matchmaking:queue:rated";
-        let player_elo = request.player.elo;
-        let max_elo_diff = request.max_elo_diff.unwrap_or(DEFAULT_MAX_ELO_DIFF);
-
-        // Lua script for atomic find-and-remove operation
-        // This prevents race conditions where two players try to match with the same opponent
-        let lua_script = r#"
-            local key = KEYS[1]
-            local player_elo = tonumber(ARGV[1])
-            local max_elo_diff = tonumber(ARGV[2])
-            
-            local members = redis.call('ZRANGE', key, 0, -1)
-            
-            for i, member in ipairs(members) do
-                local opponent = cjson.decode(member)
-                local elo_diff = math.abs(opponent.player.elo - player_elo)
-                
-                if elo_diff <= max_elo_diff then
-                    redis.call('ZREM', key, member)
-                    return member
-                end
-            end
-            
-            return nil
-        "#;
-
-        let result: Option<String> = redis::Script::new(lua_script)
-            .key(key)
-            .arg(player_elo)
-            .arg(max_elo_diff)
-            .invoke_async(&mut conn)
-            .await
-            .map_err(|e| format!("Redis Lua script failed: {}", e))?;
-
-        if let Some(opponent_json) = result {
-            if let Ok(opponent_request) = MatchRequest::from_redis_value(&opponent_json) {
-                // Create match
-                let match_id = Uuid::new_v4();
-                let new_match = Match {
-                    id: match_id,
-                    player1: opponent_request.player,
-                    player2: request.player.clone(),
-                    match_type: MatchType::Rated,
-                    created_at: Utc::now(),
-                };
-
-                let mut active_matches = self.active_matches.lock().unwrap();
-                active_matches.insert(match_id, new_match);
-
-                return Ok(Some(MatchmakingResponse {
-                    status: "Match found".to_string(),
-                    match_id: Some(match_id),
-                    request_id: request.id,
-                }));
-            }
+        if self.store.find_invite(request_id).await?.is_some() {
+            return Ok(Some(QueueStatus {
+                request_id,
+                position: 1,
+                estimated_wait_time: DEFAULT_ESTIMATED_WAIT_TIME,
+                match_type: MatchType::Private,
+            }));
         }
 
         Ok(None)
     }
 
-    async fn find_casual_match(
+    /// Pair a joining player with someone already waiting in their pool
+    async fn find_match(
         &self,
         request: &MatchRequest,
     ) -> Result<Option<MatchmakingResponse>, String> {
-        let mut conn = self.get_redis_connection().await?;
-        let key = "matchmaking:queue:casual";
-
-        // Pop the oldest player from queue (FIFO)
-        let result: Vec<(String, f64)> = conn
-            .zpopmin(key, 1)
-            .await
-            .map_err(|e| format!("Redis ZPOPMIN failed: {}", e))?;
-        
-        let result = result.into_iter().next();
-
-        if let Some((member, _score)) = result {
-            if let Ok(opponent_request) = MatchRequest::from_redis_value(&member) {
-                let match_id = Uuid::new_v4();
-                let new_match = Match {
-                    id: match_id,
-                    player1: opponent_request.player,
-                    player2: request.player.clone(),
-                    match_type: MatchType::Casual,
-                    created_at: Utc::now(),
-                };
-
-                let mut active_matches = self.active_matches.lock().unwrap();
-                active_matches.insert(match_id, new_match);
-
-                return Ok(Some(MatchmakingResponse {
-                    status: "Match found".to_string(),
-                    match_id: Some(match_id),
-                    request_id: request.id,
-                }));
-            }
-        }
-
-        Ok(None)
+        let Some(opponent_request) = self.store.take_opponent(request).await? else {
+            return Ok(None);
+        };
+
+        let new_match = Match::between(&opponent_request, request);
+
+        self.store
+            .save_match(&new_match, &[opponent_request.id, request.id])
+            .await?;
+        self.record_waits(&MatchPool::of(request), &[&opponent_request, request])
+            .await?;
+
+        Ok(Some(MatchmakingResponse {
+            status: "Match found".to_string(),
+            match_id: Some(new_match.id),
+            request_id: request.id,
+        }))
     }
 
     fn estimate_wait_time(&self, position: usize, match_type: &MatchType) -> Duration {
@@ -469,40 +246,25 @@ impl MatchmakingService {
         }
     }
 
-    pub async fn expand_elo_ranges(&self) -> Result<(), String> {
-        let mut conn = self.get_redis_connection().await?;
-        let key = "matchmaking:queue:rated";
+    /// Widen the rating range of every rated request by how long it has
+    /// waited. The expansion is recomputed from the join time, so running
+    /// this repeatedly does not compound it.
+    pub async fn expand_rating_ranges(&self) -> Result<(), String> {
         let now = Utc::now();
-
-        let members: Vec<(String, f64)> = conn
-            .zrange_withscores(key, 0, -1)
-            .await
-            .map_err(|e| format!("Redis ZRANGE failed: {}", e))?;
-
-        for (member, score) in members {
-            if let Ok(mut request) = MatchRequest::from_redis_value(&member) {
-                let wait_time = now.signed_duration_since(request.player.join_time);
-                let minutes_waiting = wait_time.num_minutes();
-
-                if minutes_waiting > 0 {
-                    let additional_range = minutes_waiting as u32 * ELO_RANGE_INCREMENT_PER_MINUTE;
-                    request.max_elo_diff = Some(
-                        request.max_elo_diff.unwrap_or(DEFAULT_MAX_ELO_DIFF) + additional_range,
-                    );
-
-                    // Update in Redis
-                    let updated_value = request
-                        .to_redis_value()
-                        .map_err(|e| format!("Serialization error: {}", e))?;
-
-                    // Remove old entry and add updated one
-                    conn.zrem::<_, _, ()>(key, &member)
-                        .await
-                        .map_err(|e| format!("Redis ZREM failed: {}", e))?;
-
-                    conn.zadd::<_, _, _, ()>(key, &updated_value, score)
-                        .await
-                        .map_err(|e| format!("Redis ZADD failed: {}", e))?;
+        let pools = self.store.pools().await?;
+
+        for pool in pools.iter().filter(|pool| pool.match_type == MatchType::Rated) {
+            for entry in self.store.queued(pool).await? {
+                let wait_time = now.signed_duration_since(entry.request.player.join_time);
+                let minutes_waiting = wait_time.num_minutes().max(0) as u32;
+                let range_expansion = minutes_waiting * RATING_RANGE_INCREMENT_PER_MINUTE;
+
+                if range_expansion != entry.request.range_expansion {
+                    let updated = MatchRequest {
+                        range_expansion,
+                        ..entry.request.clone()
+                    };
+                    self.store.replace_entry(pool, &entry, &updated).await?;
                 }
             }
         }
@@ -510,12 +272,235 @@ impl MatchmakingService {
         Ok(())
     }
 
-    pub fn get_match(&self, match_id: Uuid) -> Option<Match> {
-        let active_matches = self.active_matches.lock().unwrap();
-        active_matches.get(&match_id).cloned()
+    /// Every request waiting in a pool, oldest first
+    pub async fn queued_requests(&self, pool: &MatchPool) -> Result<Vec<QueueEntry>, String> {
+        self.store.queued(pool).await
+    }
+
+    /// Take two queued requests out of their pool and create their match in
+    /// one step. Returns `None` when either of them was matched or cancelled
+    /// since it was read.
+    pub async fn claim_pair(
+        &self,
+        pool: &MatchPool,
+        first: &QueueEntry,
+        second: &QueueEntry,
+    ) -> Result<Option<Match>, String> {
+        let new_match = Match::between(&first.request, &second.request);
+
+        if !self.store.claim_pair(pool, first, second, &new_match).await? {
+            return Ok(None);
+        }
+
+        self.record_waits(pool, &[&first.request, &second.request])
+            .await?;
+
+        Ok(Some(new_match))
+    }
+
+    /// Try to become the one replica pairing the pools for `ttl`.
+    /// `token` identifies the holder when releasing.
+    pub async fn acquire_worker_lock(&self, token: &str, ttl: Duration) -> Result<bool, String> {
+        self.store.acquire_lock(token, ttl).await
+    }
+
+    /// Release the worker lock if it is still held with `token`
+    pub async fn release_worker_lock(&self, token: &str) -> Result<(), String> {
+        self.store.release_lock(token).await
+    }
+
+    /// The match a request ended up in, if it has been matched
+    pub async fn matched_request(&self, request_id: Uuid) -> Result<Option<Uuid>, String> {
+        self.store.match_for_request(request_id).await
+    }
+
+    pub async fn get_match(&self, match_id: Uuid) -> Result<Option<Match>, String> {
+        self.store.get_match(match_id).await
     }
 }
 
 pub fn get_matchmaking_service(redis_pool: Pool) -> web::Data<MatchmakingService> {
     web::Data::new(MatchmakingService::new(redis_pool))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worker::{MatchmakingWorker, WorkerConfig};
+    use std::collections::HashMap;
+
+    fn player(wallet: &str) -> Player {
+        MatchRequest::test(wallet, 1500).player
+    }
+
+    #[tokio::test]
+    async fn rated_join_pairs_within_range_only() {
+        let service = MatchmakingService::in_memory();
+        let waiting = MatchRequest::test("a", 1500);
+        let far = MatchRequest::test("b", 1900);
+        let near = MatchRequest::test("c", 1600);
+
+        assert!(service.join_queue(waiting.clone()).await.unwrap().match_id.is_none());
+        assert!(service.join_queue(far.clone()).await.unwrap().match_id.is_none());
+
+        let matched = service.join_queue(near.clone()).await.unwrap();
+        let match_id = matched.match_id.expect("within range");
+        assert_eq!(service.matched_request(waiting.id).await.unwrap(), Some(match_id));
+        assert_eq!(service.matched_request(near.id).await.unwrap(), Some(match_id));
+
+        let created = service.get_match(match_id).await.unwrap().unwrap();
+        assert_eq!(created.player1.wallet_address, "a");
+        assert_eq!(created.player2.wallet_address, "c");
+
+        let status = service.get_queue_status(far.id).await.unwrap().unwrap();
+        assert_eq!(status.position, 1);
+    }
+
+    #[tokio::test]
+    async fn pools_do_not_mix() {
+        let service = MatchmakingService::in_memory();
+        let mut bullet = MatchRequest::test("a", 1500);
+        bullet.time_control = TimeControl { initial_secs: 60, increment_secs: 0 };
+        let mut classical = MatchRequest::test("b", 1500);
+        classical.time_control = TimeControl { initial_secs: 1800, increment_secs: 0 };
+        let mut chess960 = MatchRequest::test("c", 1500);
+        chess960.time_control = bullet.time_control;
+        chess960.variant = Variant::Chess960;
+        let casual = MatchRequest {
+            id: Uuid::new_v4(),
+            match_type: MatchType::Casual,
+            ..bullet.clone()
+        };
+
+        for queued in [&bullet, &classical, &chess960, &casual] {
+            let response = service.join_queue(queued.clone()).await.unwrap();
+            assert!(response.match_id.is_none());
+        }
+        assert_eq!(service.pool_stats().await.unwrap().len(), 4);
+
+        let mut bullet_opponent = MatchRequest::test("d", 1500);
+        bullet_opponent.time_control = bullet.time_control;
+        let matched = service.join_queue(bullet_opponent).await.unwrap();
+        let created = service.get_match(matched.match_id.unwrap()).await.unwrap().unwrap();
+        assert_eq!(created.player1.wallet_address, "a");
+        assert_eq!(created.time_control, bullet.time_control);
+    }
+
+    #[tokio::test]
+    async fn casual_join_skips_clashing_colors() {
+        let service = MatchmakingService::in_memory();
+        for wallet in ["a", "b"] {
+            let mut wants_white = MatchRequest::test(wallet, 1500).with_type(MatchType::Casual);
+            wants_white.color = Some(Color::White);
+            let response = service.join_queue(wants_white).await.unwrap();
+            assert!(response.match_id.is_none());
+        }
+
+        let mut wants_black = MatchRequest::test("c", 1500).with_type(MatchType::Casual);
+        wants_black.color = Some(Color::Black);
+        let matched = service.join_queue(wants_black).await.unwrap();
+
+        let created = service.get_match(matched.match_id.unwrap()).await.unwrap().unwrap();
+        assert_eq!(created.player1.wallet_address, "a");
+        assert_eq!(created.player1_color, Color::White);
+    }
+
+    #[tokio::test]
+    async fn cancelled_requests_leave_the_queue() {
+        let service = MatchmakingService::in_memory();
+        let queued = MatchRequest::test("a", 1500).with_type(MatchType::Casual);
+        service.join_queue(queued.clone()).await.unwrap();
+
+        assert!(service.cancel_request(queued.id).await.unwrap());
+        assert!(!service.cancel_request(queued.id).await.unwrap());
+        assert!(service.get_queue_status(queued.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn waits_of_matched_players_feed_the_estimates() {
+        let service = MatchmakingService::in_memory();
+        let mut waited = MatchRequest::test("a", 1500).with_type(MatchType::Casual);
+        waited.player.join_time = Utc::now() - chrono::Duration::seconds(40);
+        service.join_queue(waited).await.unwrap();
+        service.join_queue(MatchRequest::test("b", 1500).with_type(MatchType::Casual)).await.unwrap();
+
+        let queued = MatchRequest::test("c", 1500).with_type(MatchType::Casual);
+        service.join_queue(queued.clone()).await.unwrap();
+
+        let stats = service.pool_stats().await.unwrap();
+        assert_eq!(stats[0].players_waiting, 1);
+        assert_eq!(stats[0].recent_samples, 2);
+        assert_eq!(stats[0].estimated_wait_time, Some(Duration::from_secs(20)));
+
+        let status = service.get_queue_status(queued.id).await.unwrap().unwrap();
+        assert_eq!(status.estimated_wait_time, Duration::from_secs(20));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn invite_is_accepted_only_once() {
+        let service = MatchmakingService::in_memory();
+        let mut invite = MatchRequest::test("host", 1500).with_type(MatchType::Private);
+        invite.invite_address = Some("guest".to_string());
+        service.join_queue(invite.clone()).await.unwrap();
+        assert_eq!(service.check_private_invite("guest").await.unwrap().unwrap().id, invite.id);
+
+        let accepts = (0..8).map(|i| {
+            let service = service.clone();
+            tokio::spawn(async move {
+                service
+                    .accept_private_invite(invite.id, player(&format!("guest{}", i)))
+                    .await
+                    .unwrap()
+            })
+        });
+        let mut accepted = 0;
+        for accept in accepts.collect::<Vec<_>>() {
+            if accept.await.unwrap().is_some() {
+                accepted += 1;
+            }
+        }
+
+        assert_eq!(accepted, 1);
+        assert!(service.check_private_invite("guest").await.unwrap().is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_joins_never_share_a_player() {
+        let service = MatchmakingService::in_memory();
+        let requests: Vec<MatchRequest> = (0..64)
+            .map(|i| MatchRequest::test(&format!("player{}", i), 1500 + i % 7))
+            .collect();
+
+        let joins: Vec<_> = requests
+            .iter()
+            .cloned()
+            .map(|queued| {
+                let service = service.clone();
+                tokio::spawn(async move { service.join_queue(queued).await.unwrap() })
+            })
+            .collect();
+        for join in joins {
+            join.await.unwrap();
+        }
+
+        // Joins that raced past each other are left for the worker
+        let worker = MatchmakingWorker::new(service.clone(), WorkerConfig::default());
+        worker.run_once().await.unwrap();
+        let pool = MatchPool::of(&requests[0]);
+
+        let mut players_per_match: HashMap<Uuid, usize> = HashMap::new();
+        let queued = service.queued_requests(&pool).await.unwrap();
+        for queued_request in &requests {
+            let match_id = service.matched_request(queued_request.id).await.unwrap();
+            let waiting = queued.iter().any(|entry| entry.request.id == queued_request.id);
+            assert!(match_id.is_some() != waiting, "{} matched and queued", queued_request.player.wallet_address);
+            if let Some(match_id) = match_id {
+                *players_per_match.entry(match_id).or_default() += 1;
+            }
+        }
+
+        assert!(queued.is_empty());
+        assert_eq!(players_per_match.len(), requests.len() / 2);
+        assert!(players_per_match.values().all(|players| *players == 2));
+    }
+}
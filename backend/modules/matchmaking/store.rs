@@ -0,0 +1,133 @@
+/// Storage behind matchmaking: the pools, private invites and created
+/// matches.
+///
+/// Every method is one atomic step. Whatever several service instances or
+/// the worker do at the same time, a queued request ends up in at most one
+/// match. `RedisStore` gets there with Lua scripts, `MemoryStore` with a
+/// single lock; both follow the rules in this module so they pair alike.
+/// Both also index queued requests by id, so a request is found without
+/// walking every pool.
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::models::*;
+use super::service::{DEFAULT_MAX_RATING_DIFF, SETTLED_DEVIATION};
+
+/// Requests older than this are dropped from a pool when someone joins it
+pub const QUEUE_ENTRY_TTL_SECS: i64 = 3600;
+/// Waits kept per pool for estimates
+pub const WAIT_SAMPLES: usize = 50;
+
+/// A queued request together with its stored form, which identifies
+/// exactly this entry; a request whose range was widened is a new entry
+#[derive(Debug, Clone)]
+pub struct QueueEntry {
+    pub member: String,
+    pub request: MatchRequest,
+}
+
+#[async_trait]
+pub trait MatchmakingStore: Send + Sync {
+    /// Queue a request in its pool, oldest first by `now`, dropping entries
+    /// that waited longer than `QUEUE_ENTRY_TTL_SECS`
+    async fn enqueue(&self, request: &MatchRequest, now: DateTime<Utc>) -> Result<(), String>;
+
+    /// Every pool that may have players waiting
+    async fn pools(&self) -> Result<Vec<MatchPool>, String>;
+
+    /// Stop tracking a pool if nobody waits in it
+    async fn forget_pool_if_empty(&self, pool: &MatchPool) -> Result<(), String>;
+
+    /// Requests waiting in a pool, oldest first
+    async fn queued(&self, pool: &MatchPool) -> Result<Vec<QueueEntry>, String>;
+
+    async fn pool_size(&self, pool: &MatchPool) -> Result<usize, String>;
+
+    /// Remove and return the oldest request in `request`'s pool that
+    /// `takes_opponent` allows it to play
+    async fn take_opponent(&self, request: &MatchRequest) -> Result<Option<MatchRequest>, String>;
+
+    /// The pool a request waits in and its place there, counting from 0
+    /// for the oldest; `None` if it is not queued
+    async fn find_request(&self, request_id: Uuid) -> Result<Option<(MatchPool, usize)>, String>;
+
+    /// Remove a request from whichever pool it waits in; `false` if it was
+    /// not queued
+    async fn remove_request(&self, request_id: Uuid) -> Result<bool, String>;
+
+    /// Swap a queued entry for an updated one in place, unless it has left
+    /// the pool in the meantime
+    async fn replace_entry(
+        &self,
+        pool: &MatchPool,
+        entry: &QueueEntry,
+        updated: &MatchRequest,
+    ) -> Result<(), String>;
+
+    /// Remove both entries and save `new_match` for them, only if both are
+    /// still queued. Returns whether the match was made.
+    async fn claim_pair(
+        &self,
+        pool: &MatchPool,
+        first: &QueueEntry,
+        second: &QueueEntry,
+        new_match: &Match,
+    ) -> Result<bool, String>;
+
+    /// Offer a private game to `invite_address`, replacing any earlier
+    /// invite to them
+    async fn add_invite(&self, invite_address: &str, request: &MatchRequest) -> Result<(), String>;
+
+    /// The invite waiting for a player
+    async fn invite_for(&self, wallet_address: &str) -> Result<Option<MatchRequest>, String>;
+
+    async fn find_invite(&self, request_id: Uuid) -> Result<Option<MatchRequest>, String>;
+
+    /// Remove and return an invite, so only one player can accept it
+    async fn take_invite(&self, request_id: Uuid) -> Result<Option<MatchRequest>, String>;
+
+    /// Save a match and point the requests it came from at it
+    async fn save_match(&self, new_match: &Match, request_ids: &[Uuid]) -> Result<(), String>;
+
+    async fn get_match(&self, match_id: Uuid) -> Result<Option<Match>, String>;
+
+    /// The match a request ended up in
+    async fn match_for_request(&self, request_id: Uuid) -> Result<Option<Uuid>, String>;
+
+    /// Remember how long the players of a new match waited, in seconds
+    async fn record_waits(&self, pool: &MatchPool, waits: &[u64]) -> Result<(), String>;
+
+    /// The last `WAIT_SAMPLES` waits recorded in a pool
+    async fn recent_waits(&self, pool: &MatchPool) -> Result<Vec<u64>, String>;
+
+    /// Take the worker lock for `ttl` unless someone else holds it
+    async fn acquire_lock(&self, token: &str, ttl: Duration) -> Result<bool, String>;
+
+    /// Release the worker lock if it is still held with `token`
+    async fn release_lock(&self, token: &str) -> Result<(), String>;
+}
+
+/// Whether a player joining may be paired on the spot with `opponent`, who
+/// waits in the same pool. A wallet is never paired with itself. Only the
+/// joining player's range counts; ranges that grew while waiting are the
+/// worker's business.
+pub fn takes_opponent(request: &MatchRequest, opponent: &MatchRequest) -> bool {
+    if request.player.wallet_address == opponent.player.wallet_address
+        || !request.colors_compatible(opponent)
+    {
+        return false;
+    }
+    if request.match_type != MatchType::Rated {
+        return true;
+    }
+
+    let rating_diff = request.player.rating.abs_diff(opponent.player.rating);
+    let uncertainty = request
+        .player
+        .deviation
+        .max(opponent.player.deviation)
+        .saturating_sub(SETTLED_DEVIATION);
+    rating_diff <= request.max_rating_diff.unwrap_or(DEFAULT_MAX_RATING_DIFF) + uncertainty
+}
@@ -0,0 +1,309 @@
+/// Background pairing of the public queues.
+///
+/// `join_queue` only looks for an opponent at the moment a player joins, so
+/// two players whose ranges only overlap after some waiting would never meet.
+/// The worker periodically widens the rated ranges and runs a pairing pass
+/// over every pool. Replicas all run a worker; a Redis
+/// lock lets one of them pair per tick, and each pair is claimed with a
+/// script that fails if either player left the queue in the meantime.
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::models::*;
+use super::service::{MatchmakingService, DEFAULT_MAX_RATING_DIFF, SETTLED_DEVIATION};
+
+/// Rating points that one second of combined waiting makes up for when
+/// choosing between possible pairs
+const WAIT_WEIGHT: f64 = 1.0;
+
+#[derive(Debug, Clone)]
+pub struct WorkerConfig {
+    /// Time between pairing passes
+    pub interval: Duration,
+    /// How long a replica holds the lock; must outlast a pass
+    pub lock_ttl: Duration,
+}
+
+impl Default for WorkerConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(2),
+            lock_ttl: Duration::from_secs(10),
+        }
+    }
+}
+
+pub struct MatchmakingWorker {
+    service: MatchmakingService,
+    config: WorkerConfig,
+}
+
+impl MatchmakingWorker {
+    pub fn new(service: MatchmakingService, config: WorkerConfig) -> Self {
+        Self { service, config }
+    }
+
+    /// Run a pass every `interval` until the runtime shuts down
+    pub fn start(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.config.interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+            loop {
+                ticker.tick().await;
+                match self.run_once().await {
+                    Ok(0) => {}
+                    Ok(created) => log::info!("Matchmaking worker created {} matches", created),
+                    Err(e) => log::warn!("Matchmaking worker pass failed: {}", e),
+                }
+            }
+        })
+    }
+
+    /// One pass, if no other replica is running one. Returns the number of
+    /// matches created.
+    pub async fn run_once(&self) -> Result<usize, String> {
+        let token = Uuid::new_v4().to_string();
+        if !self
+            .service
+            .acquire_worker_lock(&token, self.config.lock_ttl)
+            .await?
+        {
+            return Ok(0);
+        }
+
+        let result = self.pair_queues().await;
+        self.service.release_worker_lock(&token).await?;
+        result
+    }
+
+    async fn pair_queues(&self) -> Result<usize, String> {
+        self.service.expand_rating_ranges().await?;
+
+        let now = Utc::now();
+        let mut created = 0;
+
+        for pool in self.service.pools().await? {
+            let queue = self.service.queued_requests(&pool).await?;
+            let requests: Vec<MatchRequest> =
+                queue.iter().map(|entry| entry.request.clone()).collect();
+
+            for (first, second) in plan_pairings(&requests, now) {
+                if self
+                    .service
+                    .claim_pair(&pool, &queue[first], &queue[second])
+                    .await?
+                    .is_some()
+                {
+                    created += 1;
+                }
+            }
+
+            self.service.forget_pool_if_empty(&pool).await?;
+        }
+
+        Ok(created)
+    }
+}
+
+/// Pick pairs from one pool, given oldest first. Pairs are chosen greedily
+/// by rating gap less a credit for the time both players have waited, so
+/// close ratings come first but long waits are not starved. A rated pair
+/// must be within both players' ranges, and color preferences must not
+/// clash. Each pair lists the player who
+/// joined first first.
+pub fn plan_pairings(requests: &[MatchRequest], now: DateTime<Utc>) -> Vec<(usize, usize)> {
+    let mut candidates = Vec::new();
+    for first in 0..requests.len() {
+        for second in first + 1..requests.len() {
+            let (a, b) = (&requests[first], &requests[second]);
+            if let Some(gap) = rating_gap(a, b) {
+                let waited = waited_secs(a, now) + waited_secs(b, now);
+                candidates.push((gap as f64 - waited * WAIT_WEIGHT, first, second));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| a.0.total_cmp(&b.0).then((a.1, a.2).cmp(&(b.1, b.2))));
+
+    let mut paired = vec![false; requests.len()];
+    let mut pairs = Vec::new();
+    for (_, first, second) in candidates {
+        if !paired[first] && !paired[second] {
+            paired[first] = true;
+            paired[second] = true;
+            pairs.push((first, second));
+        }
+    }
+    pairs
+}
+
+/// Rating gap between two requests that may be paired, or `None` if they
+/// may not. Casual pairs have no gap to speak of.
+fn rating_gap(a: &MatchRequest, b: &MatchRequest) -> Option<u32> {
+    if a.player.wallet_address == b.player.wallet_address || !a.colors_compatible(b) {
+        return None;
+    }
+    if a.match_type != MatchType::Rated {
+        return Some(0);
+    }
+
+    let gap = a.player.rating.abs_diff(b.player.rating);
+    let uncertainty = a
+        .player
+        .deviation
+        .max(b.player.deviation)
+        .saturating_sub(SETTLED_DEVIATION);
+    let accepts = |request: &MatchRequest| {
+        gap <= request.max_rating_diff.unwrap_or(DEFAULT_MAX_RATING_DIFF)
+            + request.range_expansion
+            + uncertainty
+    };
+
+    (accepts(a) && accepts(b)).then_some(gap)
+}
+
+fn waited_secs(request: &MatchRequest, now: DateTime<Utc>) -> f64 {
+    now.signed_duration_since(request.player.join_time)
+        .num_milliseconds()
+        .max(0) as f64
+        / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closest_ratings_are_paired() {
+        let queue = [
+            MatchRequest::test("a", 1500).with_wait(30),
+            MatchRequest::test("b", 1650).with_wait(30),
+            MatchRequest::test("c", 1520).with_wait(30),
+            MatchRequest::test("d", 1640).with_wait(30),
+        ];
+        assert_eq!(plan_pairings(&queue, Utc::now()), vec![(1, 3), (0, 2)]);
+    }
+
+    #[test]
+    fn pair_must_be_within_both_ranges() {
+        let mut wide = MatchRequest::test("a", 1500);
+        wide.max_rating_diff = Some(400);
+        let narrow = MatchRequest::test("b", 1800);
+        assert!(plan_pairings(&[wide.clone(), narrow.clone()], Utc::now()).is_empty());
+
+        let mut expanded = narrow;
+        expanded.range_expansion = 100;
+        assert_eq!(plan_pairings(&[wide, expanded], Utc::now()), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn uncertain_ratings_widen_the_range() {
+        let settled = MatchRequest::test("a", 1500);
+        let mut newcomer = MatchRequest::test("b", 1750);
+        assert!(plan_pairings(&[settled.clone(), newcomer.clone()], Utc::now()).is_empty());
+
+        newcomer.player.deviation = 350;
+        assert_eq!(plan_pairings(&[settled, newcomer], Utc::now()), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn long_waits_outweigh_small_rating_gaps() {
+        // Either "b" or "c" can play "a"; "c" is a bit further in rating
+        // but has waited far longer
+        let queue = [
+            MatchRequest::test("c", 1580).with_wait(300),
+            MatchRequest::test("a", 1500).with_wait(10),
+            MatchRequest::test("b", 1490).with_wait(10),
+        ];
+        assert_eq!(plan_pairings(&queue, Utc::now()), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn casual_queue_pairs_the_longest_waiting() {
+        let queue = [
+            MatchRequest::test("a", 1000).with_wait(90).with_type(MatchType::Casual),
+            MatchRequest::test("b", 2200).with_wait(60).with_type(MatchType::Casual),
+            MatchRequest::test("c", 1500).with_wait(5).with_type(MatchType::Casual),
+        ];
+        assert_eq!(plan_pairings(&queue, Utc::now()), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn player_is_not_paired_with_themselves() {
+        let queue = [
+            MatchRequest::test("a", 1500).with_wait(30),
+            MatchRequest::test("a", 1500).with_wait(10),
+        ];
+        assert!(plan_pairings(&queue, Utc::now()).is_empty());
+    }
+
+    #[test]
+    fn clashing_color_preferences_are_not_paired() {
+        let mut white = MatchRequest::test("a", 1500).with_wait(30);
+        white.color = Some(Color::White);
+        let mut also_white = MatchRequest::test("b", 1500).with_wait(30);
+        also_white.color = Some(Color::White);
+        let mut black = MatchRequest::test("c", 1600).with_wait(10);
+        black.color = Some(Color::Black);
+
+        assert_eq!(plan_pairings(&[white, also_white, black], Utc::now()), vec![(0, 2)]);
+    }
+
+    #[test]
+    fn matches_honor_color_preferences() {
+        let mut first = MatchRequest::test("a", 1500).with_wait(30);
+        let mut second = MatchRequest::test("b", 1500).with_wait(10);
+        second.color = Some(Color::White);
+        assert_eq!(Match::between(&first, &second).player1_color, Color::Black);
+
+        first.color = Some(Color::White);
+        second.color = None;
+        assert_eq!(Match::between(&first, &second).player1_color, Color::White);
+    }
+
+    #[test]
+    fn pools_are_keyed_by_type_variant_and_time_control() {
+        let mut bullet = MatchRequest::test("a", 1500);
+        bullet.time_control = TimeControl { initial_secs: 60, increment_secs: 0 };
+        let mut classical = bullet.clone();
+        classical.time_control = TimeControl { initial_secs: 1800, increment_secs: 0 };
+        let mut chess960 = bullet.clone();
+        chess960.variant = Variant::Chess960;
+
+        assert_eq!(MatchPool::of(&bullet).redis_key(), "matchmaking:queue:rated:standard:60+0");
+        assert_ne!(MatchPool::of(&bullet), MatchPool::of(&classical));
+        assert_ne!(MatchPool::of(&bullet), MatchPool::of(&chess960));
+        assert_eq!(MatchPool::of(&chess960).waits_key(), "matchmaking:waits:rated:chess960:60+0");
+    }
+
+    #[tokio::test]
+    async fn worker_pairs_players_once_their_ranges_have_grown() {
+        let service = MatchmakingService::in_memory();
+        let first = MatchRequest::test("a", 1500).with_wait(180);
+        let second = MatchRequest::test("b", 1800).with_wait(180);
+        for queued in [&first, &second] {
+            let response = service.join_queue(queued.clone()).await.unwrap();
+            assert!(response.match_id.is_none());
+        }
+
+        let worker = MatchmakingWorker::new(service.clone(), WorkerConfig::default());
+        assert_eq!(worker.run_once().await.unwrap(), 1);
+
+        let match_id = service.matched_request(first.id).await.unwrap().unwrap();
+        assert_eq!(service.matched_request(second.id).await.unwrap(), Some(match_id));
+        assert!(service.pools().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn worker_skips_the_pass_while_another_holds_the_lock() {
+        let service = MatchmakingService::in_memory();
+        service.join_queue(MatchRequest::test("a", 1500)).await.unwrap();
+        service.join_queue(MatchRequest::test("b", 1500).with_type(MatchType::Casual)).await.unwrap();
+
+        assert!(service.acquire_worker_lock("other", Duration::from_secs(10)).await.unwrap());
+        let worker = MatchmakingWorker::new(service.clone(), WorkerConfig::default());
+        assert_eq!(worker.run_once().await.unwrap(), 0);
+        assert_eq!(service.pools().await.unwrap().len(), 2);
+    }
+}
@@ -1,15 +1,16 @@
 use actix_web::{
-    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    dev::{Payload, Service, ServiceRequest, ServiceResponse, Transform},
     error::{Error, ErrorUnauthorized},
     body::{BoxBody, MessageBody},
-    HttpMessage, HttpResponse,
+    web, FromRequest, HttpMessage, HttpRequest, HttpResponse,
 };
-use futures_util::future::{ok, LocalBoxFuture, Ready};
+use futures_util::future::{ok, ready, LocalBoxFuture, Ready};
 use std::task::{Context, Poll};
 use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use std::rc::Rc;
 use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
 
 /// JWT Claims structure containing user identification and expiration
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -87,6 +88,51 @@ impl JwtService {
     }
 }
 
+/// The player acting on a request, taken from the subject of its validated JWT.
+///
+/// Reads the claims stored by `JwtAuthMiddleware` when the route is wrapped by
+/// it, and otherwise validates the bearer token with the app's `JwtService`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthenticatedPlayer(pub Uuid);
+
+impl AuthenticatedPlayer {
+    fn from_http(req: &HttpRequest) -> Result<Self, Error> {
+        let stored = req.extensions().get::<Claims>().cloned();
+        let claims = match stored {
+            Some(claims) => claims,
+            None => {
+                let jwt_service = req
+                    .app_data::<web::Data<JwtService>>()
+                    .ok_or_else(|| ErrorUnauthorized("Token validation is not configured"))?;
+                let header = req
+                    .headers()
+                    .get("Authorization")
+                    .ok_or_else(|| ErrorUnauthorized("Missing authorization header"))?
+                    .to_str()
+                    .map_err(|_| ErrorUnauthorized("Invalid authorization header"))?;
+                let token = JwtService::extract_token_from_header(header)
+                    .ok_or_else(|| ErrorUnauthorized("Invalid authorization format"))?;
+                jwt_service
+                    .validate_token(&token)
+                    .map_err(|_| ErrorUnauthorized("Invalid or expired token"))?
+            }
+        };
+
+        Uuid::parse_str(&claims.sub)
+            .map(AuthenticatedPlayer)
+            .map_err(|_| ErrorUnauthorized("Token does not identify a player"))
+    }
+}
+
+impl FromRequest for AuthenticatedPlayer {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Self::from_http(req))
+    }
+}
+
 /// Middleware for JWT authentication
 pub struct JwtAuthMiddleware {
     secret_key: Rc<String>,
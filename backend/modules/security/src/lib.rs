@@ -1,5 +1,5 @@
 pub mod jwt;
 pub mod token_service;
 
-pub use jwt::{JwtAuthMiddleware, JwtService, Claims};
+pub use jwt::{AuthenticatedPlayer, JwtAuthMiddleware, JwtService, Claims};
 pub use token_service::{TokenService, TokenServiceError};
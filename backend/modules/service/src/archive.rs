@@ -0,0 +1,722 @@
+//! Archival of finished games to cold storage.
+//!
+//! Finished games that have not changed for a configurable period are moved
+//! out of `smdb.game` into `smdb.game_archive`, one zstd-compressed JSON
+//! record per game that also carries its `game_move` rows. This keeps the
+//! hot table and its indexes bounded. `GameService::get_game` falls back to
+//! the archive, and game listings, search and exports merge archived games
+//! in with live ones, so archival never hides a game.
+//!
+//! Archived games are filed under the month they were archived in. Once a
+//! month is over, its games are also dumped as a zstd-compressed PGN file
+//! (`games-YYYY-MM.pgn.zst`) to a local directory. Filing by archive date
+//! means a closed month never gains games, so its dump stays complete.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use db_entity::game::GameStatus;
+use db_entity::{game, game_archive, game_move, prelude::{Game, GameArchive, GameMove}};
+use dto::games::ExportGameQuery;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::{LockBehavior, LockType, OnConflict};
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+use crate::games::GameService;
+
+/// Games moved per transaction
+const BATCH_SIZE: u64 = 500;
+
+/// zstd compression level for archive records and dumps
+const ZSTD_LEVEL: i32 = 9;
+
+/// Archive rows decoded per query while scanning for a listing
+const SCAN_BATCH_SIZE: u64 = 100;
+
+/// Archive rows one listing request may decode before returning a short page
+const MAX_SCAN_ROWS: usize = 2_000;
+
+/// Dumps carry every annotation we store
+const DUMP_ANNOTATIONS: ExportGameQuery = ExportGameQuery { clocks: true, evals: true, comments: true };
+
+/// When and where finished games are archived
+#[derive(Debug, Clone)]
+pub struct ArchiveConfig {
+    /// Finished games untouched for this long are archived
+    pub archive_after: Duration,
+    /// Pause between archival runs
+    pub interval: std::time::Duration,
+    /// Directory receiving the monthly PGN dumps
+    pub dump_dir: PathBuf,
+}
+
+/// Errors raised while archiving or dumping games
+#[derive(Debug)]
+pub enum ArchiveError {
+    Database(DbErr),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveError::Database(err) => write!(f, "Database error {}", err),
+            ArchiveError::Io(err) => write!(f, "I/O error {}", err),
+        }
+    }
+}
+
+impl From<DbErr> for ArchiveError {
+    fn from(value: DbErr) -> Self {
+        Self::Database(value)
+    }
+}
+
+impl From<std::io::Error> for ArchiveError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// Which archived games a listing may include.
+///
+/// Seats and the start date are columns of `game_archive`; every other
+/// filter needs the decoded game and is answered by `matches`.
+pub struct ArchiveScope<'a> {
+    pub player_id: Option<Uuid>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub matches: &'a (dyn Fn(&game::Model) -> bool + Sync),
+}
+
+/// Archived games found for one page of a listing
+#[derive(Debug, Default)]
+pub struct ArchiveScan {
+    /// Matching games, newest first
+    pub games: Vec<game::Model>,
+    /// Set when the scan gave up after `MAX_SCAN_ROWS` rows: the listing key
+    /// of the last row examined. Archived games beyond it were not looked at.
+    pub stopped_at: Option<(DateTime<Utc>, Uuid)>,
+}
+
+/// What a single archival run did
+#[derive(Debug, Default)]
+pub struct ArchiveRun {
+    pub archived: usize,
+    pub dumps: Vec<PathBuf>,
+}
+
+/// Layout of `game_archive.payload` written by this build. Records from
+/// before the version was stored read as format 0, which has the same fields.
+const ARCHIVE_FORMAT: u32 = 1;
+
+/// Contents of `game_archive.payload` before compression
+#[derive(Serialize, Deserialize)]
+struct ArchivedGame {
+    #[serde(default)]
+    format: u32,
+    game: ArchivedGameRow,
+    #[serde(default)]
+    moves: Vec<ArchivedMove>,
+}
+
+/// The archived `game` row.
+///
+/// Kept apart from `game::Model` so records outlive schema changes: a column
+/// added to `game` later must get a serde default here, and records written
+/// before it existed still decode.
+#[derive(Serialize, Deserialize)]
+struct ArchivedGameRow {
+    id: Uuid,
+    white_player: Option<Uuid>,
+    black_player: Option<Uuid>,
+    fen: String,
+    pgn: serde_json::Value,
+    result: Option<game::ResultSide>,
+    variant: game::GameVariant,
+    started_at: DateTimeWithTimeZone,
+    duration_sec: i32,
+    created_at: DateTimeWithTimeZone,
+    updated_at: DateTimeWithTimeZone,
+    #[serde(default)]
+    is_imported: bool,
+    #[serde(default)]
+    original_pgn: Option<String>,
+    #[serde(default)]
+    import_hash: Option<String>,
+    #[serde(default)]
+    position_keys: Vec<i64>,
+    #[serde(default = "ArchivedGameRow::default_status")]
+    status: GameStatus,
+    #[serde(default)]
+    initial_time_ms: i64,
+    #[serde(default)]
+    increment_ms: i64,
+    #[serde(default = "ArchivedGameRow::default_rated")]
+    rated: bool,
+    #[serde(default)]
+    termination: Option<String>,
+    #[serde(default)]
+    last_move_at: Option<DateTimeWithTimeZone>,
+    #[serde(default)]
+    moves_encoded: Option<Vec<u8>>,
+    #[serde(default = "ArchivedGameRow::default_initial_fen")]
+    initial_fen: String,
+    #[serde(default)]
+    version: i64,
+}
+
+impl ArchivedGameRow {
+    /// Only finished games are archived
+    fn default_status() -> GameStatus {
+        GameStatus::Completed
+    }
+
+    fn default_rated() -> bool {
+        true
+    }
+
+    fn default_initial_fen() -> String {
+        chess::STARTING_FEN.to_string()
+    }
+}
+
+impl From<game::Model> for ArchivedGameRow {
+    fn from(game: game::Model) -> Self {
+        Self {
+            id: game.id,
+            white_player: game.white_player,
+            black_player: game.black_player,
+            fen: game.fen,
+            pgn: game.pgn,
+            result: game.result,
+            variant: game.variant,
+            started_at: game.started_at,
+            duration_sec: game.duration_sec,
+            created_at: game.created_at,
+            updated_at: game.updated_at,
+            is_imported: game.is_imported,
+            original_pgn: game.original_pgn,
+            import_hash: game.import_hash,
+            position_keys: game.position_keys,
+            status: game.status,
+            initial_time_ms: game.initial_time_ms,
+            increment_ms: game.increment_ms,
+            rated: game.rated,
+            termination: game.termination,
+            last_move_at: game.last_move_at,
+            moves_encoded: game.moves_encoded,
+            initial_fen: game.initial_fen,
+            version: game.version,
+        }
+    }
+}
+
+impl From<ArchivedGameRow> for game::Model {
+    fn from(row: ArchivedGameRow) -> Self {
+        Self {
+            id: row.id,
+            white_player: row.white_player,
+            black_player: row.black_player,
+            fen: row.fen,
+            pgn: row.pgn,
+            result: row.result,
+            variant: row.variant,
+            started_at: row.started_at,
+            duration_sec: row.duration_sec,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            is_imported: row.is_imported,
+            original_pgn: row.original_pgn,
+            import_hash: row.import_hash,
+            position_keys: row.position_keys,
+            status: row.status,
+            initial_time_ms: row.initial_time_ms,
+            increment_ms: row.increment_ms,
+            rated: row.rated,
+            termination: row.termination,
+            last_move_at: row.last_move_at,
+            moves_encoded: row.moves_encoded,
+            initial_fen: row.initial_fen,
+            version: row.version,
+        }
+    }
+}
+
+/// An archived `game_move` row; see `ArchivedGameRow` for the rules
+#[derive(Serialize, Deserialize)]
+struct ArchivedMove {
+    id: i32,
+    game_id: Uuid,
+    move_number: i32,
+    san: String,
+    fen: String,
+    timestamp: DateTimeWithTimeZone,
+    #[serde(default)]
+    clock_ms: Option<i64>,
+    #[serde(default)]
+    time_spent_ms: Option<i64>,
+    #[serde(default)]
+    lag_compensation_ms: Option<i64>,
+}
+
+impl From<game_move::Model> for ArchivedMove {
+    fn from(game_move: game_move::Model) -> Self {
+        Self {
+            id: game_move.id,
+            game_id: game_move.game_id,
+            move_number: game_move.move_number,
+            san: game_move.san,
+            fen: game_move.fen,
+            timestamp: game_move.timestamp,
+            clock_ms: game_move.clock_ms,
+            time_spent_ms: game_move.time_spent_ms,
+            lag_compensation_ms: game_move.lag_compensation_ms,
+        }
+    }
+}
+
+pub struct ArchiveService;
+
+impl ArchiveService {
+    /// Run archival periodically on a spawned task.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn start(db: Arc<DatabaseConnection>, config: ArchiveConfig) {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(config.interval);
+            loop {
+                ticker.tick().await;
+                match Self::run_once(&db, &config).await {
+                    Ok(run) if run.archived > 0 || !run.dumps.is_empty() => {
+                        eprintln!("Archived {} games, wrote {} monthly dumps", run.archived, run.dumps.len())
+                    }
+                    Ok(_) => {}
+                    Err(e) => eprintln!("Game archival failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Archive every eligible game, then dump months that are now complete.
+    pub async fn run_once(db: &DatabaseConnection, config: &ArchiveConfig) -> Result<ArchiveRun, ArchiveError> {
+        let now = Utc::now();
+        let cutoff = now - config.archive_after;
+        let mut run = ArchiveRun::default();
+
+        loop {
+            let archived = Self::archive_finished(db, cutoff).await?;
+            run.archived += archived;
+            if (archived as u64) < BATCH_SIZE {
+                break;
+            }
+        }
+
+        run.dumps = Self::export_pending_months(db, now, &config.dump_dir).await?;
+        Ok(run)
+    }
+
+    /// Move one batch of finished games last updated before `cutoff` into
+    /// the archive. Returns how many games were moved.
+    ///
+    /// Rows are locked with `SKIP LOCKED`, so concurrent runs on several
+    /// servers split the work instead of blocking each other.
+    pub async fn archive_finished(db: &DatabaseConnection, cutoff: DateTime<Utc>) -> Result<usize, DbErr> {
+        let txn = db.begin().await?;
+        let games = Game::find()
+            .filter(game::Column::Status.is_in([GameStatus::Completed, GameStatus::Aborted, GameStatus::Adjudicated]))
+            .filter(game::Column::UpdatedAt.lt(cutoff))
+            .order_by_asc(game::Column::UpdatedAt)
+            .limit(BATCH_SIZE)
+            .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
+            .all(&txn)
+            .await?;
+        if games.is_empty() {
+            return Ok(0);
+        }
+
+        let ids: Vec<Uuid> = games.iter().map(|g| g.id).collect();
+        let mut moves_by_game: HashMap<Uuid, Vec<game_move::Model>> = HashMap::new();
+        for game_move in GameMove::find()
+            .filter(game_move::Column::GameId.is_in(ids.clone()))
+            .order_by_asc(game_move::Column::MoveNumber)
+            .all(&txn)
+            .await?
+        {
+            moves_by_game.entry(game_move.game_id).or_default().push(game_move);
+        }
+
+        let now = Utc::now();
+        let rows = games
+            .into_iter()
+            .map(|game| {
+                let moves = moves_by_game.remove(&game.id).unwrap_or_default();
+                Self::archive_row(game, moves, now)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        GameArchive::insert_many(rows)
+            .on_conflict(OnConflict::column(game_archive::Column::Id).do_nothing().to_owned())
+            .exec_without_returning(&txn)
+            .await?;
+        // Move rows go with the game through ON DELETE CASCADE
+        Game::delete_many()
+            .filter(game::Column::Id.is_in(ids.clone()))
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(ids.len())
+    }
+
+    /// Look up an archived game by ID.
+    pub async fn find(db: &DatabaseConnection, game_id: Uuid) -> Result<Option<game::Model>, DbErr> {
+        match GameArchive::find_by_id(game_id).one(db).await? {
+            Some(row) => Ok(Some(Self::unpack(&row)?.game.into())),
+            None => Ok(None),
+        }
+    }
+
+    /// Find up to `want` archived games in `scope`, newest first by
+    /// (created_at, id) and strictly after the `after` key.
+    ///
+    /// Each request decodes at most `MAX_SCAN_ROWS` rows, so a selective
+    /// filter over a large archive returns early with `stopped_at` set and
+    /// the caller continues from there on its next page. Records that fail
+    /// to decode are logged and skipped rather than failing the listing.
+    pub async fn scan(
+        db: &DatabaseConnection,
+        scope: &ArchiveScope<'_>,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        want: usize,
+    ) -> Result<ArchiveScan, DbErr> {
+        let mut scan = ArchiveScan::default();
+        let mut position = after;
+        let mut examined = 0;
+
+        loop {
+            let mut query = GameArchive::find()
+                .order_by_desc(game_archive::Column::CreatedAt)
+                .order_by_desc(game_archive::Column::Id)
+                .limit(SCAN_BATCH_SIZE);
+            if let Some(player_id) = scope.player_id {
+                query = query.filter(
+                    Condition::any()
+                        .add(game_archive::Column::WhitePlayer.eq(player_id))
+                        .add(game_archive::Column::BlackPlayer.eq(player_id)),
+                );
+            }
+            if let Some(since) = scope.since {
+                query = query.filter(game_archive::Column::StartedAt.gte(since));
+            }
+            if let Some(until) = scope.until {
+                query = query.filter(game_archive::Column::StartedAt.lt(until));
+            }
+            if let Some((created_at, id)) = position {
+                query = query.filter(
+                    Condition::any()
+                        .add(game_archive::Column::CreatedAt.lt(created_at))
+                        .add(
+                            Condition::all()
+                                .add(game_archive::Column::CreatedAt.eq(created_at))
+                                .add(game_archive::Column::Id.lt(id)),
+                        ),
+                );
+            }
+
+            let rows = query.all(db).await?;
+            let exhausted = (rows.len() as u64) < SCAN_BATCH_SIZE;
+            for row in &rows {
+                examined += 1;
+                position = Some((row.created_at.into(), row.id));
+                let game: game::Model = match Self::unpack(row) {
+                    Ok(archived) => archived.game.into(),
+                    Err(e) => {
+                        eprintln!("Skipping archived game: {}", e);
+                        continue;
+                    }
+                };
+                if (scope.matches)(&game) {
+                    scan.games.push(game);
+                    if scan.games.len() >= want {
+                        return Ok(scan);
+                    }
+                }
+            }
+
+            if exhausted {
+                return Ok(scan);
+            }
+            if examined >= MAX_SCAN_ROWS {
+                scan.stopped_at = position;
+                return Ok(scan);
+            }
+        }
+    }
+
+    /// Write every archived game of `month` as a zstd-compressed PGN file
+    /// in `dir`, replacing any earlier dump of the same month.
+    pub async fn export_month(
+        db: &DatabaseConnection,
+        month: NaiveDate,
+        dir: &Path,
+    ) -> Result<PathBuf, ArchiveError> {
+        tokio::fs::create_dir_all(dir).await?;
+        let path = dir.join(Self::dump_file_name(month));
+        // Written under a temporary name so readers never see a partial dump
+        let partial = path.with_extension("zst.partial");
+        let mut file = tokio::fs::File::create(&partial).await?;
+        let mut encoder = zstd::stream::Encoder::new(Vec::new(), ZSTD_LEVEL)?;
+
+        let mut last_id: Option<Uuid> = None;
+        loop {
+            let mut query = GameArchive::find()
+                .filter(game_archive::Column::ArchiveMonth.eq(month))
+                .order_by_asc(game_archive::Column::Id)
+                .limit(BATCH_SIZE);
+            if let Some(id) = last_id {
+                query = query.filter(game_archive::Column::Id.gt(id));
+            }
+            let rows = query.all(db).await?;
+            let Some(last) = rows.last() else {
+                break;
+            };
+            last_id = Some(last.id);
+
+            let games = rows
+                .iter()
+                .map(|row| Self::unpack(row).map(|archived| archived.game.into()))
+                .collect::<Result<Vec<_>, _>>()?;
+            let names = GameService::player_names(db, &games).await?;
+            for game in &games {
+                encoder.write_all(GameService::game_to_pgn(game, &names, &DUMP_ANNOTATIONS).as_bytes())?;
+                encoder.write_all(b"\n")?;
+            }
+            // Hand compressed output to the file page by page to bound memory
+            file.write_all(&std::mem::take(encoder.get_mut())).await?;
+        }
+
+        file.write_all(&encoder.finish()?).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&partial, &path).await?;
+        Ok(path)
+    }
+
+    /// Dump every month that can no longer receive games and has no dump yet.
+    pub async fn export_pending_months(
+        db: &DatabaseConnection,
+        now: DateTime<Utc>,
+        dir: &Path,
+    ) -> Result<Vec<PathBuf>, ArchiveError> {
+        let months: Vec<NaiveDate> = GameArchive::find()
+            .select_only()
+            .column(game_archive::Column::ArchiveMonth)
+            .distinct()
+            .filter(game_archive::Column::ArchiveMonth.lt(Self::first_open_month(now)))
+            .order_by_asc(game_archive::Column::ArchiveMonth)
+            .into_tuple()
+            .all(db)
+            .await?;
+
+        let mut dumps = Vec::new();
+        for month in months {
+            if tokio::fs::try_exists(dir.join(Self::dump_file_name(month))).await? {
+                continue;
+            }
+            dumps.push(Self::export_month(db, month, dir).await?);
+        }
+        Ok(dumps)
+    }
+
+    /// Earliest month that may still gain archived games.
+    ///
+    /// Rows are filed under their archive date, so only the current month
+    /// is open. A day of slack covers an archival transaction that began
+    /// before midnight and committed after it, and clock skew between servers.
+    fn first_open_month(now: DateTime<Utc>) -> NaiveDate {
+        Self::month_of((now - Duration::days(1)).date_naive())
+    }
+
+    fn month_of(date: NaiveDate) -> NaiveDate {
+        date.with_day(1).expect("every month has a first day")
+    }
+
+    fn dump_file_name(month: NaiveDate) -> String {
+        format!("games-{}.pgn.zst", month.format("%Y-%m"))
+    }
+
+    fn archive_row(
+        game: game::Model,
+        moves: Vec<game_move::Model>,
+        archived_at: DateTime<Utc>,
+    ) -> Result<game_archive::ActiveModel, DbErr> {
+        let row = game_archive::ActiveModel {
+            id: Set(game.id),
+            white_player: Set(game.white_player),
+            black_player: Set(game.black_player),
+            started_at: Set(game.started_at),
+            archive_month: Set(Self::month_of(archived_at.date_naive())),
+            archived_at: Set(archived_at.into()),
+            created_at: Set(game.created_at),
+            payload: Set(Vec::new()),
+        };
+        let archived = ArchivedGame {
+            format: ARCHIVE_FORMAT,
+            game: game.into(),
+            moves: moves.into_iter().map(ArchivedMove::from).collect(),
+        };
+        let json = serde_json::to_vec(&archived)
+            .map_err(|e| DbErr::Custom(format!("Failed to serialize archived game: {}", e)))?;
+        let payload = zstd::encode_all(json.as_slice(), ZSTD_LEVEL)
+            .map_err(|e| DbErr::Custom(format!("Failed to compress archived game: {}", e)))?;
+        Ok(game_archive::ActiveModel { payload: Set(payload), ..row })
+    }
+
+    fn unpack(row: &game_archive::Model) -> Result<ArchivedGame, DbErr> {
+        let json = zstd::decode_all(row.payload.as_slice())
+            .map_err(|e| DbErr::Custom(format!("Corrupt archive record {}: {}", row.id, e)))?;
+        let archived: ArchivedGame = serde_json::from_slice(&json)
+            .map_err(|e| DbErr::Custom(format!("Corrupt archive record {}: {}", row.id, e)))?;
+        if archived.format > ARCHIVE_FORMAT {
+            return Err(DbErr::Custom(format!(
+                "Archive record {} has format {}, newer than this server supports",
+                row.id, archived.format
+            )));
+        }
+        Ok(archived)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use chrono::{FixedOffset, TimeZone};
+    use sea_orm::{DbBackend, MockDatabase, MockExecResult};
+
+    pub(crate) fn finished_game() -> game::Model {
+        let started = FixedOffset::east_opt(0).unwrap().with_ymd_and_hms(2026, 3, 14, 12, 0, 0).unwrap();
+        game::Model {
+            id: Uuid::new_v4(),
+            white_player: None,
+            black_player: None,
+            fen: chess::STARTING_FEN.to_string(),
+            pgn: serde_json::json!({ "moves": ["e4", "e5"], "final_ply": 2, "clocks": [59000, 58000] }),
+            result: Some(game::ResultSide::Draw),
+            variant: game::GameVariant::Standard,
+            started_at: started,
+            duration_sec: 60,
+            created_at: started,
+            updated_at: started,
+            is_imported: false,
+            original_pgn: None,
+            import_hash: None,
+            position_keys: vec![],
+            status: GameStatus::Completed,
+            initial_time_ms: 60_000,
+            increment_ms: 0,
+            rated: true,
+            termination: None,
+            last_move_at: None,
+            moves_encoded: None,
+            initial_fen: chess::STARTING_FEN.to_string(),
+            version: 0,
+        }
+    }
+
+    pub(crate) fn archived(game: &game::Model) -> game_archive::Model {
+        let row = ArchiveService::archive_row(game.clone(), vec![], Utc::now()).unwrap();
+        game_archive::Model {
+            id: row.id.unwrap(),
+            white_player: row.white_player.unwrap(),
+            black_player: row.black_player.unwrap(),
+            started_at: row.started_at.unwrap(),
+            archive_month: row.archive_month.unwrap(),
+            archived_at: row.archived_at.unwrap(),
+            created_at: row.created_at.unwrap(),
+            payload: row.payload.unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_archive_record_round_trip() {
+        let game = finished_game();
+        let row = archived(&game);
+        // Filed by when it was archived, not when it was played
+        assert_eq!(row.archive_month, ArchiveService::month_of(Utc::now().date_naive()));
+        assert_eq!(game::Model::from(ArchiveService::unpack(&row).unwrap().game), game);
+    }
+
+    #[test]
+    fn test_records_without_later_columns_still_decode() {
+        let game = finished_game();
+        let mut record = serde_json::to_value(&game).unwrap();
+        // As written before the version field and the later game columns existed
+        for column in ["status", "rated", "initial_fen", "moves_encoded", "version", "position_keys"] {
+            record.as_object_mut().unwrap().remove(column);
+        }
+        let json = serde_json::to_vec(&serde_json::json!({ "game": record, "moves": [] })).unwrap();
+        let row = game_archive::Model { payload: zstd::encode_all(json.as_slice(), ZSTD_LEVEL).unwrap(), ..archived(&game) };
+
+        let unpacked = ArchiveService::unpack(&row).unwrap();
+        assert_eq!(unpacked.format, 0);
+        assert_eq!(game::Model::from(unpacked.game), game);
+    }
+
+    #[test]
+    fn test_month_is_dumped_only_once_closed() {
+        let now = Utc.with_ymd_and_hms(2026, 5, 1, 6, 0, 0).unwrap();
+        // A run that started on April 30th may still be committing
+        assert_eq!(ArchiveService::first_open_month(now), NaiveDate::from_ymd_opt(2026, 4, 1).unwrap());
+        let now = Utc.with_ymd_and_hms(2026, 5, 2, 6, 0, 0).unwrap();
+        assert_eq!(ArchiveService::first_open_month(now), NaiveDate::from_ymd_opt(2026, 5, 1).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_archive_finished_moves_games_out_of_hot_table() {
+        let game = finished_game();
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![game.clone()]])
+            .append_query_results(vec![Vec::<game_move::Model>::new()])
+            .append_exec_results(vec![
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+            ])
+            .into_connection();
+
+        let archived = ArchiveService::archive_finished(&db, Utc::now()).await.unwrap();
+        assert_eq!(archived, 1);
+
+        let log_str = format!("{:?}", db.into_transaction_log());
+        assert!(log_str.contains("FOR UPDATE SKIP LOCKED"));
+        assert!(log_str.contains(r#"INSERT INTO \"smdb\".\"game_archive\""#));
+        assert!(log_str.contains(r#"DELETE FROM \"smdb\".\"game\""#));
+    }
+
+    #[tokio::test]
+    async fn test_export_month_writes_compressed_pgn() {
+        let game = finished_game();
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![archived(&game)], vec![]])
+            .into_connection();
+
+        let dir = std::env::temp_dir().join(format!("archive-test-{}", Uuid::new_v4()));
+        let month = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        let path = ArchiveService::export_month(&db, month, &dir).await.unwrap();
+        assert!(path.ends_with("games-2026-03.pgn.zst"));
+
+        let pgn = String::from_utf8(zstd::decode_all(std::fs::read(&path).unwrap().as_slice()).unwrap()).unwrap();
+        assert!(pgn.contains(&format!("[GameId \"{}\"]", game.id)));
+        assert!(pgn.contains("1. e4 {[%clk 0:00:59]} 1... e5 {[%clk 0:00:58]} 1/2-1/2"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
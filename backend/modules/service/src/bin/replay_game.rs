@@ -0,0 +1,52 @@
+use sea_orm::Database;
+use service::game_events::GameEventLog;
+use std::env;
+use dotenv::dotenv;
+use uuid::Uuid;
+
+/// Rebuild a game from its event log as it stood after a given event, for
+/// reviewing disputes. Prints the events up to that point and the replayed
+/// state as JSON.
+///
+/// With `--check`, replays every game in progress instead and lists those
+/// whose log does not agree with their game row.
+///
+/// Usage: replay_game <game-id> [event-index] | replay_game --check
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv().ok();
+    let mut args = env::args().skip(1);
+    let usage = "Usage: replay_game <game-id> [event-index] | replay_game --check";
+    let first = args.next().ok_or(usage)?;
+
+    let db_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let db = Database::connect(&db_url).await?;
+
+    if first == "--check" {
+        let checked = GameEventLog::check_in_progress(&db).await.map_err(|e| e.to_string())?;
+        let mut broken = 0;
+        for (game_id, result) in &checked {
+            if let Err(e) = result {
+                eprintln!("Game {}: {}", game_id, e);
+                broken += 1;
+            }
+        }
+        println!("Checked {} in-progress games with an event log, {} broken.", checked.len(), broken);
+        return if broken == 0 { Ok(()) } else { Err(format!("{} broken event logs", broken).into()) };
+    }
+
+    let game_id: Uuid = first.parse()?;
+    let up_to: Option<i32> = args.next().map(|index| index.parse()).transpose()?;
+
+    let events = GameEventLog::events(&db, game_id).await.map_err(|e| e.to_string())?;
+    for event in events.iter().filter(|e| up_to.is_none_or(|index| e.event_index <= index)) {
+        let actor = event.actor.map(|id| id.to_string()).unwrap_or_else(|| "-".to_string());
+        println!("{:>5}  {}  {:<18} {:<36}  {}", event.event_index, event.created_at, event.kind, actor, event.payload);
+    }
+
+    match GameEventLog::load(&db, game_id, up_to).await.map_err(|e| e.to_string())? {
+        Some(replayed) => println!("{}", serde_json::to_string_pretty(&replayed)?),
+        None => eprintln!("Game {} has no event log", game_id),
+    }
+    Ok(())
+}
@@ -0,0 +1,112 @@
+//! SeaORM storage for `chess::Game` clocks.
+
+use std::sync::Arc;
+
+use chess::{ClockRepository, ClockSnapshot};
+use chrono::Utc;
+use db_entity::{prelude::TimeControl, time_control};
+use sea_orm::{sea_query::OnConflict, ActiveValue::Set, DatabaseConnection, DbErr, EntityTrait};
+use uuid::Uuid;
+
+/// Keeps clocks in the `time_controls` table, one row per game
+#[derive(Clone)]
+pub struct ClockStore {
+    db: Arc<DatabaseConnection>,
+}
+
+impl ClockStore {
+    pub fn new(db: Arc<DatabaseConnection>) -> Self {
+        Self { db }
+    }
+}
+
+impl ClockRepository for ClockStore {
+    type Error = DbErr;
+
+    async fn save_clocks(&self, game_id: Uuid, clocks: &ClockSnapshot) -> Result<(), DbErr> {
+        let row = time_control::ActiveModel {
+            game_id: Set(game_id),
+            white_remaining_time: Set(clocks.white_remaining_ms as i64),
+            black_remaining_time: Set(clocks.black_remaining_ms as i64),
+            initial_time: Set(clocks.initial_time_ms as i64),
+            increment: Set(clocks.increment_ms as i64),
+            delay: Set(clocks.delay_ms as i64),
+            updated_at: Set(Utc::now().into()),
+        };
+        // The time control is fixed when the game starts; only the clocks move
+        TimeControl::insert(row)
+            .on_conflict(
+                OnConflict::column(time_control::Column::GameId)
+                    .update_columns([
+                        time_control::Column::WhiteRemainingTime,
+                        time_control::Column::BlackRemainingTime,
+                        time_control::Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec_without_returning(self.db.as_ref())
+            .await?;
+        Ok(())
+    }
+
+    async fn load_clocks(&self, game_id: Uuid) -> Result<Option<ClockSnapshot>, DbErr> {
+        Ok(TimeControl::find_by_id(game_id)
+            .one(self.db.as_ref())
+            .await?
+            .map(|row| ClockSnapshot {
+                white_remaining_ms: row.white_remaining_time.max(0) as u64,
+                black_remaining_ms: row.black_remaining_time.max(0) as u64,
+                initial_time_ms: row.initial_time.max(0) as u64,
+                increment_ms: row.increment.max(0) as u64,
+                delay_ms: row.delay.max(0) as u64,
+            }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chess::{Game, TimeControl as GameTimeControl};
+    use sea_orm::{DbBackend, MockDatabase, MockExecResult};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_game_clocks_save_and_load() {
+        let game_id = Uuid::new_v4();
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_exec_results(vec![MockExecResult { last_insert_id: 0, rows_affected: 1 }])
+            .append_query_results(vec![
+                vec![time_control::Model {
+                    game_id,
+                    white_remaining_time: 42_000,
+                    black_remaining_time: 57_500,
+                    initial_time: 60_000,
+                    increment: 1_000,
+                    delay: 0,
+                    updated_at: Utc::now().into(),
+                }],
+                vec![],
+            ])
+            .into_connection();
+        let store = ClockStore::new(Arc::new(db));
+
+        let time_control = GameTimeControl {
+            initial_time: Duration::from_secs(60),
+            increment: Duration::from_secs(1),
+            delay: Duration::ZERO,
+        };
+        let mut game = Game::new(time_control);
+        game.save_clocks(&store, game_id).await.unwrap();
+
+        assert!(game.load_clocks(&store, game_id).await.unwrap());
+        assert_eq!(game.white_clock.get_real_time_remaining(), Duration::from_millis(42_000));
+        assert_eq!(game.black_clock.get_real_time_remaining(), Duration::from_millis(57_500));
+        assert!(!game.load_clocks(&store, Uuid::new_v4()).await.unwrap());
+
+        let db = Arc::try_unwrap(store.db).ok().unwrap();
+        let log_str = format!("{:?}", db.into_transaction_log());
+        assert!(log_str.contains(r#"INSERT INTO \"smdb\".\"time_controls\""#));
+        assert!(log_str.contains(r#"ON CONFLICT (\"game_id\") DO UPDATE"#));
+        assert!(log_str.contains("BigInt(Some(60000))"));
+    }
+}
@@ -0,0 +1,687 @@
+//! Event-sourced game history.
+//!
+//! Everything that happens in a game (moves, draw and takeback offers, clock
+//! adjustments, lifecycle changes) is appended to `game_event` in the
+//! transaction that updates the game row, or by the socket server's room
+//! store for games played there. A game's state can be rebuilt at
+//! any point by replaying its log; every `SNAPSHOT_INTERVAL` events, and when
+//! the game finishes, the replayed state is stored in `game_player_states`
+//! so a rebuild only has to apply the events after the latest snapshot.
+
+use chrono::Utc;
+use db_entity::game::{self, GameStatus};
+use db_entity::prelude::{Game, GameEvent as GameEventEntity, GamePlayerState};
+use db_entity::{game_event, game_player_state};
+use error::error::ApiError;
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QueryOrder,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::lifecycle::{LifecycleEvent, LifecycleState};
+
+/// Events between stored snapshots of a game
+pub const SNAPSHOT_INTERVAL: i32 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    White,
+    Black,
+}
+
+impl Side {
+    pub fn of(white: bool) -> Self {
+        if white {
+            Side::White
+        } else {
+            Side::Black
+        }
+    }
+}
+
+/// One entry in a game's event log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GameEvent {
+    /// Always the first event; fixes the starting position and time control
+    Created { initial_fen: String, initial_time_ms: i64, increment_ms: i64 },
+    /// Lifecycle transition, including the result once the game is decided
+    StateChanged { to: LifecycleState },
+    /// `clock_ms` is the mover's clock after the move, increment included
+    MovePlayed { uci: String, san: String, clock_ms: Option<i64> },
+    DrawOffered { by: Side },
+    DrawDeclined { by: Side },
+    TakebackOffered { by: Side },
+    /// Takes back the last `plies` moves
+    TakebackAccepted { by: Side, plies: u32 },
+    TakebackDeclined { by: Side },
+    /// Time added to (or removed from) a clock, e.g. by an arbiter
+    ClockAdjusted { side: Side, delta_ms: i64 },
+}
+
+impl GameEvent {
+    /// Value stored in the `kind` column
+    pub fn kind(&self) -> &'static str {
+        match self {
+            GameEvent::Created { .. } => "created",
+            GameEvent::StateChanged { .. } => "state_changed",
+            GameEvent::MovePlayed { .. } => "move_played",
+            GameEvent::DrawOffered { .. } => "draw_offered",
+            GameEvent::DrawDeclined { .. } => "draw_declined",
+            GameEvent::TakebackOffered { .. } => "takeback_offered",
+            GameEvent::TakebackAccepted { .. } => "takeback_accepted",
+            GameEvent::TakebackDeclined { .. } => "takeback_declined",
+            GameEvent::ClockAdjusted { .. } => "clock_adjusted",
+        }
+    }
+}
+
+impl From<&LifecycleEvent> for GameEvent {
+    fn from(event: &LifecycleEvent) -> Self {
+        GameEvent::StateChanged { to: event.to.clone() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayedMove {
+    pub uci: String,
+    pub san: String,
+    /// Position after the move
+    pub fen: String,
+    pub clock_ms: Option<i64>,
+}
+
+/// State of a game rebuilt from its event log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayedGame {
+    /// Last event applied; 0 before the game is created
+    pub event_index: i32,
+    pub state: LifecycleState,
+    pub initial_fen: String,
+    pub fen: String,
+    pub moves: Vec<ReplayedMove>,
+    pub initial_time_ms: i64,
+    pub increment_ms: i64,
+    pub white_clock_ms: i64,
+    pub black_clock_ms: i64,
+    pub draw_offer: Option<Side>,
+    pub takeback_offer: Option<Side>,
+}
+
+impl Default for ReplayedGame {
+    fn default() -> Self {
+        Self {
+            event_index: 0,
+            state: LifecycleState::Created,
+            initial_fen: String::new(),
+            fen: String::new(),
+            moves: Vec::new(),
+            initial_time_ms: 0,
+            increment_ms: 0,
+            white_clock_ms: 0,
+            black_clock_ms: 0,
+            draw_offer: None,
+            takeback_offer: None,
+        }
+    }
+}
+
+impl ReplayedGame {
+    /// Apply the next event of the log, rejecting events the game's rules
+    /// would not have allowed at that point
+    pub fn apply(&mut self, event: &GameEvent) -> Result<(), String> {
+        match event {
+            GameEvent::Created { initial_fen, initial_time_ms, increment_ms } => {
+                if self.event_index != 0 {
+                    return Err("game was already created".to_string());
+                }
+                chess::is_white_to_move(initial_fen).map_err(|e| e.to_string())?;
+                self.initial_fen = initial_fen.clone();
+                self.fen = initial_fen.clone();
+                self.initial_time_ms = *initial_time_ms;
+                self.increment_ms = *increment_ms;
+                self.white_clock_ms = *initial_time_ms;
+                self.black_clock_ms = *initial_time_ms;
+            }
+            _ if self.event_index == 0 => return Err("log does not start with the game's creation".to_string()),
+            GameEvent::StateChanged { to } => {
+                self.state = self.state.transition(to.clone()).map_err(|e| e.to_string())?;
+                if self.state.is_terminal() {
+                    self.draw_offer = None;
+                    self.takeback_offer = None;
+                }
+            }
+            GameEvent::MovePlayed { uci, san, clock_ms } => {
+                self.require_started()?;
+                let mover = self.side_to_move()?;
+                let played = chess::play_move(&self.fen, uci).map_err(|e| e.to_string())?;
+                if played.san != *san {
+                    return Err(format!("{} is {} in this position, not {}", uci, played.san, san));
+                }
+                if let Some(clock_ms) = clock_ms {
+                    *self.clock_mut(mover) = *clock_ms;
+                }
+                self.fen = played.fen.clone();
+                self.moves.push(ReplayedMove { uci: played.uci, san: played.san, fen: played.fen, clock_ms: *clock_ms });
+                // Moving on answers any pending offer
+                self.draw_offer = None;
+                self.takeback_offer = None;
+            }
+            GameEvent::DrawOffered { by } => {
+                self.require_started()?;
+                if self.draw_offer.is_some() {
+                    return Err("a draw offer is already pending".to_string());
+                }
+                self.draw_offer = Some(*by);
+            }
+            GameEvent::DrawDeclined { by } => {
+                Self::answer(&mut self.draw_offer, *by).ok_or("no draw offer to decline")?;
+            }
+            GameEvent::TakebackOffered { by } => {
+                self.require_started()?;
+                if self.moves.is_empty() {
+                    return Err("there is no move to take back".to_string());
+                }
+                if self.takeback_offer.is_some() {
+                    return Err("a takeback offer is already pending".to_string());
+                }
+                self.takeback_offer = Some(*by);
+            }
+            GameEvent::TakebackAccepted { by, plies } => {
+                let plies = *plies as usize;
+                if plies == 0 || plies > self.moves.len() {
+                    return Err(format!("cannot take back {} of {} plies", plies, self.moves.len()));
+                }
+                Self::answer(&mut self.takeback_offer, *by).ok_or("no takeback offer to accept")?;
+                self.moves.truncate(self.moves.len() - plies);
+                self.fen = self.moves.last().map_or_else(|| self.initial_fen.clone(), |m| m.fen.clone());
+                self.reset_clocks();
+            }
+            GameEvent::TakebackDeclined { by } => {
+                Self::answer(&mut self.takeback_offer, *by).ok_or("no takeback offer to decline")?;
+            }
+            GameEvent::ClockAdjusted { side, delta_ms } => {
+                self.require_started()?;
+                let clock = self.clock_mut(*side);
+                *clock = (*clock + delta_ms).max(0);
+            }
+        }
+        self.event_index += 1;
+        Ok(())
+    }
+
+    pub fn side_to_move(&self) -> Result<Side, String> {
+        chess::is_white_to_move(&self.fen).map(Side::of).map_err(|e| e.to_string())
+    }
+
+    fn require_started(&self) -> Result<(), String> {
+        match self.state {
+            LifecycleState::Started => Ok(()),
+            ref other => Err(format!("game is {}, not started", other)),
+        }
+    }
+
+    /// Clear an offer answered by the other side
+    fn answer(offer: &mut Option<Side>, by: Side) -> Option<()> {
+        match offer {
+            Some(offered_by) if *offered_by != by => offer.take().map(|_| ()),
+            _ => None,
+        }
+    }
+
+    fn clock_mut(&mut self, side: Side) -> &mut i64 {
+        match side {
+            Side::White => &mut self.white_clock_ms,
+            Side::Black => &mut self.black_clock_ms,
+        }
+    }
+
+    /// Clocks as recorded with each side's last remaining move
+    fn reset_clocks(&mut self) {
+        let white_first = chess::is_white_to_move(&self.initial_fen).unwrap_or(true);
+        let last_clock = |side: Side| {
+            self.moves
+                .iter()
+                .enumerate()
+                .filter(|(ply, _)| Side::of((ply % 2 == 0) == white_first) == side)
+                .filter_map(|(_, m)| m.clock_ms)
+                .next_back()
+                .unwrap_or(self.initial_time_ms)
+        };
+        self.white_clock_ms = last_clock(Side::White);
+        self.black_clock_ms = last_clock(Side::Black);
+    }
+}
+
+pub struct GameEventLog;
+
+impl GameEventLog {
+    /// Append events to a game's log, normally inside the transaction that
+    /// updates the game.
+    ///
+    /// `game` is the row as it was before the change. Games stored before
+    /// the log existed get their history written from it first, so their
+    /// log replays like any other.
+    pub async fn append<C: ConnectionTrait>(
+        conn: &C,
+        game: &game::Model,
+        actor: Option<Uuid>,
+        events: Vec<GameEvent>,
+    ) -> Result<(), ApiError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let last_index = Self::last_index(conn, game.id).await?;
+        let seeded = if last_index == 0 && !matches!(events.first(), Some(GameEvent::Created { .. })) {
+            Self::history_of(game)?
+        } else {
+            Vec::new()
+        };
+        Self::insert(conn, game.id, last_index, seeded, actor, events).await
+    }
+
+    /// Append events to the log of a game that has no `game` row, such as
+    /// one played over the socket server. Its log has to start with
+    /// `GameEvent::Created`, as there is no row to seed it from.
+    pub async fn append_to<C: ConnectionTrait>(
+        conn: &C,
+        game_id: Uuid,
+        actor: Option<Uuid>,
+        events: Vec<GameEvent>,
+    ) -> Result<(), ApiError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        let last_index = Self::last_index(conn, game_id).await?;
+        Self::insert(conn, game_id, last_index, Vec::new(), actor, events).await
+    }
+
+    async fn last_index<C: ConnectionTrait>(conn: &C, game_id: Uuid) -> Result<i32, ApiError> {
+        Ok(GameEventEntity::find()
+            .filter(game_event::Column::GameId.eq(game_id))
+            .order_by_desc(game_event::Column::EventIndex)
+            .one(conn)
+            .await?
+            .map_or(0, |event| event.event_index))
+    }
+
+    /// Write `seeded` (without an actor) and then `events` after event
+    /// `last_index`, snapshotting when an interval is crossed or the game ends
+    async fn insert<C: ConnectionTrait>(
+        conn: &C,
+        game_id: Uuid,
+        last_index: i32,
+        seeded: Vec<GameEvent>,
+        actor: Option<Uuid>,
+        events: Vec<GameEvent>,
+    ) -> Result<(), ApiError> {
+        let finishes = events
+            .iter()
+            .any(|event| matches!(event, GameEvent::StateChanged { to } if to.is_terminal()));
+        let now = Utc::now();
+        let rows: Vec<game_event::ActiveModel> = seeded
+            .iter()
+            .map(|event| (event, None))
+            .chain(events.iter().map(|event| (event, actor)))
+            .zip(last_index + 1..)
+            .map(|((event, actor), event_index)| game_event::ActiveModel {
+                game_id: Set(game_id),
+                event_index: Set(event_index),
+                kind: Set(event.kind().to_string()),
+                payload: Set(serde_json::to_value(event).unwrap_or_default()),
+                actor: Set(actor),
+                created_at: Set(now.into()),
+                ..Default::default()
+            })
+            .collect();
+        let new_last_index = last_index + rows.len() as i32;
+        GameEventEntity::insert_many(rows).exec_without_returning(conn).await?;
+
+        let crossed_interval = new_last_index / SNAPSHOT_INTERVAL > last_index / SNAPSHOT_INTERVAL;
+        if crossed_interval || finishes {
+            // A log that no longer replays (`corrupt`) only misses its
+            // snapshot; the events are still written
+            match Self::load(conn, game_id, None).await {
+                Ok(Some(replayed)) => Self::snapshot(conn, game_id, &replayed, actor).await?,
+                Ok(None) => {}
+                Err(ApiError::DatabaseError(DbErr::Custom(reason))) => {
+                    eprintln!("Not snapshotting game {}: {}", game_id, reason);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Rebuild a game from its latest snapshot and the events after it,
+    /// stopping after event `up_to` if given. `None` if the game has no log.
+    pub async fn load<C: ConnectionTrait>(
+        conn: &C,
+        game_id: Uuid,
+        up_to: Option<i32>,
+    ) -> Result<Option<ReplayedGame>, ApiError> {
+        let mut snapshots = GamePlayerState::find()
+            .filter(game_player_state::Column::GameId.eq(game_id))
+            .order_by_desc(game_player_state::Column::EventIndex);
+        if let Some(up_to) = up_to {
+            snapshots = snapshots.filter(game_player_state::Column::EventIndex.lte(up_to));
+        }
+        let mut replayed = match snapshots.one(conn).await? {
+            Some(snapshot) => serde_json::from_value(snapshot.state_json)
+                .map_err(|e| Self::corrupt(game_id, snapshot.event_index, e.to_string()))?,
+            None => ReplayedGame::default(),
+        };
+
+        let mut query = GameEventEntity::find()
+            .filter(game_event::Column::GameId.eq(game_id))
+            .filter(game_event::Column::EventIndex.gt(replayed.event_index))
+            .order_by_asc(game_event::Column::EventIndex);
+        if let Some(up_to) = up_to {
+            query = query.filter(game_event::Column::EventIndex.lte(up_to));
+        }
+        let events = query.all(conn).await?;
+        if replayed.event_index == 0 && events.is_empty() {
+            return Ok(None);
+        }
+
+        for row in events {
+            let event: GameEvent = serde_json::from_value(row.payload)
+                .map_err(|e| Self::corrupt(game_id, row.event_index, e.to_string()))?;
+            replayed
+                .apply(&event)
+                .map_err(|reason| Self::corrupt(game_id, row.event_index, reason))?;
+        }
+        Ok(Some(replayed))
+    }
+
+    /// A game's full log, oldest first
+    pub async fn events<C: ConnectionTrait>(conn: &C, game_id: Uuid) -> Result<Vec<game_event::Model>, ApiError> {
+        Ok(GameEventEntity::find()
+            .filter(game_event::Column::GameId.eq(game_id))
+            .order_by_asc(game_event::Column::EventIndex)
+            .all(conn)
+            .await?)
+    }
+
+    /// Check that the log of every game in progress replays to its game
+    /// row, e.g. after a crash or before a deploy. The row is what play
+    /// continues from; this only confirms the log agrees with it.
+    ///
+    /// Games without a log are skipped; their row is all there is. Each game
+    /// gets its own result, so one broken log does not hide the others.
+    pub async fn check_in_progress<C: ConnectionTrait>(
+        conn: &C,
+    ) -> Result<Vec<(Uuid, Result<ReplayedGame, ApiError>)>, ApiError> {
+        let games = Game::find()
+            .filter(game::Column::Status.eq(GameStatus::InProgress))
+            .all(conn)
+            .await?;
+
+        let mut checked = Vec::with_capacity(games.len());
+        for game in games {
+            let replayed = match Self::load(conn, game.id, None).await {
+                Ok(Some(replayed)) => replayed,
+                Ok(None) => continue,
+                Err(e) => {
+                    checked.push((game.id, Err(e)));
+                    continue;
+                }
+            };
+            if replayed.fen != game.fen || replayed.state != LifecycleState::of(&game) {
+                let reason = format!("log ends at {} but the game row is at {}", replayed.fen, game.fen);
+                checked.push((game.id, Err(Self::corrupt(game.id, replayed.event_index, reason))));
+            } else {
+                checked.push((game.id, Ok(replayed)));
+            }
+        }
+        Ok(checked)
+    }
+
+    async fn snapshot<C: ConnectionTrait>(
+        conn: &C,
+        game_id: Uuid,
+        replayed: &ReplayedGame,
+        actor: Option<Uuid>,
+    ) -> Result<(), ApiError> {
+        let row = game_player_state::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            game_id: Set(game_id),
+            event_index: Set(replayed.event_index),
+            move_number: Set(replayed.moves.len() as i32),
+            player_id: Set(actor),
+            state_json: Set(serde_json::to_value(replayed).unwrap_or_default()),
+            timestamp: Set(Utc::now().into()),
+        };
+        GamePlayerState::insert(row).exec_without_returning(conn).await?;
+        Ok(())
+    }
+
+    /// Events reproducing a game stored before it had a log
+    fn history_of(game: &game::Model) -> Result<Vec<GameEvent>, ApiError> {
+        let mut events = vec![GameEvent::Created {
+            initial_fen: game.initial_fen.clone(),
+            initial_time_ms: game.initial_time_ms,
+            increment_ms: game.increment_ms,
+        }];
+        if game.status == GameStatus::Waiting {
+            events.push(GameEvent::StateChanged { to: LifecycleState::Waiting });
+            return Ok(events);
+        }
+        events.push(GameEvent::StateChanged { to: LifecycleState::Started });
+
+        let mut fen = game.initial_fen.clone();
+        let moves = game.pgn["moves"].as_array().cloned().unwrap_or_default();
+        for (idx, san) in moves.iter().filter_map(|m| m.as_str()).enumerate() {
+            let played = chess::play_move(&fen, san).map_err(|e| Self::corrupt(game.id, 0, e.to_string()))?;
+            fen = played.fen;
+            events.push(GameEvent::MovePlayed {
+                uci: played.uci,
+                san: played.san,
+                clock_ms: game.pgn["clocks"][idx].as_i64(),
+            });
+        }
+        Ok(events)
+    }
+
+    fn corrupt(game_id: Uuid, event_index: i32, reason: String) -> ApiError {
+        ApiError::DatabaseError(DbErr::Custom(format!(
+            "Event log of game {} is invalid at event {}: {}",
+            game_id, event_index, reason
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lifecycle::{EndReason, Outcome};
+    use sea_orm::{DbBackend, MockDatabase};
+
+    fn created() -> GameEvent {
+        GameEvent::Created {
+            initial_fen: chess::STARTING_FEN.to_string(),
+            initial_time_ms: 60_000,
+            increment_ms: 0,
+        }
+    }
+
+    fn played(uci: &str, san: &str, clock_ms: i64) -> GameEvent {
+        GameEvent::MovePlayed { uci: uci.to_string(), san: san.to_string(), clock_ms: Some(clock_ms) }
+    }
+
+    fn replay(events: &[GameEvent]) -> Result<ReplayedGame, String> {
+        let mut replayed = ReplayedGame::default();
+        events.iter().try_for_each(|event| replayed.apply(event))?;
+        Ok(replayed)
+    }
+
+    #[test]
+    fn test_replay_moves_offers_and_takebacks() {
+        let replayed = replay(&[
+            created(),
+            GameEvent::StateChanged { to: LifecycleState::Started },
+            played("e2e4", "e4", 59_000),
+            played("e7e5", "e5", 58_000),
+            GameEvent::DrawOffered { by: Side::White },
+            GameEvent::DrawDeclined { by: Side::Black },
+            played("g1f3", "Nf3", 57_000),
+            GameEvent::TakebackOffered { by: Side::White },
+            GameEvent::TakebackAccepted { by: Side::Black, plies: 1 },
+            GameEvent::ClockAdjusted { side: Side::Black, delta_ms: 15_000 },
+        ])
+        .unwrap();
+
+        assert_eq!(replayed.event_index, 10);
+        assert_eq!(replayed.moves.iter().map(|m| m.san.as_str()).collect::<Vec<_>>(), ["e4", "e5"]);
+        assert_eq!(replayed.fen, "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2");
+        assert_eq!((replayed.white_clock_ms, replayed.black_clock_ms), (59_000, 73_000));
+        assert_eq!((replayed.draw_offer, replayed.takeback_offer), (None, None));
+    }
+
+    #[test]
+    fn test_replay_rejects_events_out_of_turn() {
+        let started = [created(), GameEvent::StateChanged { to: LifecycleState::Started }];
+
+        assert!(replay(&[GameEvent::StateChanged { to: LifecycleState::Started }]).is_err());
+        assert!(replay(&[created(), played("e2e4", "e4", 0)]).is_err());
+        assert!(replay(&[started.as_slice(), &[played("e2e4", "d4", 0)]].concat()).is_err());
+        // Offers are answered by the other side
+        assert!(replay(&[started.as_slice(), &[
+            GameEvent::DrawOffered { by: Side::White },
+            GameEvent::DrawDeclined { by: Side::White },
+        ]].concat())
+        .is_err());
+
+        let finished = replay(&[started.as_slice(), &[
+            played("f2f3", "f3", 0),
+            played("e7e5", "e5", 0),
+            played("g2g4", "g4", 0),
+            played("d8h4", "Qh4#", 0),
+            GameEvent::StateChanged {
+                to: LifecycleState::Ended { result: Outcome::BlackWins, reason: EndReason::Checkmate },
+            },
+        ]].concat())
+        .unwrap();
+        assert!(finished.clone().apply(&GameEvent::DrawOffered { by: Side::White }).is_err());
+    }
+
+    #[test]
+    fn test_event_payloads_round_trip() {
+        let event = GameEvent::StateChanged {
+            to: LifecycleState::Ended { result: Outcome::Draw, reason: EndReason::DrawAgreement },
+        };
+        let payload = serde_json::to_value(&event).unwrap();
+        assert_eq!(payload["kind"], event.kind());
+        assert_eq!(payload["to"]["state"], "ended");
+        assert_eq!(serde_json::from_value::<GameEvent>(payload).unwrap(), event);
+    }
+
+    #[tokio::test]
+    async fn test_load_applies_events_after_snapshot() {
+        let game_id = Uuid::new_v4();
+        let snapshot = replay(&[
+            created(),
+            GameEvent::StateChanged { to: LifecycleState::Started },
+            played("e2e4", "e4", 59_000),
+        ])
+        .unwrap();
+        let now = Utc::now().into();
+
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![game_player_state::Model {
+                id: Uuid::new_v4(),
+                game_id,
+                event_index: 3,
+                move_number: 1,
+                player_id: None,
+                state_json: serde_json::to_value(&snapshot).unwrap(),
+                timestamp: now,
+            }]])
+            .append_query_results(vec![vec![game_event::Model {
+                id: 4,
+                game_id,
+                event_index: 4,
+                kind: "move_played".to_string(),
+                payload: serde_json::to_value(played("c7c5", "c5", 58_000)).unwrap(),
+                actor: None,
+                created_at: now,
+            }]])
+            .into_connection();
+
+        let replayed = GameEventLog::load(&db, game_id, Some(4)).await.unwrap().unwrap();
+        assert_eq!(replayed.event_index, 4);
+        assert_eq!(replayed.moves.len(), 2);
+        assert_eq!(replayed.black_clock_ms, 58_000);
+
+        let log_str = format!("{:?}", db.into_transaction_log());
+        assert!(log_str.contains(r#"\"game_player_states\".\"event_index\" <= $2"#));
+        assert!(log_str.contains(r#"\"game_event\".\"event_index\" > $2"#));
+    }
+
+    #[tokio::test]
+    async fn test_append_skips_snapshot_of_a_log_that_does_not_replay() {
+        let game_id = Uuid::new_v4();
+        let now = Utc::now().into();
+        let event = |event_index, payload| game_event::Model {
+            id: event_index as i64,
+            game_id,
+            event_index,
+            kind: "move_played".to_string(),
+            payload,
+            actor: None,
+            created_at: now,
+        };
+
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![event(SNAPSHOT_INTERVAL - 1, serde_json::json!({}))]])
+            .append_exec_results(vec![sea_orm::MockExecResult { last_insert_id: 0, rows_affected: 1 }])
+            .append_query_results(vec![Vec::<game_player_state::Model>::new()])
+            .append_query_results(vec![vec![event(1, serde_json::to_value(played("e2e4", "e4", 0)).unwrap())]])
+            .into_connection();
+
+        GameEventLog::append_to(&db, game_id, None, vec![GameEvent::DrawOffered { by: Side::White }])
+            .await
+            .unwrap();
+
+        let log_str = format!("{:?}", db.into_transaction_log());
+        assert!(log_str.contains(r#"INSERT INTO \"smdb\".\"game_event\""#));
+        assert!(!log_str.contains(r#"INSERT INTO \"smdb\".\"game_player_states\""#));
+    }
+
+    #[tokio::test]
+    async fn test_check_in_progress_reports_each_game() {
+        let broken = crate::games::tests::seated_game(Some(Uuid::new_v4()), Some(Uuid::new_v4()));
+        let healthy = crate::games::tests::seated_game(Some(Uuid::new_v4()), Some(Uuid::new_v4()));
+        let now = Utc::now().into();
+        let event = |game_id, event_index, payload| game_event::Model {
+            id: event_index as i64,
+            game_id,
+            event_index,
+            kind: "created".to_string(),
+            payload,
+            actor: None,
+            created_at: now,
+        };
+
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![broken.clone(), healthy.clone()]])
+            .append_query_results(vec![Vec::<game_player_state::Model>::new()])
+            .append_query_results(vec![vec![event(broken.id, 1, serde_json::json!({ "kind": "unknown" }))]])
+            .append_query_results(vec![Vec::<game_player_state::Model>::new()])
+            .append_query_results(vec![vec![
+                event(healthy.id, 1, serde_json::to_value(created()).unwrap()),
+                event(healthy.id, 2, serde_json::to_value(GameEvent::StateChanged { to: LifecycleState::Started }).unwrap()),
+            ]])
+            .into_connection();
+
+        let checked = GameEventLog::check_in_progress(&db).await.unwrap();
+        assert_eq!(checked.len(), 2);
+        assert_eq!(checked[0].0, broken.id);
+        assert!(checked[0].1.as_ref().unwrap_err().to_string().contains("invalid at event 1"));
+        assert_eq!(checked[1].0, healthy.id);
+        assert_eq!(checked[1].1.as_ref().unwrap().event_index, 2);
+    }
+}
@@ -1,17 +1,1097 @@
-use db_entity::{game, prelude::Game};
+use db_entity::{game, game_move, prelude::{Game, GameMove, Player}};
+use db_entity::game::{GameStatus as GameRowStatus, GameVariant, ResultSide};
+use db_entity::player_rating::RatingCategory;
 use sea_orm::{
-    ColumnTrait, DbErr, EntityTrait, Order, QueryFilter,
-    QueryOrder, QuerySelect,
+    ActiveModelTrait, ColumnTrait, DbErr, EntityTrait, Order, QueryFilter,
+    QueryOrder, QuerySelect, Select, Set, SqlErr, TransactionTrait,
 };
-use sea_orm::{Condition, DatabaseConnection};
+use sea_orm::{sea_query::Expr, Condition, ConnectionTrait, DatabaseConnection};
 use uuid::Uuid;
-use chrono::{DateTime, Utc, TimeZone};
+use chrono::{DateTime, NaiveDate, Utc, TimeZone};
 use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
-use dto::games::GameStatus;
+use dto::games::{
+    variant_name, CreateGameRequest, ExportGameQuery, GameDisplayDTO, GameSearchQuery, GameStatus, PlayerColor,
+    PlayerGamesExportQuery, StartPosition,
+};
+use std::collections::HashMap;
+use error::error::ApiError;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use rand::Rng;
+
+use crate::archive::{ArchiveScope, ArchiveService};
+use crate::game_events::{GameEvent, GameEventLog};
+use crate::lifecycle::{
+    EndReason, GameLifecycle, LifecycleEvent, LifecycleState, Outcome, TransitionSource,
+};
+use crate::move_log::MoveLog;
+use crate::ratings::{category_of, game_category, GameRatingChanges, RatingService, MIN_RATED_PLIES};
+
+/// Largest client-reported lag credited back to the mover's clock
+pub const MAX_LAG_COMPENSATION_MS: i64 = 1000;
+
+/// Clock accounting for a single move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MoveTiming {
+    Played {
+        /// Mover's clock after the move, increment included
+        clock_ms: i64,
+        time_spent_ms: i64,
+        lag_compensation_ms: i64,
+    },
+    /// The mover's clock ran out before the move arrived
+    Flagged,
+}
+
+/// What a move request did to its game
+#[derive(Debug)]
+pub enum MoveOutcome {
+    /// The move was played, or repeated one already played. `ended` is set
+    /// only when this request finished the game, and rating changes when
+    /// that game was rated.
+    Played {
+        game: game::Model,
+        played: chess::PlayedMove,
+        ended: bool,
+        rating_changes: Option<GameRatingChanges>,
+    },
+    /// The mover's clock had run out, so the game was lost on time instead
+    Flagged {
+        game: game::Model,
+        rating_changes: Option<GameRatingChanges>,
+    },
+}
+
+/// Outcome of storing an imported PGN game
+#[derive(Debug)]
+pub enum ImportStatus {
+    /// The game was stored
+    Imported(Box<game::Model>),
+    /// The same game was imported before; holds the existing game's ID
+    Duplicate(Uuid),
+}
+
+/// Parsed filters for a player's game archive export
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveFilter {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub variant: Option<GameVariant>,
+    pub rated: Option<bool>,
+    /// `Some(None)` selects games without a result
+    pub result: Option<Option<ResultSide>>,
+}
+
+impl TryFrom<&PlayerGamesExportQuery> for ArchiveFilter {
+    type Error = ApiError;
+
+    fn try_from(query: &PlayerGamesExportQuery) -> Result<Self, Self::Error> {
+        check_date_window(query.since, query.until)?;
+        Ok(Self {
+            since: query.since,
+            until: query.until,
+            variant: query.variant.as_deref().map(parse_variant).transpose()?,
+            rated: query.rated,
+            result: query.result.as_deref().map(parse_result).transpose()?,
+        })
+    }
+}
+
+impl ArchiveFilter {
+    /// Whether a decoded game passes the filters that are not archive columns
+    fn matches(&self, game: &game::Model) -> bool {
+        self.variant.as_ref().is_none_or(|variant| &game.variant == variant)
+            && self.rated.is_none_or(|rated| game.rated == rated)
+            && self.result.as_ref().is_none_or(|result| result_matches(result, &game.result))
+    }
+}
+
+/// Parsed filters for game search
+#[derive(Debug, Clone, Default)]
+pub struct GameSearchFilter {
+    pub player_id: Option<Uuid>,
+    /// Upper-cased ECO prefix
+    pub eco: Option<String>,
+    /// Lower-cased opening name prefix
+    pub opening: Option<String>,
+    pub min_rating: Option<i32>,
+    pub max_rating: Option<i32>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// `Some(None)` selects games without a result
+    pub result: Option<Option<ResultSide>>,
+    pub termination: Option<String>,
+    pub variant: Option<GameVariant>,
+    pub speed: Option<chess::TimeControlCategory>,
+    pub min_moves: Option<i32>,
+    pub max_moves: Option<i32>,
+    pub position_key: Option<i64>,
+}
+
+impl TryFrom<&GameSearchQuery> for GameSearchFilter {
+    type Error = ApiError;
+
+    fn try_from(query: &GameSearchQuery) -> Result<Self, Self::Error> {
+        check_date_window(query.since, query.until)?;
+        if let (Some(min), Some(max)) = (query.min_rating, query.max_rating) {
+            if min > max {
+                return Err(ApiError::BadRequest("'min_rating' must not exceed 'max_rating'".to_string()));
+            }
+        }
+        if let (Some(min), Some(max)) = (query.min_moves, query.max_moves) {
+            if min > max {
+                return Err(ApiError::BadRequest("'min_moves' must not exceed 'max_moves'".to_string()));
+            }
+        }
+
+        let position_key = match (&query.fen, query.zobrist) {
+            (Some(_), Some(_)) => {
+                return Err(ApiError::BadRequest("Use either 'fen' or 'zobrist', not both".to_string()))
+            }
+            (Some(fen), None) => Some(GameService::position_key(fen)?),
+            (None, zobrist) => zobrist,
+        };
+
+        Ok(Self {
+            player_id: query.player_id,
+            eco: query.eco.as_deref().map(|eco| eco.trim().to_uppercase()),
+            opening: query.opening.as_deref().map(|name| name.trim().to_lowercase()),
+            min_rating: query.min_rating,
+            max_rating: query.max_rating,
+            since: query.since,
+            until: query.until,
+            result: query.result.as_deref().map(parse_result).transpose()?,
+            termination: query.termination.as_deref().map(|t| t.trim().to_lowercase()),
+            variant: query.variant.as_deref().map(parse_variant).transpose()?,
+            speed: query
+                .speed
+                .as_deref()
+                .map(|speed| speed.parse().map_err(ApiError::BadRequest))
+                .transpose()?,
+            min_moves: query.min_moves,
+            max_moves: query.max_moves,
+            position_key,
+        })
+    }
+}
+
+impl GameSearchFilter {
+    /// Whether a decoded game passes the filters that are not archive
+    /// columns, mirroring the SQL of `GameService::search_games`
+    fn matches(&self, game: &game::Model) -> bool {
+        let pgn = &game.pgn;
+        let rating = |key: &str| pgn[key].as_i64();
+        let average_rating = rating("white_rating").zip(rating("black_rating")).map(|(w, b)| (w + b) / 2);
+        let plies = pgn["final_ply"].as_i64();
+        let estimate = game.initial_time_ms + chess::TimeControlCategory::ESTIMATED_MOVES as i64 * game.increment_ms;
+
+        self.eco.as_ref().is_none_or(|eco| pgn["eco"].as_str().is_some_and(|e| e.starts_with(eco.as_str())))
+            && self.opening.as_ref().is_none_or(|opening| {
+                pgn["opening"].as_str().is_some_and(|o| o.to_lowercase().starts_with(opening.as_str()))
+            })
+            && self.min_rating.is_none_or(|min| average_rating.is_some_and(|avg| avg >= min as i64))
+            && self.max_rating.is_none_or(|max| average_rating.is_some_and(|avg| avg <= max as i64))
+            && self.result.as_ref().is_none_or(|result| result_matches(result, &game.result))
+            && self.termination.as_ref().is_none_or(|t| game.termination.as_ref() == Some(t))
+            && self.variant.as_ref().is_none_or(|variant| &game.variant == variant)
+            && self.speed.is_none_or(|speed| {
+                let (lower, upper) = speed.duration_bounds();
+                estimate >= lower as i64 * 1000 && upper.is_none_or(|upper| estimate < upper as i64 * 1000)
+            })
+            && self.min_moves.is_none_or(|min| plies.is_some_and(|p| p >= 2 * min as i64 - 1))
+            && self.max_moves.is_none_or(|max| plies.is_some_and(|p| p <= 2 * max as i64))
+            && self.position_key.is_none_or(|key| game.position_keys.contains(&key))
+    }
+}
+
+/// `Some(None)` in a result filter selects games without a result
+fn result_matches(wanted: &Option<ResultSide>, actual: &Option<ResultSide>) -> bool {
+    match wanted {
+        Some(side) => actual.as_ref() == Some(side),
+        None => matches!(actual, None | Some(ResultSide::Ongoing)),
+    }
+}
+
+fn parse_variant(variant: &str) -> Result<GameVariant, ApiError> {
+    match variant {
+        "standard" => Ok(GameVariant::Standard),
+        "chess960" => Ok(GameVariant::Chess960),
+        "three_check" => Ok(GameVariant::ThreeCheck),
+        "blitz" => Ok(GameVariant::Blitz),
+        "rapid" => Ok(GameVariant::Rapid),
+        "classical" => Ok(GameVariant::Classical),
+        other => Err(ApiError::BadRequest(format!("Unknown variant '{}'", other))),
+    }
+}
+
+fn parse_result(result: &str) -> Result<Option<ResultSide>, ApiError> {
+    match result {
+        "white_win" => Ok(Some(ResultSide::WhiteWins)),
+        "black_win" => Ok(Some(ResultSide::BlackWins)),
+        "draw" => Ok(Some(ResultSide::Draw)),
+        "abandoned" => Ok(Some(ResultSide::Abandoned)),
+        "in_progress" => Ok(None),
+        other => Err(ApiError::BadRequest(format!("Unknown result '{}'", other))),
+    }
+}
+
+fn check_date_window(since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>) -> Result<(), ApiError> {
+    match (since, until) {
+        (Some(since), Some(until)) if since >= until => {
+            Err(ApiError::BadRequest("'since' must be before 'until'".to_string()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Escape LIKE wildcards in user input
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
 
 pub struct GameService;
 
 impl GameService {
+    /// Create a game with the requesting player seated.
+    ///
+    /// The creator takes the requested color (random if omitted). If an
+    /// opponent is given they are seated immediately, otherwise the game
+    /// waits for someone to join.
+    pub async fn create_game(
+        db: &DatabaseConnection,
+        player_id: Uuid,
+        request: CreateGameRequest,
+    ) -> Result<game::Model, ApiError> {
+        Self::create_seated(db, player_id, request, TransitionSource::Rest, Some(player_id)).await
+    }
+
+    /// Create a started, rated game between two players paired by a
+    /// tournament. Nobody acts on the pairing, so the transition has no actor.
+    pub async fn create_tournament_game(
+        db: &DatabaseConnection,
+        white: Uuid,
+        black: Uuid,
+        time_control: i32,
+        increment: i32,
+    ) -> Result<game::Model, ApiError> {
+        let request = CreateGameRequest {
+            time_control,
+            increment,
+            player_color: Some(PlayerColor::White),
+            opponent_id: Some(black),
+            rated: Some(true),
+            variant: None,
+            start_position: None,
+        };
+        Self::create_seated(db, white, request, TransitionSource::Tournament, None).await
+    }
+
+    async fn create_seated(
+        db: &DatabaseConnection,
+        player_id: Uuid,
+        request: CreateGameRequest,
+        source: TransitionSource,
+        actor: Option<Uuid>,
+    ) -> Result<game::Model, ApiError> {
+        if request.opponent_id == Some(player_id) {
+            return Err(ApiError::BadRequest("Cannot play against yourself".to_string()));
+        }
+
+        Self::ensure_player_exists(db, player_id).await?;
+        if let Some(opponent_id) = request.opponent_id {
+            Self::ensure_player_exists(db, opponent_id).await?;
+        }
+
+        let (variant, initial_fen) =
+            Self::resolve_start(request.variant.as_deref(), request.start_position.as_ref())?;
+
+        let creator_is_white = match request.player_color {
+            Some(PlayerColor::White) => true,
+            Some(PlayerColor::Black) => false,
+            Some(PlayerColor::Random) | None => rand::random(),
+        };
+        let (white_player, black_player) = if creator_is_white {
+            (Some(player_id), request.opponent_id)
+        } else {
+            (request.opponent_id, Some(player_id))
+        };
+
+        let state = if request.opponent_id.is_some() {
+            LifecycleState::Started
+        } else {
+            LifecycleState::Waiting
+        };
+        let game_id = Uuid::new_v4();
+        let event = LifecycleEvent::new(
+            game_id,
+            &LifecycleState::Created,
+            state,
+            source,
+            actor,
+        )?;
+
+        let initial_time_ms = request.time_control as i64 * 1000;
+        let increment_ms = request.increment as i64 * 1000;
+        let mut pgn = json!({ "moves": [], "final_ply": 0 });
+        if let (Some(white), Some(black)) = (white_player, black_player) {
+            let category = category_of(&variant, initial_time_ms, increment_ms);
+            Self::record_starting_ratings(db, category, white, black, &mut pgn).await?;
+        }
+
+        let now = Utc::now();
+        let mut new_game = game::ActiveModel {
+            id: Set(game_id),
+            white_player: Set(white_player),
+            black_player: Set(black_player),
+            fen: Set(initial_fen.clone()),
+            pgn: Set(pgn),
+            result: Set(None),
+            variant: Set(variant),
+            started_at: Set(now.into()),
+            duration_sec: Set(request.time_control),
+            created_at: Set(now.into()),
+            updated_at: Set(now.into()),
+            is_imported: Set(false),
+            original_pgn: Set(None),
+            import_hash: Set(None),
+            position_keys: Set(vec![Self::position_key(&initial_fen)?]),
+            initial_time_ms: Set(initial_time_ms),
+            increment_ms: Set(increment_ms),
+            rated: Set(request.rated.unwrap_or(true)),
+            last_move_at: Set(None),
+            moves_encoded: Set(Some(Self::encode_moves::<&str>(&initial_fen, &[])?)),
+            initial_fen: Set(initial_fen),
+            ..Default::default()
+        };
+        event.to.apply_to(&mut new_game);
+
+        let txn = db.begin().await?;
+        let created = new_game.insert(&txn).await?;
+        GameLifecycle::record(&txn, &event).await?;
+        let history = vec![
+            GameEvent::Created {
+                initial_fen: created.initial_fen.clone(),
+                initial_time_ms: created.initial_time_ms,
+                increment_ms: created.increment_ms,
+            },
+            (&event).into(),
+        ];
+        GameEventLog::append(&txn, &created, actor, history).await?;
+        txn.commit().await?;
+        GameLifecycle::publish(event);
+        Ok(created)
+    }
+
+    /// Store the ratings both players start the game with in its PGN, where
+    /// the rating filters of game search read them
+    async fn record_starting_ratings<C: ConnectionTrait>(
+        conn: &C,
+        category: RatingCategory,
+        white: Uuid,
+        black: Uuid,
+        pgn: &mut serde_json::Value,
+    ) -> Result<(), ApiError> {
+        let (white_rating, black_rating) = RatingService::starting_ratings(conn, category, white, black).await?;
+        pgn["white_rating"] = json!(white_rating);
+        pgn["black_rating"] = json!(black_rating);
+        Ok(())
+    }
+
+    /// Fetch a single game by ID.
+    pub async fn get_game(db: &DatabaseConnection, game_id: Uuid) -> Result<game::Model, ApiError> {
+        if let Some(game) = Game::find_by_id(game_id).one(db).await? {
+            return Ok(game);
+        }
+        // Finished games eventually move to cold storage
+        ArchiveService::find(db, game_id)
+            .await?
+            .ok_or_else(|| ApiError::NotFound(format!("Game {}", game_id)))
+    }
+
+    /// Seat a second player in a waiting game.
+    pub async fn join_game(
+        db: &DatabaseConnection,
+        game_id: Uuid,
+        player_id: Uuid,
+    ) -> Result<game::Model, ApiError> {
+        Self::ensure_player_exists(db, player_id).await?;
+
+        let txn = db.begin().await?;
+        let game = Self::find_for_update(&txn, game_id).await?;
+
+        let state = LifecycleState::of(&game);
+        if state.is_terminal() {
+            return Err(ApiError::Conflict("Game is already finished".to_string()));
+        }
+        if game.white_player == Some(player_id) || game.black_player == Some(player_id) {
+            return Err(ApiError::Conflict("Player is already seated in this game".to_string()));
+        }
+
+        let now = Utc::now();
+        let version = game.version;
+        let mut active: game::ActiveModel = game.clone().into();
+        let seats = match (game.white_player, game.black_player) {
+            (None, black) => {
+                active.white_player = Set(Some(player_id));
+                (Some(player_id), black)
+            }
+            (white, None) => {
+                active.black_player = Set(Some(player_id));
+                (white, Some(player_id))
+            }
+            _ => return Err(ApiError::Conflict("Game is already full".to_string())),
+        };
+        if let (Some(white), Some(black)) = seats {
+            let mut pgn = game.pgn.clone();
+            Self::record_starting_ratings(&txn, game_category(&game), white, black, &mut pgn).await?;
+            active.pgn = Set(pgn);
+        }
+        let event = LifecycleEvent::new(
+            game.id,
+            &state,
+            LifecycleState::Started,
+            TransitionSource::Rest,
+            Some(player_id),
+        )?;
+        event.to.apply_to(&mut active);
+        active.started_at = Set(now.into());
+        active.updated_at = Set(now.into());
+
+        let updated = Self::update_versioned(&txn, active, version).await?;
+        GameLifecycle::record(&txn, &event).await?;
+        GameEventLog::append(&txn, &game, Some(player_id), vec![(&event).into()]).await?;
+        txn.commit().await?;
+        GameLifecycle::publish(event);
+        Ok(updated)
+    }
+
+    /// Validate a move through the chess crate and persist it.
+    ///
+    /// The move may be given in UCI or SAN (see `chess::play_move`); the
+    /// played move is returned with its canonical forms. The game's FEN, move list, clocks and result are updated in one
+    /// transaction; the `game_move` row is queued on the move log and
+    /// written in a batch. A move arriving after the mover's clock has run
+    /// out loses the game on time instead. When a rated game ends, both
+    /// players' ratings are updated in the same transaction and the changes
+    /// are returned for announcing the result.
+    ///
+    /// With `expected_ply`, a move for a ply that was already played is a
+    /// retry: the same move returns the game unchanged, any other move is
+    /// rejected as stale along with the current game.
+    pub async fn make_move(
+        db: &DatabaseConnection,
+        move_log: &MoveLog,
+        game_id: Uuid,
+        player_id: Uuid,
+        notation: &str,
+        lag_ms: Option<u32>,
+        expected_ply: Option<u32>,
+    ) -> Result<MoveOutcome, ApiError> {
+        let txn = db.begin().await?;
+        let game = Self::find_for_update(&txn, game_id).await?;
+
+        let is_white = Self::seat_of(&game, player_id)?;
+        let mut moves: Vec<String> = game.pgn["moves"]
+            .as_array()
+            .map(|moves| moves.iter().filter_map(|m| m.as_str().map(str::to_string)).collect())
+            .unwrap_or_default();
+
+        if let Some(ply) = expected_ply.map(|ply| ply as usize) {
+            if ply <= moves.len() {
+                return match Self::replayed_move(&game, &moves, ply, notation, is_white)? {
+                    Some(played) => Ok(MoveOutcome::Played { game, played, ended: false, rating_changes: None }),
+                    None => Err(Self::stale(game, format!("Ply {} has already been played", ply))),
+                };
+            }
+            if ply > moves.len() + 1 {
+                let message = format!("Expected ply {} but the game is at ply {}", ply, moves.len());
+                return Err(Self::stale(game, message));
+            }
+        }
+
+        let state = LifecycleState::of(&game);
+        if state == LifecycleState::Waiting {
+            return Err(ApiError::Conflict("Game is waiting for an opponent".to_string()));
+        }
+        if state.is_terminal() {
+            return Err(ApiError::Conflict("Game is already finished".to_string()));
+        }
+
+        let white_to_move = chess::is_white_to_move(&game.fen)
+            .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+        if white_to_move != is_white {
+            return Err(ApiError::Conflict("It is not your turn".to_string()));
+        }
+
+        let now = Utc::now();
+        let mut pgn = game.pgn.clone();
+        let mut clocks = pgn["clocks"].as_array().cloned().unwrap_or_default();
+        let timing = Self::move_timing(&game, &clocks, moves.len() + 1, now, lag_ms);
+
+        if let Some(MoveTiming::Flagged) = timing {
+            let event = LifecycleEvent::new(
+                game.id,
+                &state,
+                LifecycleState::Ended { result: Outcome::win_for(!is_white), reason: EndReason::TimeForfeit },
+                TransitionSource::Rest,
+                Some(player_id),
+            )?;
+            let version = game.version;
+            let mut active: game::ActiveModel = game.clone().into();
+            event.to.apply_to(&mut active);
+            active.updated_at = Set(now.into());
+            let ended = Self::update_versioned(&txn, active, version).await?;
+            let rating_changes = RatingService::rate_finished_game(&txn, &ended, now).await?;
+            GameLifecycle::record(&txn, &event).await?;
+            GameEventLog::append(&txn, &game, Some(player_id), vec![(&event).into()]).await?;
+            txn.commit().await?;
+            GameLifecycle::publish(event);
+            return Ok(MoveOutcome::Flagged { game: ended, rating_changes });
+        }
+
+        let played = chess::play_move(&game.fen, notation)
+            .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+        moves.push(played.san.clone());
+        let ply = moves.len() as i32;
+        pgn["moves"] = json!(moves);
+        pgn["final_ply"] = json!(ply);
+
+        let (clock_ms, time_spent_ms, lag_compensation_ms) = match timing {
+            Some(MoveTiming::Played { clock_ms, time_spent_ms, lag_compensation_ms }) => {
+                (Some(clock_ms), Some(time_spent_ms), Some(lag_compensation_ms))
+            }
+            _ => (None, None, None),
+        };
+        // Keep "clocks" parallel to "moves" for games stored before clocks were tracked
+        clocks.resize(ply as usize - 1, serde_json::Value::Null);
+        clocks.push(json!(clock_ms));
+        pgn["clocks"] = json!(clocks);
+
+        let new_move = game_move::ActiveModel {
+            game_id: Set(game.id),
+            move_number: Set(ply),
+            san: Set(played.san.clone()),
+            fen: Set(played.fen.clone()),
+            timestamp: Set(now.into()),
+            clock_ms: Set(clock_ms),
+            time_spent_ms: Set(time_spent_ms),
+            lag_compensation_ms: Set(lag_compensation_ms),
+            ..Default::default()
+        };
+
+        let mut position_keys = game.position_keys.clone();
+        position_keys.push(Self::position_key(&played.fen)?);
+
+        let moves_encoded = Self::encode_moves(&game.initial_fen, &moves)?;
+
+        let event = match (&played.outcome, played.termination) {
+            (Some(outcome), Some(termination)) => {
+                let ended = LifecycleState::Ended {
+                    result: Outcome::from_pgn(outcome).unwrap_or(Outcome::Draw),
+                    reason: termination.into(),
+                };
+                Some(LifecycleEvent::new(game.id, &state, ended, TransitionSource::Rest, Some(player_id))?)
+            }
+            _ => None,
+        };
+
+        let mut history = vec![GameEvent::MovePlayed {
+            uci: played.uci.clone(),
+            san: played.san.clone(),
+            clock_ms,
+        }];
+        history.extend(event.iter().map(GameEvent::from));
+
+        let version = game.version;
+        let mut active: game::ActiveModel = game.clone().into();
+        active.fen = Set(played.fen.clone());
+        active.pgn = Set(pgn);
+        active.moves_encoded = Set(Some(moves_encoded));
+        active.position_keys = Set(position_keys);
+        if let Some(event) = &event {
+            event.to.apply_to(&mut active);
+        }
+        active.last_move_at = Set(Some(now.into()));
+        active.updated_at = Set(now.into());
+
+        let updated = Self::update_versioned(&txn, active, version).await?;
+        let mut rating_changes = None;
+        if let Some(event) = &event {
+            rating_changes = RatingService::rate_finished_game(&txn, &updated, now).await?;
+            GameLifecycle::record(&txn, event).await?;
+        }
+        GameEventLog::append(&txn, &game, Some(player_id), history).await?;
+        txn.commit().await?;
+        move_log.record(new_move).await;
+        let ended = event.is_some();
+        if let Some(event) = event {
+            GameLifecycle::publish(event);
+        }
+        Ok(MoveOutcome::Played { game: updated, played, ended, rating_changes })
+    }
+
+    /// Charge the mover for the time since the previous move.
+    ///
+    /// The clock runs from the later of the game start and the previous move.
+    /// Lag reported by the client is credited back, capped by
+    /// `MAX_LAG_COMPENSATION_MS`. Returns `None` for untimed games.
+    fn move_timing(
+        game: &game::Model,
+        clocks: &[serde_json::Value],
+        ply: usize,
+        now: DateTime<Utc>,
+        lag_ms: Option<u32>,
+    ) -> Option<MoveTiming> {
+        if game.initial_time_ms <= 0 {
+            return None;
+        }
+
+        let clock_started = game.last_move_at.unwrap_or(game.started_at);
+        let elapsed_ms = (now - clock_started.with_timezone(&Utc)).num_milliseconds().max(0);
+        let lag_compensation_ms = i64::from(lag_ms.unwrap_or(0))
+            .min(MAX_LAG_COMPENSATION_MS)
+            .min(elapsed_ms);
+        let time_spent_ms = elapsed_ms - lag_compensation_ms;
+
+        // The mover's previous clock is two plies back
+        let previous_ms = ply
+            .checked_sub(3)
+            .and_then(|idx| clocks.get(idx))
+            .and_then(|clock| clock.as_i64())
+            .unwrap_or(game.initial_time_ms);
+
+        if time_spent_ms > previous_ms {
+            return Some(MoveTiming::Flagged);
+        }
+        Some(MoveTiming::Played {
+            clock_ms: previous_ms - time_spent_ms + game.increment_ms,
+            time_spent_ms,
+            lag_compensation_ms,
+        })
+    }
+
+    /// Handle a player leaving a game.
+    ///
+    /// Games that have not started, or are too short to be rated, are
+    /// aborted. Otherwise the game ends as a loss for the player who left and
+    /// is rated in the same transaction; the rating changes are returned.
+    pub async fn abandon_game(
+        db: &DatabaseConnection,
+        game_id: Uuid,
+        player_id: Uuid,
+    ) -> Result<(game::Model, Option<GameRatingChanges>), ApiError> {
+        let txn = db.begin().await?;
+        let game = Self::find_for_update(&txn, game_id).await?;
+
+        let is_white = Self::seat_of(&game, player_id)?;
+        let from = LifecycleState::of(&game);
+        let plies = game.pgn["moves"].as_array().map_or(0, Vec::len);
+        let to = if from == LifecycleState::Started && plies >= MIN_RATED_PLIES {
+            LifecycleState::Ended { result: Outcome::win_for(!is_white), reason: EndReason::Abandonment }
+        } else {
+            LifecycleState::Aborted
+        };
+        let event = LifecycleEvent::new(game.id, &from, to, TransitionSource::Rest, Some(player_id))?;
+
+        let now = Utc::now();
+        let version = game.version;
+        let mut active: game::ActiveModel = game.clone().into();
+        event.to.apply_to(&mut active);
+        active.updated_at = Set(now.into());
+
+        let updated = Self::update_versioned(&txn, active, version).await?;
+        let rating_changes = RatingService::rate_finished_game(&txn, &updated, now).await?;
+        GameLifecycle::record(&txn, &event).await?;
+        GameEventLog::append(&txn, &game, event.actor, vec![(&event).into()]).await?;
+        txn.commit().await?;
+        GameLifecycle::publish(event);
+        Ok((updated, rating_changes))
+    }
+
+    /// Decide a started game off the board, e.g. by a tournament arbiter.
+    /// The game is rated in the same transaction like any finished game.
+    pub async fn adjudicate_game(
+        db: &DatabaseConnection,
+        game_id: Uuid,
+        result: Outcome,
+        source: TransitionSource,
+        actor: Option<Uuid>,
+    ) -> Result<(game::Model, Option<GameRatingChanges>), ApiError> {
+        let txn = db.begin().await?;
+        let game = Self::find_for_update(&txn, game_id).await?;
+        let event = LifecycleEvent::new(
+            game.id,
+            &LifecycleState::of(&game),
+            LifecycleState::Adjudicated { result },
+            source,
+            actor,
+        )?;
+
+        let now = Utc::now();
+        let version = game.version;
+        let mut active: game::ActiveModel = game.clone().into();
+        event.to.apply_to(&mut active);
+        active.updated_at = Set(now.into());
+
+        let updated = Self::update_versioned(&txn, active, version).await?;
+        let rating_changes = RatingService::rate_finished_game(&txn, &updated, now).await?;
+        GameLifecycle::record(&txn, &event).await?;
+        GameEventLog::append(&txn, &game, event.actor, vec![(&event).into()]).await?;
+        txn.commit().await?;
+        GameLifecycle::publish(event);
+        Ok((updated, rating_changes))
+    }
+
+    /// Parse, validate and store a single PGN game.
+    pub async fn import_pgn(db: &DatabaseConnection, pgn: &str) -> Result<ImportStatus, ApiError> {
+        let parsed = chess::parse_pgn(pgn).map_err(Self::pgn_error)?;
+        let validated = chess::validate_game(&parsed).map_err(Self::pgn_error)?;
+        Self::store_imported_game(db, &validated, pgn).await
+    }
+
+    /// Store an already validated PGN game along with its moves.
+    ///
+    /// Seats are left empty: the `White`/`Black` headers are whatever the
+    /// uploader wrote, so player names are kept in the PGN headers only and
+    /// never attach the game to an account. Games already imported are
+    /// reported as duplicates instead of being stored twice.
+    pub async fn store_imported_game(
+        db: &DatabaseConnection,
+        validated: &chess::ValidatedGame,
+        original_pgn: &str,
+    ) -> Result<ImportStatus, ApiError> {
+        let headers = &validated.headers;
+        let hash = Self::import_hash(validated);
+
+        if let Some(existing) = Self::find_by_import_hash(db, &hash).await? {
+            return Ok(ImportStatus::Duplicate(existing));
+        }
+
+        let (initial_sec, increment_sec) = headers
+            .other
+            .get("TimeControl")
+            .map(|tc| Self::parse_time_control(tc))
+            .unwrap_or((0, 0));
+
+        let now = Utc::now();
+        let started_at = headers
+            .date
+            .as_deref()
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y.%m.%d").ok())
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|date| date.and_utc())
+            .unwrap_or(now);
+
+        let position_keys = std::iter::once(chess::STARTING_FEN)
+            .chain(validated.positions.iter().map(String::as_str))
+            .map(Self::position_key)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Kept as written in the PGN rather than normalised by the lifecycle
+        let termination = headers
+            .other
+            .get("Termination")
+            .map(|t| t.trim().to_lowercase().replace(' ', "_"));
+
+        // Imported games are replayed through the lifecycle: started, then
+        // finished if the PGN carries a result
+        let game_id = Uuid::new_v4();
+        let mut events = vec![LifecycleEvent::new(
+            game_id,
+            &LifecycleState::Created,
+            LifecycleState::Started,
+            TransitionSource::Import,
+            None,
+        )?];
+        if let Some(result) = Outcome::from_pgn(&headers.result) {
+            let finished = match termination.as_deref() {
+                Some("adjudication") => LifecycleState::Adjudicated { result },
+                other => LifecycleState::Ended {
+                    result,
+                    reason: EndReason::from_termination(other.unwrap_or_default()),
+                },
+            };
+            events.push(LifecycleEvent::new(
+                game_id,
+                &LifecycleState::Started,
+                finished,
+                TransitionSource::Import,
+                None,
+            )?);
+        }
+
+        let mut new_game = game::ActiveModel {
+            id: Set(game_id),
+            white_player: Set(None),
+            black_player: Set(None),
+            fen: Set(validated.final_fen.clone()),
+            pgn: Set(json!({
+                "event": headers.event,
+                "site": headers.site,
+                "date": headers.date,
+                "round": headers.round,
+                "white": headers.white,
+                "black": headers.black,
+                "result": headers.result.to_pgn_string(),
+                "moves": validated.moves,
+                "final_ply": validated.moves.len(),
+                "eco": headers.other.get("ECO"),
+                "opening": headers.other.get("Opening"),
+                "white_rating": headers.other.get("WhiteElo").and_then(|r| r.parse::<i32>().ok()),
+                "black_rating": headers.other.get("BlackElo").and_then(|r| r.parse::<i32>().ok()),
+                "headers": headers.other,
+            })),
+            variant: Set(GameVariant::Standard),
+            started_at: Set(started_at.into()),
+            duration_sec: Set(initial_sec),
+            created_at: Set(now.into()),
+            updated_at: Set(now.into()),
+            is_imported: Set(true),
+            original_pgn: Set(Some(original_pgn.to_string())),
+            import_hash: Set(Some(hash.clone())),
+            position_keys: Set(position_keys),
+            initial_time_ms: Set(initial_sec as i64 * 1000),
+            increment_ms: Set(increment_sec as i64 * 1000),
+            rated: Set(false),
+            last_move_at: Set(None),
+            moves_encoded: Set(Some(Self::encode_moves(chess::STARTING_FEN, &validated.moves)?)),
+            initial_fen: Set(chess::STARTING_FEN.to_string()),
+            ..Default::default()
+        };
+        if let Some(event) = events.last() {
+            event.to.apply_to(&mut new_game);
+        }
+        new_game.termination = Set(termination);
+
+        let txn = db.begin().await?;
+        let stored = match new_game.insert(&txn).await {
+            Ok(stored) => stored,
+            // Lost a race with a concurrent import of the same game
+            Err(err) if matches!(err.sql_err(), Some(SqlErr::UniqueConstraintViolation(_))) => {
+                txn.rollback().await?;
+                return match Self::find_by_import_hash(db, &hash).await? {
+                    Some(existing) => Ok(ImportStatus::Duplicate(existing)),
+                    None => Err(ApiError::DatabaseError(err)),
+                };
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let moves: Vec<game_move::ActiveModel> = validated
+            .moves
+            .iter()
+            .zip(&validated.positions)
+            .enumerate()
+            .map(|(idx, (san, fen))| game_move::ActiveModel {
+                game_id: Set(stored.id),
+                move_number: Set(idx as i32 + 1),
+                san: Set(san.clone()),
+                fen: Set(fen.clone()),
+                timestamp: Set(started_at.into()),
+                ..Default::default()
+            })
+            .collect();
+        if !moves.is_empty() {
+            GameMove::insert_many(moves).exec_without_returning(&txn).await?;
+        }
+        for event in &events {
+            GameLifecycle::record(&txn, event).await?;
+        }
+        GameEventLog::append(&txn, &stored, None, Self::imported_history(&stored, validated, &events)?).await?;
+
+        txn.commit().await?;
+        events.into_iter().for_each(GameLifecycle::publish);
+        Ok(ImportStatus::Imported(Box::new(stored)))
+    }
+
+    /// Event log of an imported game: its moves between the lifecycle events
+    fn imported_history(
+        stored: &game::Model,
+        validated: &chess::ValidatedGame,
+        events: &[LifecycleEvent],
+    ) -> Result<Vec<GameEvent>, ApiError> {
+        let mut history = vec![GameEvent::Created {
+            initial_fen: stored.initial_fen.clone(),
+            initial_time_ms: stored.initial_time_ms,
+            increment_ms: stored.increment_ms,
+        }];
+        history.extend(events.first().map(GameEvent::from));
+
+        let mut fen = stored.initial_fen.clone();
+        for san in &validated.moves {
+            let played = chess::play_move(&fen, san).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+            fen = played.fen;
+            history.push(GameEvent::MovePlayed { uci: played.uci, san: played.san, clock_ms: None });
+        }
+        history.extend(events.get(1).map(GameEvent::from));
+        Ok(history)
+    }
+
+    /// Fingerprint of the parts of a game that identify it across uploads
+    fn import_hash(validated: &chess::ValidatedGame) -> String {
+        let headers = &validated.headers;
+        let mut hasher = Sha256::new();
+        hasher.update(headers.white.as_bytes());
+        hasher.update(b"|");
+        hasher.update(headers.black.as_bytes());
+        hasher.update(b"|");
+        hasher.update(headers.date.as_deref().unwrap_or("").as_bytes());
+        hasher.update(b"|");
+        hasher.update(headers.result.to_pgn_string().as_bytes());
+        hasher.update(b"|");
+        hasher.update(validated.moves.join(" ").as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    async fn find_by_import_hash(db: &DatabaseConnection, hash: &str) -> Result<Option<Uuid>, ApiError> {
+        Ok(Game::find()
+            .filter(game::Column::ImportHash.eq(hash))
+            .one(db)
+            .await?
+            .map(|game| game.id))
+    }
+
+    /// Parse a PGN `TimeControl` header such as "600+5" into (initial, increment) seconds
+    fn parse_time_control(time_control: &str) -> (i32, i32) {
+        let mut parts = time_control.splitn(2, '+');
+        let initial = parts.next().and_then(|p| p.trim().parse().ok()).unwrap_or(0);
+        let increment = parts.next().and_then(|p| p.trim().parse().ok()).unwrap_or(0);
+        (initial, increment)
+    }
+
+    /// Work out the variant and starting FEN of a new game.
+    ///
+    /// Chess960 starts imply the Chess960 variant when none is given, and a
+    /// Chess960 game without a start gets a random position.
+    fn resolve_start(
+        variant: Option<&str>,
+        start: Option<&StartPosition>,
+    ) -> Result<(GameVariant, String), ApiError> {
+        let variant = match variant.map(parse_variant).transpose()? {
+            Some(variant @ (GameVariant::Standard | GameVariant::Chess960)) => variant,
+            Some(other) => {
+                return Err(ApiError::BadRequest(format!(
+                    "Games cannot be created with variant '{}'",
+                    variant_name(&other)
+                )))
+            }
+            None => match start {
+                Some(StartPosition::Chess960 { .. } | StartPosition::RandomChess960) => GameVariant::Chess960,
+                _ => GameVariant::Standard,
+            },
+        };
+
+        let fen = match (start, &variant) {
+            // The regular setup is also Chess960 position 518
+            (Some(StartPosition::Standard), _) | (None, GameVariant::Standard) => {
+                Ok(chess::STARTING_FEN.to_string())
+            }
+            (None | Some(StartPosition::RandomChess960), GameVariant::Chess960) => {
+                chess::chess960_fen(rand::thread_rng().gen_range(0..chess::CHESS960_POSITIONS))
+            }
+            (Some(StartPosition::Chess960 { number }), GameVariant::Chess960) => chess::chess960_fen(*number),
+            (Some(StartPosition::Fen { fen }), _) => chess::validate_start_position(fen),
+            _ => {
+                return Err(ApiError::BadRequest(
+                    "Chess960 starting positions require the chess960 variant".to_string(),
+                ))
+            }
+        }
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+        Ok((variant, fen))
+    }
+
+    fn position_key(fen: &str) -> Result<i64, ApiError> {
+        chess::position_key(fen).map_err(|e| ApiError::BadRequest(e.to_string()))
+    }
+
+    fn encode_moves<S: AsRef<str>>(start_fen: &str, moves: &[S]) -> Result<Vec<u8>, ApiError> {
+        chess::encode_moves(start_fen, moves).map_err(|e| ApiError::BadRequest(e.to_string()))
+    }
+
+    fn pgn_error(err: chess::PgnError) -> ApiError {
+        match err {
+            chess::PgnError::IllegalMove { move_number, move_text, reason } => {
+                ApiError::IllegalMoveError { move_number, move_text, reason }
+            }
+            other => ApiError::PgnParseError(other.to_string()),
+        }
+    }
+
+    pub async fn ensure_player_exists(db: &DatabaseConnection, player_id: Uuid) -> Result<(), ApiError> {
+        match Player::find_by_id(player_id).one(db).await? {
+            Some(_) => Ok(()),
+            None => Err(ApiError::NotFound(format!("Player {}", player_id))),
+        }
+    }
+
+    /// Load a game and lock its row for the rest of the transaction
+    async fn find_for_update<C: ConnectionTrait>(conn: &C, game_id: Uuid) -> Result<game::Model, ApiError> {
+        Game::find_by_id(game_id)
+            .lock_exclusive()
+            .one(conn)
+            .await?
+            .ok_or_else(|| ApiError::NotFound(format!("Game {}", game_id)))
+    }
+
+    /// Write a game update only if the row is still at `version`, bumping it
+    async fn update_versioned<C: ConnectionTrait>(
+        conn: &C,
+        mut active: game::ActiveModel,
+        version: i64,
+    ) -> Result<game::Model, ApiError> {
+        active.version = Set(version + 1);
+        Game::update(active)
+            .filter(game::Column::Version.eq(version))
+            .exec(conn)
+            .await
+            .map_err(|err| match err {
+                DbErr::RecordNotUpdated => ApiError::Conflict("Game was changed by another request".to_string()),
+                err => err.into(),
+            })
+    }
+
+    /// Reject a request made against an outdated view of the game
+    fn stale(game: game::Model, message: String) -> ApiError {
+        let current = serde_json::to_value(GameDisplayDTO::from(game)).unwrap_or_default();
+        ApiError::Stale { message, current }
+    }
+
+    /// If `notation` is the move already played at `ply` by the same side,
+    /// return it as played then; `None` if it is a different move.
+    fn replayed_move(
+        game: &game::Model,
+        moves: &[String],
+        ply: usize,
+        notation: &str,
+        is_white: bool,
+    ) -> Result<Option<chess::PlayedMove>, ApiError> {
+        let mut fen = game.initial_fen.clone();
+        for san in &moves[..ply - 1] {
+            fen = chess::play_move(&fen, san).map_err(|e| ApiError::BadRequest(e.to_string()))?.fen;
+        }
+        let white_to_move = chess::is_white_to_move(&fen).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+        if white_to_move != is_white {
+            return Ok(None);
+        }
+        Ok(chess::play_move(&fen, notation)
+            .ok()
+            .filter(|played| played.san == moves[ply - 1]))
+    }
+
+    /// Returns true if the player holds the white seat, false for black
+    fn seat_of(game: &game::Model, player_id: Uuid) -> Result<bool, ApiError> {
+        if game.white_player == Some(player_id) {
+            Ok(true)
+        } else if game.black_player == Some(player_id) {
+            Ok(false)
+        } else {
+            Err(ApiError::Forbidden("Player is not part of this game".to_string()))
+        }
+    }
+
     /// List games with keyset pagination.
     /// 
     /// # Arguments
@@ -19,10 +1099,7 @@ impl GameService {
     /// * `cursor` - Optional cursor string (base64 encoded "timestamp,id")
     /// * `limit` - Number of items to return
     /// * `player_id` - Optional player ID filter (checks both white and black players)
-    /// * `status` - Optional status filter (currently maps to result being not null for finished games, or specific status if column exists)
-    /// 
-    /// Note: The current schema uses `result` to determine if a game is finished. 
-    /// Active games might have `result` as NULL (after our migration).
+    /// * `status` - Optional status filter on the game's `status` column
     pub async fn list_games(
         db: &DatabaseConnection,
         cursor: Option<String>,
@@ -45,69 +1122,343 @@ impl GameService {
         }
 
         if let Some(s) = status {
-            match s {
-                GameStatus::Waiting | GameStatus::InProgress => {
-                     // Active games: result is NULL
-                     query = query.filter(game::Column::Result.is_null());
-                },
-                GameStatus::Completed | GameStatus::Aborted => {
-                    // Finished games: result is NOT NULL
-                    // Note: "Aborted" vs "Completed" might need distinguishing via ResultSide if we had it, 
-                    // but for now we just check if it has a result.
-                    query = query.filter(game::Column::Result.is_not_null());
-                }
+            query = query.filter(game::Column::Status.eq(GameRowStatus::from(s)));
+        }
+
+        Self::paginate(db, query, None, cursor, limit).await
+    }
+
+    /// List a player's games for export, newest first, with keyset pagination.
+    ///
+    /// Archived games are included.
+    pub async fn list_player_games(
+        db: &DatabaseConnection,
+        player_id: Uuid,
+        filter: &ArchiveFilter,
+        cursor: Option<String>,
+        limit: u64,
+    ) -> Result<(Vec<game::Model>, Option<String>), DbErr> {
+        let mut query = Game::find().filter(
+            Condition::any()
+                .add(game::Column::WhitePlayer.eq(player_id))
+                .add(game::Column::BlackPlayer.eq(player_id)),
+        );
+
+        if let Some(since) = filter.since {
+            query = query.filter(game::Column::StartedAt.gte(since));
+        }
+        if let Some(until) = filter.until {
+            query = query.filter(game::Column::StartedAt.lt(until));
+        }
+        if let Some(variant) = &filter.variant {
+            query = query.filter(game::Column::Variant.eq(variant.clone()));
+        }
+        if let Some(rated) = filter.rated {
+            query = query.filter(game::Column::Rated.eq(rated));
+        }
+        if let Some(result) = &filter.result {
+            query = Self::filter_result(query, result);
+        }
+
+        let archive = ArchiveScope {
+            player_id: Some(player_id),
+            since: filter.since,
+            until: filter.until,
+            matches: &|game| filter.matches(game),
+        };
+        Self::paginate(db, query, Some(archive), cursor, limit).await
+    }
+
+    /// Search stored games, newest first, with keyset pagination.
+    ///
+    /// Opening and rating filters read the `pgn` document and are backed by
+    /// the expression indexes of the advanced search migration. Ratings are
+    /// stored when a game starts, but games played here are not classified
+    /// by opening, so the ECO and opening filters only match imported games.
+    /// Archived games are included and filtered once decoded.
+    pub async fn search_games(
+        db: &DatabaseConnection,
+        filter: &GameSearchFilter,
+        cursor: Option<String>,
+        limit: u64,
+    ) -> Result<(Vec<game::Model>, Option<String>), DbErr> {
+        let mut query = Game::find();
+
+        if let Some(pid) = filter.player_id {
+            query = query.filter(
+                Condition::any()
+                    .add(game::Column::WhitePlayer.eq(pid))
+                    .add(game::Column::BlackPlayer.eq(pid)),
+            );
+        }
+        if let Some(eco) = &filter.eco {
+            query = query.filter(Expr::cust_with_values(
+                r#"("pgn" ->> 'eco') LIKE $1"#,
+                [format!("{}%", escape_like(eco))],
+            ));
+        }
+        if let Some(opening) = &filter.opening {
+            query = query.filter(Expr::cust_with_values(
+                r#"lower("pgn" ->> 'opening') LIKE $1"#,
+                [format!("{}%", escape_like(opening))],
+            ));
+        }
+        const AVERAGE_RATING: &str =
+            r#"((("pgn" ->> 'white_rating')::int + ("pgn" ->> 'black_rating')::int) / 2)"#;
+        if let Some(min) = filter.min_rating {
+            query = query.filter(Expr::cust_with_values(format!("{} >= $1", AVERAGE_RATING), [min]));
+        }
+        if let Some(max) = filter.max_rating {
+            query = query.filter(Expr::cust_with_values(format!("{} <= $1", AVERAGE_RATING), [max]));
+        }
+        if let Some(since) = filter.since {
+            query = query.filter(game::Column::StartedAt.gte(since));
+        }
+        if let Some(until) = filter.until {
+            query = query.filter(game::Column::StartedAt.lt(until));
+        }
+        if let Some(result) = &filter.result {
+            query = Self::filter_result(query, result);
+        }
+        if let Some(termination) = &filter.termination {
+            query = query.filter(game::Column::Termination.eq(termination.clone()));
+        }
+        if let Some(variant) = &filter.variant {
+            query = query.filter(game::Column::Variant.eq(variant.clone()));
+        }
+        if let Some(speed) = filter.speed {
+            let estimate = format!(
+                r#"("initial_time_ms" + {} * "increment_ms")"#,
+                chess::TimeControlCategory::ESTIMATED_MOVES
+            );
+            let (lower, upper) = speed.duration_bounds();
+            query = query.filter(Expr::cust_with_values(format!("{} >= $1", estimate), [lower as i64 * 1000]));
+            if let Some(upper) = upper {
+                query = query.filter(Expr::cust_with_values(format!("{} < $1", estimate), [upper as i64 * 1000]));
             }
         }
+        // Full move N is made up of plies 2N-1 and 2N
+        const PLY_COUNT: &str = r#"(("pgn" ->> 'final_ply')::int)"#;
+        if let Some(min) = filter.min_moves {
+            query = query.filter(Expr::cust_with_values(format!("{} >= $1", PLY_COUNT), [min.saturating_mul(2).saturating_sub(1)]));
+        }
+        if let Some(max) = filter.max_moves {
+            query = query.filter(Expr::cust_with_values(format!("{} <= $1", PLY_COUNT), [max.saturating_mul(2)]));
+        }
+        if let Some(key) = filter.position_key {
+            query = query.filter(Expr::cust_with_values(
+                r#""position_keys" @> $1"#,
+                [vec![key]],
+            ));
+        }
+
+        let archive = ArchiveScope {
+            player_id: filter.player_id,
+            since: filter.since,
+            until: filter.until,
+            matches: &|game| filter.matches(game),
+        };
+        Self::paginate(db, query, Some(archive), cursor, limit).await
+    }
+
+    fn filter_result(query: Select<Game>, result: &Option<ResultSide>) -> Select<Game> {
+        match result {
+            Some(side) => query.filter(game::Column::Result.eq(side.clone())),
+            None => query.filter(
+                Condition::any()
+                    .add(game::Column::Result.is_null())
+                    .add(game::Column::Result.eq(ResultSide::Ongoing)),
+            ),
+        }
+    }
 
-        // 2. Apply Cursor (Keyset Pagination)
+    /// Render a single game as PGN.
+    pub async fn export_pgn(
+        db: &DatabaseConnection,
+        game_id: Uuid,
+        options: &ExportGameQuery,
+    ) -> Result<String, ApiError> {
+        let game = Self::get_game(db, game_id).await?;
+        let names = Self::player_names(db, std::slice::from_ref(&game)).await?;
+        Ok(Self::game_to_pgn(&game, &names, options))
+    }
+
+    /// Usernames of the seated players of the given games
+    pub async fn player_names(
+        db: &DatabaseConnection,
+        games: &[game::Model],
+    ) -> Result<HashMap<Uuid, String>, DbErr> {
+        let mut ids: Vec<Uuid> = games
+            .iter()
+            .flat_map(|g| [g.white_player, g.black_player])
+            .flatten()
+            .collect();
+        ids.sort();
+        ids.dedup();
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        Ok(Player::find()
+            .filter(db_entity::player::Column::Id.is_in(ids))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|p| (p.id, p.username))
+            .collect())
+    }
+
+    /// Render a game as PGN using the stored move list and headers.
+    ///
+    /// Seated players are named by username; guests of imported games keep
+    /// the names from their original headers.
+    pub fn game_to_pgn(
+        game: &game::Model,
+        names: &HashMap<Uuid, String>,
+        options: &ExportGameQuery,
+    ) -> String {
+        let pgn = &game.pgn;
+        let header = |key: &str| pgn[key].as_str().filter(|v| !v.is_empty()).map(str::to_string);
+        let player_name = |seat: Option<Uuid>, key: &str| {
+            seat.and_then(|id| names.get(&id).cloned())
+                .or_else(|| header(key))
+                .unwrap_or_else(|| "?".to_string())
+        };
+
+        let result = match game.result {
+            Some(ResultSide::WhiteWins) => chess::PgnGameResult::WhiteWins,
+            Some(ResultSide::BlackWins) => chess::PgnGameResult::BlackWins,
+            Some(ResultSide::Draw) => chess::PgnGameResult::Draw,
+            _ => chess::PgnGameResult::Ongoing,
+        };
+
+        let mut headers = vec![
+            ("Event".to_string(), header("event").unwrap_or_else(|| "XLMate game".to_string())),
+            ("Site".to_string(), header("site").unwrap_or_else(|| "XLMate".to_string())),
+            (
+                "Date".to_string(),
+                header("date").unwrap_or_else(|| game.started_at.format("%Y.%m.%d").to_string()),
+            ),
+            ("Round".to_string(), header("round").unwrap_or_else(|| "-".to_string())),
+            ("White".to_string(), player_name(game.white_player, "white")),
+            ("Black".to_string(), player_name(game.black_player, "black")),
+            ("Result".to_string(), result.to_pgn_string().to_string()),
+            ("GameId".to_string(), game.id.to_string()),
+        ];
+        if game.variant != GameVariant::Standard {
+            headers.push(("Variant".to_string(), format!("{:?}", game.variant)));
+        }
+        if game.initial_fen != chess::STARTING_FEN {
+            headers.push(("SetUp".to_string(), "1".to_string()));
+            headers.push(("FEN".to_string(), game.initial_fen.clone()));
+        }
+        if game.initial_time_ms > 0 {
+            headers.push((
+                "TimeControl".to_string(),
+                format!("{}+{}", game.initial_time_ms / 1000, game.increment_ms / 1000),
+            ));
+        }
+        if let Some(termination) = &game.termination {
+            headers.push(("Termination".to_string(), termination.clone()));
+        }
+
+        let moves: Vec<String> = pgn["moves"]
+            .as_array()
+            .map(|m| m.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+            .unwrap_or_default();
+
+        // Per-move annotations are stored as arrays parallel to "moves"
+        let annotations: Vec<chess::MoveAnnotation> = (0..moves.len())
+            .map(|idx| chess::MoveAnnotation {
+                clock_ms: if options.clocks { pgn["clocks"][idx].as_u64() } else { None },
+                eval: if options.evals { pgn["evals"][idx].as_f64() } else { None },
+                comment: if options.comments {
+                    pgn["comments"][idx].as_str().map(str::to_string)
+                } else {
+                    None
+                },
+            })
+            .collect();
+
+        chess::write_pgn(&headers, &moves, &annotations, &result)
+    }
+
+    /// Run a game query with keyset pagination on (created_at, id), newest first.
+    ///
+    /// With an archive scope, archived games are merged into the page. Both
+    /// tables share the key, so a page is the newest `limit` games of the
+    /// two. When the archive scan stops early, games past its last examined
+    /// key are held back for the next page, which continues from that key.
+    async fn paginate(
+        db: &DatabaseConnection,
+        mut query: Select<Game>,
+        archive: Option<ArchiveScope<'_>>,
+        cursor: Option<String>,
+        limit: u64,
+    ) -> Result<(Vec<game::Model>, Option<String>), DbErr> {
+        let after = cursor.as_deref().and_then(|c| Self::decode_cursor(c).ok());
+
+        // 1. Apply Cursor (Keyset Pagination)
         // Sort by created_at DESC, id DESC
         query = query
             .order_by(game::Column::CreatedAt, Order::Desc)
             .order_by(game::Column::Id, Order::Desc);
 
-        if let Some(cursor_str) = cursor {
-            if let Ok((last_created_at, last_id)) = Self::decode_cursor(&cursor_str) {
-                // created_at < last_created_at OR (created_at = last_created_at AND id < last_id)
-                // SeaORM tuple comparison: (col1, col2) < (val1, val2)
-                // query = query.filter(
-                //    Condition::any()
-                //        .add(game::Column::CreatedAt.lt(last_created_at))
-                //        .add(
-                //            Condition::all()
-                //                .add(game::Column::CreatedAt.eq(last_created_at))
-                //                .add(game::Column::Id.lt(last_id))
-                //        )
-                // );
-                // Actually, SeaORM supports tuple comparison conveniently? 
-                // Not directly in the builder API widely in all versions, but the composite condition above is correct for (A, B) < (a, b) logic.
-                // However, tuple comparison `(A, B) < (a, b)` logic is standard SQL but SeaORM DSL is explicit.
-                
-                // Constructing: (created_at, id) < (last_created_at, last_id)
-                // Equivalent to: created_at < last_created_at OR (created_at = last_created_at AND id < last_id) (for DESC, DESC)
-                // WAIT! For DESC sort, "next page" means values SMALLER than cursor?
-                // Yes. Sorting DESC means newest first. Cursor is at some point. We want older stuff.
-                // So we want `created_at < cursor.created_at`.
-                // If created_at == cursor.created_at, then `id < cursor.id` (assuming ID also DESC).
-                
-                let condition = Condition::any()
-                    .add(game::Column::CreatedAt.lt(last_created_at))
-                    .add(
-                        Condition::all()
-                            .add(game::Column::CreatedAt.eq(last_created_at))
-                            .add(game::Column::Id.lt(last_id))
-                    );
-                
-                query = query.filter(condition);
-            }
+        if let Some((last_created_at, last_id)) = after {
+            // created_at < last_created_at OR (created_at = last_created_at AND id < last_id)
+            // SeaORM tuple comparison: (col1, col2) < (val1, val2)
+            // query = query.filter(
+            //    Condition::any()
+            //        .add(game::Column::CreatedAt.lt(last_created_at))
+            //        .add(
+            //            Condition::all()
+            //                .add(game::Column::CreatedAt.eq(last_created_at))
+            //                .add(game::Column::Id.lt(last_id))
+            //        )
+            // );
+            // Actually, SeaORM supports tuple comparison conveniently? 
+            // Not directly in the builder API widely in all versions, but the composite condition above is correct for (A, B) < (a, b) logic.
+            // However, tuple comparison `(A, B) < (a, b)` logic is standard SQL but SeaORM DSL is explicit.
+            
+            // Constructing: (created_at, id) < (last_created_at, last_id)
+            // Equivalent to: created_at < last_created_at OR (created_at = last_created_at AND id < last_id) (for DESC, DESC)
+            // WAIT! For DESC sort, "next page" means values SMALLER than cursor?
+            // Yes. Sorting DESC means newest first. Cursor is at some point. We want older stuff.
+            // So we want `created_at < cursor.created_at`.
+            // If created_at == cursor.created_at, then `id < cursor.id` (assuming ID also DESC).
+            
+            let condition = Condition::any()
+                .add(game::Column::CreatedAt.lt(last_created_at))
+                .add(
+                    Condition::all()
+                        .add(game::Column::CreatedAt.eq(last_created_at))
+                        .add(game::Column::Id.lt(last_id))
+                );
+            
+            query = query.filter(condition);
         }
 
-        // 3. Limit and Execution
+        // 2. Limit and Execution
         // Fetch limit + 1 to check if there is a next page
         let results = query.limit(limit + 1).all(db).await?;
 
         let mut games = results;
         let mut next_cursor: Option<String> = None;
 
+        // 3. Merge in archived games
+        if let Some(scope) = archive {
+            let scan = ArchiveService::scan(db, &scope, after, limit as usize + 1).await?;
+            games.extend(scan.games);
+            games.sort_by_key(|g| std::cmp::Reverse((g.created_at, g.id)));
+
+            if let Some((stopped_created_at, stopped_id)) = scan.stopped_at {
+                games.retain(|g| (DateTime::<Utc>::from(g.created_at), g.id) >= (stopped_created_at, stopped_id));
+                if games.len() as u64 <= limit {
+                    return Ok((games, Some(Self::encode_cursor(stopped_created_at, stopped_id))));
+                }
+            }
+        }
+
         if games.len() as u64 > limit {
             // We have a next page
             games.truncate(limit as usize);
@@ -153,9 +1504,9 @@ impl GameService {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
-    use sea_orm::{MockDatabase, DbBackend};
+    use sea_orm::{MockDatabase, DbBackend, MockExecResult};
     use chrono::FixedOffset;
 
     #[test]
@@ -180,8 +1531,8 @@ mod tests {
                 // First query result (empty list is fine, we check SQL)
                 vec![game::Model {
                     id: Uuid::new_v4(),
-                    white_player: Uuid::new_v4(),
-                    black_player: Uuid::new_v4(),
+                    white_player: Some(Uuid::new_v4()),
+                    black_player: Some(Uuid::new_v4()),
                     fen: "fen".to_string(),
                     pgn: serde_json::json!({}),
                     result: None,
@@ -192,6 +1543,17 @@ mod tests {
                     updated_at: Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap()),
                     is_imported: false,
                     original_pgn: None,
+                    import_hash: None,
+                    position_keys: vec![],
+                    status: GameRowStatus::InProgress,
+                    initial_time_ms: 600_000,
+                    increment_ms: 0,
+                    rated: true,
+                    termination: None,
+                    last_move_at: None,
+                    moves_encoded: None,
+                    initial_fen: chess::STARTING_FEN.to_string(),
+                    version: 0,
                 }],
             ])
             .into_connection();
@@ -235,8 +1597,8 @@ mod tests {
         let db = MockDatabase::new(DbBackend::Postgres)
             .append_query_results(vec![vec![game::Model {
                  id: Uuid::new_v4(),
-                    white_player: Uuid::new_v4(),
-                    black_player: Uuid::new_v4(),
+                    white_player: Some(Uuid::new_v4()),
+                    black_player: Some(Uuid::new_v4()),
                     fen: "fen".to_string(),
                     pgn: serde_json::json!({}),
                     result: None,
@@ -247,6 +1609,17 @@ mod tests {
                     updated_at: Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap()),
                     is_imported: false,
                     original_pgn: None,
+                    import_hash: None,
+                    position_keys: vec![],
+                    status: GameRowStatus::InProgress,
+                    initial_time_ms: 600_000,
+                    increment_ms: 0,
+                    rated: true,
+                    termination: None,
+                    last_move_at: None,
+                    moves_encoded: None,
+                    initial_fen: chess::STARTING_FEN.to_string(),
+                    version: 0,
             }]])
             .into_connection();
             
@@ -268,4 +1641,681 @@ mod tests {
         assert!(log_str.contains(r#"\"game\".\"created_at\" = $2"#));
         assert!(log_str.contains(r#"\"game\".\"id\" < $3"#));
     }
+
+    #[tokio::test]
+    async fn test_list_games_filters_on_status_column() {
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![Vec::<game::Model>::new()])
+            .into_connection();
+
+        GameService::list_games(&db, None, 10, None, Some(GameStatus::Aborted))
+            .await
+            .unwrap();
+
+        let log_str = format!("{:?}", db.into_transaction_log());
+        assert!(log_str.contains(r#"\"game\".\"status\" = (CAST($1 AS \"game_status\"))"#));
+        assert!(log_str.contains("aborted"));
+    }
+
+    pub(crate) fn seated_game(white: Option<Uuid>, black: Option<Uuid>) -> game::Model {
+        let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+        game::Model {
+            id: Uuid::new_v4(),
+            white_player: white,
+            black_player: black,
+            fen: chess::STARTING_FEN.to_string(),
+            pgn: serde_json::json!({ "moves": [], "final_ply": 0 }),
+            result: None,
+            variant: db_entity::game::GameVariant::Standard,
+            started_at: now,
+            duration_sec: 600,
+            created_at: now,
+            updated_at: now,
+            is_imported: false,
+            original_pgn: None,
+            import_hash: None,
+            position_keys: vec![],
+            status: if white.is_some() && black.is_some() {
+                GameRowStatus::InProgress
+            } else {
+                GameRowStatus::Waiting
+            },
+            initial_time_ms: 600_000,
+            increment_ms: 0,
+            rated: true,
+            termination: None,
+            last_move_at: None,
+            moves_encoded: None,
+            initial_fen: chess::STARTING_FEN.to_string(),
+            version: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_make_move_persists_move_and_fen() {
+        let white = Uuid::new_v4();
+        let mut game = seated_game(Some(white), Some(Uuid::new_v4()));
+        game.started_at -= chrono::Duration::seconds(5);
+        game.increment_ms = 2_000;
+
+        let mut updated = game.clone();
+        updated.fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1".to_string();
+        updated.pgn = serde_json::json!({ "moves": ["e4"], "final_ply": 1 });
+
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![game.clone()]])
+            .append_query_results(vec![vec![updated.clone()]])
+            .append_query_results(vec![Vec::<db_entity::game_event::Model>::new()])
+            .append_exec_results(vec![MockExecResult { last_insert_id: 0, rows_affected: 3 }])
+            .into_connection();
+        let (move_log, mut queued) = MoveLog::channel();
+
+        let outcome = GameService::make_move(&db, &move_log, game.id, white, "e4", Some(300), Some(1))
+            .await
+            .unwrap();
+        let MoveOutcome::Played { game: result, played, ended: false, rating_changes: None } = outcome else {
+            panic!("expected the move to be played");
+        };
+        assert_eq!(result.fen, updated.fen);
+        assert_eq!((played.uci.as_str(), played.san.as_str()), ("e2e4", "e4"));
+
+        let log_str = format!("{:?}", db.into_transaction_log());
+        assert!(log_str.contains("FOR UPDATE"));
+        assert!(log_str.contains(r#"UPDATE \"smdb\".\"game\""#));
+        assert!(log_str.contains(r#""clocks": Array"#));
+        assert!(log_str.contains(r#"\"moves_encoded\" = $"#));
+        assert!(log_str.contains(r#"\"version\" = $"#));
+        // A game from before the event log gets its history written first
+        assert!(log_str.contains(r#"INSERT INTO \"smdb\".\"game_event\""#));
+        assert!(log_str.contains(r#"String(Some("created")), Json(Some(Object"#));
+        assert!(log_str.contains(r#"String(Some("move_played"))"#));
+
+        let recorded = queued.try_recv().expect("move should be queued");
+        assert_eq!(recorded.move_number.unwrap(), 1);
+        assert_eq!(recorded.lag_compensation_ms.unwrap(), Some(300));
+        let spent = recorded.time_spent_ms.unwrap().unwrap();
+        assert!((4_700..5_700).contains(&spent));
+        assert_eq!(recorded.clock_ms.unwrap(), Some(600_000 - spent + 2_000));
+    }
+
+    #[tokio::test]
+    async fn test_retrying_the_final_move_does_not_end_the_game_again() {
+        let black = Uuid::new_v4();
+        let mut game = seated_game(Some(Uuid::new_v4()), Some(black));
+        game.fen = "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3".to_string();
+        game.pgn = serde_json::json!({ "moves": ["f3", "e5", "g4", "Qh4#"], "final_ply": 4 });
+        game.status = GameRowStatus::Completed;
+        game.result = Some(ResultSide::BlackWins);
+        game.termination = Some("checkmate".to_string());
+
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![game.clone()]])
+            .into_connection();
+        let (move_log, _queued) = MoveLog::channel();
+
+        let outcome = GameService::make_move(&db, &move_log, game.id, black, "d8h4", None, Some(4))
+            .await
+            .unwrap();
+        assert!(matches!(outcome, MoveOutcome::Played { ended: false, rating_changes: None, .. }));
+    }
+
+    #[tokio::test]
+    async fn test_make_move_retry_is_idempotent_and_stale_ply_conflicts() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut game = seated_game(Some(white), Some(black));
+        game.fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1".to_string();
+        game.pgn = serde_json::json!({ "moves": ["e4"], "final_ply": 1 });
+        game.version = 3;
+
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![game.clone()], vec![game.clone()], vec![game.clone()]])
+            .into_connection();
+        let (move_log, mut queued) = MoveLog::channel();
+
+        // The same move for the same ply is a retry
+        let outcome = GameService::make_move(&db, &move_log, game.id, white, "e2e4", None, Some(1))
+            .await
+            .unwrap();
+        let MoveOutcome::Played { game: result, played, ended, .. } = outcome else {
+            panic!("expected the move to be played");
+        };
+        assert_eq!(result, game);
+        assert_eq!(played.san, "e4");
+        assert!(!ended);
+        assert!(queued.try_recv().is_err());
+
+        let err = GameService::make_move(&db, &move_log, game.id, white, "d2d4", None, Some(1))
+            .await
+            .unwrap_err();
+        let ApiError::Stale { message, current } = err else { panic!("expected a stale move") };
+        assert_eq!(message, "Ply 1 has already been played");
+        assert_eq!((current["ply"].as_i64(), current["version"].as_i64()), (Some(1), Some(3)));
+
+        let err = GameService::make_move(&db, &move_log, game.id, black, "e7e5", None, Some(3))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ApiError::Stale { message, .. } if message == "Expected ply 3 but the game is at ply 1"));
+
+        let log_str = format!("{:?}", db.into_transaction_log());
+        assert!(!log_str.contains(r#"UPDATE \"smdb\""#));
+    }
+
+    #[tokio::test]
+    async fn test_make_move_validates_against_chess960_start() {
+        let white = Uuid::new_v4();
+        let mut game = seated_game(Some(white), Some(Uuid::new_v4()));
+        game.variant = GameVariant::Chess960;
+        game.initial_fen = chess::chess960_fen(0).unwrap();
+        game.fen = game.initial_fen.clone();
+
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![game.clone()], vec![game.clone()], vec![game.clone()]])
+            .append_query_results(vec![Vec::<db_entity::game_event::Model>::new()])
+            .append_exec_results(vec![MockExecResult { last_insert_id: 0, rows_affected: 3 }])
+            .into_connection();
+        let (move_log, mut queued) = MoveLog::channel();
+
+        // The standard-position move g1f3 has no knight to move here
+        let err = GameService::make_move(&db, &move_log, game.id, white, "g1f3", None, None).await.unwrap_err();
+        assert!(matches!(err, ApiError::BadRequest(_)));
+
+        GameService::make_move(&db, &move_log, game.id, white, "d1c3", None, None).await.unwrap();
+        assert_eq!(queued.try_recv().unwrap().san.unwrap(), "Nc3");
+    }
+
+    #[test]
+    fn test_resolve_start_position() {
+        use StartPosition::*;
+
+        let (variant, fen) = GameService::resolve_start(None, None).unwrap();
+        assert_eq!((variant, fen.as_str()), (GameVariant::Standard, chess::STARTING_FEN));
+
+        let (variant, fen) = GameService::resolve_start(None, Some(&Chess960 { number: 0 })).unwrap();
+        assert_eq!(variant, GameVariant::Chess960);
+        assert!(fen.starts_with("bbqnnrkr/"));
+
+        let (variant, fen) = GameService::resolve_start(Some("chess960"), None).unwrap();
+        assert_eq!(variant, GameVariant::Chess960);
+        assert!(chess::legal_moves(&fen).is_ok());
+
+        let custom = Fen { fen: "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1".to_string() };
+        let (variant, fen) = GameService::resolve_start(None, Some(&custom)).unwrap();
+        assert_eq!((variant, fen.as_str()), (GameVariant::Standard, "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"));
+
+        for (variant, start) in [
+            (Some("standard"), Some(RandomChess960)),
+            (Some("chess960"), Some(Chess960 { number: 960 })),
+            (Some("three_check"), None),
+            (None, Some(Fen { fen: "8/8/8/8/8/8/8/8 w - - 0 1".to_string() })),
+            // Black is already checkmated
+            (None, Some(Fen { fen: "R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1".to_string() })),
+        ] {
+            assert!(matches!(
+                GameService::resolve_start(variant, start.as_ref()),
+                Err(ApiError::BadRequest(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_make_move_after_flag_fall_loses_on_time() {
+        let white = Uuid::new_v4();
+        let mut game = seated_game(Some(white), Some(Uuid::new_v4()));
+        game.started_at -= chrono::Duration::minutes(11);
+
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![game.clone()], vec![game.clone()]])
+            .append_query_results(vec![Vec::<db_entity::game_event::Model>::new()])
+            .append_query_results(vec![Vec::<db_entity::game_player_state::Model>::new()])
+            .append_query_results(vec![Vec::<db_entity::game_event::Model>::new()])
+            .append_exec_results(vec![
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+                MockExecResult { last_insert_id: 0, rows_affected: 3 },
+            ])
+            .into_connection();
+        let (move_log, mut queued) = MoveLog::channel();
+
+        let outcome = GameService::make_move(&db, &move_log, game.id, white, "e2e4", Some(5_000), None)
+            .await
+            .unwrap();
+        // Too short a game to be rated
+        assert!(matches!(outcome, MoveOutcome::Flagged { rating_changes: None, .. }));
+        assert!(queued.try_recv().is_err());
+
+        let log_str = format!("{:?}", db.into_transaction_log());
+        assert!(log_str.contains("time_forfeit"));
+        assert!(log_str.contains("black_wins"));
+        // The transition is audited in the same transaction
+        assert!(log_str.contains(r#"INSERT INTO \"smdb\".\"game_transition\""#));
+        assert!(log_str.contains(r#"String(Some("started")), String(Some("ended")), String(Some("black_wins")), String(Some("time_forfeit")), String(Some("rest"))"#));
+    }
+
+    #[tokio::test]
+    async fn test_abandoning_finished_game_is_rejected() {
+        let white = Uuid::new_v4();
+        let mut game = seated_game(Some(white), Some(Uuid::new_v4()));
+        game.status = GameRowStatus::Completed;
+        game.result = Some(ResultSide::Draw);
+
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![game.clone()]])
+            .into_connection();
+
+        let err = GameService::abandon_game(&db, game.id, white).await.unwrap_err();
+        assert!(matches!(err, ApiError::Conflict(msg) if msg == "Game is already finished"));
+    }
+
+    #[tokio::test]
+    async fn test_abandoning_started_game_loses_and_is_rated() {
+        let white = Uuid::new_v4();
+        let mut game = seated_game(Some(white), Some(Uuid::new_v4()));
+        game.pgn = serde_json::json!({ "moves": ["e4", "e5", "Nf3", "Nc6"], "final_ply": 4 });
+        let mut ended = game.clone();
+        ended.status = GameRowStatus::Completed;
+        ended.result = Some(ResultSide::BlackWins);
+        ended.termination = Some("abandonment".to_string());
+
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![game.clone()], vec![ended]])
+            .append_query_results(vec![Vec::<db_entity::player_rating::Model>::new(), Vec::new()])
+            .append_query_results(vec![Vec::<db_entity::game_event::Model>::new()])
+            .append_query_results(vec![Vec::<db_entity::game_player_state::Model>::new()])
+            .append_query_results(vec![Vec::<db_entity::game_event::Model>::new()])
+            .append_exec_results((0..8).map(|_| MockExecResult { last_insert_id: 0, rows_affected: 1 }))
+            .into_connection();
+
+        let (result, rating_changes) = GameService::abandon_game(&db, game.id, white).await.unwrap();
+        assert_eq!(result.result, Some(ResultSide::BlackWins));
+        let changes = rating_changes.expect("a long enough game is rated");
+        assert!(changes.white.rating_change < 0);
+
+        let log_str = format!("{:?}", db.into_transaction_log());
+        assert!(log_str.contains(r#"String(Some("started")), String(Some("ended")), String(Some("black_wins")), String(Some("abandonment")), String(Some("rest"))"#));
+        assert!(log_str.contains(r#"INSERT INTO \"smdb\".\"rating_history\""#));
+    }
+
+    #[tokio::test]
+    async fn test_abandoning_short_game_aborts_it() {
+        let white = Uuid::new_v4();
+        let game = seated_game(Some(white), Some(Uuid::new_v4()));
+        let mut aborted = game.clone();
+        aborted.status = GameRowStatus::Aborted;
+        aborted.result = Some(ResultSide::Abandoned);
+
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![game.clone()], vec![aborted]])
+            .append_query_results(vec![Vec::<db_entity::game_event::Model>::new()])
+            .append_query_results(vec![Vec::<db_entity::game_player_state::Model>::new()])
+            .append_query_results(vec![Vec::<db_entity::game_event::Model>::new()])
+            .append_exec_results((0..2).map(|_| MockExecResult { last_insert_id: 0, rows_affected: 1 }))
+            .into_connection();
+
+        let (result, rating_changes) = GameService::abandon_game(&db, game.id, white).await.unwrap();
+        assert_eq!(result.status, GameRowStatus::Aborted);
+        assert!(rating_changes.is_none());
+        let log_str = format!("{:?}", db.into_transaction_log());
+        assert!(log_str.contains(r#"String(Some("started")), String(Some("aborted"))"#));
+    }
+
+    #[tokio::test]
+    async fn test_adjudicating_records_the_tournament_ruling() {
+        let game = seated_game(Some(Uuid::new_v4()), Some(Uuid::new_v4()));
+        let mut adjudicated = game.clone();
+        adjudicated.status = GameRowStatus::Adjudicated;
+        adjudicated.result = Some(ResultSide::Draw);
+        adjudicated.termination = Some("adjudication".to_string());
+
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![game.clone()], vec![adjudicated]])
+            .append_query_results(vec![Vec::<db_entity::game_event::Model>::new()])
+            .append_query_results(vec![Vec::<db_entity::game_player_state::Model>::new()])
+            .append_query_results(vec![Vec::<db_entity::game_event::Model>::new()])
+            .append_exec_results((0..2).map(|_| MockExecResult { last_insert_id: 0, rows_affected: 1 }))
+            .into_connection();
+
+        let arbiter = Uuid::new_v4();
+        let (result, _) =
+            GameService::adjudicate_game(&db, game.id, Outcome::Draw, TransitionSource::Tournament, Some(arbiter))
+                .await
+                .unwrap();
+        assert_eq!(result.status, GameRowStatus::Adjudicated);
+
+        let log_str = format!("{:?}", db.into_transaction_log());
+        assert!(log_str.contains(r#"String(Some("started")), String(Some("adjudicated")), String(Some("draw")), String(Some("adjudication")), String(Some("tournament"))"#));
+        assert!(log_str.contains(&format!("Uuid(Some({}))", arbiter)));
+    }
+
+    #[test]
+    fn test_move_timing_uses_movers_previous_clock() {
+        let mut game = seated_game(Some(Uuid::new_v4()), Some(Uuid::new_v4()));
+        let now = Utc::now();
+        game.last_move_at = Some((now - chrono::Duration::seconds(3)).into());
+        game.increment_ms = 1_000;
+        let clocks = vec![serde_json::json!(50_000), serde_json::json!(40_000)];
+
+        // Lag above the cap is only partly credited
+        let timing = GameService::move_timing(&game, &clocks, 3, now, Some(5_000)).unwrap();
+        assert_eq!(
+            timing,
+            MoveTiming::Played {
+                clock_ms: 50_000 - 2_000 + 1_000,
+                time_spent_ms: 2_000,
+                lag_compensation_ms: MAX_LAG_COMPENSATION_MS,
+            }
+        );
+
+        game.initial_time_ms = 0;
+        assert!(GameService::move_timing(&game, &clocks, 3, now, None).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_make_move_rejects_wrong_turn_and_outsiders() {
+        let black = Uuid::new_v4();
+        let game = seated_game(Some(Uuid::new_v4()), Some(black));
+
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![game.clone()], vec![game.clone()]])
+            .into_connection();
+        let (move_log, _queued) = MoveLog::channel();
+
+        let err = GameService::make_move(&db, &move_log, game.id, black, "e7e5", None, None).await.unwrap_err();
+        assert!(matches!(err, ApiError::Conflict(_)));
+
+        let err = GameService::make_move(&db, &move_log, game.id, Uuid::new_v4(), "e2e4", None, None).await.unwrap_err();
+        assert!(matches!(err, ApiError::Forbidden(_)));
+    }
+
+    #[tokio::test]
+    async fn test_make_move_rejects_illegal_move() {
+        let white = Uuid::new_v4();
+        let game = seated_game(Some(white), Some(Uuid::new_v4()));
+
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![game.clone()]])
+            .into_connection();
+        let (move_log, _queued) = MoveLog::channel();
+
+        let err = GameService::make_move(&db, &move_log, game.id, white, "e2e5", None, None).await.unwrap_err();
+        assert!(matches!(err, ApiError::BadRequest(_)));
+    }
+
+    #[tokio::test]
+    async fn test_join_full_game_conflicts() {
+        let joiner = Uuid::new_v4();
+        let game = seated_game(Some(Uuid::new_v4()), Some(Uuid::new_v4()));
+        let player = db_entity::player::Model {
+            id: joiner,
+            username: "joiner".to_string(),
+            email: "joiner@example.com".to_string(),
+            password_hash: vec![],
+            biography: String::new(),
+            country: String::new(),
+            flair: String::new(),
+            real_name: String::new(),
+            location: None,
+            fide_rating: None,
+            social_links: None,
+            is_enabled: true,
+        };
+
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![player]])
+            .append_query_results(vec![vec![game.clone()]])
+            .into_connection();
+
+        let err = GameService::join_game(&db, game.id, joiner).await.unwrap_err();
+        assert!(matches!(err, ApiError::Conflict(_)));
+    }
+
+    #[tokio::test]
+    async fn test_join_records_starting_ratings() {
+        let (white, joiner) = (Uuid::new_v4(), Uuid::new_v4());
+        let game = seated_game(Some(white), None);
+        let player = db_entity::player::Model {
+            id: joiner,
+            username: "joiner".to_string(),
+            email: "joiner@example.com".to_string(),
+            password_hash: vec![],
+            biography: String::new(),
+            country: String::new(),
+            flair: String::new(),
+            real_name: String::new(),
+            location: None,
+            fide_rating: None,
+            social_links: None,
+            is_enabled: true,
+        };
+        let rating = db_entity::player_rating::Model {
+            player_id: white,
+            category: RatingCategory::Rapid,
+            rating: 1712.4,
+            deviation: 80.0,
+            volatility: 0.06,
+            last_rated_at: None,
+            games_played: 30,
+        };
+
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![player]])
+            .append_query_results(vec![vec![game.clone()]])
+            .append_query_results(vec![vec![rating], Vec::new()])
+            .append_query_results(vec![vec![game.clone()]])
+            .append_query_results(vec![Vec::<db_entity::game_event::Model>::new()])
+            .append_exec_results((0..2).map(|_| MockExecResult { last_insert_id: 0, rows_affected: 1 }))
+            .into_connection();
+
+        GameService::join_game(&db, game.id, joiner).await.unwrap();
+        let log_str = format!("{:?}", db.into_transaction_log());
+        assert!(log_str.contains(r#""white_rating": Number(1712)"#));
+        assert!(log_str.contains(r#""black_rating": Number(1500)"#));
+    }
+
+    #[tokio::test]
+    async fn test_get_missing_game_is_not_found() {
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![Vec::<game::Model>::new()])
+            .append_query_results(vec![Vec::<db_entity::game_archive::Model>::new()])
+            .into_connection();
+
+        let err = GameService::get_game(&db, Uuid::new_v4()).await.unwrap_err();
+        assert!(matches!(err, ApiError::NotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn test_repeated_import_is_duplicate() {
+        let pgn = "[White \"A\"]\n[Black \"B\"]\n[Result \"1-0\"]\n\n1. e4 e5 1-0";
+        let mut existing = seated_game(None, None);
+        existing.is_imported = true;
+
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![existing.clone()]])
+            .into_connection();
+
+        match GameService::import_pgn(&db, pgn).await.unwrap() {
+            ImportStatus::Duplicate(id) => assert_eq!(id, existing.id),
+            other => panic!("expected duplicate, got {:?}", other),
+        }
+
+        let log_str = format!("{:?}", db.into_transaction_log());
+        assert!(log_str.contains(r#"\"game\".\"import_hash\" = $1"#));
+    }
+
+    #[test]
+    fn test_parse_time_control_header() {
+        assert_eq!(GameService::parse_time_control("600+5"), (600, 5));
+        assert_eq!(GameService::parse_time_control("300"), (300, 0));
+        assert_eq!(GameService::parse_time_control("-"), (0, 0));
+    }
+
+    #[test]
+    fn test_game_to_pgn_uses_usernames_and_annotations() {
+        let white = Uuid::new_v4();
+        let mut game = seated_game(Some(white), None);
+        game.is_imported = true;
+        game.result = Some(ResultSide::WhiteWins);
+        game.pgn = serde_json::json!({
+            "black": "Guest",
+            "moves": ["e4", "e5"],
+            "clocks": [59000, 58000],
+            "comments": ["Opening", null],
+        });
+        game.increment_ms = 2000;
+        game.status = GameRowStatus::Completed;
+        game.termination = Some("checkmate".to_string());
+        let names = HashMap::from([(white, "alice".to_string())]);
+
+        let options = ExportGameQuery { clocks: true, evals: false, comments: false };
+        let pgn = GameService::game_to_pgn(&game, &names, &options);
+        assert!(pgn.contains("[White \"alice\"]"));
+        assert!(pgn.contains("[Black \"Guest\"]"));
+        assert!(pgn.contains("[TimeControl \"600+2\"]"));
+        assert!(pgn.contains("[Termination \"checkmate\"]"));
+        assert!(pgn.contains("1. e4 {[%clk 0:00:59]} 1... e5 {[%clk 0:00:58]} 1-0"));
+        assert!(!pgn.contains("Opening"));
+        assert!(!pgn.contains("[FEN"));
+
+        game.variant = GameVariant::Chess960;
+        game.initial_fen = chess::chess960_fen(0).unwrap();
+        game.pgn = serde_json::json!({ "moves": ["Nc3"] });
+        let pgn = GameService::game_to_pgn(&game, &names, &options);
+        assert!(pgn.contains("[Variant \"Chess960\"]"));
+        assert!(pgn.contains("[SetUp \"1\"]"));
+        assert!(pgn.contains(&format!("[FEN \"{}\"]", game.initial_fen)));
+    }
+
+    #[tokio::test]
+    async fn test_list_player_games_applies_archive_filters() {
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![Vec::<game::Model>::new()])
+            .append_query_results(vec![Vec::<db_entity::game_archive::Model>::new()])
+            .into_connection();
+
+        let query = PlayerGamesExportQuery {
+            since: Some(Utc::now() - chrono::Duration::days(30)),
+            variant: Some("chess960".to_string()),
+            result: Some("draw".to_string()),
+            ..Default::default()
+        };
+        let filter = ArchiveFilter::try_from(&query).unwrap();
+        let (games, next) = GameService::list_player_games(&db, Uuid::new_v4(), &filter, None, 50)
+            .await
+            .unwrap();
+        assert!(games.is_empty());
+        assert!(next.is_none());
+
+        let log_str = format!("{:?}", db.into_transaction_log());
+        println!("Log: {}", log_str);
+        assert!(log_str.contains(r#"\"game\".\"started_at\" >= $3"#));
+        assert!(log_str.contains(r#"\"game\".\"variant\" = (CAST($4 AS \"game_variant\"))"#));
+        assert!(log_str.contains(r#"\"game\".\"result\" = (CAST($5 AS \"result_side\"))"#));
+        assert!(log_str.contains(r#"ORDER BY \"game\".\"created_at\" DESC, \"game\".\"id\" DESC"#));
+        assert!(log_str.contains(r#"\"game_archive\".\"started_at\" >= $3"#));
+    }
+
+    #[tokio::test]
+    async fn test_list_player_games_merges_archived_games() {
+        use crate::archive::tests::{archived, finished_game};
+
+        let archived_match = game::Model { variant: GameVariant::Chess960, ..finished_game() };
+        let archived_other = finished_game();
+        let live = game::Model {
+            id: Uuid::new_v4(),
+            created_at: archived_match.created_at + chrono::Duration::days(20),
+            ..archived_match.clone()
+        };
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![live.clone()]])
+            .append_query_results(vec![vec![archived(&archived_match), archived(&archived_other)]])
+            .into_connection();
+
+        let query = PlayerGamesExportQuery { variant: Some("chess960".to_string()), ..Default::default() };
+        let filter = ArchiveFilter::try_from(&query).unwrap();
+        let (games, next) = GameService::list_player_games(&db, Uuid::new_v4(), &filter, None, 50)
+            .await
+            .unwrap();
+        let ids: Vec<Uuid> = games.iter().map(|g| g.id).collect();
+        assert_eq!(ids, vec![live.id, archived_match.id]);
+        assert!(next.is_none());
+
+        let log_str = format!("{:?}", db.into_transaction_log());
+        assert!(log_str.contains(r#"FROM \"smdb\".\"game_archive\""#));
+        assert!(log_str.contains(r#"ORDER BY \"game_archive\".\"created_at\" DESC, \"game_archive\".\"id\" DESC"#));
+    }
+
+    #[test]
+    fn test_archive_filter_rejects_unknown_values() {
+        let query = PlayerGamesExportQuery { variant: Some("bughouse".to_string()), ..Default::default() };
+        assert!(matches!(ArchiveFilter::try_from(&query), Err(ApiError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_search_games_uses_indexed_expressions() {
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![Vec::<game::Model>::new()])
+            .append_query_results(vec![Vec::<db_entity::game_archive::Model>::new()])
+            .into_connection();
+
+        let query = GameSearchQuery {
+            eco: Some("b9".to_string()),
+            opening: Some("Sicilian 100%".to_string()),
+            min_rating: Some(1800),
+            speed: Some("blitz".to_string()),
+            min_moves: Some(20),
+            max_moves: Some(40),
+            fen: Some(chess::STARTING_FEN.to_string()),
+            ..Default::default()
+        };
+        let filter = GameSearchFilter::try_from(&query).unwrap();
+        assert_eq!(filter.eco.as_deref(), Some("B9"));
+        let (games, _) = GameService::search_games(&db, &filter, None, 10).await.unwrap();
+        assert!(games.is_empty());
+
+        let log_str = format!("{:?}", db.into_transaction_log());
+        println!("Log: {}", log_str);
+        assert!(log_str.contains(r#"(\"pgn\" ->> 'eco') LIKE $1"#));
+        assert!(log_str.contains(r#"lower(\"pgn\" ->> 'opening') LIKE $2"#));
+        assert!(log_str.contains(r#"sicilian 100\\%%"#));
+        assert!(log_str.contains(r#"/ 2) >= $3"#));
+        assert!(log_str.contains(r#"(\"initial_time_ms\" + 40 * \"increment_ms\") >= $4"#));
+        assert!(log_str.contains("BigInt(Some(180000))"));
+        assert!(log_str.contains(r#"((\"pgn\" ->> 'final_ply')::int) >= $6"#));
+        assert!(log_str.contains("Int(Some(39))"));
+        assert!(log_str.contains("Int(Some(80))"));
+        assert!(log_str.contains(r#"\"position_keys\" @> $8"#));
+    }
+
+    #[test]
+    fn test_search_filter_validation() {
+        let both = GameSearchQuery {
+            fen: Some(chess::STARTING_FEN.to_string()),
+            zobrist: Some(1),
+            ..Default::default()
+        };
+        assert!(matches!(GameSearchFilter::try_from(&both), Err(ApiError::BadRequest(_))));
+
+        let bad_fen = GameSearchQuery { fen: Some("not a fen".to_string()), ..Default::default() };
+        assert!(matches!(GameSearchFilter::try_from(&bad_fen), Err(ApiError::BadRequest(_))));
+
+        let inverted = GameSearchQuery { min_rating: Some(2000), max_rating: Some(1500), ..Default::default() };
+        assert!(matches!(GameSearchFilter::try_from(&inverted), Err(ApiError::BadRequest(_))));
+
+        let speed = GameSearchQuery { speed: Some("hyperbullet".to_string()), ..Default::default() };
+        assert!(matches!(GameSearchFilter::try_from(&speed), Err(ApiError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_search_move_bounds_do_not_overflow() {
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![Vec::<game::Model>::new()])
+            .append_query_results(vec![Vec::<db_entity::game_archive::Model>::new()])
+            .into_connection();
+
+        let query = GameSearchQuery { min_moves: Some(i32::MAX), max_moves: Some(i32::MAX), ..Default::default() };
+        let filter = GameSearchFilter::try_from(&query).unwrap();
+        GameService::search_games(&db, &filter, None, 10).await.unwrap();
+
+        let log_str = format!("{:?}", db.into_transaction_log());
+        assert!(log_str.contains(&format!("Int(Some({}))", i32::MAX - 1)));
+        assert!(log_str.contains(&format!("Int(Some({}))", i32::MAX)));
+    }
 }
@@ -0,0 +1,137 @@
+//! Background jobs for bulk PGN imports.
+//!
+//! A multi-game upload is split into games up front and processed one game
+//! at a time on a spawned task. Progress is kept in memory and polled by
+//! job ID.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use chrono::{Duration, Utc};
+use dto::games::{BulkImportError, BulkImportJob, ImportJobState};
+use sea_orm::DatabaseConnection;
+use uuid::Uuid;
+
+use crate::games::{GameService, ImportStatus};
+
+/// How long finished jobs stay available for polling
+const FINISHED_JOB_RETENTION_MINUTES: i64 = 60;
+
+/// Registry of bulk import jobs, shared across workers
+#[derive(Clone, Default)]
+pub struct ImportJobs {
+    jobs: Arc<RwLock<HashMap<Uuid, BulkImportJob>>>,
+}
+
+impl ImportJobs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue already split PGN games and import them in the background.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn start(&self, db: Arc<DatabaseConnection>, games: Vec<String>) -> BulkImportJob {
+        let job = BulkImportJob {
+            id: Uuid::new_v4(),
+            state: ImportJobState::Queued,
+            total: games.len(),
+            processed: 0,
+            imported: 0,
+            duplicates: 0,
+            failed: 0,
+            errors: Vec::new(),
+            created_at: Utc::now(),
+            finished_at: None,
+        };
+
+        {
+            let mut jobs = self.jobs.write().unwrap();
+            let cutoff = Utc::now() - Duration::minutes(FINISHED_JOB_RETENTION_MINUTES);
+            jobs.retain(|_, j| j.finished_at.is_none_or(|finished| finished > cutoff));
+            jobs.insert(job.id, job.clone());
+        }
+
+        let registry = self.clone();
+        let job_id = job.id;
+        tokio::spawn(async move { registry.run(db, job_id, games).await });
+
+        job
+    }
+
+    /// Current progress of a job
+    pub fn get(&self, job_id: Uuid) -> Option<BulkImportJob> {
+        self.jobs.read().unwrap().get(&job_id).cloned()
+    }
+
+    async fn run(&self, db: Arc<DatabaseConnection>, job_id: Uuid, games: Vec<String>) {
+        self.update(job_id, |job| job.state = ImportJobState::Running);
+
+        for (index, pgn) in games.iter().enumerate() {
+            let outcome = GameService::import_pgn(&db, pgn).await;
+
+            self.update(job_id, |job| {
+                job.processed += 1;
+                match outcome {
+                    Ok(ImportStatus::Imported(_)) => job.imported += 1,
+                    Ok(ImportStatus::Duplicate(_)) => job.duplicates += 1,
+                    Err(err) => {
+                        let headers = chess::parse_pgn(pgn).ok().map(|p| p.headers);
+                        job.failed += 1;
+                        job.errors.push(BulkImportError {
+                            index,
+                            white_player: headers.as_ref().map(|h| h.white.clone()),
+                            black_player: headers.as_ref().map(|h| h.black.clone()),
+                            error: err.to_string(),
+                        });
+                    }
+                }
+            });
+        }
+
+        self.update(job_id, |job| {
+            job.state = ImportJobState::Completed;
+            job.finished_at = Some(Utc::now());
+        });
+    }
+
+    fn update(&self, job_id: Uuid, apply: impl FnOnce(&mut BulkImportJob)) {
+        if let Some(job) = self.jobs.write().unwrap().get_mut(&job_id) {
+            apply(job);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{DbBackend, MockDatabase};
+
+    #[tokio::test]
+    async fn test_job_reports_per_game_errors() {
+        // Neither game reaches the database: one is missing headers, the other has an illegal move
+        let upload = "[Black \"B\"]\n\n1. e4 *\n\n[White \"C\"]\n[Black \"D\"]\n\n1. e4 e5 2. Ke3 *\n";
+        let games = chess::split_pgn_games(upload);
+        let db = Arc::new(MockDatabase::new(DbBackend::Postgres).into_connection());
+
+        let jobs = ImportJobs::new();
+        let job = jobs.start(db, games);
+        assert_eq!(job.total, 2);
+
+        let finished = loop {
+            let current = jobs.get(job.id).unwrap();
+            if current.state == ImportJobState::Completed {
+                break current;
+            }
+            tokio::task::yield_now().await;
+        };
+
+        assert_eq!(finished.processed, 2);
+        assert_eq!(finished.failed, 2);
+        assert_eq!(finished.imported, 0);
+        assert_eq!(finished.errors[0].index, 0);
+        assert_eq!(finished.errors[1].index, 1);
+        assert_eq!(finished.errors[1].white_player.as_deref(), Some("C"));
+        assert!(finished.errors[1].error.contains("Ke3"));
+    }
+}
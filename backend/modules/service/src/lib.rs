@@ -1,4 +1,11 @@
 pub mod helper;
 pub mod players;
 pub mod engine_service;
+pub mod archive;
+pub mod clocks;
+pub mod game_events;
 pub mod games;
+pub mod imports;
+pub mod lifecycle;
+pub mod move_log;
+pub mod ratings;
@@ -0,0 +1,418 @@
+//! Game lifecycle state machine.
+//!
+//! Every change to where a game stands (waiting for an opponent, being
+//! played, finished) goes through `LifecycleState::transition`, which rejects
+//! moves the rules do not allow:
+//!
+//! ```text
+//! created -> waiting | started
+//! waiting -> started | aborted
+//! started -> ended | aborted | adjudicated
+//! ```
+//!
+//! Each accepted transition becomes a `LifecycleEvent`. Persisted games write
+//! the event to `game_transition` inside the transaction that changes the
+//! game row, then publish it to in-process subscribers once committed.
+
+use std::fmt;
+use std::sync::LazyLock;
+
+use chrono::{DateTime, Utc};
+use db_entity::game::{self, GameStatus, ResultSide};
+use db_entity::{game_transition, prelude::GameTransition};
+use error::error::ApiError;
+use sea_orm::{ActiveValue::Set, ConnectionTrait, EntityTrait};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// Events kept for subscribers that fall behind
+const EVENT_BUFFER: usize = 1024;
+
+static EVENTS: LazyLock<broadcast::Sender<LifecycleEvent>> =
+    LazyLock::new(|| broadcast::channel(EVENT_BUFFER).0);
+
+/// Decided result of a game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl Outcome {
+    /// A win for White if `white`, otherwise for Black
+    pub fn win_for(white: bool) -> Self {
+        if white {
+            Outcome::WhiteWins
+        } else {
+            Outcome::BlackWins
+        }
+    }
+
+    pub fn from_pgn(result: &chess::PgnGameResult) -> Option<Self> {
+        match result {
+            chess::PgnGameResult::WhiteWins => Some(Outcome::WhiteWins),
+            chess::PgnGameResult::BlackWins => Some(Outcome::BlackWins),
+            chess::PgnGameResult::Draw => Some(Outcome::Draw),
+            chess::PgnGameResult::Ongoing => None,
+        }
+    }
+}
+
+impl From<Outcome> for ResultSide {
+    fn from(value: Outcome) -> Self {
+        match value {
+            Outcome::WhiteWins => ResultSide::WhiteWins,
+            Outcome::BlackWins => ResultSide::BlackWins,
+            Outcome::Draw => ResultSide::Draw,
+        }
+    }
+}
+
+/// Why a game that was played out ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndReason {
+    Checkmate,
+    Stalemate,
+    InsufficientMaterial,
+    TimeForfeit,
+    Resignation,
+    DrawAgreement,
+    /// A player left a started game
+    Abandonment,
+    /// Decided on the board without further detail, as in PGN's "Normal"
+    Normal,
+}
+
+impl EndReason {
+    /// Value stored in the game's `termination` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EndReason::Checkmate => "checkmate",
+            EndReason::Stalemate => "stalemate",
+            EndReason::InsufficientMaterial => "insufficient_material",
+            EndReason::TimeForfeit => "time_forfeit",
+            EndReason::Resignation => "resignation",
+            EndReason::DrawAgreement => "draw_agreement",
+            EndReason::Abandonment => "abandonment",
+            EndReason::Normal => "normal",
+        }
+    }
+
+    /// Read a stored termination, treating unknown values as `Normal`
+    pub fn from_termination(termination: &str) -> Self {
+        match termination {
+            "checkmate" => EndReason::Checkmate,
+            "stalemate" => EndReason::Stalemate,
+            "insufficient_material" => EndReason::InsufficientMaterial,
+            "time_forfeit" => EndReason::TimeForfeit,
+            "resignation" => EndReason::Resignation,
+            "draw_agreement" => EndReason::DrawAgreement,
+            "abandonment" => EndReason::Abandonment,
+            _ => EndReason::Normal,
+        }
+    }
+}
+
+impl From<chess::Termination> for EndReason {
+    fn from(value: chess::Termination) -> Self {
+        match value {
+            chess::Termination::Checkmate => EndReason::Checkmate,
+            chess::Termination::Stalemate => EndReason::Stalemate,
+            chess::Termination::InsufficientMaterial => EndReason::InsufficientMaterial,
+        }
+    }
+}
+
+/// Where a game stands in its lifecycle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum LifecycleState {
+    /// Being set up; never stored
+    Created,
+    /// Waiting for a second player
+    Waiting,
+    /// Both seats taken, moves can be played
+    Started,
+    /// Finished over the board or on the clock
+    Ended { result: Outcome, reason: EndReason },
+    /// Called off before a result, e.g. a player abandoned it
+    Aborted,
+    /// Result decided by an arbiter or tournament director
+    Adjudicated { result: Outcome },
+}
+
+/// Termination stored for aborted games
+const ABANDONED: &str = "abandoned";
+/// Termination stored for adjudicated games, as in PGN
+const ADJUDICATION: &str = "adjudication";
+
+impl LifecycleState {
+    /// Read the state of a stored game
+    pub fn of(game: &game::Model) -> Self {
+        let result = match game.result {
+            Some(ResultSide::WhiteWins) => Outcome::WhiteWins,
+            Some(ResultSide::BlackWins) => Outcome::BlackWins,
+            _ => Outcome::Draw,
+        };
+        match game.status {
+            GameStatus::Waiting => LifecycleState::Waiting,
+            GameStatus::InProgress => LifecycleState::Started,
+            GameStatus::Completed => LifecycleState::Ended {
+                result,
+                reason: EndReason::from_termination(game.termination.as_deref().unwrap_or_default()),
+            },
+            GameStatus::Aborted => LifecycleState::Aborted,
+            GameStatus::Adjudicated => LifecycleState::Adjudicated { result },
+        }
+    }
+
+    /// Short name used in the audit table
+    pub fn name(&self) -> &'static str {
+        match self {
+            LifecycleState::Created => "created",
+            LifecycleState::Waiting => "waiting",
+            LifecycleState::Started => "started",
+            LifecycleState::Ended { .. } => "ended",
+            LifecycleState::Aborted => "aborted",
+            LifecycleState::Adjudicated { .. } => "adjudicated",
+        }
+    }
+
+    /// No further transitions are possible
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            LifecycleState::Ended { .. } | LifecycleState::Aborted | LifecycleState::Adjudicated { .. }
+        )
+    }
+
+    pub fn result(&self) -> Option<Outcome> {
+        match self {
+            LifecycleState::Ended { result, .. } | LifecycleState::Adjudicated { result } => Some(*result),
+            _ => None,
+        }
+    }
+
+    /// Value for the game's `termination` column
+    pub fn termination(&self) -> Option<&'static str> {
+        match self {
+            LifecycleState::Ended { reason, .. } => Some(reason.as_str()),
+            LifecycleState::Aborted => Some(ABANDONED),
+            LifecycleState::Adjudicated { .. } => Some(ADJUDICATION),
+            _ => None,
+        }
+    }
+
+    /// Check that the rules allow moving to `next`, returning it if so
+    pub fn transition(&self, next: LifecycleState) -> Result<LifecycleState, ApiError> {
+        use LifecycleState::*;
+
+        if self.is_terminal() {
+            return Err(ApiError::Conflict("Game is already finished".to_string()));
+        }
+        let allowed = matches!(
+            (self, &next),
+            (Created, Waiting | Started)
+                | (Waiting, Started | Aborted)
+                | (Started, Ended { .. } | Aborted | Adjudicated { .. })
+        );
+        if !allowed {
+            return Err(ApiError::Conflict(format!(
+                "Game cannot go from {} to {}",
+                self.name(),
+                next.name()
+            )));
+        }
+        Ok(next)
+    }
+
+    /// Value for the `result` columns
+    fn result_side(&self) -> Option<ResultSide> {
+        match self {
+            LifecycleState::Aborted => Some(ResultSide::Abandoned),
+            other => other.result().map(ResultSide::from),
+        }
+    }
+
+    /// Write the state onto a game row
+    pub fn apply_to(&self, active: &mut game::ActiveModel) {
+        active.status = Set(match self {
+            LifecycleState::Created | LifecycleState::Waiting => GameStatus::Waiting,
+            LifecycleState::Started => GameStatus::InProgress,
+            LifecycleState::Ended { .. } => GameStatus::Completed,
+            LifecycleState::Aborted => GameStatus::Aborted,
+            LifecycleState::Adjudicated { .. } => GameStatus::Adjudicated,
+        });
+        active.result = Set(self.result_side());
+        active.termination = Set(self.termination().map(str::to_string));
+    }
+}
+
+impl fmt::Display for LifecycleState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What drove a transition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransitionSource {
+    Rest,
+    Socket,
+    Tournament,
+    Import,
+}
+
+impl TransitionSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransitionSource::Rest => "rest",
+            TransitionSource::Socket => "socket",
+            TransitionSource::Tournament => "tournament",
+            TransitionSource::Import => "import",
+        }
+    }
+}
+
+/// Domain event for one accepted transition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LifecycleEvent {
+    pub game_id: Uuid,
+    pub from: LifecycleState,
+    pub to: LifecycleState,
+    pub source: TransitionSource,
+    /// Player or arbiter who caused the transition
+    pub actor: Option<Uuid>,
+    pub at: DateTime<Utc>,
+}
+
+impl LifecycleEvent {
+    /// Validate a transition and describe it as an event
+    pub fn new(
+        game_id: Uuid,
+        from: &LifecycleState,
+        to: LifecycleState,
+        source: TransitionSource,
+        actor: Option<Uuid>,
+    ) -> Result<Self, ApiError> {
+        let to = from.transition(to)?;
+        Ok(Self { game_id, from: from.clone(), to, source, actor, at: Utc::now() })
+    }
+}
+
+pub struct GameLifecycle;
+
+impl GameLifecycle {
+    /// Write the event to the audit table, normally inside the transaction
+    /// that updates the game
+    pub async fn record<C: ConnectionTrait>(conn: &C, event: &LifecycleEvent) -> Result<(), ApiError> {
+        let row = game_transition::ActiveModel {
+            game_id: Set(event.game_id),
+            from_state: Set(event.from.name().to_string()),
+            to_state: Set(event.to.name().to_string()),
+            result: Set(event.to.result_side()),
+            termination: Set(event.to.termination().map(str::to_string)),
+            source: Set(event.source.as_str().to_string()),
+            actor: Set(event.actor),
+            created_at: Set(event.at.into()),
+            ..Default::default()
+        };
+        GameTransition::insert(row).exec_without_returning(conn).await?;
+        Ok(())
+    }
+
+    /// Hand a committed event to subscribers. Dropped when nobody listens.
+    pub fn publish(event: LifecycleEvent) {
+        let _ = EVENTS.send(event);
+    }
+
+    /// Receive every event published from now on
+    pub fn subscribe() -> broadcast::Receiver<LifecycleEvent> {
+        EVENTS.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ended() -> LifecycleState {
+        LifecycleState::Ended { result: Outcome::WhiteWins, reason: EndReason::Checkmate }
+    }
+
+    #[test]
+    fn test_allowed_transitions() {
+        use LifecycleState::*;
+
+        assert_eq!(Created.transition(Waiting).unwrap(), Waiting);
+        assert_eq!(Created.transition(Started).unwrap(), Started);
+        assert_eq!(Waiting.transition(Started).unwrap(), Started);
+        assert_eq!(Waiting.transition(Aborted).unwrap(), Aborted);
+        assert_eq!(Started.transition(ended()).unwrap(), ended());
+        assert_eq!(Started.transition(Aborted).unwrap(), Aborted);
+        assert!(Started.transition(Adjudicated { result: Outcome::Draw }).is_ok());
+    }
+
+    #[test]
+    fn test_rejected_transitions() {
+        use LifecycleState::*;
+
+        assert!(matches!(Waiting.transition(ended()), Err(ApiError::Conflict(msg)) if msg == "Game cannot go from waiting to ended"));
+        assert!(Created.transition(Aborted).is_err());
+        assert!(Started.transition(Waiting).is_err());
+        assert!(Waiting.transition(Adjudicated { result: Outcome::Draw }).is_err());
+        for finished in [ended(), Aborted, Adjudicated { result: Outcome::Draw }] {
+            assert!(matches!(finished.transition(Started), Err(ApiError::Conflict(msg)) if msg == "Game is already finished"));
+        }
+    }
+
+    #[test]
+    fn test_state_round_trips_through_game_row() {
+        let states = [
+            LifecycleState::Waiting,
+            LifecycleState::Started,
+            ended(),
+            LifecycleState::Ended { result: Outcome::Draw, reason: EndReason::Stalemate },
+            LifecycleState::Aborted,
+            LifecycleState::Adjudicated { result: Outcome::BlackWins },
+        ];
+        let mut game = crate::games::tests::seated_game(Some(Uuid::new_v4()), Some(Uuid::new_v4()));
+        for state in states {
+            let mut active: game::ActiveModel = game.clone().into();
+            state.apply_to(&mut active);
+            game.status = active.status.clone().unwrap();
+            game.result = active.result.clone().unwrap();
+            game.termination = active.termination.clone().unwrap();
+            assert_eq!(LifecycleState::of(&game), state);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_published_events_reach_subscribers() {
+        let mut events = GameLifecycle::subscribe();
+        let game_id = Uuid::new_v4();
+        let event = LifecycleEvent::new(
+            game_id,
+            &LifecycleState::Created,
+            LifecycleState::Waiting,
+            TransitionSource::Rest,
+            None,
+        )
+        .unwrap();
+        GameLifecycle::publish(event.clone());
+
+        // Other tests publish concurrently, so skip their events
+        loop {
+            let received = events.recv().await.unwrap();
+            if received.game_id == game_id {
+                assert_eq!(received, event);
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! Batched persistence of played moves.
+//!
+//! Moves are queued in memory and written to `game_move` by a background
+//! task, so a fast game costs one insert per flush rather than one round
+//! trip per move. The game row remains the source of truth for the current
+//! position and clocks.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use db_entity::{game_move, prelude::GameMove};
+use sea_orm::{sea_query::OnConflict, DatabaseConnection, DbErr, EntityTrait};
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+
+/// Most moves written in a single insert
+const MAX_BATCH_SIZE: usize = 256;
+
+/// Moves that may wait for the writer before `record` applies backpressure
+const QUEUE_CAPACITY: usize = 8 * MAX_BATCH_SIZE;
+
+/// How long a queued move may wait for others before being written
+const FLUSH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Delay before the first retry of a failed batch, doubled up to `MAX_RETRY_DELAY`
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(100);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Attempts per batch once shutdown has begun, so the process can exit
+const SHUTDOWN_ATTEMPTS: u32 = 3;
+
+/// Handle for queueing moves, shared across workers
+#[derive(Clone)]
+pub struct MoveLog {
+    sender: mpsc::Sender<game_move::ActiveModel>,
+}
+
+/// The spawned writer task, kept by the server to drain it on shutdown
+pub struct MoveLogWriter {
+    shutdown: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl MoveLog {
+    /// Create a handle and the receiving end that `run` drains.
+    pub fn channel() -> (Self, mpsc::Receiver<game_move::ActiveModel>) {
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        (Self { sender }, receiver)
+    }
+
+    /// Create a handle backed by a spawned writer task.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn start(db: Arc<DatabaseConnection>) -> (Self, MoveLogWriter) {
+        let (log, receiver) = Self::channel();
+        let (shutdown, stopping) = watch::channel(false);
+        let task = tokio::spawn(Self::run(db, receiver, stopping));
+        (log, MoveLogWriter { shutdown, task })
+    }
+
+    /// Queue a move for writing, waiting while the queue is full.
+    pub async fn record(&self, game_move: game_move::ActiveModel) {
+        if self.sender.send(game_move).await.is_err() {
+            eprintln!("Move log writer has stopped; dropping move");
+        }
+    }
+
+    /// Write queued moves in batches until every handle is dropped or
+    /// `shutdown` is set, then write whatever is still queued.
+    pub async fn run(
+        db: Arc<DatabaseConnection>,
+        mut receiver: mpsc::Receiver<game_move::ActiveModel>,
+        mut shutdown: watch::Receiver<bool>,
+    ) {
+        let mut batch = Vec::with_capacity(MAX_BATCH_SIZE);
+        let mut stopping = false;
+
+        loop {
+            let first = tokio::select! {
+                next = receiver.recv() => next,
+                _ = shutdown.changed(), if !stopping => {
+                    // Refuse new moves but keep draining the queued ones
+                    receiver.close();
+                    stopping = true;
+                    continue;
+                }
+            };
+            let Some(first) = first else {
+                break;
+            };
+            batch.push(first);
+
+            if stopping {
+                while batch.len() < MAX_BATCH_SIZE {
+                    match receiver.try_recv() {
+                        Ok(game_move) => batch.push(game_move),
+                        Err(_) => break,
+                    }
+                }
+            } else {
+                // Give moves from other games a short window to join the batch
+                let deadline = tokio::time::sleep(FLUSH_INTERVAL);
+                tokio::pin!(deadline);
+                while batch.len() < MAX_BATCH_SIZE {
+                    tokio::select! {
+                        next = receiver.recv() => match next {
+                            Some(game_move) => batch.push(game_move),
+                            None => break,
+                        },
+                        _ = &mut deadline => break,
+                    }
+                }
+            }
+
+            Self::write(&db, std::mem::take(&mut batch), &shutdown).await;
+        }
+    }
+
+    /// Write a batch, retrying with backoff until it succeeds. Meanwhile new
+    /// moves wait in the bounded queue. Once shutdown has begun a batch gets
+    /// only `SHUTDOWN_ATTEMPTS` tries.
+    async fn write(db: &DatabaseConnection, batch: Vec<game_move::ActiveModel>, shutdown: &watch::Receiver<bool>) {
+        let mut delay = INITIAL_RETRY_DELAY;
+        let mut attempt = 1;
+        loop {
+            let err = match Self::flush(db, batch.clone()).await {
+                Ok(()) => return,
+                Err(err) => err,
+            };
+            if *shutdown.borrow() && attempt >= SHUTDOWN_ATTEMPTS {
+                eprintln!("Dropping {} moves after {} failed writes during shutdown: {}", batch.len(), attempt, err);
+                return;
+            }
+            eprintln!("Failed to write {} moves (attempt {}), retrying in {:?}: {}", batch.len(), attempt, delay, err);
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RETRY_DELAY);
+            attempt += 1;
+        }
+    }
+
+    async fn flush(db: &DatabaseConnection, batch: Vec<game_move::ActiveModel>) -> Result<(), DbErr> {
+        // A move already written (e.g. by a retried request) is left as is
+        GameMove::insert_many(batch)
+            .on_conflict(
+                OnConflict::columns([game_move::Column::GameId, game_move::Column::MoveNumber])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await?;
+        Ok(())
+    }
+}
+
+impl MoveLogWriter {
+    /// Stop accepting moves, write the ones still queued and wait for the
+    /// writer to finish.
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(true);
+        if let Err(e) = self.task.await {
+            eprintln!("Move log writer failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use sea_orm::{ActiveValue::Set, DbBackend, MockDatabase, MockExecResult};
+    use uuid::Uuid;
+
+    fn queued_move(game_id: Uuid, ply: i32) -> game_move::ActiveModel {
+        game_move::ActiveModel {
+            game_id: Set(game_id),
+            move_number: Set(ply),
+            san: Set("e4".to_string()),
+            fen: Set(chess::STARTING_FEN.to_string()),
+            timestamp: Set(Utc::now().into()),
+            clock_ms: Set(Some(59_000)),
+            time_spent_ms: Set(Some(1_000)),
+            lag_compensation_ms: Set(Some(0)),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queued_moves_are_written_in_one_batch() {
+        let db = Arc::new(
+            MockDatabase::new(DbBackend::Postgres)
+                .append_exec_results(vec![MockExecResult { last_insert_id: 0, rows_affected: 3 }])
+                .into_connection(),
+        );
+
+        let (log, receiver) = MoveLog::channel();
+        let game_id = Uuid::new_v4();
+        for ply in 1..=3 {
+            log.record(queued_move(game_id, ply)).await;
+        }
+        drop(log);
+
+        let (_shutdown, stopping) = watch::channel(false);
+        MoveLog::run(db.clone(), receiver, stopping).await;
+
+        let db = Arc::try_unwrap(db).ok().unwrap();
+        let transaction_log = db.into_transaction_log();
+        assert_eq!(transaction_log.len(), 1);
+        let log_str = format!("{:?}", transaction_log[0]);
+        assert!(log_str.contains(r#"INSERT INTO \"smdb\".\"game_move\""#));
+        assert!(log_str.contains("ON CONFLICT"));
+        assert!(log_str.contains("Int(Some(3))"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_batch_is_retried() {
+        let db = Arc::new(
+            MockDatabase::new(DbBackend::Postgres)
+                .append_exec_errors(vec![DbErr::Custom("connection reset".to_string())])
+                .append_exec_results(vec![MockExecResult { last_insert_id: 0, rows_affected: 1 }])
+                .into_connection(),
+        );
+
+        let (log, receiver) = MoveLog::channel();
+        log.record(queued_move(Uuid::new_v4(), 1)).await;
+        drop(log);
+
+        let (_shutdown, stopping) = watch::channel(false);
+        MoveLog::run(db.clone(), receiver, stopping).await;
+
+        let db = Arc::try_unwrap(db).ok().unwrap();
+        assert_eq!(db.into_transaction_log().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_queued_moves() {
+        let db = Arc::new(
+            MockDatabase::new(DbBackend::Postgres)
+                .append_exec_results(vec![MockExecResult { last_insert_id: 0, rows_affected: 2 }])
+                .into_connection(),
+        );
+
+        // A handle is still alive, as it is in the server's app data
+        let (log, receiver) = MoveLog::channel();
+        let game_id = Uuid::new_v4();
+        log.record(queued_move(game_id, 1)).await;
+        log.record(queued_move(game_id, 2)).await;
+
+        let (shutdown, stopping) = watch::channel(false);
+        shutdown.send(true).unwrap();
+        MoveLog::run(db.clone(), receiver, stopping).await;
+
+        let db = Arc::try_unwrap(db).ok().unwrap();
+        let transaction_log = db.into_transaction_log();
+        assert_eq!(transaction_log.len(), 1);
+        assert!(format!("{:?}", transaction_log[0]).contains("Int(Some(2))"));
+
+        // Moves recorded after shutdown are refused rather than queued forever
+        log.record(queued_move(game_id, 3)).await;
+    }
+}
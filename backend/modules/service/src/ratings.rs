@@ -0,0 +1,470 @@
+//! Per-category Glicko-2 ratings and their history.
+//!
+//! Every player has a separate rating for each speed of standard chess and
+//! for each variant. A category without a row is unrated and starts from
+//! `Glicko2Rating::INITIAL`. Rated games are rated in the transaction that
+//! records their result, each game as its own rating period.
+
+use chess::TimeControlCategory;
+use chrono::{DateTime, Utc};
+use db_entity::{
+    game::{self, GameVariant},
+    player_rating::{self, RatingCategory},
+    prelude::{PlayerRating, RatingHistory},
+    rating_history,
+};
+use error::error::ApiError;
+use async_trait::async_trait;
+use db_entity::prelude::Player;
+use matchmaking::{GameOutcome, Glicko2, Glicko2Rating, MatchPool, RatingSource, Variant};
+use sea_orm::{
+    sea_query::OnConflict, ActiveValue::Set, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::lifecycle::{LifecycleState, Outcome};
+
+/// Games with fewer plies than this are not rated, so a game given up
+/// before both sides played two moves costs nothing
+pub const MIN_RATED_PLIES: usize = 4;
+
+/// How one player's rating moved after a game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RatingChange {
+    pub player_id: Uuid,
+    pub category: RatingCategory,
+    /// Rating after the game
+    pub rating: i32,
+    pub rating_change: i32,
+}
+
+/// Rating changes of both players of a game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameRatingChanges {
+    pub white: RatingChange,
+    pub black: RatingChange,
+}
+
+/// The rating category a game counts towards. Variants are rated on their
+/// own; standard games by the speed of their time control, untimed games
+/// being correspondence.
+pub fn category_of(variant: &GameVariant, initial_time_ms: i64, increment_ms: i64) -> RatingCategory {
+    match variant {
+        GameVariant::Chess960 => RatingCategory::Chess960,
+        GameVariant::ThreeCheck => RatingCategory::ThreeCheck,
+        GameVariant::Standard | GameVariant::Blitz | GameVariant::Rapid | GameVariant::Classical => {
+            if initial_time_ms <= 0 && increment_ms <= 0 {
+                return RatingCategory::Correspondence;
+            }
+            let initial_secs = (initial_time_ms.max(0) / 1000) as u32;
+            let increment_secs = (increment_ms.max(0) / 1000) as u32;
+            match TimeControlCategory::from_time_control(initial_secs, increment_secs) {
+                TimeControlCategory::Bullet => RatingCategory::Bullet,
+                TimeControlCategory::Blitz => RatingCategory::Blitz,
+                TimeControlCategory::Rapid => RatingCategory::Rapid,
+                TimeControlCategory::Classical => RatingCategory::Classical,
+            }
+        }
+    }
+}
+
+pub fn game_category(game: &game::Model) -> RatingCategory {
+    category_of(&game.variant, game.initial_time_ms, game.increment_ms)
+}
+
+/// The rating category the games of a matchmaking pool count towards
+pub fn pool_category(pool: &MatchPool) -> RatingCategory {
+    let variant = match pool.variant {
+        Variant::Standard => GameVariant::Standard,
+        Variant::Chess960 => GameVariant::Chess960,
+        Variant::ThreeCheck => GameVariant::ThreeCheck,
+    };
+    category_of(
+        &variant,
+        pool.time_control.initial_secs as i64 * 1000,
+        pool.time_control.increment_secs as i64 * 1000,
+    )
+}
+
+pub struct RatingService;
+
+impl RatingService {
+    /// The player's rated categories
+    pub async fn ratings(db: &DatabaseConnection, player_id: Uuid) -> Result<Vec<player_rating::Model>, ApiError> {
+        Ok(PlayerRating::find()
+            .filter(player_rating::Column::PlayerId.eq(player_id))
+            .order_by_asc(player_rating::Column::Category)
+            .all(db)
+            .await?)
+    }
+
+    /// The player's rating in a category, locked for the rest of the
+    /// transaction. A category they have not played yet gets its initial
+    /// rating stored first, as `FOR UPDATE` does not lock a missing row and
+    /// two games finishing at once would both rate from the initial rating.
+    pub async fn rating<C: ConnectionTrait>(
+        conn: &C,
+        player_id: Uuid,
+        category: RatingCategory,
+    ) -> Result<player_rating::Model, ApiError> {
+        let initial = Glicko2Rating::INITIAL;
+        let unrated = player_rating::Model {
+            player_id,
+            category,
+            rating: initial.rating,
+            deviation: initial.deviation,
+            volatility: initial.volatility,
+            last_rated_at: None,
+            games_played: 0,
+        };
+        PlayerRating::insert(player_rating::ActiveModel::from(unrated.clone()))
+            .on_conflict(
+                OnConflict::columns([player_rating::Column::PlayerId, player_rating::Column::Category])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec_without_returning(conn)
+            .await?;
+
+        let rating = PlayerRating::find_by_id((player_id, category)).lock_exclusive().one(conn).await?;
+        Ok(rating.unwrap_or(unrated))
+    }
+
+    /// Store the rating `previous` changed to after `game_id` and add it to
+    /// the player's history.
+    pub async fn record<C: ConnectionTrait>(
+        conn: &C,
+        previous: &player_rating::Model,
+        rating: Glicko2Rating,
+        game_id: Option<Uuid>,
+        now: DateTime<Utc>,
+    ) -> Result<player_rating::Model, ApiError> {
+        let updated = player_rating::Model {
+            player_id: previous.player_id,
+            category: previous.category,
+            rating: rating.rating,
+            deviation: rating.deviation,
+            volatility: rating.volatility,
+            last_rated_at: Some(now.into()),
+            games_played: previous.games_played + 1,
+        };
+
+        PlayerRating::insert(player_rating::ActiveModel::from(updated.clone()))
+            .on_conflict(
+                OnConflict::columns([player_rating::Column::PlayerId, player_rating::Column::Category])
+                    .update_columns([
+                        player_rating::Column::Rating,
+                        player_rating::Column::Deviation,
+                        player_rating::Column::Volatility,
+                        player_rating::Column::LastRatedAt,
+                        player_rating::Column::GamesPlayed,
+                    ])
+                    .to_owned(),
+            )
+            .exec_without_returning(conn)
+            .await?;
+
+        RatingHistory::insert(rating_history::ActiveModel {
+            player_id: Set(updated.player_id),
+            category: Set(updated.category),
+            game_id: Set(game_id),
+            rating: Set(updated.rating),
+            deviation: Set(updated.deviation),
+            volatility: Set(updated.volatility),
+            rating_change: Set(updated.rating - previous.rating),
+            recorded_at: Set(now.into()),
+            ..Default::default()
+        })
+        .exec_without_returning(conn)
+        .await?;
+
+        Ok(updated)
+    }
+
+    /// Rate a game that has just finished, as part of the transaction that
+    /// stores its result. Unrated, aborted and very short games are skipped
+    /// and give `None`.
+    pub async fn rate_finished_game<C: ConnectionTrait>(
+        conn: &C,
+        game: &game::Model,
+        now: DateTime<Utc>,
+    ) -> Result<Option<GameRatingChanges>, ApiError> {
+        let Some(outcome) = LifecycleState::of(game).result() else {
+            return Ok(None);
+        };
+        let (Some(white_id), Some(black_id)) = (game.white_player, game.black_player) else {
+            return Ok(None);
+        };
+        let plies = game.pgn["moves"].as_array().map_or(0, Vec::len);
+        if !game.rated || game.is_imported || white_id == black_id || plies < MIN_RATED_PLIES {
+            return Ok(None);
+        }
+
+        // Lock in a fixed order so two games between the same players can't deadlock
+        let category = game_category(game);
+        let (white, black) = if white_id < black_id {
+            let white = Self::rating(conn, white_id, category).await?;
+            (white, Self::rating(conn, black_id, category).await?)
+        } else {
+            let black = Self::rating(conn, black_id, category).await?;
+            (Self::rating(conn, white_id, category).await?, black)
+        };
+
+        let glicko = Glicko2::default();
+        let inflate = |rating: &player_rating::Model| {
+            let current = Glicko2Rating {
+                rating: rating.rating,
+                deviation: rating.deviation,
+                volatility: rating.volatility,
+            };
+            match rating.last_rated_at {
+                Some(last) => glicko.inflate(current, glicko.periods_since(last.with_timezone(&Utc), now)),
+                None => current,
+            }
+        };
+        let white_outcome = match outcome {
+            Outcome::WhiteWins => GameOutcome::Win,
+            Outcome::BlackWins => GameOutcome::Loss,
+            Outcome::Draw => GameOutcome::Draw,
+        };
+        let (new_white, new_black) = glicko.rate_game(inflate(&white), inflate(&black), white_outcome);
+
+        let white_after = Self::record(conn, &white, new_white, Some(game.id), now).await?;
+        let black_after = Self::record(conn, &black, new_black, Some(game.id), now).await?;
+        let change = |before: &player_rating::Model, after: &player_rating::Model| RatingChange {
+            player_id: after.player_id,
+            category,
+            rating: after.rating.round() as i32,
+            rating_change: (after.rating - before.rating).round() as i32,
+        };
+        Ok(Some(GameRatingChanges {
+            white: change(&white, &white_after),
+            black: change(&black, &black_after),
+        }))
+    }
+
+    /// The player's current rating in a category without storing or
+    /// locking anything, `None` if there is no such player
+    pub async fn current_rating(
+        db: &DatabaseConnection,
+        player_id: Uuid,
+        category: RatingCategory,
+    ) -> Result<Option<Glicko2Rating>, ApiError> {
+        if Player::find_by_id(player_id).one(db).await?.is_none() {
+            return Ok(None);
+        }
+
+        let rating = PlayerRating::find_by_id((player_id, category)).one(db).await?;
+        Ok(Some(rating.map_or(Glicko2Rating::INITIAL, |rating| Glicko2Rating {
+            rating: rating.rating,
+            deviation: rating.deviation,
+            volatility: rating.volatility,
+        })))
+    }
+
+    /// Ratings the two players bring to a game of `category`, rounded as
+    /// they are stored on the game for search. Unrated players count with
+    /// the initial rating.
+    pub async fn starting_ratings<C: ConnectionTrait>(
+        conn: &C,
+        category: RatingCategory,
+        white: Uuid,
+        black: Uuid,
+    ) -> Result<(i32, i32), ApiError> {
+        let mut ratings = [0; 2];
+        for (slot, player_id) in ratings.iter_mut().zip([white, black]) {
+            let rating = PlayerRating::find_by_id((player_id, category)).one(conn).await?;
+            *slot = rating.map_or(Glicko2Rating::INITIAL.rating, |rating| rating.rating).round() as i32;
+        }
+        Ok((ratings[0], ratings[1]))
+    }
+
+    /// Rating changes oldest first, optionally limited to one category
+    pub async fn history(
+        db: &DatabaseConnection,
+        player_id: Uuid,
+        category: Option<RatingCategory>,
+    ) -> Result<Vec<rating_history::Model>, ApiError> {
+        let mut query = RatingHistory::find().filter(rating_history::Column::PlayerId.eq(player_id));
+        if let Some(category) = category {
+            query = query.filter(rating_history::Column::Category.eq(category));
+        }
+        Ok(query
+            .order_by_asc(rating_history::Column::RecordedAt)
+            .order_by_asc(rating_history::Column::Id)
+            .all(db)
+            .await?)
+    }
+}
+
+/// Stored ratings for matchmaking, so players are paired by the rating
+/// they have in the category of the pool they join
+pub struct MatchmakingRatings {
+    db: Arc<DatabaseConnection>,
+}
+
+impl MatchmakingRatings {
+    pub fn new(db: Arc<DatabaseConnection>) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl RatingSource for MatchmakingRatings {
+    async fn rating(&self, player_id: Uuid, pool: &MatchPool) -> Result<Option<Glicko2Rating>, String> {
+        RatingService::current_rating(&self.db, player_id, pool_category(pool))
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::tests::seated_game;
+    use db_entity::game::{GameStatus, ResultSide};
+    use sea_orm::{DbBackend, MockDatabase, MockExecResult};
+
+    fn finished_game(white: Uuid, black: Uuid, plies: usize) -> game::Model {
+        let mut game = seated_game(Some(white), Some(black));
+        let moves = ["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7#"];
+        game.pgn = serde_json::json!({ "moves": moves[..plies], "final_ply": plies });
+        game.status = GameStatus::Completed;
+        game.result = Some(ResultSide::WhiteWins);
+        game.termination = Some("checkmate".to_string());
+        game
+    }
+
+    #[test]
+    fn test_category_of_game() {
+        assert_eq!(category_of(&GameVariant::Standard, 60_000, 0), RatingCategory::Bullet);
+        assert_eq!(category_of(&GameVariant::Standard, 180_000, 2_000), RatingCategory::Blitz);
+        assert_eq!(category_of(&GameVariant::Standard, 600_000, 5_000), RatingCategory::Rapid);
+        assert_eq!(category_of(&GameVariant::Standard, 1_800_000, 0), RatingCategory::Classical);
+        assert_eq!(category_of(&GameVariant::Standard, 0, 0), RatingCategory::Correspondence);
+        assert_eq!(category_of(&GameVariant::Chess960, 60_000, 0), RatingCategory::Chess960);
+        assert_eq!(category_of(&GameVariant::ThreeCheck, 0, 0), RatingCategory::ThreeCheck);
+    }
+
+    #[tokio::test]
+    async fn test_matchmaking_reads_the_rating_of_the_pool() {
+        let player_id = Uuid::new_v4();
+        let player = db_entity::player::Model {
+            id: player_id,
+            username: "queued".to_string(),
+            email: "queued@example.com".to_string(),
+            password_hash: vec![],
+            biography: String::new(),
+            country: String::new(),
+            flair: String::new(),
+            real_name: String::new(),
+            location: None,
+            fide_rating: None,
+            social_links: None,
+            is_enabled: true,
+        };
+        let bullet = player_rating::Model {
+            player_id,
+            category: RatingCategory::Bullet,
+            rating: 1712.4,
+            deviation: 64.6,
+            volatility: 0.06,
+            last_rated_at: None,
+            games_played: 30,
+        };
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![player.clone()]])
+            .append_query_results(vec![vec![bullet]])
+            .append_query_results(vec![vec![player]])
+            .append_query_results(vec![Vec::<player_rating::Model>::new()])
+            .append_query_results(vec![Vec::<db_entity::player::Model>::new()])
+            .into_connection();
+        let ratings = MatchmakingRatings::new(Arc::new(db));
+        let pool = |initial_secs, variant| MatchPool {
+            match_type: matchmaking::MatchType::Rated,
+            variant,
+            time_control: matchmaking::TimeControl { initial_secs, increment_secs: 0 },
+        };
+        assert_eq!(pool_category(&pool(60, Variant::Standard)), RatingCategory::Bullet);
+        assert_eq!(pool_category(&pool(60, Variant::Chess960)), RatingCategory::Chess960);
+
+        let rated = ratings.rating(player_id, &pool(60, Variant::Standard)).await.unwrap().unwrap();
+        assert_eq!((rated.rating, rated.deviation), (1712.4, 64.6));
+        let unrated = ratings.rating(player_id, &pool(1800, Variant::Standard)).await.unwrap().unwrap();
+        assert_eq!(unrated.rating, Glicko2Rating::INITIAL.rating);
+        assert!(ratings.rating(Uuid::new_v4(), &pool(60, Variant::Standard)).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_record_updates_rating_and_history() {
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![Vec::<player_rating::Model>::new()])
+            .append_exec_results(vec![
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+                MockExecResult { last_insert_id: 1, rows_affected: 1 },
+            ])
+            .into_connection();
+
+        let player_id = Uuid::new_v4();
+        let previous = RatingService::rating(&db, player_id, RatingCategory::Rapid).await.unwrap();
+        assert_eq!(previous.rating, 1500.0);
+        assert_eq!(previous.games_played, 0);
+
+        let new_rating = Glicko2Rating { rating: 1662.0, deviation: 290.0, volatility: 0.06 };
+        let updated = RatingService::record(&db, &previous, new_rating, Some(Uuid::new_v4()), Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(updated.games_played, 1);
+        assert_eq!(updated.category, RatingCategory::Rapid);
+
+        let log_str = format!("{:?}", db.into_transaction_log());
+        assert!(log_str.contains(r#"INSERT INTO \"smdb\".\"player_rating\""#));
+        assert!(log_str.contains(r#"ON CONFLICT (\"player_id\", \"category\") DO UPDATE"#));
+        assert!(log_str.contains(r#"INSERT INTO \"smdb\".\"rating_history\""#));
+        assert!(log_str.contains("Double(Some(162.0))"));
+    }
+
+    #[tokio::test]
+    async fn test_finished_game_rates_both_players() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let game = finished_game(white, black, 7);
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![Vec::<player_rating::Model>::new(), Vec::new()])
+            .append_exec_results((0..6).map(|_| MockExecResult { last_insert_id: 0, rows_affected: 1 }))
+            .into_connection();
+
+        let changes = RatingService::rate_finished_game(&db, &game, Utc::now()).await.unwrap().unwrap();
+        assert_eq!((changes.white.player_id, changes.black.player_id), (white, black));
+        assert_eq!(changes.white.category, RatingCategory::Rapid);
+        assert!(changes.white.rating_change > 0);
+        assert_eq!(changes.white.rating_change, -changes.black.rating_change);
+
+        // Unrated players get a row to lock before their rating is read
+        let log_str = format!("{:?}", db.into_transaction_log());
+        assert_eq!(log_str.matches(r#"ON CONFLICT (\"player_id\", \"category\") DO NOTHING"#).count(), 2);
+        assert!(log_str.contains("FOR UPDATE"));
+        assert_eq!(log_str.matches(r#"INSERT INTO \"smdb\".\"rating_history\""#).count(), 2);
+        assert!(log_str.contains(&format!("Uuid(Some({}))", game.id)));
+    }
+
+    #[tokio::test]
+    async fn test_aborted_unrated_and_short_games_are_not_rated() {
+        let (white, black) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut aborted = finished_game(white, black, 7);
+        aborted.status = GameStatus::Aborted;
+        aborted.result = Some(ResultSide::Abandoned);
+        let mut unrated = finished_game(white, black, 7);
+        unrated.rated = false;
+        let short = finished_game(white, black, MIN_RATED_PLIES - 1);
+
+        let db = MockDatabase::new(DbBackend::Postgres).into_connection();
+        for game in [aborted, unrated, short] {
+            assert_eq!(RatingService::rate_finished_game(&db, &game, Utc::now()).await.unwrap(), None);
+        }
+        assert!(db.into_transaction_log().is_empty());
+    }
+}
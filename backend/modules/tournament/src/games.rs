@@ -0,0 +1,145 @@
+//! Swiss rounds played as stored games.
+//!
+//! Each pairing becomes a started game through `GameService`, so tournament
+//! games follow the same lifecycle as any other game and their transitions
+//! are recorded as coming from the tournament. A round is scored from the
+//! decided games; games that will not finish on the board are adjudicated.
+
+use db_entity::game;
+use error::error::ApiError;
+use sea_orm::DatabaseConnection;
+use service::games::GameService;
+use service::lifecycle::{LifecycleState, Outcome, TransitionSource};
+use service::ratings::GameRatingChanges;
+use uuid::Uuid;
+
+use crate::swiss::{Pairing, PairingResult, TournamentState};
+
+/// A pairing of the current round and the game it is played in
+#[derive(Debug, Clone)]
+pub struct RoundGame {
+    pub pairing: Pairing,
+    pub game: game::Model,
+}
+
+/// Start a game for every pairing of a round. Byes have no game; the pairer
+/// already scored them.
+pub async fn start_round(
+    db: &DatabaseConnection,
+    pairings: &[PairingResult],
+    time_control: i32,
+    increment: i32,
+) -> Result<Vec<RoundGame>, ApiError> {
+    let mut games = Vec::new();
+    for result in pairings {
+        let PairingResult::Paired(pairing) = result else {
+            continue;
+        };
+        let game = GameService::create_tournament_game(
+            db,
+            pairing.white_player,
+            pairing.black_player,
+            time_control,
+            increment,
+        )
+        .await?;
+        games.push(RoundGame { pairing: pairing.clone(), game });
+    }
+    Ok(games)
+}
+
+/// Decide a tournament game on the arbiter's ruling
+pub async fn adjudicate(
+    db: &DatabaseConnection,
+    game_id: Uuid,
+    result: Outcome,
+    arbiter: Option<Uuid>,
+) -> Result<(game::Model, Option<GameRatingChanges>), ApiError> {
+    GameService::adjudicate_game(db, game_id, result, TransitionSource::Tournament, arbiter).await
+}
+
+/// Score a round from its games as currently stored. Returns `false` and
+/// leaves the tournament unchanged while any game is still undecided.
+pub fn apply_round(state: &mut TournamentState, games: &[RoundGame]) -> bool {
+    let mut results = Vec::with_capacity(games.len() * 2);
+    for round_game in games {
+        match round_game.pairing.results(&LifecycleState::of(&round_game.game)) {
+            Some(pair) => results.extend(pair),
+            None => return false,
+        }
+    }
+    state.apply_round_results(results);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swiss::Player;
+    use chrono::Utc;
+    use db_entity::game::{GameStatus, GameVariant, ResultSide};
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    fn round_game(pairing: &Pairing, result: Option<ResultSide>) -> RoundGame {
+        let now = Utc::now().fixed_offset();
+        let decided = result.is_some();
+        let game = game::Model {
+            id: Uuid::new_v4(),
+            white_player: Some(pairing.white_player),
+            black_player: Some(pairing.black_player),
+            fen: START.to_string(),
+            pgn: serde_json::json!({ "moves": [], "final_ply": 0 }),
+            termination: decided.then(|| "normal".to_string()),
+            result,
+            variant: GameVariant::Standard,
+            started_at: now,
+            duration_sec: 600,
+            created_at: now,
+            updated_at: now,
+            is_imported: false,
+            original_pgn: None,
+            import_hash: None,
+            position_keys: vec![],
+            status: if decided { GameStatus::Completed } else { GameStatus::InProgress },
+            initial_time_ms: 600_000,
+            increment_ms: 0,
+            rated: true,
+            last_move_at: None,
+            moves_encoded: None,
+            initial_fen: START.to_string(),
+            version: 0,
+        };
+        RoundGame { pairing: pairing.clone(), game }
+    }
+
+    #[test]
+    fn test_round_is_scored_once_every_game_is_decided() {
+        let players: Vec<Player> = (0..4)
+            .map(|i| Player::new(Uuid::new_v4(), format!("Player {}", i), 1500))
+            .collect();
+        let pairing = |white: usize, black: usize| Pairing {
+            white_player: players[white].id,
+            black_player: players[black].id,
+            round: 1,
+        };
+        let (first, second) = (pairing(0, 1), pairing(2, 3));
+        let mut state = TournamentState::new(players.clone(), 3);
+        state.pairings = vec![first.clone(), second.clone()];
+
+        let mut games = vec![
+            round_game(&first, Some(ResultSide::WhiteWins)),
+            round_game(&second, None),
+        ];
+        assert!(!apply_round(&mut state, &games));
+        assert_eq!(state.completed_rounds, 0);
+
+        games[1] = round_game(&second, Some(ResultSide::Draw));
+        assert!(apply_round(&mut state, &games));
+        assert_eq!(state.completed_rounds, 1);
+        assert_eq!(state.players[&first.white_player].score, 1.0);
+        assert_eq!(state.players[&first.black_player].score, 0.0);
+        assert_eq!(state.players[&second.black_player].score, 0.5);
+        assert_eq!(state.players[&second.white_player].opponents, vec![second.black_player]);
+    }
+}
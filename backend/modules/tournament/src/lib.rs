@@ -1,6 +1,7 @@
 pub mod swiss;
 pub mod pairing;
 pub mod arena;
+pub mod games;
 
 pub use swiss::{
     Player, Color, Pairing, TournamentState, PairingResult, SwissConfig, GameResult,
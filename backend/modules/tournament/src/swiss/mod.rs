@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use service::lifecycle::{LifecycleState, Outcome};
 use std::collections::HashMap;
 use uuid::Uuid;
 
@@ -33,6 +34,19 @@ pub struct Pairing {
     pub round: u32,
 }
 
+impl Pairing {
+    /// Results for both players once the paired game has been decided
+    /// through the game lifecycle. Running and aborted games have none.
+    pub fn results(&self, game: &LifecycleState) -> Option<[(Uuid, GameResult); 2]> {
+        let (white, black) = match game.result()? {
+            Outcome::WhiteWins => (GameResult::Win, GameResult::Loss),
+            Outcome::BlackWins => (GameResult::Loss, GameResult::Win),
+            Outcome::Draw => (GameResult::Draw, GameResult::Draw),
+        };
+        Some([(self.white_player, white), (self.black_player, black)])
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TournamentState {
     pub players: HashMap<Uuid, Player>,
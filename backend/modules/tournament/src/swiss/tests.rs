@@ -293,4 +293,24 @@ mod tests {
         let round2_pairings = pairer.pair_round(&mut tournament).unwrap();
         assert_eq!(round2_pairings.len(), 4);
     }
+
+    #[test]
+    fn test_pairing_results_follow_game_lifecycle() {
+        use service::lifecycle::{EndReason, LifecycleState, Outcome};
+
+        let pairing = Pairing { white_player: Uuid::new_v4(), black_player: Uuid::new_v4(), round: 1 };
+
+        let mate = LifecycleState::Ended { result: Outcome::BlackWins, reason: EndReason::Checkmate };
+        assert_eq!(
+            pairing.results(&mate),
+            Some([(pairing.white_player, GameResult::Loss), (pairing.black_player, GameResult::Win)])
+        );
+        let adjudicated = LifecycleState::Adjudicated { result: Outcome::Draw };
+        assert_eq!(
+            pairing.results(&adjudicated),
+            Some([(pairing.white_player, GameResult::Draw), (pairing.black_player, GameResult::Draw)])
+        );
+        assert_eq!(pairing.results(&LifecycleState::Started), None);
+        assert_eq!(pairing.results(&LifecycleState::Aborted), None);
+    }
 }
@@ -4,10 +4,19 @@ use std::time::SystemTime;
 use tokio::sync::broadcast;
 use uuid::Uuid;
 
-use crate::models::{GameStatus, PieceColor, Player, Room, ServerMessage};
+use crate::models::{GameState, PieceColor, Player, Room, ServerMessage};
+use crate::persistence::RoomStore;
+use sea_orm::DatabaseConnection;
+use service::game_events::{GameEvent, Side};
+use service::lifecycle::{
+    EndReason, GameLifecycle, LifecycleEvent, LifecycleState, Outcome, TransitionSource,
+};
 
 const LATENCY_BUFFER_MS: u64 = 750;
 
+/// Time a player can give their opponent with `AddTime`
+const ADD_TIME_MS: u64 = 15_000;
+
 type MessageSender = broadcast::Sender<ServerMessage>;
 
 pub struct ServerState {
@@ -35,13 +44,72 @@ pub fn get_room_sender(room_id: &str) -> Option<MessageSender> {
     state.message_senders.get(room_id).cloned()
 }
 
+// Validate a lifecycle transition, queue it for the audit table and publish
+// it as a domain event. Rooms named by clients rather than created here have
+// no game ID, so their transitions are checked but not recorded.
+fn advance(room_id: &str, from: &LifecycleState, to: LifecycleState, player_id: &str) -> Result<LifecycleState, String> {
+    let Ok(game_id) = Uuid::parse_str(room_id) else {
+        return from.transition(to).map_err(|e| e.to_string());
+    };
+    let actor = Uuid::parse_str(player_id).ok();
+    let event = LifecycleEvent::new(game_id, from, to, TransitionSource::Socket, actor)
+        .map_err(|e| e.to_string())?;
+    let next = event.to.clone();
+    if let Some(store) = RoomStore::global() {
+        store.record(event.clone());
+    }
+    GameLifecycle::publish(event);
+    Ok(next)
+}
+
+// Queue events for the log of the room's game. Like transitions they are only
+// kept for rooms with a game ID.
+fn log_events(room_id: &str, player_id: Option<&str>, events: Vec<GameEvent>) {
+    let (Ok(game_id), Some(store)) = (Uuid::parse_str(room_id), RoomStore::global()) else {
+        return;
+    };
+    let actor = player_id.and_then(|id| Uuid::parse_str(id).ok());
+    store.append(game_id, actor, events);
+}
+
+// First event of a room's log
+fn log_created(room: &Room) {
+    let created = GameEvent::Created {
+        initial_fen: chess::STARTING_FEN.to_string(),
+        initial_time_ms: room.initial_time_ms as i64,
+        increment_ms: room.increment_ms as i64,
+    };
+    log_events(&room.id, None, vec![created]);
+}
+
+// Side of a seated player, for the game's log
+fn side_of(room: &Room, player_id: &str) -> Result<Side, String> {
+    match room.color_of(player_id) {
+        Some(PieceColor::White) => Ok(Side::White),
+        Some(PieceColor::Black) => Ok(Side::Black),
+        None => Err("Player not in room".to_string()),
+    }
+}
+
+// Offers and clock changes only make sense while the game is being played
+fn require_active(room: &Room) -> Result<(), String> {
+    match room.lifecycle() {
+        LifecycleState::Started => Ok(()),
+        _ => Err("Game is not active".to_string()),
+    }
+}
+
 // Create a new room
 pub fn create_room() -> String {
     let room_id = Uuid::new_v4().to_string();
     let (tx, _) = broadcast::channel(100);
 
+    let room = Room::new(room_id.clone());
+    log_created(&room);
+    save_game_to_db(&room);
+
     let mut state = GAME_STATE.lock().unwrap();
-    state.rooms.insert(room_id.clone(), Room::new(room_id.clone()));
+    state.rooms.insert(room_id.clone(), room);
     state.message_senders.insert(room_id.clone(), tx);
 
     room_id
@@ -52,11 +120,12 @@ pub fn create_room_with_time(initial_time_ms: u64, increment_ms: u64) -> String
     let room_id = Uuid::new_v4().to_string();
     let (tx, _) = broadcast::channel(100);
 
+    let room = Room::new_with_time(room_id.clone(), initial_time_ms, increment_ms);
+    log_created(&room);
+    save_game_to_db(&room);
+
     let mut state = GAME_STATE.lock().unwrap();
-    state.rooms.insert(
-        room_id.clone(),
-        Room::new_with_time(room_id.clone(), initial_time_ms, increment_ms),
-    );
+    state.rooms.insert(room_id.clone(), room);
     state.message_senders.insert(room_id.clone(), tx);
 
     log::info!(
@@ -81,7 +150,9 @@ pub fn join_room(room_id: &str, player_id: &str, player_name: Option<String>) ->
         state = GAME_STATE.lock().unwrap();
         // Re-check after re-locking to avoid race condition
         if !state.rooms.contains_key(room_id) {
-            state.rooms.insert(room_id.to_string(), Room::new(room_id.to_string()));
+            let room = Room::new(room_id.to_string());
+            log_created(&room);
+            state.rooms.insert(room_id.to_string(), room);
             state.message_senders.insert(room_id.to_string(), tx);
         }
     }
@@ -90,6 +161,10 @@ pub fn join_room(room_id: &str, player_id: &str, player_name: Option<String>) ->
 
     // Check if this is the second player (game will start)
     let is_game_starting = room.players.len() == 1;
+    let before = room.lifecycle();
+    if before.is_terminal() {
+        return Err("Game is already finished".to_string());
+    }
 
     // Create player
     let player = Player {
@@ -100,6 +175,7 @@ pub fn join_room(room_id: &str, player_id: &str, player_name: Option<String>) ->
 
     // Add player to room
     room.add_player(player)?;
+    advance(room_id, &before, room.lifecycle(), player_id)?;
 
     // If second player joined, start White's clock
     if is_game_starting {
@@ -110,6 +186,7 @@ pub fn join_room(room_id: &str, player_id: &str, player_name: Option<String>) ->
         room.last_move_at = Some(now_ms);
         log::info!("Game started in room {}, clock started at {}ms", room_id, now_ms);
     }
+    save_game_to_db(room);
 
     // Create response message
     let response = ServerMessage::RoomJoined {
@@ -130,7 +207,7 @@ pub fn join_room(room_id: &str, player_id: &str, player_name: Option<String>) ->
 }
 
 // Send a move
-pub fn send_move(room_id: &str, player_id: &str, move_notation: &str) -> Result<ServerMessage, String> {
+pub fn send_move(room_id: &str, player_id: &str, move_notation: &str, ply: Option<u32>) -> Result<ServerMessage, String> {
     let mut state = GAME_STATE.lock().unwrap();
 
     // Check if room exists
@@ -141,6 +218,13 @@ pub fn send_move(room_id: &str, player_id: &str, move_notation: &str) -> Result<
         return Err("Player not in room".to_string());
     }
 
+    // A move for a ply that was already played is a retry or stale
+    if let Some(ply) = ply {
+        if let Some(response) = check_ply(room, player_id, move_notation, ply)? {
+            return Ok(response);
+        }
+    }
+
     // Check if game has started
     let game_state = room.game_state.as_mut().ok_or_else(|| "Game not started".to_string())?;
 
@@ -169,7 +253,13 @@ pub fn send_move(room_id: &str, player_id: &str, move_notation: &str) -> Result<
             player_id, room_id, elapsed_ms, player_remaining, LATENCY_BUFFER_MS
         );
 
-        game_state.status = GameStatus::Timeout;
+        let timed_out = LifecycleState::Ended {
+            result: Outcome::win_for(!is_white),
+            reason: EndReason::TimeForfeit,
+        };
+        game_state.status = advance(room_id, &game_state.status, timed_out, player_id)?;
+        room.clear_offers();
+        save_game_to_db(room);
 
         // Find winner and loser player IDs
         let (winner_id, loser_id) = room.players.iter().fold(
@@ -199,6 +289,12 @@ pub fn send_move(room_id: &str, player_id: &str, move_notation: &str) -> Result<
         return Err(format!("Time expired. {} wins on time.", winner_color));
     }
 
+    // Reject illegal moves before touching the clocks
+    let before = game_state.status.clone();
+    let played = game_state.apply_move(move_notation)?;
+    let ended = (game_state.status != before).then(|| game_state.status.clone());
+    let current_turn = game_state.current_turn.clone();
+
     // Deduct elapsed time from player's clock and add increment
     if is_white {
         room.white_remaining_ms = room.white_remaining_ms.saturating_sub(elapsed_ms);
@@ -209,15 +305,32 @@ pub fn send_move(room_id: &str, player_id: &str, move_notation: &str) -> Result<
     }
 
     room.last_move_at = Some(now_ms);
-    game_state.apply_move(move_notation)?;
-    let game_state_clone = game_state.clone();
-    room.add_move(player_id.to_string(), move_notation.to_string());
+    room.add_move(player_id.to_string(), played.uci.clone(), played.san.clone());
+    // Moving on answers any pending offer
+    room.clear_offers();
+
+    // The move is logged before the result it leads to
+    let clock_ms = if is_white { room.white_remaining_ms } else { room.black_remaining_ms };
+    let move_played = GameEvent::MovePlayed {
+        uci: played.uci.clone(),
+        san: played.san.clone(),
+        clock_ms: Some(clock_ms as i64),
+    };
+    log_events(room_id, Some(player_id), vec![move_played]);
+    if let Some(ended) = ended {
+        advance(room_id, &before, ended, player_id)?;
+    }
+    save_game_to_db(room);
 
     let response = ServerMessage::MoveMade {
         room_id: room_id.to_string(),
         player_id: player_id.to_string(),
-        move_notation: move_notation.to_string(),
-        game_state: game_state_clone,
+        move_notation: played.uci,
+        san: played.san,
+        ply: room.moves.len() as u32,
+        white_remaining_ms: room.white_remaining_ms,
+        black_remaining_ms: room.black_remaining_ms,
+        current_turn,
     };
 
     if let Some(sender) = state.message_senders.get(room_id) {
@@ -227,15 +340,72 @@ pub fn send_move(room_id: &str, player_id: &str, move_notation: &str) -> Result<
     Ok(response)
 }
 
+// Compare the ply a client sent with the room's move list. Returns `None`
+// when the move is the next one, the original `MoveMade` (not broadcast
+// again) when it repeats the move played at that ply, and `MoveRejected`
+// otherwise.
+fn check_ply(room: &Room, player_id: &str, move_notation: &str, ply: u32) -> Result<Option<ServerMessage>, String> {
+    let played = room.moves.len();
+    if ply as usize == played + 1 {
+        return Ok(None);
+    }
+
+    if ply >= 1 && ply as usize <= played {
+        let record = &room.moves[ply as usize - 1];
+        let mut fen = chess::STARTING_FEN.to_string();
+        for earlier in &room.moves[..ply as usize - 1] {
+            fen = chess::play_move(&fen, &earlier.move_notation).map_err(|e| e.to_string())?.fen;
+        }
+        let is_retry = record.player_id == player_id
+            && chess::play_move(&fen, move_notation).is_ok_and(|m| m.uci == record.move_notation);
+
+        if is_retry {
+            let mover = room.players.iter().find(|p| p.id == player_id).and_then(|p| p.color.clone());
+            return Ok(Some(ServerMessage::MoveMade {
+                room_id: room.id.clone(),
+                player_id: player_id.to_string(),
+                move_notation: record.move_notation.clone(),
+                san: record.san.clone(),
+                ply,
+                white_remaining_ms: room.white_remaining_ms,
+                black_remaining_ms: room.black_remaining_ms,
+                current_turn: match mover {
+                    Some(PieceColor::White) => PieceColor::Black,
+                    _ => PieceColor::White,
+                },
+            }));
+        }
+    }
+
+    Ok(Some(ServerMessage::MoveRejected {
+        room_id: room.id.clone(),
+        player_id: player_id.to_string(),
+        reason: format!("Expected ply {} but the game is at ply {}", played + 1, played),
+        ply: played as u32,
+        game_state: room.game_state.clone(),
+    }))
+}
+
 pub fn leave_room(room_id: &str, player_id: &str) -> Result<ServerMessage, String> {
     let mut state = GAME_STATE.lock().unwrap();
 
     // Check if room exists and remove player
     let should_cleanup = {
         let room = state.rooms.get_mut(room_id).ok_or_else(|| "Room not found".to_string())?;
+        let current = room.lifecycle();
         if !room.remove_player(player_id) {
             return Err("Player not in room".to_string());
         }
+
+        // Leaving a game that has not finished abandons it
+        if matches!(current, LifecycleState::Waiting | LifecycleState::Started) {
+            let aborted = advance(room_id, &current, LifecycleState::Aborted, player_id)?;
+            if let Some(game_state) = room.game_state.as_mut() {
+                game_state.status = aborted;
+            }
+            room.clear_offers();
+        }
+        save_game_to_db(room);
         room.players.is_empty()
     };
 
@@ -254,6 +424,9 @@ pub fn leave_room(room_id: &str, player_id: &str) -> Result<ServerMessage, Strin
     if should_cleanup {
         state.rooms.remove(room_id);
         state.message_senders.remove(room_id);
+        if let Some(store) = RoomStore::global() {
+            store.delete(room_id);
+        }
     }
 
     Ok(response)
@@ -286,9 +459,8 @@ pub fn offer_takeback(room_id: &str, player_id: &str) -> Result<ServerMessage, S
         .ok_or_else(|| "Room not found".to_string())?;
 
     // Ensure player is in the room
-    if !room.players.iter().any(|p| p.id == player_id) {
-        return Err("Player not in room".to_string());
-    }
+    let by = side_of(room, player_id)?;
+    require_active(room)?;
 
     // Require at least one full move (two half-moves) to be able to take back
     if room.moves.len() < 2 {
@@ -301,6 +473,8 @@ pub fn offer_takeback(room_id: &str, player_id: &str) -> Result<ServerMessage, S
     }
 
     room.pending_takeback = Some(player_id.to_string());
+    log_events(room_id, Some(player_id), vec![GameEvent::TakebackOffered { by }]);
+    save_game_to_db(room);
 
     let response = ServerMessage::TakebackOffered {
         room_id: room_id.to_string(),
@@ -324,9 +498,8 @@ pub fn accept_takeback(room_id: &str, player_id: &str) -> Result<ServerMessage,
         .ok_or_else(|| "Room not found".to_string())?;
 
     // Ensure player is in the room
-    if !room.players.iter().any(|p| p.id == player_id) {
-        return Err("Player not in room".to_string());
-    }
+    let by = side_of(room, player_id)?;
+    require_active(room)?;
 
     // There must be a pending takeback request
     let requester_id = match &room.pending_takeback {
@@ -348,14 +521,18 @@ pub fn accept_takeback(room_id: &str, player_id: &str) -> Result<ServerMessage,
     let new_len = room.moves.len() - 2;
     room.moves.truncate(new_len);
 
-    // Rebuild game state from initial position and remaining moves
+    // Rebuild game state from initial position and remaining moves, keeping
+    // the game's status
     let mut game_state = GameState::new_game();
     for mv in &room.moves {
         game_state.apply_move(&mv.move_notation)?;
     }
+    game_state.status = room.lifecycle();
 
     room.game_state = Some(game_state.clone());
     room.pending_takeback = None;
+    log_events(room_id, Some(player_id), vec![GameEvent::TakebackAccepted { by, plies: 2 }]);
+    save_game_to_db(room);
 
     let response = ServerMessage::TakebackAccepted {
         room_id: room_id.to_string(),
@@ -380,16 +557,20 @@ pub fn reject_takeback(room_id: &str, player_id: &str) -> Result<ServerMessage,
         .ok_or_else(|| "Room not found".to_string())?;
 
     // Ensure player is in the room
-    if !room.players.iter().any(|p| p.id == player_id) {
-        return Err("Player not in room".to_string());
-    }
+    let by = side_of(room, player_id)?;
 
-    // There must be a pending takeback request
-    if room.pending_takeback.is_none() {
-        return Err("No pending takeback request".to_string());
+    // There must be a pending takeback request, from the other player
+    match &room.pending_takeback {
+        None => return Err("No pending takeback request".to_string()),
+        Some(requester_id) if requester_id == player_id => {
+            return Err("Requester cannot reject their own takeback".to_string());
+        }
+        Some(_) => {}
     }
 
     room.pending_takeback = None;
+    log_events(room_id, Some(player_id), vec![GameEvent::TakebackDeclined { by }]);
+    save_game_to_db(room);
 
     let response = ServerMessage::TakebackRejected {
         room_id: room_id.to_string(),
@@ -403,17 +584,173 @@ pub fn reject_takeback(room_id: &str, player_id: &str) -> Result<ServerMessage,
     Ok(response)
 }
 
+// Offer the opponent a draw.
+pub fn offer_draw(room_id: &str, player_id: &str) -> Result<ServerMessage, String> {
+    let mut state = GAME_STATE.lock().unwrap();
+
+    let room = state
+        .rooms
+        .get_mut(room_id)
+        .ok_or_else(|| "Room not found".to_string())?;
+
+    let by = side_of(room, player_id)?;
+    require_active(room)?;
+
+    // Only one pending draw offer at a time
+    if room.pending_draw.is_some() {
+        return Err("A draw offer is already pending".to_string());
+    }
+
+    room.pending_draw = Some(player_id.to_string());
+    log_events(room_id, Some(player_id), vec![GameEvent::DrawOffered { by }]);
+    save_game_to_db(room);
+
+    let response = ServerMessage::DrawOffered {
+        room_id: room_id.to_string(),
+        requester_id: player_id.to_string(),
+    };
+
+    if let Some(sender) = state.message_senders.get(room_id) {
+        let _ = sender.send(response.clone());
+    }
+
+    Ok(response)
+}
+
+// Accept the opponent's draw offer, ending the game.
+pub fn accept_draw(room_id: &str, player_id: &str) -> Result<ServerMessage, String> {
+    let mut state = GAME_STATE.lock().unwrap();
+
+    let room = state
+        .rooms
+        .get_mut(room_id)
+        .ok_or_else(|| "Room not found".to_string())?;
+
+    side_of(room, player_id)?;
+    match &room.pending_draw {
+        None => return Err("No pending draw offer".to_string()),
+        Some(requester_id) if requester_id == player_id => {
+            return Err("Requester cannot accept their own draw offer".to_string());
+        }
+        Some(_) => {}
+    }
+
+    let game_state = room.game_state.as_mut().ok_or_else(|| "Game not started".to_string())?;
+    let drawn = LifecycleState::Ended { result: Outcome::Draw, reason: EndReason::DrawAgreement };
+    game_state.status = advance(room_id, &game_state.status, drawn, player_id)?;
+    let game_state = game_state.clone();
+    room.clear_offers();
+    save_game_to_db(room);
+
+    let response = ServerMessage::DrawAccepted {
+        room_id: room_id.to_string(),
+        game_state,
+    };
+
+    if let Some(sender) = state.message_senders.get(room_id) {
+        let _ = sender.send(response.clone());
+    }
+
+    Ok(response)
+}
+
+// Decline the opponent's draw offer.
+pub fn decline_draw(room_id: &str, player_id: &str) -> Result<ServerMessage, String> {
+    let mut state = GAME_STATE.lock().unwrap();
+
+    let room = state
+        .rooms
+        .get_mut(room_id)
+        .ok_or_else(|| "Room not found".to_string())?;
+
+    let by = side_of(room, player_id)?;
+    match &room.pending_draw {
+        None => return Err("No pending draw offer".to_string()),
+        Some(requester_id) if requester_id == player_id => {
+            return Err("Requester cannot decline their own draw offer".to_string());
+        }
+        Some(_) => {}
+    }
+
+    room.pending_draw = None;
+    log_events(room_id, Some(player_id), vec![GameEvent::DrawDeclined { by }]);
+    save_game_to_db(room);
+
+    let response = ServerMessage::DrawDeclined {
+        room_id: room_id.to_string(),
+        by_player_id: player_id.to_string(),
+    };
+
+    if let Some(sender) = state.message_senders.get(room_id) {
+        let _ = sender.send(response.clone());
+    }
+
+    Ok(response)
+}
+
+// Give the opponent `ADD_TIME_MS` extra on their clock.
+pub fn add_time(room_id: &str, player_id: &str) -> Result<ServerMessage, String> {
+    let mut state = GAME_STATE.lock().unwrap();
+
+    let room = state
+        .rooms
+        .get_mut(room_id)
+        .ok_or_else(|| "Room not found".to_string())?;
+
+    let side = match side_of(room, player_id)? {
+        Side::White => Side::Black,
+        Side::Black => Side::White,
+    };
+    require_active(room)?;
+
+    match side {
+        Side::White => room.white_remaining_ms += ADD_TIME_MS,
+        Side::Black => room.black_remaining_ms += ADD_TIME_MS,
+    }
+    let adjusted = GameEvent::ClockAdjusted { side, delta_ms: ADD_TIME_MS as i64 };
+    log_events(room_id, Some(player_id), vec![adjusted]);
+    save_game_to_db(room);
+
+    let response = ServerMessage::TimeAdded {
+        room_id: room_id.to_string(),
+        by_player_id: player_id.to_string(),
+        white_remaining_ms: room.white_remaining_ms,
+        black_remaining_ms: room.black_remaining_ms,
+    };
+
+    if let Some(sender) = state.message_senders.get(room_id) {
+        let _ = sender.send(response.clone());
+    }
+
+    Ok(response)
+}
+
 // Database integration functions
-// These are placeholders for future implementation
+// Rooms are saved through the room store once it is started; without one
+// they live in memory only.
 
-pub fn save_game_to_db(_room_id: &str) -> Result<(), String> {
-    // In a real implementation, this would save the game state to a database
-    Ok(())
+// Queue the room's current state for saving
+pub fn save_game_to_db(room: &Room) {
+    if let Some(store) = RoomStore::global() {
+        store.save(room);
+    }
 }
 
-pub fn load_game_from_db(_room_id: &str) -> Result<Room, String> {
-    // In a real implementation, this would load the game state from a database
-    Err("Not implemented".to_string())
+// Reload the rooms of unfinished games saved before a restart, with their
+// clocks paused for the downtime. Returns the number of rooms restored.
+pub async fn load_games_from_db(db: &DatabaseConnection) -> Result<usize, String> {
+    let rooms = RoomStore::load_active(db, chrono::Utc::now())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut state = GAME_STATE.lock().unwrap();
+    let restored = rooms.len();
+    for room in rooms {
+        let (tx, _) = broadcast::channel(100);
+        state.message_senders.insert(room.id.clone(), tx);
+        state.rooms.insert(room.id.clone(), room);
+    }
+    Ok(restored)
 }
 
 #[cfg(test)]
@@ -433,7 +770,7 @@ mod tests {
         let room_id = create_room_with_time(10_000, 0);
         join_room(&room_id, "white_player", None).unwrap();
         join_room(&room_id, "black_player", None).unwrap();
-        let result = send_move(&room_id, "white_player", "e2e4");
+        let result = send_move(&room_id, "white_player", "e2e4", None);
         assert!(result.is_ok());
         cleanup_room(&room_id);
     }
@@ -444,7 +781,7 @@ mod tests {
         join_room(&room_id, "white_player", None).unwrap();
         join_room(&room_id, "black_player", None).unwrap();
         thread::sleep(Duration::from_millis(2000));
-        let result = send_move(&room_id, "white_player", "e2e4");
+        let result = send_move(&room_id, "white_player", "e2e4", None);
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("Time expired"));
         cleanup_room(&room_id);
@@ -456,7 +793,7 @@ mod tests {
         join_room(&room_id, "white_player", None).unwrap();
         join_room(&room_id, "black_player", None).unwrap();
         thread::sleep(Duration::from_millis(800));
-        let result = send_move(&room_id, "white_player", "e2e4");
+        let result = send_move(&room_id, "white_player", "e2e4", None);
         assert!(result.is_ok());
         cleanup_room(&room_id);
     }
@@ -467,7 +804,7 @@ mod tests {
         join_room(&room_id, "white_player", None).unwrap();
         join_room(&room_id, "black_player", None).unwrap();
         thread::sleep(Duration::from_millis(1500));
-        let result = send_move(&room_id, "white_player", "e2e4");
+        let result = send_move(&room_id, "white_player", "e2e4", None);
         assert!(result.is_err());
         cleanup_room(&room_id);
     }
@@ -478,7 +815,7 @@ mod tests {
         join_room(&room_id, "white_player", None).unwrap();
         join_room(&room_id, "black_player", None).unwrap();
         thread::sleep(Duration::from_millis(100));
-        send_move(&room_id, "white_player", "e2e4").unwrap();
+        send_move(&room_id, "white_player", "e2e4", None).unwrap();
         let state = GAME_STATE.lock().unwrap();
         let room = state.rooms.get(&room_id).unwrap();
         assert!(room.white_remaining_ms < 10_000);
@@ -500,7 +837,7 @@ mod tests {
             room.white_remaining_ms
         };
         
-        send_move(&room_id, "white_player", "e2e4").unwrap();
+        send_move(&room_id, "white_player", "e2e4", None).unwrap();
         
         let state = GAME_STATE.lock().unwrap();
         let room = state.rooms.get(&room_id).unwrap();
@@ -517,17 +854,176 @@ mod tests {
         cleanup_room(&room_id);
     }
 
+    #[test]
+    fn test_move_accepts_san_and_returns_canonical_forms() {
+        let room_id = create_room_with_time(10_000, 0);
+        join_room(&room_id, "white_player", None).unwrap();
+        join_room(&room_id, "black_player", None).unwrap();
+
+        let Ok(ServerMessage::MoveMade { move_notation, san, .. }) = send_move(&room_id, "white_player", "Nf3", None) else {
+            panic!("expected MoveMade");
+        };
+        assert_eq!((move_notation.as_str(), san.as_str()), ("g1f3", "Nf3"));
+
+        let result = send_move(&room_id, "black_player", "e7e4", None);
+        assert!(result.unwrap_err().contains("Illegal move"));
+        cleanup_room(&room_id);
+    }
+
+    #[test]
+    fn test_retried_move_is_not_applied_twice() {
+        let room_id = create_room_with_time(10_000, 0);
+        join_room(&room_id, "white_player", None).unwrap();
+        join_room(&room_id, "black_player", None).unwrap();
+        send_move(&room_id, "white_player", "e2e4", Some(1)).unwrap();
+
+        // Same move again, in another notation: answered, not replayed
+        let Ok(ServerMessage::MoveMade { ply, san, current_turn, .. }) = send_move(&room_id, "white_player", "e4", Some(1)) else {
+            panic!("expected MoveMade");
+        };
+        assert_eq!((ply, san.as_str()), (1, "e4"));
+        assert!(matches!(current_turn, PieceColor::Black));
+
+        // A different move for a played ply, or a ply from the future, is stale
+        for (player, notation, ply) in [("white_player", "d2d4", 1), ("black_player", "e7e5", 3)] {
+            let Ok(ServerMessage::MoveRejected { ply: current, game_state, .. }) = send_move(&room_id, player, notation, Some(ply)) else {
+                panic!("expected MoveRejected");
+            };
+            assert_eq!(current, 1);
+            assert!(game_state.is_some());
+        }
+
+        let state = GAME_STATE.lock().unwrap();
+        assert_eq!(state.rooms.get(&room_id).unwrap().moves.len(), 1);
+        drop(state);
+        send_move(&room_id, "black_player", "e7e5", Some(2)).unwrap();
+        cleanup_room(&room_id);
+    }
+
+    #[test]
+    fn test_lifecycle_events_for_abandoned_game() {
+        let mut events = GameLifecycle::subscribe();
+        let room_id = create_room_with_time(10_000, 0);
+        join_room(&room_id, "white_player", None).unwrap();
+        join_room(&room_id, "black_player", None).unwrap();
+        leave_room(&room_id, "black_player").unwrap();
+
+        let state = GAME_STATE.lock().unwrap();
+        let room = state.rooms.get(&room_id).unwrap();
+        assert_eq!(room.lifecycle(), LifecycleState::Aborted);
+        drop(state);
+        assert_eq!(
+            join_room(&room_id, "late_player", None).unwrap_err(),
+            "Game is already finished"
+        );
+
+        let game_id = Uuid::parse_str(&room_id).unwrap();
+        let mut transitions = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if event.game_id == game_id {
+                assert_eq!(event.source, TransitionSource::Socket);
+                transitions.push((event.from.name(), event.to.name()));
+            }
+        }
+        assert_eq!(
+            transitions,
+            vec![("created", "waiting"), ("waiting", "started"), ("started", "aborted")]
+        );
+        cleanup_room(&room_id);
+    }
+
+    #[test]
+    fn test_draw_offer_answered_by_opponent_or_next_move() {
+        let room_id = create_room_with_time(10_000, 0);
+        join_room(&room_id, "white_player", None).unwrap();
+        join_room(&room_id, "black_player", None).unwrap();
+
+        offer_draw(&room_id, "white_player").unwrap();
+        assert!(offer_draw(&room_id, "black_player").is_err());
+        assert!(accept_draw(&room_id, "white_player").is_err());
+        decline_draw(&room_id, "black_player").unwrap();
+
+        // Moving on answers an offer as well
+        offer_draw(&room_id, "white_player").unwrap();
+        send_move(&room_id, "white_player", "e2e4", None).unwrap();
+        assert!(accept_draw(&room_id, "black_player").is_err());
+
+        offer_draw(&room_id, "black_player").unwrap();
+        let Ok(ServerMessage::DrawAccepted { game_state, .. }) = accept_draw(&room_id, "white_player") else {
+            panic!("expected DrawAccepted");
+        };
+        assert_eq!(
+            game_state.status,
+            LifecycleState::Ended { result: Outcome::Draw, reason: EndReason::DrawAgreement }
+        );
+        assert!(offer_draw(&room_id, "white_player").is_err());
+        cleanup_room(&room_id);
+    }
+
+    #[test]
+    fn test_add_time_credits_the_opponent() {
+        let room_id = create_room_with_time(10_000, 0);
+        join_room(&room_id, "white_player", None).unwrap();
+        assert!(add_time(&room_id, "white_player").is_err());
+        join_room(&room_id, "black_player", None).unwrap();
+
+        let Ok(ServerMessage::TimeAdded { white_remaining_ms, black_remaining_ms, .. }) = add_time(&room_id, "white_player") else {
+            panic!("expected TimeAdded");
+        };
+        assert_eq!((white_remaining_ms, black_remaining_ms), (10_000, 10_000 + ADD_TIME_MS));
+        cleanup_room(&room_id);
+    }
+
+    #[test]
+    fn test_takeback_requester_cannot_reject_own_offer() {
+        let room_id = create_room_with_time(10_000, 0);
+        join_room(&room_id, "white_player", None).unwrap();
+        join_room(&room_id, "black_player", None).unwrap();
+        send_move(&room_id, "white_player", "e2e4", None).unwrap();
+        send_move(&room_id, "black_player", "e7e5", None).unwrap();
+
+        offer_takeback(&room_id, "white_player").unwrap();
+        assert!(reject_takeback(&room_id, "white_player").is_err());
+        reject_takeback(&room_id, "black_player").unwrap();
+        cleanup_room(&room_id);
+    }
+
+    #[test]
+    fn test_takeback_cannot_be_accepted_after_the_game_ends() {
+        let room_id = create_room_with_time(10_000, 0);
+        join_room(&room_id, "white_player", None).unwrap();
+        join_room(&room_id, "black_player", None).unwrap();
+        send_move(&room_id, "white_player", "e2e4", None).unwrap();
+        send_move(&room_id, "black_player", "e7e5", None).unwrap();
+
+        offer_takeback(&room_id, "white_player").unwrap();
+        offer_draw(&room_id, "white_player").unwrap();
+        accept_draw(&room_id, "black_player").unwrap();
+
+        assert_eq!(accept_takeback(&room_id, "black_player").unwrap_err(), "Game is not active");
+        let state = GAME_STATE.lock().unwrap();
+        let room = state.rooms.get(&room_id).unwrap();
+        assert_eq!(room.moves.len(), 2);
+        assert!(room.lifecycle().is_terminal());
+        assert!(room.pending_takeback.is_none());
+        drop(state);
+        cleanup_room(&room_id);
+    }
+
     #[test]
     fn test_game_timeout_status() {
         let room_id = create_room_with_time(100, 0);
         join_room(&room_id, "white_player", None).unwrap();
         join_room(&room_id, "black_player", None).unwrap();
         thread::sleep(Duration::from_millis(1000));
-        let _ = send_move(&room_id, "white_player", "e2e4");
+        let _ = send_move(&room_id, "white_player", "e2e4", None);
         let state = GAME_STATE.lock().unwrap();
         let room = state.rooms.get(&room_id).unwrap();
         let game_state = room.game_state.as_ref().unwrap();
-        assert!(matches!(game_state.status, GameStatus::Timeout));
+        assert!(matches!(
+            game_state.status,
+            LifecycleState::Ended { result: Outcome::BlackWins, reason: EndReason::TimeForfeit }
+        ));
         drop(state);
         cleanup_room(&room_id);
     }
@@ -1,31 +1,36 @@
 use futures_util::{SinkExt, StreamExt};
-use serde_json::{from_str, to_string};
 use tokio::sync::broadcast;
 use tokio_tungstenite::tungstenite::Message;
 
 use crate::game::{
+    accept_draw,
     accept_takeback,
+    add_time,
+    decline_draw,
     get_game_log,
     get_room_sender,
     join_room,
     leave_room,
+    offer_draw,
     offer_takeback,
     reject_takeback,
     send_move,
 };
 use crate::models::{ClientMessage, ServerMessage};
+use crate::protocol::WireFormat;
 
 // Handle a client message
 pub async fn handle_client_message(
-    message: &str,
+    client_message: Result<ClientMessage, String>,
     sender: &mut futures_util::stream::SplitSink<
         tokio_tungstenite::WebSocketStream<tokio::net::TcpStream>,
         Message,
     >,
     room_senders: &mut Vec<(String, broadcast::Sender<ServerMessage>)>,
+    format: WireFormat,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Parse the message
-    let client_message: ClientMessage = match from_str(message) {
+    let client_message: ClientMessage = match client_message {
         Ok(msg) => msg,
         Err(e) => {
             log::error!("Failed to parse client message: {}", e);
@@ -34,7 +39,7 @@ pub async fn handle_client_message(
                 message: "Failed to parse message".to_string(),
             };
             sender
-                .send(Message::Text(to_string(&error_msg)?))
+                .send(format.encode(&error_msg)?)
                 .await?;
             return Ok(());
         }
@@ -52,7 +57,7 @@ pub async fn handle_client_message(
             match join_room(&payload.room_id, &payload.player_id, payload.player_name) {
                 Ok(response) => {
                     // Send response to client
-                    sender.send(Message::Text(to_string(&response)?)).await?;
+                    sender.send(format.encode(&response)?).await?;
 
                     // Subscribe to room messages
                     if let Some(room_sender) = get_room_sender(&payload.room_id) {
@@ -64,7 +69,7 @@ pub async fn handle_client_message(
                         code: "JOIN_ERROR".to_string(),
                         message: e,
                     };
-                    sender.send(Message::Text(to_string(&error_msg)?)).await?;
+                    sender.send(format.encode(&error_msg)?).await?;
                 }
             }
         }
@@ -76,16 +81,16 @@ pub async fn handle_client_message(
                 payload.room_id
             );
 
-            match send_move(&payload.room_id, &payload.player_id, &payload.move_notation) {
+            match send_move(&payload.room_id, &payload.player_id, &payload.move_notation, payload.ply) {
                 Ok(response) => {
-                    sender.send(Message::Text(to_string(&response)?)).await?;
+                    sender.send(format.encode(&response)?).await?;
                 }
                 Err(e) => {
                     let error_msg = ServerMessage::Error {
                         code: "MOVE_ERROR".to_string(),
                         message: e,
                     };
-                    sender.send(Message::Text(to_string(&error_msg)?)).await?;
+                    sender.send(format.encode(&error_msg)?).await?;
                 }
             }
         }
@@ -98,7 +103,7 @@ pub async fn handle_client_message(
 
             match leave_room(&payload.room_id, &payload.player_id) {
                 Ok(response) => {
-                    sender.send(Message::Text(to_string(&response)?)).await?;
+                    sender.send(format.encode(&response)?).await?;
 
                     // Unsubscribe from room messages
                     room_senders.retain(|(id, _)| id != &payload.room_id);
@@ -108,7 +113,7 @@ pub async fn handle_client_message(
                         code: "LEAVE_ERROR".to_string(),
                         message: e,
                     };
-                    sender.send(Message::Text(to_string(&error_msg)?)).await?;
+                    sender.send(format.encode(&error_msg)?).await?;
                 }
             }
         }
@@ -117,14 +122,14 @@ pub async fn handle_client_message(
 
             match get_game_log(&payload.room_id) {
                 Ok(response) => {
-                    sender.send(Message::Text(to_string(&response)?)).await?;
+                    sender.send(format.encode(&response)?).await?;
                 }
                 Err(e) => {
                     let error_msg = ServerMessage::Error {
                         code: "LOG_ERROR".to_string(),
                         message: e,
                     };
-                    sender.send(Message::Text(to_string(&error_msg)?)).await?;
+                    sender.send(format.encode(&error_msg)?).await?;
                 }
             }
         }
@@ -137,14 +142,14 @@ pub async fn handle_client_message(
 
             match offer_takeback(&payload.room_id, &payload.player_id) {
                 Ok(response) => {
-                    sender.send(Message::Text(to_string(&response)?)).await?;
+                    sender.send(format.encode(&response)?).await?;
                 }
                 Err(e) => {
                     let error_msg = ServerMessage::Error {
                         code: "TAKEBACK_OFFER_ERROR".to_string(),
                         message: e,
                     };
-                    sender.send(Message::Text(to_string(&error_msg)?)).await?;
+                    sender.send(format.encode(&error_msg)?).await?;
                 }
             }
         }
@@ -157,14 +162,14 @@ pub async fn handle_client_message(
 
             match accept_takeback(&payload.room_id, &payload.player_id) {
                 Ok(response) => {
-                    sender.send(Message::Text(to_string(&response)?)).await?;
+                    sender.send(format.encode(&response)?).await?;
                 }
                 Err(e) => {
                     let error_msg = ServerMessage::Error {
                         code: "TAKEBACK_ACCEPT_ERROR".to_string(),
                         message: e,
                     };
-                    sender.send(Message::Text(to_string(&error_msg)?)).await?;
+                    sender.send(format.encode(&error_msg)?).await?;
                 }
             }
         }
@@ -177,14 +182,94 @@ pub async fn handle_client_message(
 
             match reject_takeback(&payload.room_id, &payload.player_id) {
                 Ok(response) => {
-                    sender.send(Message::Text(to_string(&response)?)).await?;
+                    sender.send(format.encode(&response)?).await?;
                 }
                 Err(e) => {
                     let error_msg = ServerMessage::Error {
                         code: "TAKEBACK_REJECT_ERROR".to_string(),
                         message: e,
                     };
-                    sender.send(Message::Text(to_string(&error_msg)?)).await?;
+                    sender.send(format.encode(&error_msg)?).await?;
+                }
+            }
+        }
+        ClientMessage::OfferDraw(payload) => {
+            log::info!(
+                "Player {} offering a draw in room {}",
+                payload.player_id,
+                payload.room_id
+            );
+
+            match offer_draw(&payload.room_id, &payload.player_id) {
+                Ok(response) => {
+                    sender.send(format.encode(&response)?).await?;
+                }
+                Err(e) => {
+                    let error_msg = ServerMessage::Error {
+                        code: "DRAW_OFFER_ERROR".to_string(),
+                        message: e,
+                    };
+                    sender.send(format.encode(&error_msg)?).await?;
+                }
+            }
+        }
+        ClientMessage::AcceptDraw(payload) => {
+            log::info!(
+                "Player {} accepting a draw in room {}",
+                payload.player_id,
+                payload.room_id
+            );
+
+            match accept_draw(&payload.room_id, &payload.player_id) {
+                Ok(response) => {
+                    sender.send(format.encode(&response)?).await?;
+                }
+                Err(e) => {
+                    let error_msg = ServerMessage::Error {
+                        code: "DRAW_ACCEPT_ERROR".to_string(),
+                        message: e,
+                    };
+                    sender.send(format.encode(&error_msg)?).await?;
+                }
+            }
+        }
+        ClientMessage::DeclineDraw(payload) => {
+            log::info!(
+                "Player {} declining a draw in room {}",
+                payload.player_id,
+                payload.room_id
+            );
+
+            match decline_draw(&payload.room_id, &payload.player_id) {
+                Ok(response) => {
+                    sender.send(format.encode(&response)?).await?;
+                }
+                Err(e) => {
+                    let error_msg = ServerMessage::Error {
+                        code: "DRAW_DECLINE_ERROR".to_string(),
+                        message: e,
+                    };
+                    sender.send(format.encode(&error_msg)?).await?;
+                }
+            }
+        }
+        ClientMessage::AddTime(payload) => {
+            log::info!(
+                "Player {} adding time for their opponent in room {}",
+                payload.player_id,
+                payload.room_id
+            );
+
+            match add_time(&payload.room_id, &payload.player_id) {
+                Ok(response) => {
+                    sender.send(format.encode(&response)?).await?;
+                }
+                Err(e) => {
+                    let error_msg = ServerMessage::Error {
+                        code: "ADD_TIME_ERROR".to_string(),
+                        message: e,
+                    };
+                    sender.send(format.encode(&error_msg)?).await?;
                 }
             }
         }
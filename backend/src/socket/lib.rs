@@ -2,4 +2,6 @@
 pub mod game;
 pub mod handlers;
 pub mod models;
+pub mod persistence;
+pub mod protocol;
 pub mod websocket;
@@ -1,8 +1,11 @@
 mod game;
 mod handlers;
 mod models;
+mod persistence;
+mod protocol;
 mod websocket;
 
+use sea_orm::Database;
 use std::env;
 use tokio::net::TcpListener;
 use websocket::handle_connection;
@@ -19,30 +22,54 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     // Initialize the game state
     game::init_game_state();
+
+    // Restore rooms saved before the last shutdown and keep saving them
+    let room_store = match env::var("DATABASE_URL") {
+        Ok(database_url) => {
+            let db = Database::connect(&database_url).await?;
+            let restored = game::load_games_from_db(&db).await?;
+            log::info!("Restored {} rooms from the database", restored);
+            Some(persistence::RoomStore::start(db))
+        }
+        Err(_) => {
+            log::warn!("DATABASE_URL is not set; rooms are kept in memory only");
+            None
+        }
+    };
     
     // Create the TCP listener
     let listener = TcpListener::bind(&addr).await?;
     log::info!("WebSocket server listening on: {}", addr);
     
-       // Accept connections
-loop {
-       match listener.accept().await {
-                Ok((stream, addr)) => {
-                    log::info!("New connection from: {}", addr);
-                    
-                    // Spawn a new task for each connection
-                    tokio::spawn(async move {
-                        if let Err(e) = handle_connection(stream, addr).await {
-                            log::error!("Error handling connection: {}", e);
-                        }
-                    });
-                }
-                Err(e) => {
-                    log::error!("Failed to accept connection: {}", e);
-                    // Continue accepting connections despite errors
-                }
+    // Accept connections until the process is asked to stop
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = tokio::signal::ctrl_c() => break,
+        };
+        match accepted {
+            Ok((stream, addr)) => {
+                log::info!("New connection from: {}", addr);
+                
+                // Spawn a new task for each connection
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, addr).await {
+                        log::error!("Error handling connection: {}", e);
+                    }
+                });
+            }
+            Err(e) => {
+                log::error!("Failed to accept connection: {}", e);
+                // Continue accepting connections despite errors
             }
         }
+    }
+
+    // Write the latest state of every room before exiting
+    log::info!("Shutting down");
+    if let Some(room_store) = room_store {
+        room_store.shutdown().await;
+    }
     
     Ok(())
 }
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use service::lifecycle::{LifecycleState, Outcome};
 use std::collections::HashMap;
 use std::time::SystemTime;
 
@@ -13,6 +14,10 @@ pub enum ClientMessage {
     OfferTakeback(OfferTakebackPayload),
     AcceptTakeback(AcceptTakebackPayload),
     RejectTakeback(RejectTakebackPayload),
+    OfferDraw(OfferDrawPayload),
+    AcceptDraw(AcceptDrawPayload),
+    DeclineDraw(DeclineDrawPayload),
+    AddTime(AddTimePayload),
 }
 
 #[derive(Debug, Deserialize)]
@@ -26,7 +31,13 @@ pub struct JoinRoomPayload {
 pub struct SendMovePayload {
     pub room_id: String,
     pub player_id: String,
+    /// UCI (`e2e4`, king-takes-rook `e1h1`) or SAN (`Nf3`, `O-O`)
     pub move_notation: String,
+    /// Ply this move will have (1 for White's first move). Resending a
+    /// played move for its ply is a safe retry; other moves for an old ply
+    /// are rejected.
+    #[serde(default)]
+    pub ply: Option<u32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -58,6 +69,31 @@ pub struct RejectTakebackPayload {
     pub player_id: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct OfferDrawPayload {
+    pub room_id: String,
+    pub player_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AcceptDrawPayload {
+    pub room_id: String,
+    pub player_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeclineDrawPayload {
+    pub room_id: String,
+    pub player_id: String,
+}
+
+/// Gives the opponent extra time on their clock
+#[derive(Debug, Deserialize)]
+pub struct AddTimePayload {
+    pub room_id: String,
+    pub player_id: String,
+}
+
 // Server message types
 #[derive(Debug, Clone, Serialize)]
 #[serde(tag = "type")]
@@ -68,11 +104,19 @@ pub enum ServerMessage {
         players: Vec<Player>,
         game_state: Option<GameState>,
     },
+    /// Delta update for a single move; clients apply it to their own board
+    /// instead of receiving a full snapshot. `ply` lets them detect gaps and
+    /// request a resync with `RequestGameLog`. `move_notation` is the
+    /// canonical UCI form whatever notation the mover sent.
     MoveMade {
         room_id: String,
         player_id: String,
         move_notation: String,
-        game_state: GameState,
+        san: String,
+        ply: u32,
+        white_remaining_ms: u64,
+        black_remaining_ms: u64,
+        current_turn: PieceColor,
     },
     PlayerLeft {
         room_id: String,
@@ -95,6 +139,25 @@ pub enum ServerMessage {
         room_id: String,
         by_player_id: String,
     },
+    DrawOffered {
+        room_id: String,
+        requester_id: String,
+    },
+    /// The game ended in a draw by agreement
+    DrawAccepted {
+        room_id: String,
+        game_state: GameState,
+    },
+    DrawDeclined {
+        room_id: String,
+        by_player_id: String,
+    },
+    TimeAdded {
+        room_id: String,
+        by_player_id: String,
+        white_remaining_ms: u64,
+        black_remaining_ms: u64,
+    },
     Error {
         code: String,
         message: String,
@@ -105,10 +168,14 @@ pub enum ServerMessage {
         loser_id: String,
         reason: String,
     },
+    /// Sent only to the mover when a move was made against an outdated
+    /// position; carries the current ply and game state to resync from
     MoveRejected {
         room_id: String,
         player_id: String,
         reason: String,
+        ply: u32,
+        game_state: Option<GameState>,
     },
 }
 
@@ -128,9 +195,10 @@ pub enum PieceColor {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameState {
+    pub fen: String,
     pub board: HashMap<String, ChessPiece>,
     pub current_turn: PieceColor,
-    pub status: GameStatus,
+    pub status: LifecycleState,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -149,25 +217,17 @@ pub enum PieceType {
     King,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum GameStatus {
-    Waiting,
-    InProgress,
-    Checkmate,
-    Stalemate,
-    Draw,
-    Timeout,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MoveRecord {
     pub player_id: String,
+    /// Canonical UCI of the move
     pub move_notation: String,
+    pub san: String,
     pub timestamp: u64,
 }
 
 impl MoveRecord {
-    pub fn new(player_id: String, move_notation: String) -> Self {
+    pub fn new(player_id: String, move_notation: String, san: String) -> Self {
         let timestamp = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap_or_default()
@@ -176,6 +236,7 @@ impl MoveRecord {
         Self {
             player_id,
             move_notation,
+            san,
             timestamp,
         }
     }
@@ -193,6 +254,8 @@ pub struct Room {
     pub initial_time_ms: u64,
     pub increment_ms: u64,
     pub pending_takeback: Option<String>,
+    #[serde(default)]
+    pub pending_draw: Option<String>,
 }
 
 // Default time control: 10 minutes (600000ms)
@@ -212,6 +275,7 @@ impl Room {
             initial_time_ms: DEFAULT_INITIAL_TIME_MS,
             increment_ms: DEFAULT_INCREMENT_MS,
             pending_takeback: None,
+            pending_draw: None,
         }
     }
 
@@ -227,6 +291,7 @@ impl Room {
             initial_time_ms,
             increment_ms,
             pending_takeback: None,
+            pending_draw: None,
         }
     }
     
@@ -261,61 +326,111 @@ impl Room {
         initial_len != self.players.len()
     }
     
-    pub fn add_move(&mut self, player_id: String, move_notation: String) {
-        let move_record = MoveRecord::new(player_id, move_notation);
+    pub fn add_move(&mut self, player_id: String, move_notation: String, san: String) {
+        let move_record = MoveRecord::new(player_id, move_notation, san);
         self.moves.push(move_record);
     }
+
+    /// Restart the clock of the player to move after the room was restored.
+    /// Time between the last save and `now_ms` is not charged, as if the
+    /// clocks had been paused while the server was down.
+    pub fn resume(&mut self, saved_at_ms: u64, now_ms: u64) {
+        if let Some(last_move_at) = self.last_move_at {
+            let used_ms = saved_at_ms.saturating_sub(last_move_at);
+            self.last_move_at = Some(now_ms.saturating_sub(used_ms));
+        }
+    }
+
+    /// Color of a seated player
+    pub fn color_of(&self, player_id: &str) -> Option<PieceColor> {
+        self.players.iter().find(|p| p.id == player_id).and_then(|p| p.color.clone())
+    }
+
+    /// Withdraw pending draw and takeback offers, which nobody may accept
+    /// once the position changed or the game ended
+    pub fn clear_offers(&mut self) {
+        self.pending_takeback = None;
+        self.pending_draw = None;
+    }
+
+    /// Lifecycle state of the room's game; the game state only exists once
+    /// both players are seated
+    pub fn lifecycle(&self) -> LifecycleState {
+        match &self.game_state {
+            Some(game_state) => game_state.status.clone(),
+            None if self.players.is_empty() => LifecycleState::Created,
+            None => LifecycleState::Waiting,
+        }
+    }
 }
 
 impl GameState {
     pub fn new_game() -> Self {
-        // Initialize a standard chess board
-        let mut board = HashMap::new();
-        
-        // Set up pawns
-        for file in "abcdefgh".chars() {
-            let white_pawn_pos = format!("{}{}", file, 2);
-            let black_pawn_pos = format!("{}{}", file, 7);
-            
-            board.insert(white_pawn_pos, ChessPiece { piece_type: PieceType::Pawn, color: PieceColor::White });
-            board.insert(black_pawn_pos, ChessPiece { piece_type: PieceType::Pawn, color: PieceColor::Black });
-        }
-        
-        // Set up other pieces
-        for (file, piece_type) in "abcdefgh".chars().zip([
-            PieceType::Rook, PieceType::Knight, PieceType::Bishop, PieceType::Queen,
-            PieceType::King, PieceType::Bishop, PieceType::Knight, PieceType::Rook
-        ].iter()) {
-            let white_pos = format!("{}{}", file, 1);
-            let black_pos = format!("{}{}", file, 8);
-            
-            board.insert(white_pos, ChessPiece { piece_type: piece_type.clone(), color: PieceColor::White });
-            board.insert(black_pos, ChessPiece { piece_type: piece_type.clone(), color: PieceColor::Black });
-        }
-        
         Self {
-            board,
+            fen: chess::STARTING_FEN.to_string(),
+            board: board_from_fen(chess::STARTING_FEN),
             current_turn: PieceColor::White,
-            status: GameStatus::InProgress,
+            status: LifecycleState::Started,
         }
     }
     
-    // Apply a move to the game state
-    // This is a simplified implementation that doesn't validate chess rules
-    pub fn apply_move(&mut self, move_notation: &str) -> Result<(), String> {
+    /// Validate a move in UCI or SAN against the current position and
+    /// apply it, returning the move in its canonical forms.
+    pub fn apply_move(&mut self, move_notation: &str) -> Result<chess::PlayedMove, String> {
         // Defensive guard: only allow moves when game is in progress
-        if !matches!(self.status, GameStatus::InProgress) {
+        if self.status != LifecycleState::Started {
             return Err("Game is not active".to_string());
         }
 
-        // In a real implementation, this would parse the move notation and update the board
-        // For now, we'll just toggle the current turn
+        let played = chess::play_move(&self.fen, move_notation).map_err(|e| e.to_string())?;
 
+        self.fen = played.fen.clone();
+        self.board = board_from_fen(&played.fen);
         self.current_turn = match self.current_turn {
             PieceColor::White => PieceColor::Black,
             PieceColor::Black => PieceColor::White,
         };
+        if let (Some(outcome), Some(termination)) = (&played.outcome, played.termination) {
+            let ended = LifecycleState::Ended {
+                result: Outcome::from_pgn(outcome).unwrap_or(Outcome::Draw),
+                reason: termination.into(),
+            };
+            self.status = self.status.transition(ended).map_err(|e| e.to_string())?;
+        }
 
-        Ok(())
+        Ok(played)
     }
 }
+
+/// Piece map keyed by square name, from the placement field of a FEN
+fn board_from_fen(fen: &str) -> HashMap<String, ChessPiece> {
+    let mut board = HashMap::new();
+    let placement = fen.split_whitespace().next().unwrap_or_default();
+
+    for (rank_idx, rank) in placement.split('/').enumerate() {
+        let rank_number = 8 - rank_idx;
+        let mut file = 0u8;
+        for c in rank.chars() {
+            if let Some(skip) = c.to_digit(10) {
+                file += skip as u8;
+                continue;
+            }
+            let piece_type = match c.to_ascii_lowercase() {
+                'p' => PieceType::Pawn,
+                'r' => PieceType::Rook,
+                'n' => PieceType::Knight,
+                'b' => PieceType::Bishop,
+                'q' => PieceType::Queen,
+                _ => PieceType::King,
+            };
+            let color = if c.is_ascii_uppercase() { PieceColor::White } else { PieceColor::Black };
+            board.insert(
+                format!("{}{}", (b'a' + file) as char, rank_number),
+                ChessPiece { piece_type, color },
+            );
+            file += 1;
+        }
+    }
+
+    board
+}
@@ -0,0 +1,681 @@
+//! Room persistence for crash recovery.
+//!
+//! Every change to a room queues a copy of it, and a background task writes
+//! the latest copy of each room to `socket_room` in batches. At startup the
+//! rooms of unfinished games are loaded back with their clocks paused for
+//! the time the server was down. Lifecycle transitions and the other events
+//! of the rooms' games go through the same queue into the `game_transition`
+//! audit table and the `game_event` log.
+//!
+//! Handlers queue while holding the room lock, so queueing never waits.
+//! When the writer falls behind, for example while the database is
+//! unreachable, room copies that find the queue full are kept aside with
+//! only the latest copy of each room, and log events are dropped. Failed
+//! batches are retried a few times and then split, so a write that can
+//! never succeed is set aside on its own instead of holding up the rest.
+//! Rooms and log events are written in separate transactions, so a game
+//! whose log is broken does not keep its room from being saved. Queued
+//! updates are written before the server exits.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use db_entity::{prelude::SocketRoom, socket_room};
+use sea_orm::{
+    sea_query::OnConflict, ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+    TransactionTrait,
+};
+use service::game_events::{GameEvent, GameEventLog};
+use service::lifecycle::{GameLifecycle, LifecycleEvent};
+use uuid::Uuid;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+
+use crate::models::Room;
+
+/// Most rooms written in a single insert
+const MAX_BATCH_SIZE: usize = 256;
+
+/// Updates that may wait for the writer before room copies are coalesced
+/// and events dropped
+const QUEUE_CAPACITY: usize = 8 * MAX_BATCH_SIZE;
+
+/// How long a queued update may wait for others before being written
+const FLUSH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Delay before the first retry of a failed batch, doubled up to `MAX_RETRY_DELAY`
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(100);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Attempts per batch before it is split to find the writes that fail
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Attempts per batch once shutdown has begun, so the process can exit
+const SHUTDOWN_ATTEMPTS: u32 = 3;
+
+static STORE: OnceLock<RoomStore> = OnceLock::new();
+
+/// Queued change to a saved room
+#[derive(Debug, Clone)]
+pub enum RoomWrite {
+    Save { room: Box<Room>, saved_at: DateTime<Utc> },
+    Delete { room_id: String, deleted_at: DateTime<Utc> },
+    /// Lifecycle transition of the room's game, for the audit table and
+    /// the game's log
+    Transition(LifecycleEvent),
+    /// Other events of the room's game, for its log
+    Events { game_id: Uuid, actor: Option<Uuid>, events: Vec<GameEvent> },
+}
+
+impl RoomWrite {
+    /// The room a save or delete is for, and when it was made
+    fn room(&self) -> Option<(&str, DateTime<Utc>)> {
+        match self {
+            RoomWrite::Save { room, saved_at } => Some((&room.id, *saved_at)),
+            RoomWrite::Delete { room_id, deleted_at } => Some((room_id, *deleted_at)),
+            RoomWrite::Transition(_) | RoomWrite::Events { .. } => None,
+        }
+    }
+}
+
+/// Latest save or delete of each room that found the queue full
+type Overflow = Arc<Mutex<HashMap<String, RoomWrite>>>;
+
+/// Handle for queueing room updates
+#[derive(Clone)]
+pub struct RoomStore {
+    sender: mpsc::Sender<RoomWrite>,
+    overflow: Overflow,
+}
+
+/// What the writer drains: the queue and the room writes kept aside while
+/// it was full
+pub struct RoomQueue {
+    receiver: mpsc::Receiver<RoomWrite>,
+    overflow: Overflow,
+}
+
+/// The spawned writer task, kept by the server to drain it on shutdown
+pub struct RoomStoreWriter {
+    shutdown: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl RoomStore {
+    /// Create a handle and the receiving end that `run` drains.
+    pub fn channel() -> (Self, RoomQueue) {
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        let overflow = Overflow::default();
+        (Self { sender, overflow: overflow.clone() }, RoomQueue { receiver, overflow })
+    }
+
+    /// Spawn the writer task and make its handle the one `global` returns.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn start(db: DatabaseConnection) -> RoomStoreWriter {
+        let (store, queue) = Self::channel();
+        let (shutdown, stopping) = watch::channel(false);
+        let task = tokio::spawn(async move { Self::run(&db, queue, stopping).await });
+        if STORE.set(store).is_err() {
+            log::warn!("Room store was already started; keeping the first one");
+        }
+        RoomStoreWriter { shutdown, task }
+    }
+
+    /// The started store; `None` when rooms are kept in memory only
+    pub fn global() -> Option<&'static RoomStore> {
+        STORE.get()
+    }
+
+    /// Queue the room's current state. Rooms whose game is over are
+    /// removed instead, as there is nothing left to recover.
+    pub fn save(&self, room: &Room) {
+        if room.lifecycle().is_terminal() {
+            return self.delete(&room.id);
+        }
+        self.send(RoomWrite::Save { room: Box::new(room.clone()), saved_at: Utc::now() });
+    }
+
+    pub fn delete(&self, room_id: &str) {
+        self.send(RoomWrite::Delete { room_id: room_id.to_string(), deleted_at: Utc::now() });
+    }
+
+    /// Queue a transition the room's game went through
+    pub fn record(&self, event: LifecycleEvent) {
+        self.send(RoomWrite::Transition(event));
+    }
+
+    /// Queue events for the log of the room's game
+    pub fn append(&self, game_id: Uuid, actor: Option<Uuid>, events: Vec<GameEvent>) {
+        self.send(RoomWrite::Events { game_id, actor, events });
+    }
+
+    // Callers hold the room lock, so this never waits for the writer. The
+    // overflow lock is held across `try_send` so the writer cannot take the
+    // overflow between a full queue and the copy being kept aside.
+    fn send(&self, write: RoomWrite) {
+        let mut overflow = self.overflow.lock().unwrap();
+        let write = match self.sender.try_send(write) {
+            Ok(()) => return,
+            Err(mpsc::error::TrySendError::Full(write)) => write,
+            Err(mpsc::error::TrySendError::Closed(_)) => {
+                log::error!("Room store writer has stopped; dropping room update");
+                return;
+            }
+        };
+
+        let Some((room_id, at)) = write.room() else {
+            log::error!("Room store queue is full; dropping {:?}", write);
+            return;
+        };
+        let newer = overflow
+            .get(room_id)
+            .and_then(RoomWrite::room)
+            .is_none_or(|(_, kept_at)| kept_at <= at);
+        if newer {
+            log::warn!("Room store queue is full; keeping the latest copy of room {}", room_id);
+            overflow.insert(room_id.to_string(), write);
+        }
+    }
+
+    /// Write queued updates in batches until every handle is dropped or
+    /// `shutdown` is set, then write whatever is still queued.
+    pub async fn run(db: &DatabaseConnection, queue: RoomQueue, mut shutdown: watch::Receiver<bool>) {
+        let RoomQueue { mut receiver, overflow } = queue;
+        let mut batch = Vec::with_capacity(MAX_BATCH_SIZE);
+        let mut stopping = false;
+
+        loop {
+            let first = tokio::select! {
+                next = receiver.recv() => next,
+                _ = shutdown.changed(), if !stopping => {
+                    // Refuse new updates but keep draining the queued ones
+                    receiver.close();
+                    stopping = true;
+                    continue;
+                }
+            };
+            let Some(first) = first else {
+                break;
+            };
+            batch.push(first);
+
+            if stopping {
+                while batch.len() < MAX_BATCH_SIZE {
+                    match receiver.try_recv() {
+                        Ok(write) => batch.push(write),
+                        Err(_) => break,
+                    }
+                }
+            } else {
+                // Busy rooms change many times per flush; only the last copy is written
+                let deadline = tokio::time::sleep(FLUSH_INTERVAL);
+                tokio::pin!(deadline);
+                while batch.len() < MAX_BATCH_SIZE {
+                    tokio::select! {
+                        next = receiver.recv() => match next {
+                            Some(write) => batch.push(write),
+                            None => break,
+                        },
+                        _ = &mut deadline => break,
+                    }
+                }
+            }
+
+            Self::take_overflow(&mut receiver, &overflow, &mut batch);
+            Self::write(db, std::mem::take(&mut batch), &shutdown).await;
+        }
+
+        Self::take_overflow(&mut receiver, &overflow, &mut batch);
+        if !batch.is_empty() {
+            Self::write(db, batch, &shutdown).await;
+        }
+    }
+
+    /// Add the room writes kept aside while the queue was full to `batch`,
+    /// after everything still queued. Those were queued before the queue
+    /// filled up and are older than the copies kept aside.
+    fn take_overflow(receiver: &mut mpsc::Receiver<RoomWrite>, overflow: &Overflow, batch: &mut Vec<RoomWrite>) {
+        let mut overflow = overflow.lock().unwrap();
+        if overflow.is_empty() {
+            return;
+        }
+        while let Ok(write) = receiver.try_recv() {
+            batch.push(write);
+        }
+        batch.extend(overflow.drain().map(|(_, write)| write));
+    }
+
+    /// Write a batch, retrying with backoff. Meanwhile new updates wait in
+    /// the queue. A batch that still fails after `MAX_ATTEMPTS` is split in
+    /// halves that are written on their own, and a single write that keeps
+    /// failing is logged in full and set aside. Once shutdown has begun a
+    /// batch gets only `SHUTDOWN_ATTEMPTS` tries.
+    async fn write(db: &DatabaseConnection, batch: Vec<RoomWrite>, shutdown: &watch::Receiver<bool>) {
+        let mut pending = vec![batch];
+        while let Some(mut batch) = pending.pop() {
+            let err = match Self::write_with_retries(db, &batch, shutdown).await {
+                Ok(()) => continue,
+                Err(err) => err,
+            };
+            if *shutdown.borrow() {
+                log::error!("Dropping {} room updates after failed writes during shutdown: {}", batch.len(), err);
+            } else if batch.len() > 1 {
+                // Earlier writes first, so each room still ends up with its latest copy
+                let later = batch.split_off(batch.len() / 2);
+                pending.push(later);
+                pending.push(batch);
+            } else {
+                log::error!("Setting aside room update that keeps failing ({}): {:?}", err, batch[0]);
+            }
+        }
+    }
+
+    async fn write_with_retries(
+        db: &DatabaseConnection,
+        batch: &[RoomWrite],
+        shutdown: &watch::Receiver<bool>,
+    ) -> Result<(), DbErr> {
+        let mut delay = INITIAL_RETRY_DELAY;
+        let mut attempt = 1;
+        loop {
+            let err = match Self::flush(db, batch.to_vec()).await {
+                Ok(()) => return Ok(()),
+                Err(err) => err,
+            };
+            let attempts = if *shutdown.borrow() { SHUTDOWN_ATTEMPTS } else { MAX_ATTEMPTS };
+            if attempt >= attempts {
+                return Err(err);
+            }
+            log::error!(
+                "Failed to save {} room updates (attempt {}), retrying in {:?}: {}",
+                batch.len(), attempt, delay, err
+            );
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RETRY_DELAY);
+            attempt += 1;
+        }
+    }
+
+    // Rooms are written before the log. The log is written in one
+    // transaction, so a retried batch never logs an event twice; saving a
+    // room again on retry is harmless.
+    async fn flush(db: &DatabaseConnection, batch: Vec<RoomWrite>) -> Result<(), DbErr> {
+        // Latest copy of each room; `None` deletes it
+        let mut latest: HashMap<String, (DateTime<Utc>, Option<Box<Room>>)> = HashMap::new();
+        // Events in the order they happened, with the transition they describe
+        let mut logged = Vec::new();
+        for write in batch {
+            let (room_id, at, room) = match write {
+                RoomWrite::Save { room, saved_at } => (room.id.clone(), saved_at, Some(room)),
+                RoomWrite::Delete { room_id, deleted_at } => (room_id, deleted_at, None),
+                RoomWrite::Transition(event) => {
+                    logged.push((event.game_id, event.actor, vec![GameEvent::from(&event)], Some(event)));
+                    continue;
+                }
+                RoomWrite::Events { game_id, actor, events } => {
+                    logged.push((game_id, actor, events, None));
+                    continue;
+                }
+            };
+            if latest.get(&room_id).is_none_or(|(kept_at, _)| *kept_at <= at) {
+                latest.insert(room_id, (at, room));
+            }
+        }
+
+        let mut saves = Vec::new();
+        let mut deletes = Vec::new();
+        for (room_id, (at, room)) in latest {
+            match room {
+                Some(room) => saves.push(socket_room::ActiveModel {
+                    id: Set(room_id),
+                    state: Set(serde_json::to_value(&room).map_err(|e| DbErr::Json(e.to_string()))?),
+                    saved_at: Set(at.into()),
+                }),
+                None => deletes.push(room_id),
+            }
+        }
+
+        if !saves.is_empty() || !deletes.is_empty() {
+            let txn = db.begin().await?;
+            if !saves.is_empty() {
+                SocketRoom::insert_many(saves)
+                    .on_conflict(
+                        OnConflict::column(socket_room::Column::Id)
+                            .update_columns([socket_room::Column::State, socket_room::Column::SavedAt])
+                            .to_owned(),
+                    )
+                    .exec_without_returning(&txn)
+                    .await?;
+            }
+            if !deletes.is_empty() {
+                SocketRoom::delete_many()
+                    .filter(socket_room::Column::Id.is_in(deletes))
+                    .exec(&txn)
+                    .await?;
+            }
+            txn.commit().await?;
+        }
+
+        if !logged.is_empty() {
+            let txn = db.begin().await?;
+            for (game_id, actor, events, transition) in logged {
+                if let Some(event) = &transition {
+                    GameLifecycle::record(&txn, event)
+                        .await
+                        .map_err(|e| DbErr::Custom(e.to_string()))?;
+                }
+                GameEventLog::append_to(&txn, game_id, actor, events)
+                    .await
+                    .map_err(|e| DbErr::Custom(e.to_string()))?;
+            }
+            txn.commit().await?;
+        }
+        Ok(())
+    }
+
+    /// Load the saved rooms of unfinished games, resuming their clocks as of
+    /// `now`. Rows that no longer deserialize are logged in full and
+    /// deleted along with those of finished games, so they are not loaded
+    /// again on every start.
+    pub async fn load_active(db: &DatabaseConnection, now: DateTime<Utc>) -> Result<Vec<Room>, DbErr> {
+        let rows = SocketRoom::find().all(db).await?;
+
+        let mut rooms = Vec::with_capacity(rows.len());
+        let mut stale = Vec::new();
+        for row in rows {
+            let mut room: Room = match serde_json::from_value(row.state.clone()) {
+                Ok(room) => room,
+                Err(e) => {
+                    log::error!("Discarding saved room {} that no longer loads ({}): {}", row.id, e, row.state);
+                    stale.push(row.id);
+                    continue;
+                }
+            };
+            if room.lifecycle().is_terminal() {
+                stale.push(row.id);
+                continue;
+            }
+            let saved_at = row.saved_at.with_timezone(&Utc);
+            room.resume(saved_at.timestamp_millis() as u64, now.timestamp_millis() as u64);
+            rooms.push(room);
+        }
+
+        if !stale.is_empty() {
+            SocketRoom::delete_many()
+                .filter(socket_room::Column::Id.is_in(stale))
+                .exec(db)
+                .await?;
+        }
+        Ok(rooms)
+    }
+}
+
+impl RoomStoreWriter {
+    /// Stop accepting room updates, write the ones still queued and wait
+    /// for the writer to finish.
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(true);
+        if let Err(e) = self.task.await {
+            log::error!("Room store writer failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Player;
+    use sea_orm::{DbBackend, MockDatabase, MockExecResult};
+    use serde_json::json;
+    use service::game_events::Side;
+    use service::lifecycle::{LifecycleState, TransitionSource};
+
+    fn started_room(id: &str) -> Room {
+        let mut room = Room::new_with_time(id.to_string(), 60_000, 0);
+        for player_id in ["white_player", "black_player"] {
+            room.add_player(Player { id: player_id.to_string(), name: player_id.to_string(), color: None })
+                .unwrap();
+        }
+        room
+    }
+
+    #[tokio::test]
+    async fn test_flush_writes_latest_copy_of_each_room() {
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_exec_results(vec![
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+            ])
+            .into_connection();
+
+        let mut room = started_room("room-1");
+        let earlier = Utc::now() - chrono::Duration::seconds(1);
+        let first = RoomWrite::Save { room: Box::new(room.clone()), saved_at: earlier };
+        room.pending_takeback = Some("white_player".to_string());
+        let second = RoomWrite::Save { room: Box::new(room), saved_at: Utc::now() };
+
+        let deleted = RoomWrite::Delete { room_id: "room-2".to_string(), deleted_at: Utc::now() };
+        RoomStore::flush(&db, vec![second, first, deleted]).await.unwrap();
+
+        let log = db.into_transaction_log();
+        assert_eq!(log.len(), 1);
+        let log_str = format!("{:?}", log);
+        assert!(log_str.contains(r#"INSERT INTO \"smdb\".\"socket_room\""#));
+        assert!(log_str.contains(r#"ON CONFLICT (\"id\") DO UPDATE"#));
+        assert!(log_str.contains(r#""pending_takeback": String("white_player")"#));
+        assert!(log_str.contains(r#"DELETE FROM \"smdb\".\"socket_room\""#));
+    }
+
+    #[tokio::test]
+    async fn test_flush_records_transitions_with_the_rooms() {
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![Vec::<db_entity::game_event::Model>::new()])
+            .append_exec_results(vec![
+                MockExecResult { last_insert_id: 1, rows_affected: 1 },
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+            ])
+            .into_connection();
+
+        let room = started_room(&Uuid::new_v4().to_string());
+        let game_id = Uuid::parse_str(&room.id).unwrap();
+        let started = LifecycleEvent::new(
+            game_id,
+            &LifecycleState::Waiting,
+            LifecycleState::Started,
+            TransitionSource::Socket,
+            None,
+        )
+        .unwrap();
+        let save = RoomWrite::Save { room: Box::new(room), saved_at: Utc::now() };
+
+        RoomStore::flush(&db, vec![RoomWrite::Transition(started), save]).await.unwrap();
+
+        // The room is saved on its own, before the log
+        let log = db.into_transaction_log();
+        assert_eq!(log.len(), 2);
+        assert!(format!("{:?}", log[0]).contains(r#"INSERT INTO \"smdb\".\"socket_room\""#));
+        let log_str = format!("{:?}", log);
+        assert!(log_str.contains(r#"INSERT INTO \"smdb\".\"game_transition\""#));
+        assert!(log_str.contains(r#"String(Some("socket"))"#));
+        assert!(log_str.contains(r#"INSERT INTO \"smdb\".\"game_event\""#));
+        assert!(log_str.contains(r#"String(Some("state_changed"))"#));
+        assert!(log_str.contains(r#"INSERT INTO \"smdb\".\"socket_room\""#));
+    }
+
+    #[tokio::test]
+    async fn test_flush_logs_events_in_order() {
+        let game_id = Uuid::new_v4();
+        let last = db_entity::game_event::Model {
+            id: 1,
+            game_id,
+            event_index: 7,
+            kind: "move_played".to_string(),
+            payload: json!({}),
+            actor: None,
+            created_at: Utc::now().into(),
+        };
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![last.clone()], vec![last]])
+            .append_exec_results(vec![
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+                MockExecResult { last_insert_id: 0, rows_affected: 1 },
+            ])
+            .into_connection();
+
+        let offered = RoomWrite::Events { game_id, actor: None, events: vec![GameEvent::DrawOffered { by: Side::White }] };
+        let declined = RoomWrite::Events { game_id, actor: None, events: vec![GameEvent::DrawDeclined { by: Side::Black }] };
+        RoomStore::flush(&db, vec![offered, declined]).await.unwrap();
+
+        let log_str = format!("{:?}", db.into_transaction_log());
+        let offered_at = log_str.find(r#"String(Some("draw_offered"))"#).unwrap();
+        let declined_at = log_str.find(r#"String(Some("draw_declined"))"#).unwrap();
+        assert!(offered_at < declined_at);
+    }
+
+    #[tokio::test]
+    async fn test_load_active_pauses_clocks_for_downtime() {
+        let now = Utc::now();
+        let saved_at = now - chrono::Duration::minutes(5);
+
+        // White had been thinking for 3s when the room was last saved
+        let mut live = started_room("live");
+        live.last_move_at = Some(saved_at.timestamp_millis() as u64 - 3_000);
+        let mut finished = started_room("finished");
+        finished.game_state.as_mut().unwrap().status = LifecycleState::Aborted;
+
+        let row = |room: &Room| socket_room::Model {
+            id: room.id.clone(),
+            state: serde_json::to_value(room).unwrap(),
+            saved_at: saved_at.into(),
+        };
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![row(&live), row(&finished)]])
+            .append_exec_results(vec![MockExecResult { last_insert_id: 0, rows_affected: 1 }])
+            .into_connection();
+
+        let rooms = RoomStore::load_active(&db, now).await.unwrap();
+        assert_eq!(rooms.len(), 1);
+        assert_eq!(rooms[0].id, "live");
+        assert_eq!(rooms[0].players.len(), 2);
+        assert_eq!(rooms[0].last_move_at, Some(now.timestamp_millis() as u64 - 3_000));
+    }
+
+    #[tokio::test]
+    async fn test_load_active_deletes_rooms_that_no_longer_load() {
+        let broken = socket_room::Model {
+            id: "broken".to_string(),
+            state: json!({ "id": "broken", "players": "not a list" }),
+            saved_at: Utc::now().into(),
+        };
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results(vec![vec![broken]])
+            .append_exec_results(vec![MockExecResult { last_insert_id: 0, rows_affected: 1 }])
+            .into_connection();
+
+        let rooms = RoomStore::load_active(&db, Utc::now()).await.unwrap();
+        assert!(rooms.is_empty());
+
+        let log = db.into_transaction_log();
+        assert_eq!(log.len(), 2);
+        let log_str = format!("{:?}", log[1]);
+        assert!(log_str.contains(r#"DELETE FROM \"smdb\".\"socket_room\""#));
+        assert!(log_str.contains("broken"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_batch_is_retried() {
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_exec_errors(vec![DbErr::Custom("connection reset".to_string())])
+            .append_exec_results(vec![MockExecResult { last_insert_id: 0, rows_affected: 1 }])
+            .into_connection();
+
+        let (store, queue) = RoomStore::channel();
+        store.save(&started_room("room-1"));
+        drop(store);
+
+        let (_shutdown, stopping) = watch::channel(false);
+        RoomStore::run(&db, queue, stopping).await;
+
+        assert_eq!(db.into_transaction_log().len(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_write_that_keeps_failing_is_set_aside() {
+        let failure = || DbErr::Custom("value too long".to_string());
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_exec_errors((0..2 * MAX_ATTEMPTS).map(|_| failure()))
+            .append_exec_results(vec![MockExecResult { last_insert_id: 0, rows_affected: 1 }])
+            .into_connection();
+
+        // The whole batch fails, then its first half on its own
+        let (store, queue) = RoomStore::channel();
+        store.save(&started_room("poisoned"));
+        store.save(&started_room("healthy"));
+        drop(store);
+
+        let (_shutdown, stopping) = watch::channel(false);
+        RoomStore::run(&db, queue, stopping).await;
+
+        let log = db.into_transaction_log();
+        assert_eq!(log.len(), 2 * MAX_ATTEMPTS as usize + 1);
+        let written = format!("{:?}", log.last().unwrap());
+        assert!(written.contains("healthy") && !written.contains("poisoned"));
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_keeps_latest_room_copy_without_waiting() {
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_exec_results((0..QUEUE_CAPACITY / MAX_BATCH_SIZE + 1).map(|_| MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 1,
+            }))
+            .into_connection();
+
+        let (store, queue) = RoomStore::channel();
+        let busy = started_room("busy");
+        for _ in 0..QUEUE_CAPACITY {
+            store.save(&busy);
+        }
+        let mut late = started_room("late");
+        store.save(&late);
+        late.pending_takeback = Some("white_player".to_string());
+        store.save(&late);
+        store.append(Uuid::new_v4(), None, vec![GameEvent::DrawOffered { by: Side::White }]);
+        drop(store);
+
+        let (_shutdown, stopping) = watch::channel(false);
+        RoomStore::run(&db, queue, stopping).await;
+
+        let log_str = format!("{:?}", db.into_transaction_log());
+        assert!(log_str.contains(r#""pending_takeback": String("white_player")"#));
+        assert!(!log_str.contains("draw_offered"));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_queued_rooms() {
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_exec_results(vec![MockExecResult { last_insert_id: 0, rows_affected: 2 }])
+            .into_connection();
+
+        // The global handle stays alive for the life of the server
+        let (store, queue) = RoomStore::channel();
+        store.save(&started_room("room-1"));
+        store.save(&started_room("room-2"));
+
+        let (shutdown, stopping) = watch::channel(false);
+        shutdown.send(true).unwrap();
+        RoomStore::run(&db, queue, stopping).await;
+
+        let log = db.into_transaction_log();
+        assert_eq!(log.len(), 1);
+        let log_str = format!("{:?}", log[0]);
+        assert!(log_str.contains("room-1") && log_str.contains("room-2"));
+
+        // Rooms saved after shutdown are refused rather than queued forever
+        store.save(&started_room("room-3"));
+    }
+}
@@ -0,0 +1,129 @@
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::models::{ClientMessage, ServerMessage};
+
+const SEC_WEBSOCKET_PROTOCOL: &str = "Sec-WebSocket-Protocol";
+
+/// Wire encoding of a connection, negotiated through `Sec-WebSocket-Protocol`.
+///
+/// MessagePack is the compact encoding for production clients; JSON stays
+/// available for debugging and for clients that don't request a subprotocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireFormat {
+    Json,
+    MessagePack,
+}
+
+impl WireFormat {
+    pub const JSON_PROTOCOL: &'static str = "xlmate.json.v1";
+    pub const MSGPACK_PROTOCOL: &'static str = "xlmate.msgpack.v1";
+
+    pub fn protocol(&self) -> &'static str {
+        match self {
+            WireFormat::Json => Self::JSON_PROTOCOL,
+            WireFormat::MessagePack => Self::MSGPACK_PROTOCOL,
+        }
+    }
+
+    fn from_protocol(protocol: &str) -> Option<Self> {
+        match protocol {
+            Self::JSON_PROTOCOL => Some(WireFormat::Json),
+            Self::MSGPACK_PROTOCOL => Some(WireFormat::MessagePack),
+            _ => None,
+        }
+    }
+
+    /// Picks the first subprotocol offered by the client that the server supports.
+    pub fn negotiate(offered: Option<&str>) -> Option<Self> {
+        offered?.split(',').map(str::trim).find_map(Self::from_protocol)
+    }
+
+    /// Serializes a server message into a text (JSON) or binary (MessagePack) frame.
+    pub fn encode(&self, msg: &ServerMessage) -> Result<Message, String> {
+        match self {
+            WireFormat::Json => serde_json::to_string(msg)
+                .map(Message::Text)
+                .map_err(|e| e.to_string()),
+            WireFormat::MessagePack => rmp_serde::to_vec_named(msg)
+                .map(Message::Binary)
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Parses a client frame. JSON text frames are accepted on every connection.
+    pub fn decode(&self, frame: &Message) -> Option<Result<ClientMessage, String>> {
+        match frame {
+            Message::Text(text) => Some(serde_json::from_str(text).map_err(|e| e.to_string())),
+            Message::Binary(bytes) if *self == WireFormat::MessagePack => {
+                Some(rmp_serde::from_slice(bytes).map_err(|e| e.to_string()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Handshake callback for `accept_hdr_async`: echoes the negotiated
+/// subprotocol and records it in `format`.
+pub fn negotiate_handshake<'a>(
+    format: &'a mut WireFormat,
+) -> impl FnOnce(&Request, Response) -> Result<Response, ErrorResponse> + 'a {
+    move |request, mut response| {
+        let offered = request
+            .headers()
+            .get(SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|h| h.to_str().ok());
+        if let Some(negotiated) = WireFormat::negotiate(offered) {
+            *format = negotiated;
+            response.headers_mut().insert(
+                SEC_WEBSOCKET_PROTOCOL,
+                HeaderValue::from_static(negotiated.protocol()),
+            );
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PieceColor;
+
+    #[test]
+    fn test_negotiate_prefers_first_supported_protocol() {
+        assert_eq!(
+            WireFormat::negotiate(Some("chat, xlmate.msgpack.v1, xlmate.json.v1")),
+            Some(WireFormat::MessagePack)
+        );
+        assert_eq!(WireFormat::negotiate(Some("xlmate.json.v1")), Some(WireFormat::Json));
+        assert_eq!(WireFormat::negotiate(Some("chat")), None);
+        assert_eq!(WireFormat::negotiate(None), None);
+    }
+
+    #[test]
+    fn test_move_delta_round_trips_through_msgpack() {
+        let msg = ServerMessage::MoveMade {
+            room_id: "room".to_string(),
+            player_id: "white_player".to_string(),
+            move_notation: "e2e4".to_string(),
+            san: "e4".to_string(),
+            ply: 1,
+            white_remaining_ms: 59_000,
+            black_remaining_ms: 60_000,
+            current_turn: PieceColor::Black,
+        };
+        let Message::Binary(bytes) = WireFormat::MessagePack.encode(&msg).unwrap() else {
+            panic!("MessagePack must be sent as a binary frame");
+        };
+        let Message::Text(text) = WireFormat::Json.encode(&msg).unwrap() else {
+            panic!("JSON must be sent as a text frame");
+        };
+        assert!(bytes.len() < text.len());
+
+        let decoded: serde_json::Value = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(decoded["type"], "MoveMade");
+        assert_eq!(decoded["ply"], 1);
+        assert_eq!(decoded["white_remaining_ms"], 59_000);
+    }
+}
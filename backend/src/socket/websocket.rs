@@ -2,19 +2,21 @@ use futures_util::{SinkExt, StreamExt};
 use std::net::SocketAddr;
 use tokio::net::TcpStream;
 use tokio::sync::broadcast;
-use tokio_tungstenite::{accept_async, tungstenite::Message, WebSocketStream};
+use tokio_tungstenite::{accept_hdr_async, tungstenite::Message, WebSocketStream};
 
 use crate::handlers::handle_client_message;
 use crate::models::ServerMessage;
+use crate::protocol::{negotiate_handshake, WireFormat};
 
 // Handle a WebSocket connection
 pub async fn handle_connection(
     stream: TcpStream,
     addr: SocketAddr,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    // Accept the WebSocket connection
-    let ws_stream = accept_async(stream).await?;
-    log::info!("WebSocket connection established with: {}", addr);
+    // Accept the WebSocket connection, negotiating the wire format
+    let mut format = WireFormat::Json;
+    let ws_stream = accept_hdr_async(stream, negotiate_handshake(&mut format)).await?;
+    log::info!("WebSocket connection established with: {} ({:?})", addr, format);
 
     // Split the WebSocket stream
     let (mut ws_sender, mut ws_receiver) = ws_stream.split();
@@ -31,8 +33,11 @@ pub async fn handle_connection(
                 match msg {
                     Some(Ok(msg)) => {
                         match msg {
-                            Message::Text(text) => {
-                                if let Err(e) = handle_client_message(&text, &mut ws_sender, &mut room_senders).await {
+                            Message::Text(_) | Message::Binary(_) => {
+                                let Some(client_message) = format.decode(&msg) else {
+                                    continue;
+                                };
+                                if let Err(e) = handle_client_message(client_message, &mut ws_sender, &mut room_senders, format).await {
                                     log::error!("Error handling client message: {}", e);
                                     break;
                                 }
@@ -74,8 +79,8 @@ if room_receivers.len() != room_senders.len() {
                 // Check for messages from each room
                 for (i, receiver) in room_receivers.iter_mut().enumerate() {
                     if let Ok(msg) = receiver.try_recv() {
-                        if let Ok(json) = serde_json::to_string(&msg) {
-                            if let Err(e) = ws_sender.send(Message::Text(json)).await {
+                        if let Ok(frame) = format.encode(&msg) {
+                            if let Err(e) = ws_sender.send(frame).await {
                                 log::error!("Error forwarding room message: {}", e);
                                 return;
                             }
@@ -10,18 +10,18 @@ echo ""
 
 # Step 1: Add players to queue
 echo "Step 1: Adding 3 players to queue..."
-docker exec xlmate-redis redis-cli ZADD matchmaking:queue:rated 1737482400 '{"id":"player-1","player":{"wallet_address":"alice","elo":1500,"join_time":"2026-01-21T18:00:00Z"},"match_type":"Rated","invite_address":null,"max_elo_diff":200}'
-docker exec xlmate-redis redis-cli ZADD matchmaking:queue:rated 1737482410 '{"id":"player-2","player":{"wallet_address":"bob","elo":1550,"join_time":"2026-01-21T18:00:10Z"},"match_type":"Rated","invite_address":null,"max_elo_diff":200}'
-docker exec xlmate-redis redis-cli ZADD matchmaking:queue:rated 1737482420 '{"id":"player-3","player":{"wallet_address":"charlie","elo":1600,"join_time":"2026-01-21T18:00:20Z"},"match_type":"Rated","invite_address":null,"max_elo_diff":200}'
+docker exec xlmate-redis redis-cli ZADD matchmaking:queue:rated:standard:300+0 1737482400 '{"id":"player-1","player":{"wallet_address":"alice","rating":1500,"join_time":"2026-01-21T18:00:00Z"},"match_type":"Rated","invite_address":null,"max_rating_diff":200}'
+docker exec xlmate-redis redis-cli ZADD matchmaking:queue:rated:standard:300+0 1737482410 '{"id":"player-2","player":{"wallet_address":"bob","rating":1550,"join_time":"2026-01-21T18:00:10Z"},"match_type":"Rated","invite_address":null,"max_rating_diff":200}'
+docker exec xlmate-redis redis-cli ZADD matchmaking:queue:rated:standard:300+0 1737482420 '{"id":"player-3","player":{"wallet_address":"charlie","rating":1600,"join_time":"2026-01-21T18:00:20Z"},"match_type":"Rated","invite_address":null,"max_rating_diff":200}'
 
 echo "✅ 3 players added"
 echo ""
 
 # Step 2: Verify queue before restart
 echo "Step 2: Queue status BEFORE restart..."
-BEFORE_COUNT=$(docker exec xlmate-redis redis-cli ZCARD matchmaking:queue:rated)
+BEFORE_COUNT=$(docker exec xlmate-redis redis-cli ZCARD matchmaking:queue:rated:standard:300+0)
 echo "Queue size: $BEFORE_COUNT players"
-docker exec xlmate-redis redis-cli ZRANGE matchmaking:queue:rated 0 -1 WITHSCORES | head -6
+docker exec xlmate-redis redis-cli ZRANGE matchmaking:queue:rated:standard:300+0 0 -1 WITHSCORES | head -6
 echo ""
 
 # Step 3: Restart Redis container
@@ -34,9 +34,9 @@ echo ""
 
 # Step 4: Verify queue after restart
 echo "Step 4: Queue status AFTER restart..."
-AFTER_COUNT=$(docker exec xlmate-redis redis-cli ZCARD matchmaking:queue:rated)
+AFTER_COUNT=$(docker exec xlmate-redis redis-cli ZCARD matchmaking:queue:rated:standard:300+0)
 echo "Queue size: $AFTER_COUNT players"
-docker exec xlmate-redis redis-cli ZRANGE matchmaking:queue:rated 0 -1 WITHSCORES | head -6
+docker exec xlmate-redis redis-cli ZRANGE matchmaking:queue:rated:standard:300+0 0 -1 WITHSCORES | head -6
 echo ""
 
 # Step 5: Validate
@@ -61,5 +61,5 @@ fi
 # Cleanup
 echo ""
 echo "Cleanup: Removing test data..."
-docker exec xlmate-redis redis-cli DEL matchmaking:queue:rated
+docker exec xlmate-redis redis-cli DEL matchmaking:queue:rated:standard:300+0
 echo "✅ Done"